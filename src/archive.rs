@@ -0,0 +1,346 @@
+//! Scenes packed into a single zip file (with the extension `.asdz`),
+//! which is easier to distribute than a directory tree.
+//!
+//! The archive must contain exactly one `.asdf` file at the top level,
+//! which is loaded as the main scene.
+//! Relative paths (of audio files and sub-scenes) are resolved within
+//! the archive.
+//!
+//! Audio files should be stored without compression (e.g. with `zip -0`),
+//! then they are streamed directly from the archive.
+//! Compressed files are decompressed into memory when they are opened.
+//!
+//! `Scene::with_options()` (and `Scene::load_dry_run()`) automatically load
+//! files with the extension `.asdz` as archives.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::loader::{normalize, resolve_seek, ByteReader, OpenCallback};
+
+/// File extension of archives
+pub const EXTENSION: &str = "asdz";
+
+struct Entry {
+    index: usize,
+    /// Begin of the (uncompressed) data within the archive
+    offset: u64,
+    size: u64,
+    compressed: bool,
+}
+
+/// An opened archive (only the list of files is read).
+///
+/// All file names are given relative to the archive path,
+/// e.g. `my-piece.asdz/audio/drums.wav`.
+pub struct Archive {
+    path: PathBuf,
+    entries: HashMap<PathBuf, Entry>,
+    main_scene: PathBuf,
+}
+
+impl Archive {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Archive> {
+        let path = path.as_ref();
+        let mut archive = ZipArchive::new(fs::File::open(path)?)?;
+        let mut entries = HashMap::new();
+        let mut scenes = Vec::new();
+        for index in 0..archive.len() {
+            let file = archive.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.enclosed_name().ok_or_else(|| {
+                invalid_data(format!("Invalid file name in archive: {}", file.name()))
+            })?;
+            if name.parent() == Some(Path::new("")) && name.extension() == Some("asdf".as_ref()) {
+                scenes.push(name.to_path_buf());
+            }
+            entries.insert(
+                normalize(&path.join(name)),
+                Entry {
+                    index,
+                    offset: file.data_start(),
+                    size: file.size(),
+                    compressed: file.compression() != CompressionMethod::Stored,
+                },
+            );
+        }
+        let main_scene = match scenes.as_slice() {
+            [scene] => normalize(&path.join(scene)),
+            [] => {
+                return Err(invalid_data(
+                    "No .asdf file at the top level of the archive",
+                ))
+            }
+            _ => {
+                return Err(invalid_data(
+                    "More than one .asdf file at the top level of the archive",
+                ))
+            }
+        };
+        Ok(Archive {
+            path: path.into(),
+            entries,
+            main_scene,
+        })
+    }
+
+    pub fn main_scene(&self) -> &Path {
+        &self.main_scene
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.entries.keys().map(|name| name.as_path())
+    }
+
+    pub fn open_file<P: AsRef<Path>>(&self, name: P) -> io::Result<Box<dyn ByteReader>> {
+        let name = name.as_ref();
+        let entry = self.entries.get(&normalize(name)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("File not found in archive: {}", name.display()),
+            )
+        })?;
+        let mut file = fs::File::open(&self.path)?;
+        if entry.compressed {
+            let mut archive = ZipArchive::new(file)?;
+            let mut data = Vec::with_capacity(entry.size as usize);
+            archive.by_index(entry.index)?.read_to_end(&mut data)?;
+            return Ok(Box::new(Cursor::new(data)));
+        }
+        file.seek(SeekFrom::Start(entry.offset))?;
+        Ok(Box::new(StoredFile {
+            file: io::BufReader::new(file),
+            offset: entry.offset,
+            size: entry.size,
+            position: 0,
+        }))
+    }
+
+    /// Can be used with
+    /// [`Scene::load_with_reader()`](../struct.Scene.html#method.load_with_reader).
+    pub fn into_open_callback(self) -> OpenCallback {
+        Arc::new(move |path: &Path| self.open_file(path))
+    }
+}
+
+/// Replaces `path` and `open` if `path` has the archive extension
+/// (and `open` is not given).
+pub(crate) fn resolve(
+    path: &Path,
+    open: Option<OpenCallback>,
+) -> io::Result<(PathBuf, Option<OpenCallback>)> {
+    if open.is_none() && path.extension() == Some(EXTENSION.as_ref()) {
+        let archive = Archive::open(path)?;
+        Ok((
+            archive.main_scene().into(),
+            Some(archive.into_open_callback()),
+        ))
+    } else {
+        Ok((path.into(), open))
+    }
+}
+
+fn invalid_data<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// An uncompressed file within an archive.
+struct StoredFile {
+    file: io::BufReader<fs::File>,
+    /// Begin of the data within the archive
+    offset: u64,
+    size: u64,
+    position: u64,
+}
+
+impl Read for StoredFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.position);
+        let len = (buf.len() as u64).min(remaining) as usize;
+        let bytes = self.file.read(&mut buf[..len])?;
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl Seek for StoredFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = resolve_seek(pos, self.position, self.size)?;
+        self.file.seek(SeekFrom::Start(self.offset + position))?;
+        self.position = position;
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    use zip::write::{FileOptions, ZipWriter};
+    use CompressionMethod::{Deflated, Stored};
+
+    const SCENE: &[u8] = b"<asdf version=\"0.4\"/>";
+
+    /// Temporary directory which is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir =
+                std::env::temp_dir().join(format!("asdf-archive-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn contents(size: usize, modulo: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % modulo) as u8).collect()
+    }
+
+    /// Creates `piece.asdz` in `dir` with the given `(name, data, compression)` entries.
+    fn create(dir: &TempDir, files: &[(&str, &[u8], CompressionMethod)]) -> PathBuf {
+        let path = dir.0.join("piece.asdz");
+        let mut writer = ZipWriter::new(fs::File::create(&path).unwrap());
+        for &(name, data, method) in files {
+            writer
+                .start_file(name, FileOptions::default().compression_method(method))
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
+        path
+    }
+
+    /// A scene, one stored and one compressed file.
+    fn create_piece(dir: &TempDir) -> PathBuf {
+        create(
+            dir,
+            &[
+                ("piece.asdf", SCENE, Stored),
+                ("audio/a.bin", &contents(5000, 251), Stored),
+                ("audio/b.bin", &contents(5000, 241), Deflated),
+                ("sub/scene.asdf", SCENE, Stored),
+            ],
+        )
+    }
+
+    fn read(archive: &Archive, name: &Path) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        archive.open_file(name)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    #[test]
+    fn list_files() {
+        let dir = TempDir::new("list");
+        let path = create_piece(&dir);
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.main_scene(), path.join("piece.asdf"));
+        let mut files: Vec<_> = archive.files().map(Path::to_path_buf).collect();
+        files.sort();
+        assert_eq!(
+            files,
+            ["audio/a.bin", "audio/b.bin", "piece.asdf", "sub/scene.asdf"]
+                .iter()
+                .map(|name| path.join(name))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn read_files() {
+        let dir = TempDir::new("read");
+        let path = create_piece(&dir);
+        let archive = Archive::open(&path).unwrap();
+        // The stored file must not include any data of the following entries
+        assert_eq!(
+            read(&archive, &path.join("audio/a.bin")).unwrap(),
+            contents(5000, 251)
+        );
+        assert_eq!(
+            read(&archive, &path.join("audio/b.bin")).unwrap(),
+            contents(5000, 241)
+        );
+        assert_eq!(
+            read(&archive, &path.join("sub/../audio/./a.bin")).unwrap(),
+            contents(5000, 251)
+        );
+        assert_eq!(
+            read(&archive, &path.join("audio/c.bin"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn seeking() {
+        let dir = TempDir::new("seeking");
+        let path = create_piece(&dir);
+        let archive = Archive::open(&path).unwrap();
+        for name in &["audio/a.bin", "audio/b.bin"] {
+            let expected = read(&archive, &path.join(name)).unwrap();
+            let mut file = archive.open_file(path.join(name)).unwrap();
+            let mut buffer = [0; 100];
+            assert_eq!(file.seek(SeekFrom::Start(1000)).unwrap(), 1000);
+            file.read_exact(&mut buffer).unwrap();
+            assert_eq!(&buffer[..], &expected[1000..1100]);
+            assert_eq!(file.seek(SeekFrom::Current(-600)).unwrap(), 500);
+            file.read_exact(&mut buffer).unwrap();
+            assert_eq!(&buffer[..], &expected[500..600]);
+            assert_eq!(file.seek(SeekFrom::End(-100)).unwrap(), 4900);
+            file.read_exact(&mut buffer).unwrap();
+            assert_eq!(&buffer[..], &expected[4900..]);
+            assert_eq!(file.read(&mut buffer).unwrap(), 0);
+            assert!(file.seek(SeekFrom::Current(-6000)).is_err());
+        }
+    }
+
+    #[test]
+    fn number_of_scenes() {
+        let dir = TempDir::new("scenes");
+        let path = create(&dir, &[("sub/scene.asdf", SCENE, Stored)]);
+        let error = Archive::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let path = create(
+            &dir,
+            &[("one.asdf", SCENE, Stored), ("two.asdf", SCENE, Stored)],
+        );
+        let error = Archive::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let dir = TempDir::new("resolve");
+        let path = create_piece(&dir);
+        let (main_scene, open) = resolve(&path, None).unwrap();
+        assert_eq!(main_scene, path.join("piece.asdf"));
+        let mut data = Vec::new();
+        open.unwrap()(&path.join("audio/a.bin"))
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, contents(5000, 251));
+
+        let scene = dir.0.join("scene.asd");
+        let (main_scene, open) = resolve(&scene, None).unwrap();
+        assert_eq!(main_scene, scene);
+        assert!(open.is_none());
+    }
+}
@@ -0,0 +1,300 @@
+//! Confinement of source positions to an area given by the host
+//! (e.g. the hull of a loudspeaker array), see `Scene::set_area()`.
+//!
+//! Positions outside of the area are moved onto its boundary,
+//! the resulting `Transform` has its `clamped` flag set.
+
+use crate::extent::BoundingBox;
+use crate::transform::Vec3;
+
+/// How positions outside of the area are moved onto its boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AreaPolicy {
+    /// The nearest point of the area is used
+    Clamp,
+    /// The position is moved towards the center of the area,
+    /// which keeps its direction (as seen from the center)
+    Project,
+}
+
+impl Default for AreaPolicy {
+    fn default() -> AreaPolicy {
+        AreaPolicy::Clamp
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Shape {
+    Box(BoundingBox),
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// Counter-clockwise vertices in the x/y plane
+    Polygon(Box<[[f32; 2]]>),
+}
+
+/// A convex region of the scene coordinate system.
+#[derive(Clone, Debug)]
+pub struct Area {
+    shape: Shape,
+    center: Vec3,
+}
+
+impl Area {
+    pub fn from_box(extent: BoundingBox) -> Area {
+        Area {
+            center: extent.center().into(),
+            shape: Shape::Box(extent),
+        }
+    }
+
+    /// Panics if `radius` is negative.
+    pub fn sphere(center: [f32; 3], radius: f32) -> Area {
+        assert!(radius >= 0.0, "Negative radius");
+        Area {
+            shape: Shape::Sphere {
+                center: center.into(),
+                radius,
+            },
+            center: center.into(),
+        }
+    }
+
+    /// Convex polygon in the horizontal (x/y) plane, which is extruded
+    /// infinitely along the z axis (i.e. the z coordinate is never changed).
+    ///
+    /// The vertices can be given in clockwise or counter-clockwise order.
+    /// Returns `None` if there are less than three vertices,
+    /// if the polygon is not convex (or intersects itself)
+    /// or if it has no area.
+    pub fn polygon(vertices: &[[f32; 2]]) -> Option<Area> {
+        if vertices.len() < 3 {
+            return None;
+        }
+        let signed_area: f32 = edges(vertices)
+            .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+            .sum();
+        let mut vertices = vertices.to_vec();
+        if signed_area < 0.0 {
+            vertices.reverse();
+        } else if signed_area == 0.0 || signed_area.is_nan() {
+            return None;
+        }
+        // NB: This also rejects self-intersecting polygons
+        //     (which may have all their corners turning in the same direction)
+        if edges(&vertices).any(|(a, b)| vertices.iter().any(|&p| outward_distance(a, b, p) > 0.0))
+        {
+            return None;
+        }
+        let count = vertices.len() as f32;
+        let center = Vec3::new(
+            vertices.iter().map(|v| v[0]).sum::<f32>() / count,
+            vertices.iter().map(|v| v[1]).sum::<f32>() / count,
+            0.0,
+        );
+        Some(Area {
+            shape: Shape::Polygon(vertices.into()),
+            center,
+        })
+    }
+
+    /// Whether `point` is inside the area (or on its boundary).
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        match &self.shape {
+            Shape::Box(extent) => extent.contains(point),
+            Shape::Sphere { center, radius } => (Vec3::from(point) - center).norm() <= *radius,
+            Shape::Polygon(vertices) => {
+                edges(vertices).all(|(a, b)| outward_distance(a, b, [point[0], point[1]]) <= 0.0)
+            }
+        }
+    }
+
+    /// Returns the new position if `point` has to be moved
+    /// (i.e. if it is outside of the area).
+    ///
+    /// This is realtime-safe.
+    pub fn confine(&self, point: [f32; 3], policy: AreaPolicy) -> Option<[f32; 3]> {
+        if self.contains(point) {
+            return None;
+        }
+        Some(match policy {
+            AreaPolicy::Clamp => self.nearest(point),
+            AreaPolicy::Project => self.project(point),
+        })
+    }
+
+    fn nearest(&self, point: [f32; 3]) -> [f32; 3] {
+        match &self.shape {
+            Shape::Box(extent) => {
+                let mut result = point;
+                for (i, value) in result.iter_mut().enumerate() {
+                    *value = value.max(extent.min[i]).min(extent.max[i]);
+                }
+                result
+            }
+            Shape::Sphere { .. } => self.project(point),
+            Shape::Polygon(vertices) => {
+                let p = [point[0], point[1]];
+                let mut best = (f32::INFINITY, p);
+                for (a, b) in edges(vertices) {
+                    let candidate = nearest_on_segment(a, b, p);
+                    let distance = (candidate[0] - p[0]).hypot(candidate[1] - p[1]);
+                    if distance < best.0 {
+                        best = (distance, candidate);
+                    }
+                }
+                [best.1[0], best.1[1], point[2]]
+            }
+        }
+    }
+
+    /// Moves `point` along the line towards `center` onto the boundary.
+    fn project(&self, point: [f32; 3]) -> [f32; 3] {
+        let direction = Vec3::from(point) - self.center;
+        let mut scale = f32::INFINITY;
+        match &self.shape {
+            Shape::Box(extent) => {
+                for i in 0..3 {
+                    let limit = if direction[i] > 0.0 {
+                        extent.max[i]
+                    } else {
+                        extent.min[i]
+                    };
+                    if direction[i] != 0.0 {
+                        scale = scale.min((limit - self.center[i]) / direction[i]);
+                    }
+                }
+            }
+            Shape::Sphere { radius, .. } => {
+                let length = direction.norm();
+                if length > 0.0 {
+                    scale = radius / length;
+                }
+            }
+            Shape::Polygon(vertices) => {
+                let c = [self.center[0], self.center[1]];
+                let d = [direction[0], direction[1]];
+                for (a, b) in edges(vertices) {
+                    // NB: Outward normal of a counter-clockwise edge
+                    let normal = [b[1] - a[1], a[0] - b[0]];
+                    let speed = normal[0] * d[0] + normal[1] * d[1];
+                    if speed > 0.0 {
+                        scale = scale.min(-outward_distance(a, b, c) / speed);
+                    }
+                }
+            }
+        }
+        let scale = scale.clamp(0.0, 1.0);
+        let mut result: [f32; 3] = (self.center + direction * scale).into();
+        if let Shape::Polygon(_) = self.shape {
+            result[2] = point[2];
+        }
+        result
+    }
+}
+
+/// Pairs of consecutive vertices, including the closing edge.
+fn edges(vertices: &[[f32; 2]]) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+    vertices
+        .iter()
+        .copied()
+        .zip(vertices.iter().copied().cycle().skip(1))
+}
+
+/// Positive if `p` is outside of the (counter-clockwise) edge from `a` to `b`
+/// (scaled by the length of the edge).
+fn outward_distance(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[1] - a[1]) * (p[0] - a[0]) - (b[0] - a[0]) * (p[1] - a[1])
+}
+
+fn nearest_on_segment(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> [f32; 2] {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let length_squared = ab[0] * ab[0] + ab[1] * ab[1];
+    if length_squared == 0.0 {
+        return a;
+    }
+    let t = ((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / length_squared;
+    let t = t.clamp(0.0, 1.0);
+    [a[0] + t * ab[0], a[1] + t * ab[1]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_square() -> Area {
+        // NB: Clockwise
+        Area::polygon(&[[-1.0, -1.0], [-1.0, 1.0], [1.0, 1.0], [1.0, -1.0]]).unwrap()
+    }
+
+    #[test]
+    fn box_area() {
+        let area = Area::from_box(BoundingBox {
+            min: [-1.0, -1.0, -1.0],
+            max: [1.0, 1.0, 1.0],
+        });
+        assert_eq!(area.confine([0.5, 0.0, -1.0], AreaPolicy::Clamp), None);
+        assert_eq!(area.confine([0.5, 0.0, -1.0], AreaPolicy::Project), None);
+        let clamp = |p| area.confine(p, AreaPolicy::Clamp).unwrap();
+        assert_eq!(clamp([2.0, 0.5, 0.0]), [1.0, 0.5, 0.0]);
+        assert_eq!(clamp([3.0, 3.0, -3.0]), [1.0, 1.0, -1.0]);
+        let project = |p| area.confine(p, AreaPolicy::Project).unwrap();
+        assert_eq!(project([2.0, 1.0, 0.0]), [1.0, 0.5, 0.0]);
+        assert_eq!(project([0.0, 0.0, -4.0]), [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn sphere_area() {
+        let area = Area::sphere([1.0, 0.0, 0.0], 2.0);
+        assert_eq!(area.confine([2.0, 1.0, 0.0], AreaPolicy::Clamp), None);
+        for &policy in &[AreaPolicy::Clamp, AreaPolicy::Project] {
+            assert_eq!(area.confine([5.0, 0.0, 0.0], policy), Some([3.0, 0.0, 0.0]));
+            assert_eq!(
+                area.confine([1.0, 0.0, -4.0], policy),
+                Some([1.0, 0.0, -2.0])
+            );
+        }
+    }
+
+    #[test]
+    fn polygon_area() {
+        let area = unit_square();
+        // NB: The z coordinate is never changed
+        assert_eq!(area.confine([0.0, 0.5, 100.0], AreaPolicy::Clamp), None);
+        let clamp = |p| area.confine(p, AreaPolicy::Clamp).unwrap();
+        assert_eq!(clamp([2.0, 0.5, 3.0]), [1.0, 0.5, 3.0]);
+        assert_eq!(clamp([3.0, 3.0, 0.0]), [1.0, 1.0, 0.0]);
+        let project = |p| area.confine(p, AreaPolicy::Project).unwrap();
+        assert_eq!(project([4.0, 2.0, 3.0]), [1.0, 0.5, 3.0]);
+        assert_eq!(project([-4.0, -4.0, 0.0]), [-1.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn invalid_polygons() {
+        assert!(Area::polygon(&[[0.0, 0.0], [1.0, 0.0]]).is_none());
+        // Collinear
+        assert!(Area::polygon(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]).is_none());
+        // L-shaped
+        assert!(Area::polygon(&[
+            [0.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 2.0],
+            [0.0, 2.0],
+        ])
+        .is_none());
+        // Pentagram
+        let star: Vec<_> = (0..5)
+            .map(|i| {
+                let angle = (i * 2) as f32 * 2.0 * std::f32::consts::PI / 5.0;
+                [angle.cos(), angle.sin()]
+            })
+            .collect();
+        assert!(Area::polygon(&star).is_none());
+        // The same corners in the right order
+        let pentagon: Vec<_> = (0..5).map(|i| star[(i * 3) % 5]).collect();
+        assert!(Area::polygon(&pentagon).is_some());
+    }
+}
@@ -0,0 +1,313 @@
+//! Cache of decoded audio data for compressed files.
+//!
+//! Seeking backwards in compressed files (e.g. Ogg Vorbis) is expensive,
+//! because the decoder has to start over at some earlier point in the file.
+//! When a memory budget is set with [`set_memory_budget()`](fn.set_memory_budget.html),
+//! compressed files (and files opened by registered decoders) are decoded
+//! in chunks of `CHUNK_FRAMES` frames which are kept in memory.
+//! When the budget is exhausted, the least recently used chunks are discarded.
+//!
+//! This avoids hitting the decoder over and over again when a loop region
+//! is played repeatedly or when the transport jumps back and forth.
+//! The budget is shared between all open files (of all scenes).
+//!
+//! The cache is disabled by default, the budget only affects files
+//! which are opened after it has been set.
+//! WAV files are never cached.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::{AudioFileBasics, AudioFileBlocks};
+
+/// Number of frames which are decoded (and cached) at once
+pub const CHUNK_FRAMES: u64 = 1 << 14;
+
+struct Entry {
+    /// Interleaved samples
+    data: Arc<[f32]>,
+    last_use: u64,
+}
+
+struct Cache {
+    /// Maximum memory usage in bytes, `0` disables the cache
+    budget: usize,
+    /// Current memory usage in bytes
+    used: usize,
+    /// Incremented on each access, used to find the least recently used chunk
+    clock: u64,
+    /// Indexed by file ID and chunk index
+    chunks: HashMap<(u64, u64), Entry>,
+}
+
+impl Cache {
+    fn get(&mut self, key: (u64, u64)) -> Option<Arc<[f32]>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.chunks.get_mut(&key).map(|entry| {
+            entry.last_use = clock;
+            entry.data.clone()
+        })
+    }
+
+    fn insert(&mut self, key: (u64, u64), data: Arc<[f32]>) {
+        let size = chunk_size(&data);
+        if size > self.budget {
+            return;
+        }
+        self.evict(self.budget - size);
+        self.clock += 1;
+        self.used += size;
+        let entry = Entry {
+            data,
+            last_use: self.clock,
+        };
+        if let Some(old) = self.chunks.insert(key, entry) {
+            self.used -= chunk_size(&old.data);
+        }
+    }
+
+    /// Discards least recently used chunks until at most `limit` bytes are used.
+    fn evict(&mut self, limit: usize) {
+        while self.used > limit {
+            let key = match self
+                .chunks
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_use)
+                .map(|(&key, _)| key)
+            {
+                Some(key) => key,
+                None => break,
+            };
+            let entry = self.chunks.remove(&key).unwrap();
+            self.used -= chunk_size(&entry.data);
+        }
+    }
+
+    fn remove_file(&mut self, id: u64) {
+        let mut freed = 0;
+        self.chunks.retain(|&(file_id, _), entry| {
+            if file_id == id {
+                freed += chunk_size(&entry.data);
+                false
+            } else {
+                true
+            }
+        });
+        self.used -= freed;
+    }
+}
+
+fn chunk_size(data: &[f32]) -> usize {
+    std::mem::size_of_val(data)
+}
+
+lazy_static! {
+    static ref CACHE: Mutex<Cache> = Mutex::new(Cache {
+        budget: 0,
+        used: 0,
+        clock: 0,
+        chunks: HashMap::new(),
+    });
+}
+
+static NEXT_FILE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the maximum amount of memory (in bytes) used for decoded audio data.
+///
+/// `0` disables the cache (for files opened afterwards).
+/// When the budget is reduced, cached data is discarded immediately.
+pub fn set_memory_budget(bytes: usize) {
+    let mut cache = CACHE.lock().unwrap();
+    cache.budget = bytes;
+    cache.evict(bytes);
+}
+
+/// Maximum amount of memory (in bytes) used for decoded audio data.
+pub fn memory_budget() -> usize {
+    CACHE.lock().unwrap().budget
+}
+
+/// Amount of memory (in bytes) currently used for decoded audio data.
+pub fn memory_usage() -> usize {
+    CACHE.lock().unwrap().used
+}
+
+/// Whether files opened now should be wrapped in a `CachedFile`.
+pub(crate) fn is_enabled() -> bool {
+    memory_budget() > 0
+}
+
+/// Decodes `F` in chunks which are shared via the global cache.
+///
+/// Seeking is cheap, the underlying file is only seeked
+/// if a chunk is missing and the decoder isn't already at its start.
+pub struct CachedFile<F> {
+    file: F,
+    id: u64,
+    frames: u64,
+    /// Next frame to be returned from `next_block()`
+    position: u64,
+    /// Next frame the underlying file would deliver without seeking
+    decoder_position: u64,
+    current_block: Block,
+}
+
+impl<F> CachedFile<F>
+where
+    F: AudioFileBasics + AudioFileBlocks,
+{
+    pub fn new(file: F) -> CachedFile<F> {
+        let channels = file.channels();
+        CachedFile {
+            frames: file.frames(),
+            id: NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed),
+            position: 0,
+            decoder_position: 0,
+            current_block: Block {
+                frames: 0,
+                channels: (0..channels)
+                    .map(|_| Channel {
+                        data: Arc::new([]),
+                        index: 0,
+                        stride: channels as usize,
+                        remaining: 0,
+                    })
+                    .collect(),
+            },
+            file,
+        }
+    }
+
+    fn get_chunk(&mut self, idx: u64) -> Result<Arc<[f32]>, Box<dyn Error + Send + Sync>> {
+        let key = (self.id, idx);
+        // NB: The lock is not held while decoding
+        if let Some(data) = CACHE.lock().unwrap().get(key) {
+            return Ok(data);
+        }
+        let start = idx * CHUNK_FRAMES;
+        if self.decoder_position != start {
+            self.file.seek(start)?;
+            self.decoder_position = start;
+        }
+        let channels = self.file.channels() as usize;
+        let frames = CHUNK_FRAMES.min(self.frames - start) as usize;
+        let mut buffer = vec![0.0; frames * channels];
+        let mut decoded = 0;
+        while decoded < frames {
+            let n = self.file.copy_block_to_interleaved(
+                (frames - decoded) as u32,
+                &mut buffer[decoded * channels..],
+            )?;
+            if n == 0 {
+                break;
+            }
+            decoded += n as usize;
+        }
+        buffer.truncate(decoded * channels);
+        self.decoder_position += decoded as u64;
+        let data: Arc<[f32]> = buffer.into();
+        CACHE.lock().unwrap().insert(key, data.clone());
+        Ok(data)
+    }
+}
+
+impl<F> Drop for CachedFile<F> {
+    fn drop(&mut self) {
+        if let Ok(mut cache) = CACHE.lock() {
+            cache.remove_file(self.id);
+        }
+    }
+}
+
+impl<F> AudioFileBasics for CachedFile<F>
+where
+    F: AudioFileBasics + AudioFileBlocks,
+{
+    fn channels(&self) -> u32 {
+        self.current_block.channels.len() as u32
+    }
+
+    fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn samplerate(&self) -> u32 {
+        self.file.samplerate()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.position = frame.min(self.frames);
+        Ok(())
+    }
+}
+
+impl<F> AudioFileBlocks for CachedFile<F>
+where
+    F: AudioFileBasics + AudioFileBlocks,
+{
+    type Block = Block;
+
+    fn next_block(&mut self, max_frames: u32) -> Result<&mut Block, Box<dyn Error + Send + Sync>> {
+        self.current_block.frames = 0;
+        if self.position >= self.frames || max_frames == 0 {
+            return Ok(&mut self.current_block);
+        }
+        let data = self.get_chunk(self.position / CHUNK_FRAMES)?;
+        let channels = self.current_block.channels.len();
+        let offset = (self.position % CHUNK_FRAMES) as usize;
+        // NB: The chunk might be shorter than expected if the decoder ends early
+        let available = (data.len() / channels).saturating_sub(offset);
+        let frames = available.min(max_frames as usize);
+        for (i, channel) in self.current_block.channels.iter_mut().enumerate() {
+            channel.data = data.clone();
+            channel.index = offset * channels + i;
+            channel.remaining = frames;
+        }
+        self.current_block.frames = frames as u32;
+        self.position += frames as u64;
+        Ok(&mut self.current_block)
+    }
+}
+
+pub struct Block {
+    channels: Box<[Channel]>,
+    frames: u32,
+}
+
+impl super::Block for Block {
+    type Channel = Channel;
+
+    fn channel_iterators(&mut self) -> &mut [Channel] {
+        &mut self.channels
+    }
+
+    fn frames(&self) -> u32 {
+        self.frames
+    }
+}
+
+pub struct Channel {
+    /// Interleaved samples of the current chunk
+    data: Arc<[f32]>,
+    index: usize,
+    stride: usize,
+    remaining: usize,
+}
+
+impl Iterator for Channel {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.remaining == 0 {
+            None
+        } else {
+            let value = self.data[self.index];
+            self.remaining -= 1;
+            self.index += self.stride;
+            Some(value)
+        }
+    }
+}
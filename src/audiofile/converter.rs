@@ -1,10 +1,9 @@
 use std::error::Error;
 use std::ffi::CStr;
 use std::fmt;
+use std::os::raw::{c_int, c_long};
 
-use libc::{c_int, c_long};
-
-use super::{AudioFileBasics, AudioFileBlocks};
+use super::{AudioFileBasics, AudioFileBlocks, Block as _};
 
 // http://www.mega-nerd.com/SRC/api_misc.html#Converters
 pub use libsamplerate_sys::SRC_LINEAR;
@@ -35,7 +34,9 @@ impl<F> Converter<F>
 where
     F: AudioFileBasics + AudioFileBlocks,
 {
-    pub fn new(file: F, samplerate: u32) -> Result<Converter<F>, LibSamplerateError> {
+    /// With a `speed` other than `1.0`, `file` is played back faster or slower
+    /// (the pitch changes accordingly).
+    pub fn new(file: F, samplerate: u32, speed: f64) -> Result<Converter<F>, LibSamplerateError> {
         // TODO: specify type of converter
         let converter_type = SRC_SINC_BEST_QUALITY;
         // TODO: specify buffer size?
@@ -69,7 +70,7 @@ where
                 input_frames_used: 0,
                 output_frames_gen: 0,
                 end_of_input: 0,
-                src_ratio: f64::from(samplerate) / f64::from(file.samplerate()),
+                src_ratio: f64::from(samplerate) / (f64::from(file.samplerate()) * speed),
             },
             file,
             state,
@@ -182,10 +183,12 @@ where
         (self.file.frames() as f64 * self.data.src_ratio) as u64
     }
 
+    /// The input file is seeked to the frame just before `frame`,
+    /// the remaining output frames are discarded.
+    /// When upsampling, the result can still be off by a fraction of a frame.
     fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
-        // TODO: is this correct? what about rounding errors?
-        self.file
-            .seek((frame as f64 / self.data.src_ratio) as u64)?;
+        let input_frame = (frame as f64 / self.data.src_ratio) as u64;
+        self.file.seek(input_frame)?;
         // http://www.mega-nerd.com/SRC/api_full.html#Reset
         let result = unsafe { libsamplerate_sys::src_reset(self.state) };
         if result != 0 {
@@ -193,6 +196,15 @@ where
         }
         self.data.input_frames = 0;
         self.data.end_of_input = 0;
+        // NB: Without this, the output would start up to `src_ratio` frames too early
+        let mut skip = (frame as f64 - input_frame as f64 * self.data.src_ratio).round() as u32;
+        while skip > 0 {
+            let frames = self.next_block(skip)?.frames();
+            if frames == 0 {
+                break;
+            }
+            skip -= frames;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,156 @@
+use std::error::Error;
+
+use crate::decoder::AudioDecoder;
+
+/// Maximum number of frames requested from a decoder at once
+const BUFFER_SIZE: u32 = 2048;
+
+/// Object-safe subset of `AudioDecoder`
+pub trait Decoded: Send + Sync {
+    fn channels(&self) -> u32;
+    fn frames(&self) -> u64;
+    fn samplerate(&self) -> u32;
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>>;
+    fn fill_channels(
+        &mut self,
+        channels: &mut [Box<[f32]>],
+        frames: u32,
+    ) -> Result<u32, Box<dyn Error + Send + Sync>>;
+}
+
+impl<D: AudioDecoder> Decoded for D {
+    fn channels(&self) -> u32 {
+        AudioDecoder::channels(self)
+    }
+
+    fn frames(&self) -> u64 {
+        AudioDecoder::frames(self)
+    }
+
+    fn samplerate(&self) -> u32 {
+        AudioDecoder::samplerate(self)
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        AudioDecoder::seek(self, frame)
+    }
+
+    fn fill_channels(
+        &mut self,
+        channels: &mut [Box<[f32]>],
+        frames: u32,
+    ) -> Result<u32, Box<dyn Error + Send + Sync>> {
+        AudioDecoder::fill_channels(self, channels, frames)
+    }
+}
+
+/// Adapter between `AudioDecoder` and the built-in file types.
+pub struct File {
+    decoder: Box<dyn Decoded>,
+    current_block: FileBlock,
+}
+
+impl File {
+    pub fn new(decoder: Box<dyn Decoded>) -> File {
+        let channels = decoder.channels();
+        File {
+            decoder,
+            current_block: FileBlock {
+                data: (0..channels)
+                    .map(|_| vec![0.0f32; BUFFER_SIZE as usize].into())
+                    .collect(),
+                channels: (0..channels)
+                    .map(|_| Channel {
+                        ptr: std::ptr::null(),
+                        len: 0,
+                    })
+                    .collect(),
+                frames: 0,
+            },
+        }
+    }
+}
+
+// NB: The raw pointers in `Channel` only point into `FileBlock::data`
+unsafe impl Send for File {}
+
+unsafe impl Sync for File {}
+
+impl super::AudioFileBasics for File {
+    fn channels(&self) -> u32 {
+        self.decoder.channels()
+    }
+
+    fn frames(&self) -> u64 {
+        self.decoder.frames()
+    }
+
+    fn samplerate(&self) -> u32 {
+        self.decoder.samplerate()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.decoder.seek(frame)
+    }
+}
+
+impl super::AudioFileBlocks for File {
+    type Block = FileBlock;
+
+    fn next_block(
+        &mut self,
+        max_frames: u32,
+    ) -> Result<&mut FileBlock, Box<dyn Error + Send + Sync>> {
+        let frames = max_frames.min(BUFFER_SIZE);
+        let frames = self
+            .decoder
+            .fill_channels(&mut self.current_block.data, frames)?;
+        if frames > max_frames.min(BUFFER_SIZE) {
+            return Err("Decoder returned too many frames".into());
+        }
+        self.current_block.frames = frames;
+        Ok(&mut self.current_block)
+    }
+}
+
+pub struct FileBlock {
+    data: Box<[Box<[f32]>]>,
+    channels: Box<[Channel]>,
+    frames: u32,
+}
+
+impl super::Block for FileBlock {
+    type Channel = Channel;
+
+    fn channel_iterators(&mut self) -> &mut [Channel] {
+        for (channel, data) in self.channels.iter_mut().zip(self.data.iter()) {
+            channel.ptr = data.as_ptr();
+            channel.len = self.frames;
+        }
+        &mut self.channels
+    }
+
+    fn frames(&self) -> u32 {
+        self.frames
+    }
+}
+
+pub struct Channel {
+    ptr: *const f32,
+    len: u32,
+}
+
+impl Iterator for Channel {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.len == 0 {
+            None
+        } else {
+            let value = unsafe { *self.ptr };
+            self.len -= 1;
+            self.ptr = unsafe { self.ptr.add(1) };
+            Some(value)
+        }
+    }
+}
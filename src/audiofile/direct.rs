@@ -0,0 +1,125 @@
+//! Unbuffered reading of (huge) files, bypassing the OS page cache.
+//!
+//! On Linux, `O_DIRECT` is used (which requires aligned buffers),
+//! on macOS, `F_NOCACHE` is used.
+//! On other systems (or if `O_DIRECT` is not supported by the file system),
+//! the file is read normally (but still in large chunks).
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::loader::resolve_seek;
+
+/// Alignment of file positions and memory addresses required by `O_DIRECT`
+const ALIGNMENT: usize = 4096;
+
+/// Number of bytes read at once (must be a multiple of `ALIGNMENT`)
+const BUFFER_SIZE: usize = 1 << 20;
+
+pub struct DirectReader {
+    file: fs::File,
+    file_len: u64,
+    /// Contains an aligned region of `BUFFER_SIZE` bytes starting at `offset`
+    buffer: Box<[u8]>,
+    offset: usize,
+    /// File position of the data in the buffer
+    buffer_pos: u64,
+    /// Number of valid bytes in the buffer
+    buffer_len: usize,
+    /// Current read position
+    position: u64,
+}
+
+impl DirectReader {
+    pub fn open(path: &Path) -> io::Result<DirectReader> {
+        let file = open_uncached(path)?;
+        let file_len = file.metadata()?.len();
+        let buffer = vec![0u8; BUFFER_SIZE + ALIGNMENT].into_boxed_slice();
+        let offset = buffer.as_ptr().align_offset(ALIGNMENT);
+        Ok(DirectReader {
+            file,
+            file_len,
+            buffer,
+            offset,
+            buffer_pos: 0,
+            buffer_len: 0,
+            position: 0,
+        })
+    }
+
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        // NB: Reads always start at aligned file positions
+        let aligned_pos = self.position - self.position % ALIGNMENT as u64;
+        self.file.seek(SeekFrom::Start(aligned_pos))?;
+        let buffer = &mut self.buffer[self.offset..self.offset + BUFFER_SIZE];
+        self.buffer_len = loop {
+            match self.file.read(buffer) {
+                Ok(bytes) => break bytes,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        };
+        self.buffer_pos = aligned_pos;
+        Ok(())
+    }
+}
+
+impl Read for DirectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position < self.buffer_pos
+            || self.position >= self.buffer_pos + self.buffer_len as u64
+        {
+            if self.position >= self.file_len {
+                return Ok(0);
+            }
+            self.fill_buffer()?;
+        }
+        let start = (self.position - self.buffer_pos) as usize;
+        let available = &self.buffer[self.offset + start..self.offset + self.buffer_len];
+        let bytes = available.len().min(buf.len());
+        buf[..bytes].copy_from_slice(&available[..bytes]);
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl Seek for DirectReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // NB: The actual reading position is only changed when the buffer is re-filled
+        self.position = resolve_seek(pos, self.position, self.file_len)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_uncached(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    match fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+    {
+        Ok(file) => Ok(file),
+        // NB: Some file systems (e.g. tmpfs) don't support O_DIRECT
+        Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => fs::File::open(path),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_uncached(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = fs::File::open(path)?;
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn open_uncached(path: &Path) -> io::Result<fs::File> {
+    fs::File::open(path)
+}
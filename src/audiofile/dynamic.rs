@@ -1,13 +1,23 @@
 use std::error::Error;
 use std::fmt;
 use std::fs;
-use std::io;
+use std::io::{self, Read, Seek};
 use std::num::NonZeroU64;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use crate::decoder;
 use crate::error::{FromSourceAndContext, ResultExt};
+use crate::loader::OpenCallback;
 
+use super::cache::{self, CachedFile};
+#[cfg(feature = "resampling")]
 use super::converter;
+use super::direct::DirectReader;
+use super::media;
+use super::network::NetworkReader;
+use super::signal::{self, TestSignal};
+#[cfg(feature = "vorbis")]
 use super::vorbis;
 use super::wav;
 use super::{AudioFileBasics, AudioFileBlocks, RepeatedAudioFile};
@@ -16,7 +26,7 @@ use super::{AudioFileBasics, AudioFileBlocks, RepeatedAudioFile};
 pub trait AudioFile: AudioFileBasics {
     fn fill_channels(
         &mut self,
-        channel_map: &[Option<usize>],
+        channel_map: &[Box<[usize]>],
         blocksize: u32,
         offset: u32,
         channels: &mut [Box<[f32]>],
@@ -31,7 +41,7 @@ where
     // This is a non-generic version of AudioFileBlocks::fill_channels():
     fn fill_channels(
         &mut self,
-        channel_map: &[Option<usize>],
+        channel_map: &[Box<[usize]>],
         blocksize: u32,
         offset: u32,
         channels: &mut [Box<[f32]>],
@@ -40,6 +50,42 @@ where
     }
 }
 
+#[cfg(feature = "vorbis")]
+type VorbisError = vorbis::OpenError;
+
+/// Reported instead of the Vorbis decoding error if the `vorbis` feature is disabled
+#[cfg(not(feature = "vorbis"))]
+#[derive(Debug)]
+struct VorbisError;
+
+#[cfg(not(feature = "vorbis"))]
+impl fmt::Display for VorbisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Not available (the \"vorbis\" feature is disabled)")
+    }
+}
+
+#[cfg(feature = "resampling")]
+type ResampleError = converter::LibSamplerateError;
+
+/// Returned for files which would need resampling if the `resampling` feature is disabled
+#[cfg(not(feature = "resampling"))]
+#[derive(Debug)]
+struct ResampleError;
+
+#[cfg(not(feature = "resampling"))]
+impl fmt::Display for ResampleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Resampling is not available (the \"resampling\" feature is disabled)"
+        )
+    }
+}
+
+#[cfg(not(feature = "resampling"))]
+impl Error for ResampleError {}
+
 #[derive(Debug)]
 pub struct LoadError {
     path: PathBuf,
@@ -50,10 +96,13 @@ pub struct LoadError {
 enum LoadErrorKind {
     Open(io::Error),
     Decode {
-        vorbis_error: vorbis::OpenError,
+        vorbis_error: VorbisError,
         wav_error: hound::Error,
+        /// Errors of registered decoders (with their names)
+        other_errors: Vec<(&'static str, Box<dyn Error + Send + Sync>)>,
     },
-    Resample(converter::LibSamplerateError),
+    Resample(ResampleError),
+    Signal(signal::ParseSignalError),
 }
 
 impl fmt::Display for LoadError {
@@ -64,12 +113,20 @@ impl fmt::Display for LoadError {
             Decode {
                 vorbis_error,
                 wav_error,
-            } => write!(
-                f,
-                "Error decoding {:?}:\nTrying Vorbis: {}\nTrying WAV: {}",
-                self.path, vorbis_error, wav_error
-            ),
+                other_errors,
+            } => {
+                write!(
+                    f,
+                    "Error decoding {:?}:\nTrying Vorbis: {}\nTrying WAV: {}",
+                    self.path, vorbis_error, wav_error
+                )?;
+                for (name, e) in other_errors {
+                    write!(f, "\nTrying {}: {}", name, e)?;
+                }
+                Ok(())
+            }
             Resample(e) => write!(f, "Error parsing {:?}: {}", self.path, e),
+            Signal(e) => write!(f, "Invalid test signal {:?}: {}", self.path, e),
         }
     }
 }
@@ -81,6 +138,7 @@ impl Error for LoadError {
             Open(ref e) => Some(e),
             Decode { .. } => None, // We have multiple underlying errors, TODO: ?
             Resample(ref e) => Some(e),
+            Signal(ref e) => Some(e),
         }
     }
 }
@@ -94,8 +152,17 @@ impl FromSourceAndContext<io::Error, &Path> for LoadError {
     }
 }
 
-impl FromSourceAndContext<converter::LibSamplerateError, &Path> for LoadError {
-    fn from_source_and_context(source: converter::LibSamplerateError, context: &Path) -> LoadError {
+impl FromSourceAndContext<signal::ParseSignalError, &Path> for LoadError {
+    fn from_source_and_context(source: signal::ParseSignalError, context: &Path) -> LoadError {
+        LoadError {
+            path: context.into(),
+            source: LoadErrorKind::Signal(source),
+        }
+    }
+}
+
+impl FromSourceAndContext<ResampleError, &Path> for LoadError {
+    fn from_source_and_context(source: ResampleError, context: &Path) -> LoadError {
         LoadError {
             path: context.into(),
             source: LoadErrorKind::Resample(source),
@@ -103,16 +170,52 @@ impl FromSourceAndContext<converter::LibSamplerateError, &Path> for LoadError {
     }
 }
 
+/// WAV files with at least `direct_io_threshold` bytes are read without
+/// using the OS page cache (see `direct` module).
+///
+/// With `network_storage`, all files are read in large chunks and
+/// failed reads are retried (see `network` module),
+/// in this case `direct_io_threshold` is ignored.
+///
+/// If `open` is given, it is used instead of the file system
+/// (and all other options are ignored).
+///
+/// Test signals (see `signal` module) are generated at `samplerate`.
+///
+/// With a `speed` other than `1.0`, the file is resampled so that it is
+/// played back faster (and with higher pitch) or slower (and with lower pitch).
+/// The number of frames changes accordingly.
 pub fn load_audio_file<P>(
     path: P,
     samplerate: u32,
+    speed: f64,
     iterations: NonZeroU64,
+    direct_io_threshold: Option<u64>,
+    network_storage: bool,
+    open: Option<&OpenCallback>,
 ) -> Result<Box<dyn AudioFile + Send + Sync>, LoadError>
 where
     P: AsRef<Path>,
 {
     let path = path.as_ref();
 
+    if let Some(location) = path.to_str().filter(|s| signal::is_location(s)) {
+        let signal = TestSignal::from_str(location).context(path)?;
+        let file = signal::File::new(signal, samplerate).context(path)?;
+        return repeat_and_convert(file, iterations, samplerate, speed).context(path);
+    }
+
+    if let Some(source) = path.to_str().and_then(media::find) {
+        let location = path.to_str().unwrap();
+        return load_from_reader(path, samplerate, speed, iterations, || {
+            source.open(location)
+        });
+    }
+
+    if let Some(open) = open {
+        return load_from_reader(path, samplerate, speed, iterations, || open(path));
+    }
+
     // TODO: canonicalize for nicer error messages?
     //path = path.canonicalize();
 
@@ -128,54 +231,168 @@ where
         });
     }
 
-    let file = fs::File::open(path).context(path)?;
-    let vorbis_error = match vorbis::File::new(file) {
+    if network_storage {
+        return load_from_reader(path, samplerate, speed, iterations, || {
+            NetworkReader::open(path)
+        });
+    }
+
+    #[cfg(feature = "vorbis")]
+    let vorbis_error = match vorbis::File::new(fs::File::open(path).context(path)?) {
         Ok(file) => {
-            return Ok(repeat_and_convert(file, iterations, samplerate).context(path)?);
+            return Ok(cache_and_convert(file, iterations, samplerate, speed).context(path)?);
         }
         Err(e) => e,
     };
+    #[cfg(not(feature = "vorbis"))]
+    let vorbis_error = VorbisError;
 
     let file = fs::File::open(path).context(path)?;
-    let reader = io::BufReader::new(file);
-    let wav_error = match wav::File::new(reader) {
-        Ok(file) => {
-            return Ok(repeat_and_convert(file, iterations, samplerate).context(path)?);
+    let direct_io = match direct_io_threshold {
+        Some(threshold) => file.metadata().context(path)?.len() >= threshold,
+        None => false,
+    };
+    let wav_error = if direct_io {
+        let reader = DirectReader::open(path).context(path)?;
+        match wav::File::new(reader) {
+            Ok(file) => {
+                return Ok(repeat_and_convert(file, iterations, samplerate, speed).context(path)?);
+            }
+            Err(e) => e,
+        }
+    } else {
+        let reader = io::BufReader::new(file);
+        match wav::File::new(reader) {
+            Ok(file) => {
+                return Ok(repeat_and_convert(file, iterations, samplerate, speed).context(path)?);
+            }
+            Err(e) => e,
         }
-        Err(e) => e,
     };
 
     // TODO: try more file types (FLAC, mp3, ...)
 
+    try_registered_decoders(path, samplerate, speed, iterations, vorbis_error, wav_error)
+}
+
+/// `open_reader` is called once for each tried file format.
+fn load_from_reader<R, F>(
+    path: &Path,
+    samplerate: u32,
+    speed: f64,
+    iterations: NonZeroU64,
+    mut open_reader: F,
+) -> Result<Box<dyn AudioFile + Send + Sync>, LoadError>
+where
+    R: Read + Seek + Send + Sync + 'static,
+    F: FnMut() -> io::Result<R>,
+{
+    #[cfg(feature = "vorbis")]
+    let vorbis_error = match vorbis::File::new(open_reader().context(path)?) {
+        Ok(file) => return cache_and_convert(file, iterations, samplerate, speed).context(path),
+        Err(e) => e,
+    };
+    #[cfg(not(feature = "vorbis"))]
+    let vorbis_error = VorbisError;
+    let reader = open_reader().context(path)?;
+    let wav_error = match wav::File::new(reader) {
+        Ok(file) => return repeat_and_convert(file, iterations, samplerate, speed).context(path),
+        Err(e) => e,
+    };
+    try_registered_decoders(path, samplerate, speed, iterations, vorbis_error, wav_error)
+}
+
+fn try_registered_decoders(
+    path: &Path,
+    samplerate: u32,
+    speed: f64,
+    iterations: NonZeroU64,
+    vorbis_error: VorbisError,
+    wav_error: hound::Error,
+) -> Result<Box<dyn AudioFile + Send + Sync>, LoadError> {
+    let other_errors = match decoder::open_registered(path) {
+        Ok(Some(file)) => {
+            return Ok(cache_and_convert(file, iterations, samplerate, speed).context(path)?);
+        }
+        Ok(None) => Vec::new(),
+        Err(errors) => errors,
+    };
+
     Err(LoadError {
         path: path.into(),
         source: LoadErrorKind::Decode {
             vorbis_error,
             wav_error,
+            other_errors,
         },
     })
 }
 
+/// Compressed files are decoded via the global cache (if enabled),
+/// see `cache` module.
+fn cache_and_convert<F>(
+    file: F,
+    iterations: NonZeroU64,
+    samplerate: u32,
+    speed: f64,
+) -> Result<Box<dyn AudioFile + Send + Sync>, ResampleError>
+where
+    F: AudioFileBasics + AudioFileBlocks + Send + Sync + 'static,
+{
+    if cache::is_enabled() {
+        repeat_and_convert(CachedFile::new(file), iterations, samplerate, speed)
+    } else {
+        repeat_and_convert(file, iterations, samplerate, speed)
+    }
+}
+
 fn repeat_and_convert<F>(
     file: F,
     iterations: NonZeroU64,
     samplerate: u32,
-) -> Result<Box<dyn AudioFile + Send + Sync>, converter::LibSamplerateError>
+    speed: f64,
+) -> Result<Box<dyn AudioFile + Send + Sync>, ResampleError>
 where
     F: AudioFileBasics + AudioFileBlocks + Send + Sync + 'static,
 {
     if iterations.get() == 1 {
-        if file.samplerate() == samplerate {
+        if file.samplerate() == samplerate && speed == 1.0 {
             Ok(Box::new(file))
         } else {
-            Ok(Box::new(converter::Converter::new(file, samplerate)?))
+            convert(file, samplerate, speed)
         }
     } else {
         let file = RepeatedAudioFile::new(file, iterations);
-        if file.samplerate() == samplerate {
+        if file.samplerate() == samplerate && speed == 1.0 {
             Ok(Box::new(file))
         } else {
-            Ok(Box::new(converter::Converter::new(file, samplerate)?))
+            convert(file, samplerate, speed)
         }
     }
 }
+
+#[cfg(feature = "resampling")]
+fn convert<F>(
+    file: F,
+    samplerate: u32,
+    speed: f64,
+) -> Result<Box<dyn AudioFile + Send + Sync>, ResampleError>
+where
+    F: AudioFileBasics + AudioFileBlocks + Send + Sync + 'static,
+{
+    Ok(Box::new(converter::Converter::new(
+        file, samplerate, speed,
+    )?))
+}
+
+#[cfg(not(feature = "resampling"))]
+fn convert<F>(
+    _file: F,
+    _samplerate: u32,
+    _speed: f64,
+) -> Result<Box<dyn AudioFile + Send + Sync>, ResampleError>
+where
+    F: AudioFileBasics + AudioFileBlocks + Send + Sync + 'static,
+{
+    Err(ResampleError)
+}
@@ -0,0 +1,91 @@
+use std::error::Error;
+
+use super::dynamic::AudioFile;
+use super::AudioFileBasics;
+
+/// An audio file with a (linear) fade-in at the beginning and/or
+/// fade-out at the end (given by the `fade-in`/`fade-out` attributes
+/// of a `<clip>`).
+///
+/// The fades are applied when reading the data,
+/// independent of fades due to starting and stopping playback.
+pub struct File {
+    file: Box<dyn AudioFile + Send + Sync>,
+    /// Length of the fade-in (in frames)
+    fade_in: u64,
+    /// Length of the fade-out (in frames)
+    fade_out: u64,
+    position: u64,
+}
+
+impl File {
+    /// `fade_in + fade_out` must not be larger than the number of frames in `file`.
+    pub fn new(file: Box<dyn AudioFile + Send + Sync>, fade_in: u64, fade_out: u64) -> File {
+        assert!(fade_in + fade_out <= file.frames());
+        File {
+            file,
+            fade_in,
+            fade_out,
+            position: 0,
+        }
+    }
+
+    fn gain(&self, frame: u64) -> f32 {
+        let frames = self.file.frames();
+        if frame < self.fade_in {
+            // NB: The first frame is not completely silent
+            (frame + 1) as f32 / (self.fade_in + 1) as f32
+        } else if frame + self.fade_out >= frames {
+            (frames - frame) as f32 / (self.fade_out + 1) as f32
+        } else {
+            1.0
+        }
+    }
+}
+
+impl AudioFileBasics for File {
+    fn channels(&self) -> u32 {
+        self.file.channels()
+    }
+
+    fn frames(&self) -> u64 {
+        self.file.frames()
+    }
+
+    fn samplerate(&self) -> u32 {
+        self.file.samplerate()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.file.seek(frame)?;
+        self.position = frame.min(self.file.frames());
+        Ok(())
+    }
+}
+
+impl AudioFile for File {
+    fn fill_channels(
+        &mut self,
+        channel_map: &[Box<[usize]>],
+        blocksize: u32,
+        offset: u32,
+        channels: &mut [Box<[f32]>],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.file
+            .fill_channels(channel_map, blocksize, offset, channels)?;
+        let begin = self.position;
+        let frames = (self.file.frames() - begin).min(u64::from(blocksize - offset)) as usize;
+        self.position += frames as u64;
+        let fades_out_at = self.file.frames() - self.fade_out;
+        if begin >= self.fade_in && begin + (frames as u64) <= fades_out_at {
+            return Ok(());
+        }
+        for &target in channel_map.iter().flat_map(|targets| targets.iter()) {
+            let data = &mut channels[target][offset as usize..][..frames];
+            for (i, value) in data.iter_mut().enumerate() {
+                *value *= self.gain(begin + i as u64);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use std::error::Error;
+
+use super::dynamic::AudioFile;
+use super::AudioFileBasics;
+
+/// Range (in seconds) before the end of the file which is searched for the loop point
+const LOOP_SEARCH_WINDOW: f32 = 0.1;
+
+/// Maximum duration (in seconds) of the end of the crossfade which is used
+/// for comparing the signals (this limits the cost of the search)
+const CORRELATION_WINDOW: f32 = 0.02;
+
+/// A repeated audio file with a crossfade between the end of each iteration
+/// and the beginning of the next one (given by the `loop-crossfade` attribute
+/// of a `<clip>`).
+///
+/// The end of the loop is searched near the end of the file,
+/// where the signal is most similar to the beginning of the file
+/// (based on the normalized cross-correlation of the end of the crossfade).
+/// During the last `crossfade` frames before the loop end, the beginning
+/// of the file is faded in, the next iteration continues after that.
+/// Therefore, all but the first iteration are shorter than the file
+/// (and the last one plays until the actual end of the file).
+pub struct File {
+    file: Box<dyn AudioFile + Send + Sync>,
+    iterations: u64,
+    /// Length of the crossfade (in frames)
+    crossfade: u64,
+    /// End of the loop (within the file), the crossfade ends here
+    loop_end: u64,
+    /// The first `crossfade` frames of each channel of the file
+    head: Box<[Box<[f32]>]>,
+    position: u64,
+    /// `None` if the file has to be seeked before reading
+    file_position: Option<u64>,
+}
+
+impl File {
+    /// `iterations` must be at least 2.
+    ///
+    /// The beginning and the end of the file are read
+    /// to find the best loop point.
+    pub fn new(
+        mut file: Box<dyn AudioFile + Send + Sync>,
+        iterations: u64,
+        crossfade: u64,
+    ) -> Result<File, Box<dyn Error + Send + Sync>> {
+        assert!(iterations >= 2);
+        let frames = file.frames();
+        let search = (LOOP_SEARCH_WINDOW * file.samplerate() as f32) as u64;
+        if crossfade == 0 || frames < search + 2 * crossfade {
+            return Err("File is too short for the loop crossfade".into());
+        }
+        let mut head = channel_buffers(file.channels(), crossfade);
+        read_range(&mut *file, 0, &mut head)?;
+        // NB: Only the end of the crossfade is compared, where the beginning
+        //     of the file dominates (and which is followed by the rest of the file)
+        let window = crossfade.min(((CORRELATION_WINDOW * file.samplerate() as f32) as u64).max(1));
+        let skip = (crossfade - window) as usize;
+        let tail_begin = frames - search - window;
+        let mut tail = channel_buffers(file.channels(), search + window);
+        read_range(&mut *file, tail_begin, &mut tail)?;
+        let head_energy: f32 = head
+            .iter()
+            .flat_map(|c| c[skip..].iter())
+            .map(|x| x * x)
+            .sum();
+        let mut best = (f32::NEG_INFINITY, frames);
+        // NB: Later loop points are preferred if the correlation is the same
+        for loop_end in (frames - search..=frames).rev() {
+            let start = (loop_end - window - tail_begin) as usize;
+            let mut product = 0.0f32;
+            let mut energy = 0.0f32;
+            for (h, t) in head.iter().zip(tail.iter()) {
+                for (a, b) in h[skip..].iter().zip(&t[start..start + window as usize]) {
+                    product += a * b;
+                    energy += b * b;
+                }
+            }
+            let denominator = (head_energy * energy).sqrt();
+            let correlation = if denominator > 0.0 {
+                product / denominator
+            } else {
+                0.0
+            };
+            if correlation > best.0 {
+                best = (correlation, loop_end);
+            }
+        }
+        Ok(File {
+            file,
+            iterations,
+            crossfade,
+            loop_end: best.1,
+            head,
+            position: 0,
+            file_position: None,
+        })
+    }
+
+    /// End of the loop (within the file), see `File`.
+    pub fn loop_end(&self) -> u64 {
+        self.loop_end
+    }
+
+    /// File position at `position` and end of the current segment,
+    /// `true` if the segment ends with a crossfade.
+    fn locate(&self, position: u64) -> (u64, u64, bool) {
+        if position < self.loop_end {
+            return (position, self.loop_end, true);
+        }
+        let loop_length = self.loop_end - self.crossfade;
+        let offset = position - self.loop_end;
+        if offset / loop_length < self.iterations - 2 {
+            (self.crossfade + offset % loop_length, self.loop_end, true)
+        } else {
+            let offset = offset - (self.iterations - 2) * loop_length;
+            (self.crossfade + offset, self.file.frames(), false)
+        }
+    }
+}
+
+fn channel_buffers(channels: u32, frames: u64) -> Box<[Box<[f32]>]> {
+    (0..channels)
+        .map(|_| vec![0.0; frames as usize].into())
+        .collect()
+}
+
+/// Fills `data` with all channels, starting at `begin`.
+fn read_range(
+    file: &mut (dyn AudioFile + Send + Sync),
+    begin: u64,
+    data: &mut [Box<[f32]>],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let channel_map: Vec<Box<[usize]>> = (0..data.len()).map(|c| vec![c].into()).collect();
+    let frames = data.first().map_or(0, |c| c.len());
+    file.seek(begin)?;
+    file.fill_channels(&channel_map, frames as u32, 0, data)
+}
+
+impl AudioFileBasics for File {
+    fn channels(&self) -> u32 {
+        self.file.channels()
+    }
+
+    fn frames(&self) -> u64 {
+        let loop_length = self.loop_end - self.crossfade;
+        self.loop_end + (self.iterations - 2) * loop_length + (self.file.frames() - self.crossfade)
+    }
+
+    fn samplerate(&self) -> u32 {
+        self.file.samplerate()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.position = frame.min(self.frames());
+        self.file_position = None;
+        Ok(())
+    }
+}
+
+impl AudioFile for File {
+    fn fill_channels(
+        &mut self,
+        channel_map: &[Box<[usize]>],
+        blocksize: u32,
+        offset: u32,
+        channels: &mut [Box<[f32]>],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut offset = offset;
+        let total = self.frames();
+        while offset < blocksize && self.position < total {
+            let (file_position, end, crossfade) = self.locate(self.position);
+            let fade_begin = self.loop_end - self.crossfade;
+            let chunk_end = if crossfade && file_position < fade_begin {
+                fade_begin
+            } else {
+                end
+            };
+            let frames = (chunk_end - file_position).min(u64::from(blocksize - offset)) as u32;
+            if self.file_position != Some(file_position) {
+                self.file.seek(file_position)?;
+            }
+            self.file
+                .fill_channels(channel_map, offset + frames, offset, channels)?;
+            if crossfade && file_position >= fade_begin {
+                for (head, targets) in self.head.iter().zip(channel_map.iter()) {
+                    for &target in targets.iter() {
+                        let data = &mut channels[target][offset as usize..][..frames as usize];
+                        for (i, value) in data.iter_mut().enumerate() {
+                            let index = file_position - fade_begin + i as u64;
+                            // NB: Neither signal is completely silent
+                            let gain_in = (index + 1) as f32 / (self.crossfade + 1) as f32;
+                            *value = *value * (1.0 - gain_in) + head[index as usize] * gain_in;
+                        }
+                    }
+                }
+            }
+            self.position += u64::from(frames);
+            self.file_position = Some(file_position + u64::from(frames));
+            offset += frames;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::signal::{self, TestSignal, Waveform};
+    use super::*;
+
+    fn file(waveform: Waveform) -> Box<dyn AudioFile + Send + Sync> {
+        let signal = TestSignal::new(waveform, 1.0);
+        Box::new(signal::File::new(signal, 44100).unwrap())
+    }
+
+    #[test]
+    fn periodic_signals() {
+        // Period: 100 frames
+        let sine = || file(Waveform::Sine { frequency: 441.0 });
+        // Period: 147 frames
+        let dirac = || {
+            file(Waveform::DiracTrain {
+                interval: 147.0 / 44100.0,
+            })
+        };
+        // Shorter and longer than the correlation window
+        for &crossfade in &[300, 1000, 4000] {
+            let looped = File::new(sine(), 2, crossfade).unwrap();
+            assert_eq!((looped.loop_end() - crossfade) % 100, 0, "{}", crossfade);
+            let looped = File::new(dirac(), 2, crossfade).unwrap();
+            assert_eq!((looped.loop_end() - crossfade) % 147, 0, "{}", crossfade);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Audio files which are not on the local file system.
+//!
+//! If the `file` attribute of a `<clip>` is handled by one of the registered
+//! [`MediaSource`](trait.MediaSource.html)s (e.g. `https://...`), the file
+//! is read from there (instead of being joined with the scene directory).
+//!
+//! With the `http` feature, [`HttpSource`](struct.HttpSource.html) is
+//! registered by default, which streams files using HTTP range requests.
+
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use crate::loader::ByteReader;
+
+/// Provides the contents of audio files given by location strings (e.g. URLs).
+pub trait MediaSource: Send + Sync {
+    /// Returns `true` if `location` (the `file` attribute of a `<clip>`)
+    /// should be opened with this source.
+    fn handles(&self, location: &str) -> bool;
+
+    /// This is called once for each tried file format.
+    fn open(&self, location: &str) -> io::Result<Box<dyn ByteReader>>;
+}
+
+lazy_static! {
+    static ref MEDIA_SOURCES: RwLock<Vec<Arc<dyn MediaSource>>> = RwLock::new(default_sources());
+}
+
+#[allow(clippy::vec_init_then_push)]
+fn default_sources() -> Vec<Arc<dyn MediaSource>> {
+    let mut sources: Vec<Arc<dyn MediaSource>> = Vec::new();
+    #[cfg(feature = "http")]
+    sources.push(Arc::new(HttpSource));
+    sources
+}
+
+/// Makes `source` available for all scenes loaded afterwards.
+///
+/// Sources are tried in the order of registration.
+pub fn register_media_source<S: MediaSource + 'static>(source: S) {
+    MEDIA_SOURCES.write().unwrap().push(Arc::new(source));
+}
+
+/// Returns the first registered source which handles `location`.
+pub(crate) fn find(location: &str) -> Option<Arc<dyn MediaSource>> {
+    MEDIA_SOURCES
+        .read()
+        .unwrap()
+        .iter()
+        .find(|source| source.handles(location))
+        .cloned()
+}
+
+#[cfg(feature = "http")]
+pub use self::http::HttpSource;
+
+#[cfg(feature = "http")]
+mod http {
+    use std::io::{self, Read, Seek, SeekFrom};
+
+    use reqwest::blocking::Client;
+    use reqwest::header::{CONTENT_RANGE, RANGE};
+    use reqwest::StatusCode;
+
+    use super::MediaSource;
+    use crate::loader::{resolve_seek, ByteReader};
+
+    /// Number of bytes requested from the server at once
+    const CHUNK_SIZE: u64 = 1 << 20;
+
+    lazy_static! {
+        // NB: The client is only created when it's actually used
+        static ref CLIENT: Client = Client::new();
+    }
+
+    /// Files given by `http://` and `https://` URLs.
+    ///
+    /// The server has to support range requests.
+    pub struct HttpSource;
+
+    impl MediaSource for HttpSource {
+        fn handles(&self, location: &str) -> bool {
+            location.starts_with("http://") || location.starts_with("https://")
+        }
+
+        fn open(&self, location: &str) -> io::Result<Box<dyn ByteReader>> {
+            Ok(Box::new(HttpReader::open(location)?))
+        }
+    }
+
+    fn to_io_error(e: reqwest::Error) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+
+    struct HttpReader {
+        url: String,
+        file_len: u64,
+        buffer: Vec<u8>,
+        /// File position of the data in the buffer
+        buffer_pos: u64,
+        /// Current read position
+        position: u64,
+    }
+
+    impl HttpReader {
+        fn open(url: &str) -> io::Result<HttpReader> {
+            let mut reader = HttpReader {
+                url: url.into(),
+                file_len: 0,
+                buffer: Vec::new(),
+                buffer_pos: 0,
+                position: 0,
+            };
+            reader.file_len = reader.fetch(0)?;
+            Ok(reader)
+        }
+
+        /// Fills the buffer starting at `position`, returns the size of the file.
+        fn fetch(&mut self, position: u64) -> io::Result<u64> {
+            let response = CLIENT
+                .get(&self.url)
+                .header(
+                    RANGE,
+                    format!("bytes={}-{}", position, position + CHUNK_SIZE - 1),
+                )
+                .send()
+                .and_then(|r| r.error_for_status())
+                .map_err(to_io_error)?;
+            if response.status() != StatusCode::PARTIAL_CONTENT {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Server doesn't support range requests: {}", self.url),
+                ));
+            }
+            // NB: The header has the form "bytes <first>-<last>/<size>"
+            let file_len = response
+                .headers()
+                .get(CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit('/').next())
+                .and_then(|size| size.parse().ok())
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Invalid Content-Range header")
+                })?;
+            self.buffer.clear();
+            self.buffer_pos = position;
+            response.take(CHUNK_SIZE).read_to_end(&mut self.buffer)?;
+            Ok(file_len)
+        }
+    }
+
+    impl Read for HttpReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.position >= self.file_len {
+                return Ok(0);
+            }
+            if self.position < self.buffer_pos
+                || self.position >= self.buffer_pos + self.buffer.len() as u64
+            {
+                self.fetch(self.position)?;
+            }
+            let start = (self.position - self.buffer_pos) as usize;
+            let available = &self.buffer[start..];
+            let bytes = available.len().min(buf.len());
+            buf[..bytes].copy_from_slice(&available[..bytes]);
+            self.position += bytes as u64;
+            Ok(bytes)
+        }
+    }
+
+    impl Seek for HttpReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            // NB: Data is only requested when the buffer is re-filled
+            self.position = resolve_seek(pos, self.position, self.file_len)?;
+            Ok(self.position)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::sync::Arc;
+        use std::thread;
+
+        /// More than two chunks, the last one is partial
+        fn contents() -> Vec<u8> {
+            (0..2 * CHUNK_SIZE + 12345)
+                .map(|i| (i % 251) as u8)
+                .collect()
+        }
+
+        /// Serves `data` on a local port, returns its URL.
+        ///
+        /// If `ranges` is `false`, range requests are ignored
+        /// (i.e. the whole file is sent).
+        fn serve(data: Vec<u8>, ranges: bool) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/file.wav", listener.local_addr().unwrap());
+            let data = Arc::new(data);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let data = Arc::clone(&data);
+                    thread::spawn(move || handle_client(stream.unwrap(), &data, ranges));
+                }
+            });
+            url
+        }
+
+        fn handle_client(mut stream: TcpStream, data: &[u8], ranges: bool) {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            // NB: Several requests can be sent over the same connection
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let found = line.starts_with("GET /file.wav ");
+                let mut range = None;
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let header = line.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    let (name, value) = header.split_once(':').unwrap();
+                    if ranges && name.eq_ignore_ascii_case("range") {
+                        let value = value.trim().trim_start_matches("bytes=");
+                        let (first, last) = value.split_once('-').unwrap();
+                        let first: usize = first.parse().unwrap();
+                        let last: usize = last.parse().unwrap();
+                        range = Some((first, last.min(data.len() - 1)));
+                    }
+                }
+                let (head, body) = match range {
+                    _ if !found => ("HTTP/1.1 404 Not Found\r\n".to_string(), &[][..]),
+                    Some((first, last)) => (
+                        format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                            first,
+                            last,
+                            data.len()
+                        ),
+                        &data[first..=last],
+                    ),
+                    None => ("HTTP/1.1 200 OK\r\n".to_string(), data),
+                };
+                let head = format!("{}Content-Length: {}\r\n\r\n", head, body.len());
+                if stream
+                    .write_all(head.as_bytes())
+                    .and_then(|_| stream.write_all(body))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+
+        #[test]
+        fn handled_locations() {
+            assert!(HttpSource.handles("http://example.com/a.wav"));
+            assert!(HttpSource.handles("https://example.com/a.wav"));
+            assert!(!HttpSource.handles("a.wav"));
+            assert!(!HttpSource.handles("ftp://example.com/a.wav"));
+        }
+
+        #[test]
+        fn read_whole_file() {
+            let expected = contents();
+            let url = serve(expected.clone(), true);
+            let mut data = Vec::new();
+            HttpSource
+                .open(&url)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert!(data == expected);
+        }
+
+        #[test]
+        fn seeking() {
+            let expected = contents();
+            let url = serve(expected.clone(), true);
+            let mut reader = HttpSource.open(&url).unwrap();
+            let mut buffer = vec![0; 1000];
+            // Across the end of the first chunk
+            let position = CHUNK_SIZE - 500;
+            assert_eq!(reader.seek(SeekFrom::Start(position)).unwrap(), position);
+            reader.read_exact(&mut buffer).unwrap();
+            let position = position as usize;
+            assert!(buffer[..] == expected[position..position + 1000]);
+            // Backwards, before the buffered data
+            reader.seek(SeekFrom::Current(-5000)).unwrap();
+            reader.read_exact(&mut buffer).unwrap();
+            assert!(buffer[..] == expected[position - 4000..position - 3000]);
+            assert_eq!(
+                reader.seek(SeekFrom::End(-1000)).unwrap(),
+                expected.len() as u64 - 1000
+            );
+            reader.read_exact(&mut buffer).unwrap();
+            assert!(buffer[..] == expected[expected.len() - 1000..]);
+            assert_eq!(reader.read(&mut buffer).unwrap(), 0);
+            assert!(reader.seek(SeekFrom::Current(-1_000_000_000)).is_err());
+        }
+
+        #[test]
+        fn range_requests_not_supported() {
+            let url = serve(contents(), false);
+            let error = HttpSource.open(&url).err().unwrap();
+            assert!(error.to_string().contains("range requests"), "{}", error);
+        }
+
+        #[test]
+        fn file_not_found() {
+            let url = serve(contents(), true).replace("file.wav", "missing.wav");
+            assert!(HttpSource.open(&url).is_err());
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::error::Error;
+
+use super::dynamic::AudioFile;
+use super::ChannelMap;
+
+/// Number of frames decoded at once while loading
+const CHUNK_SIZE: u32 = 4096;
+
+/// An audio file which has been completely decoded into memory.
+pub struct File {
+    samplerate: u32,
+    frames: u64,
+    position: u64,
+    current_block: Block,
+}
+
+impl File {
+    /// Decodes all frames of `file` (which can also be repeated and/or resampled).
+    pub fn load(
+        mut file: Box<dyn AudioFile + Send + Sync>,
+    ) -> Result<File, Box<dyn Error + Send + Sync>> {
+        let channels = file.channels() as usize;
+        let frames = file.frames();
+        let channel_map: ChannelMap = (0..channels).map(|c| vec![c].into()).collect();
+        let mut data = vec![Vec::with_capacity(frames as usize); channels];
+        let mut chunk: Vec<Box<[f32]>> = (0..channels)
+            .map(|_| vec![0.0f32; CHUNK_SIZE as usize].into())
+            .collect();
+        file.seek(0)?;
+        let mut remaining = frames;
+        while remaining > 0 {
+            let size = remaining.min(u64::from(CHUNK_SIZE)) as u32;
+            // NB: If the file ends prematurely, the rest is filled with zeros
+            for channel in chunk.iter_mut() {
+                for value in channel.iter_mut() {
+                    *value = 0.0f32;
+                }
+            }
+            file.fill_channels(&channel_map, size, 0, &mut chunk)?;
+            for (target, source) in data.iter_mut().zip(&chunk) {
+                target.extend_from_slice(&source[..size as usize]);
+            }
+            remaining -= u64::from(size);
+        }
+        Ok(File {
+            samplerate: file.samplerate(),
+            frames,
+            position: 0,
+            current_block: Block {
+                channels: data
+                    .into_iter()
+                    .map(|data| Channel {
+                        data: data.into(),
+                        index: 0,
+                        stop: 0,
+                    })
+                    .collect(),
+                frames: 0,
+            },
+        })
+    }
+
+    /// Creates a file without any frames, e.g. as a placeholder for a missing file.
+    pub fn empty(channels: u32, samplerate: u32) -> File {
+        File {
+            samplerate,
+            frames: 0,
+            position: 0,
+            current_block: Block {
+                channels: (0..channels)
+                    .map(|_| Channel {
+                        data: Box::new([]),
+                        index: 0,
+                        stop: 0,
+                    })
+                    .collect(),
+                frames: 0,
+            },
+        }
+    }
+}
+
+impl super::AudioFileBasics for File {
+    fn channels(&self) -> u32 {
+        self.current_block.channels.len() as u32
+    }
+
+    fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn samplerate(&self) -> u32 {
+        self.samplerate
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.position = frame.min(self.frames);
+        Ok(())
+    }
+}
+
+impl super::AudioFileBlocks for File {
+    type Block = Block;
+
+    fn next_block(
+        &mut self,
+        max_frames: u32,
+    ) -> Result<&mut Self::Block, Box<dyn Error + Send + Sync>> {
+        let frames = (self.frames - self.position).min(u64::from(max_frames)) as u32;
+        let start = self.position as usize;
+        for channel in self.current_block.channels.iter_mut() {
+            channel.index = start;
+            channel.stop = start + frames as usize;
+        }
+        self.current_block.frames = frames;
+        self.position += u64::from(frames);
+        Ok(&mut self.current_block)
+    }
+}
+
+pub struct Block {
+    channels: Box<[Channel]>,
+    frames: u32,
+}
+
+impl super::Block for Block {
+    type Channel = Channel;
+
+    fn channel_iterators(&mut self) -> &mut [Self::Channel] {
+        &mut self.channels
+    }
+
+    fn frames(&self) -> u32 {
+        self.frames
+    }
+}
+
+pub struct Channel {
+    data: Box<[f32]>,
+    index: usize,
+    stop: usize,
+}
+
+impl Iterator for Channel {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.index < self.stop {
+            let value = self.data[self.index];
+            self.index += 1;
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
@@ -2,11 +2,27 @@ use std::error::Error;
 use std::num::NonZeroU64;
 use std::ops::IndexMut;
 
+pub mod cache;
+#[cfg(feature = "resampling")]
 pub mod converter;
+pub mod custom;
+pub mod direct;
 pub mod dynamic;
+pub mod faded;
+pub mod looped;
+pub mod media;
+pub mod memory;
+pub mod network;
+pub mod signal;
+#[cfg(feature = "vorbis")]
 pub mod vorbis;
 pub mod wav;
 
+/// For each channel of a file, the (0-based) target channels it is written to.
+///
+/// The map can be shorter than the number of channels in the file.
+pub type ChannelMap = Box<[Box<[usize]>]>;
+
 pub trait AudioFileBasics {
     fn channels(&self) -> u32;
     fn frames(&self) -> u64;
@@ -51,7 +67,7 @@ pub trait AudioFileBlocks {
 
     fn fill_channels<D>(
         &mut self,
-        channel_map: &[Option<usize>],
+        channel_map: &[Box<[usize]>],
         blocksize: u32,
         offset: u32,
         channels: &mut [D],
@@ -65,19 +81,32 @@ pub trait AudioFileBlocks {
             if file_block.is_empty() {
                 break;
             }
+            let frames = file_block.frames();
+            let range = offset as usize..(offset + frames) as usize;
             let iterators = file_block.channel_iterators();
             // TODO: check channel_map for validity?
-            for (i, &channel) in channel_map.iter().enumerate() {
-                if let Some(channel) = channel {
+            for (i, targets) in channel_map.iter().enumerate() {
+                if let Some((&first, others)) = targets.split_first() {
                     // TODO: use iterators[i]?
                     for (a, b) in IndexMut::index_mut(iterators, i)
-                        .zip(&mut channels[channel][offset as usize..])
+                        .zip(&mut channels[first][offset as usize..])
                     {
                         *b = a;
                     }
+                    // NB: The channel iterator can only be used once
+                    for &other in others {
+                        let (source, target) = if first < other {
+                            let (left, right) = channels.split_at_mut(other);
+                            (&left[first], &mut right[0])
+                        } else {
+                            let (left, right) = channels.split_at_mut(first);
+                            (&right[0], &mut left[other])
+                        };
+                        target[range.clone()].copy_from_slice(&source[range.clone()]);
+                    }
                 }
             }
-            offset += file_block.frames();
+            offset += frames;
         }
         // TODO: return number of frames?
         Ok(())
@@ -0,0 +1,276 @@
+//! Reading files from network shares (SMB, NFS, ...).
+//!
+//! Compared to a `BufReader`, data is read in much larger chunks and
+//! further ahead, and failed operations are retried (after re-opening
+//! the file), because network file systems often have high latency
+//! and occasional hiccups.
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::loader::resolve_seek;
+
+/// Number of bytes requested from the file system at once
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Number of bytes buffered per file
+const READ_AHEAD: usize = 4 * CHUNK_SIZE;
+
+/// Number of attempts before an error is reported
+const MAX_RETRIES: u32 = 8;
+
+/// Delay before the first retry, it is doubled for each further retry
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Upper limit for the delay between retries
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub struct NetworkReader {
+    path: PathBuf,
+    file: fs::File,
+    file_len: u64,
+    buffer: Box<[u8]>,
+    /// File position of the data in the buffer
+    buffer_pos: u64,
+    /// Number of valid bytes in the buffer
+    buffer_len: usize,
+    /// Current read position
+    position: u64,
+}
+
+impl NetworkReader {
+    pub fn open(path: &Path) -> io::Result<NetworkReader> {
+        let file = retry(|| fs::File::open(path))?;
+        let file_len = retry(|| file.metadata())?.len();
+        Ok(NetworkReader {
+            path: path.into(),
+            file,
+            file_len,
+            buffer: vec![0u8; READ_AHEAD].into_boxed_slice(),
+            buffer_pos: 0,
+            buffer_len: 0,
+            position: 0,
+        })
+    }
+
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        // NB: The buffer is invalid until it has been filled successfully
+        self.buffer_len = 0;
+        let mut filled = 0;
+        let mut failures = 0;
+        while filled < READ_AHEAD {
+            let chunk = &mut self.buffer[filled..(filled + CHUNK_SIZE).min(READ_AHEAD)];
+            let file = &mut self.file;
+            let result = file
+                .seek(SeekFrom::Start(self.position + filled as u64))
+                .and_then(|_| file.read(chunk));
+            match result {
+                Ok(0) => break,
+                Ok(bytes) => {
+                    filled += bytes;
+                    failures = 0;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    if !is_transient(&e) || failures >= MAX_RETRIES {
+                        return Err(e);
+                    }
+                    thread::sleep(retry_delay(failures));
+                    failures += 1;
+                    // NB: After reconnecting, the old file handle might be stale
+                    if let Ok(file) = fs::File::open(&self.path) {
+                        self.file = file;
+                    }
+                }
+            }
+        }
+        self.buffer_pos = self.position;
+        self.buffer_len = filled;
+        Ok(())
+    }
+}
+
+impl Read for NetworkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position < self.buffer_pos
+            || self.position >= self.buffer_pos + self.buffer_len as u64
+        {
+            if self.position >= self.file_len {
+                return Ok(0);
+            }
+            self.fill_buffer()?;
+        }
+        let start = (self.position - self.buffer_pos) as usize;
+        let available = &self.buffer[start..self.buffer_len];
+        let bytes = available.len().min(buf.len());
+        buf[..bytes].copy_from_slice(&available[..bytes]);
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl Seek for NetworkReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // NB: The file is only accessed when the buffer is re-filled
+        self.position = resolve_seek(pos, self.position, self.file_len)?;
+        Ok(self.position)
+    }
+}
+
+/// Returns `false` for errors which will most likely not go away by trying again
+fn is_transient(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+    !matches!(
+        error.kind(),
+        NotFound | PermissionDenied | InvalidInput | InvalidData | UnexpectedEof
+    )
+}
+
+fn retry_delay(failures: u32) -> Duration {
+    (INITIAL_RETRY_DELAY * 2u32.saturating_pow(failures)).min(MAX_RETRY_DELAY)
+}
+
+fn retry<T, F: FnMut() -> io::Result<T>>(mut operation: F) -> io::Result<T> {
+    let mut failures = 0;
+    loop {
+        match operation() {
+            Ok(result) => return Ok(result),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                if !is_transient(&e) || failures >= MAX_RETRIES {
+                    return Err(e);
+                }
+                thread::sleep(retry_delay(failures));
+                failures += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Temporary file which is removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, data: &[u8]) -> TempFile {
+            let path =
+                std::env::temp_dir().join(format!("asdf-network-{}-{}", name, std::process::id()));
+            fs::write(&path, data).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// More than the read-ahead buffer, not a multiple of the chunk size
+    fn contents() -> Vec<u8> {
+        (0..READ_AHEAD + CHUNK_SIZE / 2 + 123)
+            .map(|i| (i % 251) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn read_whole_file() {
+        let expected = contents();
+        let file = TempFile::new("read", &expected);
+        let mut data = Vec::new();
+        NetworkReader::open(&file.0)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert!(data == expected);
+    }
+
+    #[test]
+    fn seeking() {
+        let expected = contents();
+        let file = TempFile::new("seeking", &expected);
+        let mut reader = NetworkReader::open(&file.0).unwrap();
+        let mut buffer = vec![0; 1000];
+        // Across the end of the read-ahead buffer
+        let position = READ_AHEAD as u64 - 500;
+        assert_eq!(reader.seek(SeekFrom::Start(position)).unwrap(), position);
+        reader.read_exact(&mut buffer).unwrap();
+        assert!(buffer[..] == expected[READ_AHEAD - 500..READ_AHEAD + 500]);
+        // Backwards, before the buffered data
+        assert_eq!(
+            reader.seek(SeekFrom::Current(-5000)).unwrap(),
+            position - 4000
+        );
+        reader.read_exact(&mut buffer).unwrap();
+        assert!(buffer[..] == expected[READ_AHEAD - 4500..READ_AHEAD - 3500]);
+        assert_eq!(
+            reader.seek(SeekFrom::End(-1000)).unwrap(),
+            expected.len() as u64 - 1000
+        );
+        reader.read_exact(&mut buffer).unwrap();
+        assert!(buffer[..] == expected[expected.len() - 1000..]);
+        assert_eq!(reader.read(&mut buffer).unwrap(), 0);
+        // Beyond the end
+        reader.seek(SeekFrom::End(1000)).unwrap();
+        assert_eq!(reader.read(&mut buffer).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-1_000_000_000)).is_err());
+    }
+
+    #[test]
+    fn missing_file() {
+        let path =
+            std::env::temp_dir().join(format!("asdf-network-missing-{}", std::process::id()));
+        let error = NetworkReader::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        let mut attempts = 0;
+        let result = retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "hiccup"))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn do_not_retry_permanent_errors() {
+        let mut attempts = 0;
+        let result: io::Result<()> = retry(|| {
+            attempts += 1;
+            Err(io::ErrorKind::PermissionDenied.into())
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn give_up_after_max_retries() {
+        let mut attempts = 0;
+        let result: io::Result<()> = retry(|| {
+            attempts += 1;
+            Err(io::ErrorKind::ConnectionReset.into())
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionReset);
+        assert_eq!(attempts, MAX_RETRIES + 1);
+    }
+
+    #[test]
+    fn retry_delays() {
+        assert_eq!(retry_delay(0), INITIAL_RETRY_DELAY);
+        assert_eq!(retry_delay(1), 2 * INITIAL_RETRY_DELAY);
+        assert_eq!(retry_delay(MAX_RETRIES), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+}
@@ -0,0 +1,356 @@
+//! Synthetic test signals, e.g. for checking and calibrating loudspeaker setups.
+//!
+//! A test signal can be used instead of an audio file in the `file`
+//! attribute of a `<clip>`, with the prefix `signal:`,
+//! followed by the waveform and (optionally) its parameters:
+//!
+//! ```text
+//! <clip file="signal:sine frequency=440 duration=10"/>
+//! <clip file="signal:pink duration=30 level=-30"/>
+//! <clip file="signal:sweep from=20 to=20000 duration=10"/>
+//! <clip file="signal:dirac interval=0.5 duration=10"/>
+//! ```
+//!
+//! `duration` (in seconds) is required for all waveforms,
+//! `level` (in dBFS, default: -20) is the peak level
+//! (the RMS level for pink noise).
+//! Test signals are generated while streaming, like audio files they can be
+//! repeated, faded and preloaded.
+//! When creating scenes programmatically, `writer::Clip::signal()` can be used.
+
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Used to distinguish test signals from file names
+pub const PREFIX: &str = "signal:";
+
+/// Default level (in dBFS)
+const DEFAULT_LEVEL: f32 = -20.0;
+
+/// Number of frames generated at once
+const BUFFER_SIZE: u32 = 1024;
+
+/// RMS value of the (unscaled) pink noise filter output
+const PINK_NOISE_RMS: f32 = 1.77;
+
+/// Number of frames the pink noise filter is run before the seek position
+/// (the filter state has practically decayed after that)
+const PINK_NOISE_WARM_UP: u64 = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    /// Frequency in Hz (default: 1000)
+    Sine {
+        frequency: f64,
+    },
+    PinkNoise,
+    /// Exponential sine sweep (over the whole duration),
+    /// frequencies in Hz (default: from 20 to 20000)
+    Sweep {
+        from: f64,
+        to: f64,
+    },
+    /// Single-sample impulses, `interval` in seconds (default: 1)
+    DiracTrain {
+        interval: f64,
+    },
+}
+
+/// A test signal, which can be parsed from (and displayed as)
+/// a location string, see the module documentation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TestSignal {
+    pub waveform: Waveform,
+    /// In seconds
+    pub duration: f64,
+    /// In dBFS
+    pub level: f32,
+}
+
+impl TestSignal {
+    pub fn new(waveform: Waveform, duration: f64) -> TestSignal {
+        TestSignal {
+            waveform,
+            duration,
+            level: DEFAULT_LEVEL,
+        }
+    }
+
+    pub fn level(mut self, dbfs: f32) -> TestSignal {
+        self.level = dbfs;
+        self
+    }
+}
+
+impl fmt::Display for TestSignal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", PREFIX)?;
+        match self.waveform {
+            Waveform::Sine { frequency } => write!(f, "sine frequency={}", frequency)?,
+            Waveform::PinkNoise => write!(f, "pink")?,
+            Waveform::Sweep { from, to } => write!(f, "sweep from={} to={}", from, to)?,
+            Waveform::DiracTrain { interval } => write!(f, "dirac interval={}", interval)?,
+        }
+        write!(f, " duration={} level={}", self.duration, self.level)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseSignalError(String);
+
+impl fmt::Display for ParseSignalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ParseSignalError {}
+
+impl FromStr for TestSignal {
+    type Err = ParseSignalError;
+
+    fn from_str(s: &str) -> Result<TestSignal, ParseSignalError> {
+        let error = |msg: String| Err(ParseSignalError(msg));
+        let mut tokens = match s.strip_prefix(PREFIX) {
+            Some(rest) => rest.split_whitespace(),
+            None => return error(format!("Test signals must start with {:?}", PREFIX)),
+        };
+        let (mut waveform, allowed): (Waveform, &[&str]) = match tokens.next() {
+            Some("sine") => (Waveform::Sine { frequency: 1000.0 }, &["frequency"]),
+            Some("pink") => (Waveform::PinkNoise, &[]),
+            Some("sweep") => (
+                Waveform::Sweep {
+                    from: 20.0,
+                    to: 20000.0,
+                },
+                &["from", "to"],
+            ),
+            Some("dirac") => (Waveform::DiracTrain { interval: 1.0 }, &["interval"]),
+            Some(other) => {
+                return error(format!(
+                    "Unknown waveform {:?} (expected \"sine\", \"pink\", \"sweep\" or \"dirac\")",
+                    other
+                ))
+            }
+            None => return error("Waveform is missing".into()),
+        };
+        let mut duration = None;
+        let mut level = DEFAULT_LEVEL;
+        for token in tokens {
+            let (key, value) = match token.split_once('=') {
+                Some(pair) => pair,
+                None => return error(format!("Expected key=value, not {:?}", token)),
+            };
+            if key != "duration" && key != "level" && !allowed.contains(&key) {
+                return error(format!("Unknown parameter {:?}", key));
+            }
+            let value = match f64::from_str(value) {
+                Ok(value) if value.is_finite() => value,
+                _ => return error(format!("Invalid value for {:?}: {:?}", key, value)),
+            };
+            if key != "level" && value <= 0.0 {
+                return error(format!("{:?} must be positive", key));
+            }
+            match (key, &mut waveform) {
+                ("duration", _) => duration = Some(value),
+                ("level", _) => level = value as f32,
+                ("frequency", Waveform::Sine { frequency }) => *frequency = value,
+                ("from", Waveform::Sweep { from, .. }) => *from = value,
+                ("to", Waveform::Sweep { to, .. }) => *to = value,
+                ("interval", Waveform::DiracTrain { interval }) => *interval = value,
+                _ => unreachable!(),
+            }
+        }
+        match duration {
+            Some(duration) => Ok(TestSignal::new(waveform, duration).level(level)),
+            None => error("\"duration\" is required".into()),
+        }
+    }
+}
+
+/// Whether `location` (the `file` attribute of a `<clip>`) is a test signal.
+pub(crate) fn is_location(location: &str) -> bool {
+    location.starts_with(PREFIX)
+}
+
+/// A test signal which behaves like a (mono) audio file.
+pub(crate) struct File {
+    signal: TestSignal,
+    samplerate: u32,
+    frames: u64,
+    amplitude: f32,
+    position: u64,
+    /// State of the pink noise filter
+    pink: [f32; 7],
+    current_block: Block,
+}
+
+impl File {
+    pub(crate) fn new(signal: TestSignal, samplerate: u32) -> Result<File, ParseSignalError> {
+        let frames = (signal.duration * f64::from(samplerate)).round() as u64;
+        if frames == 0 {
+            return Err(ParseSignalError(
+                "Duration is shorter than one frame".into(),
+            ));
+        }
+        let mut amplitude = 10.0f32.powf(signal.level / 20.0);
+        if signal.waveform == Waveform::PinkNoise {
+            amplitude /= PINK_NOISE_RMS;
+        }
+        Ok(File {
+            signal,
+            samplerate,
+            frames,
+            amplitude,
+            position: 0,
+            pink: [0.0; 7],
+            current_block: Block {
+                channels: [Channel {
+                    data: vec![0.0; BUFFER_SIZE as usize].into(),
+                    index: 0,
+                    stop: 0,
+                }],
+                frames: 0,
+            },
+        })
+    }
+
+    /// Value of the (unscaled) signal at `frame`,
+    /// for pink noise the frames have to be requested in order.
+    fn sample(&mut self, frame: u64) -> f32 {
+        let samplerate = f64::from(self.samplerate);
+        let time = frame as f64 / samplerate;
+        match self.signal.waveform {
+            Waveform::Sine { frequency } => (2.0 * PI * frequency * time).sin() as f32,
+            Waveform::PinkNoise => self.pink_noise(frame),
+            Waveform::Sweep { from, to } => {
+                let phase = if from == to {
+                    2.0 * PI * from * time
+                } else {
+                    let rate = (to / from).ln() / self.signal.duration;
+                    2.0 * PI * from * ((rate * time).exp() - 1.0) / rate
+                };
+                phase.sin() as f32
+            }
+            Waveform::DiracTrain { interval } => {
+                let interval = ((interval * samplerate).round() as u64).max(1);
+                if frame.is_multiple_of(interval) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Paul Kellet's filter applied to white noise,
+    /// see http://www.firstpr.com.au/dsp/pink-noise/.
+    fn pink_noise(&mut self, frame: u64) -> f32 {
+        let white = white_noise(frame);
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let result = b[..6].iter().sum::<f32>() + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        result
+    }
+}
+
+/// Uniformly distributed between -1 and 1, the same value for each `frame`
+/// (using the finalizer of SplitMix64).
+fn white_noise(frame: u64) -> f32 {
+    let mut x = frame.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+impl super::AudioFileBasics for File {
+    fn channels(&self) -> u32 {
+        1
+    }
+
+    fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    fn samplerate(&self) -> u32 {
+        self.samplerate
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.position = frame.min(self.frames);
+        if self.signal.waveform == Waveform::PinkNoise {
+            self.pink = [0.0; 7];
+            for frame in self.position.saturating_sub(PINK_NOISE_WARM_UP)..self.position {
+                self.pink_noise(frame);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl super::AudioFileBlocks for File {
+    type Block = Block;
+
+    fn next_block(
+        &mut self,
+        max_frames: u32,
+    ) -> Result<&mut Self::Block, Box<dyn Error + Send + Sync>> {
+        let frames = (self.frames - self.position).min(u64::from(max_frames.min(BUFFER_SIZE)));
+        for i in 0..frames as usize {
+            let value = self.amplitude * self.sample(self.position + i as u64);
+            self.current_block.channels[0].data[i] = value;
+        }
+        let channel = &mut self.current_block.channels[0];
+        channel.index = 0;
+        channel.stop = frames as usize;
+        self.current_block.frames = frames as u32;
+        self.position += frames;
+        Ok(&mut self.current_block)
+    }
+}
+
+pub(crate) struct Block {
+    channels: [Channel; 1],
+    frames: u32,
+}
+
+impl super::Block for Block {
+    type Channel = Channel;
+
+    fn channel_iterators(&mut self) -> &mut [Self::Channel] {
+        &mut self.channels
+    }
+
+    fn frames(&self) -> u32 {
+        self.frames
+    }
+}
+
+pub(crate) struct Channel {
+    data: Box<[f32]>,
+    index: usize,
+    stop: usize,
+}
+
+impl Iterator for Channel {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.index < self.stop {
+            let value = self.data[self.index];
+            self.index += 1;
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
@@ -3,11 +3,17 @@ use std::fmt;
 use std::io::{self, Read, Seek};
 use std::mem::MaybeUninit;
 
-use libc::{c_int, c_long, c_void};
+use std::os::raw::{c_int, c_long, c_void};
+
 use ogg_sys::ogg_int64_t;
 
 const EIO: errno::Errno = errno::Errno(5);
 
+// NB: The values are the same on all platforms
+const SEEK_SET: c_int = 0;
+const SEEK_CUR: c_int = 1;
+const SEEK_END: c_int = 2;
+
 /// https://xiph.org/vorbis/doc/vorbisfile/reference.html
 pub struct File<R>
 where
@@ -43,17 +49,17 @@ where
 
 extern "C" fn read_func<R>(
     ptr: *mut c_void,
-    size: libc::size_t,
-    nmemb: libc::size_t,
+    size: usize,
+    nmemb: usize,
     datasource: *mut c_void,
-) -> libc::size_t
+) -> usize
 where
     R: Read,
 {
     let ptr = ptr as *mut u8;
     assert!(!datasource.is_null());
     let reader = unsafe { &mut *(datasource as *mut R) };
-    let buffer = unsafe { std::slice::from_raw_parts_mut(ptr, (size * nmemb) as usize) };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(ptr, size * nmemb) };
     loop {
         match reader.read(buffer) {
             Ok(bytes) => return bytes,
@@ -73,9 +79,9 @@ where
     assert!(!datasource.is_null());
     let reader = unsafe { &mut *(datasource as *mut R) };
     let result = match whence {
-        libc::SEEK_SET => reader.seek(io::SeekFrom::Start(offset as u64)),
-        libc::SEEK_CUR => reader.seek(io::SeekFrom::Current(offset)),
-        libc::SEEK_END => reader.seek(io::SeekFrom::End(offset)),
+        SEEK_SET => reader.seek(io::SeekFrom::Start(offset as u64)),
+        SEEK_CUR => reader.seek(io::SeekFrom::Current(offset)),
+        SEEK_END => reader.seek(io::SeekFrom::End(offset)),
         w => panic!("Invalid value for \"whence\": {}", w),
     };
     result.map(|v| v as c_int).unwrap_or(-1)
@@ -0,0 +1,259 @@
+//! Command line tool for validating and inspecting ASDF scenes,
+//! e.g. for CI checks of scene repositories.
+//!
+//! Scenes are loaded in "dry run" mode, i.e. audio files are opened but not read.
+
+use std::env;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use asdf::{FadeShape, LoadOptions, Scene};
+
+const USAGE: &str = "\
+Usage: asdf-cli <command> [options] <scene>...
+
+Commands:
+  validate  Check scenes for errors (and show warnings)
+  inspect   Show sources, clips, duration and referenced files
+  probe     List referenced files (and whether they exist)
+  compare   Render two scenes and compare their audio data per source
+  punch     Render the regions around all clip boundaries (for finding clicks)
+
+Options:
+  -r, --samplerate <Hz>  Sample rate used for loading (default: 48000)
+  -b, --blocksize <N>    Block size used for loading (default: 1024)
+  --json                 JSON output (only for \"inspect\")
+  --tolerance <dB>       Ignore smaller differences (only for \"compare\")
+  --margin <ms>          Duration before and after each clip boundary
+                         (only for \"punch\", default: 100)
+  -o, --output <dir>     Existing output directory (only for \"punch\", default: .)
+  -h, --help             Show this help";
+
+/// Exit status for invalid scenes (or missing files)
+const EXIT_INVALID: i32 = 1;
+
+/// Exit status for invalid command line arguments
+const EXIT_USAGE: i32 = 2;
+
+struct Options {
+    samplerate: u32,
+    blocksize: u32,
+    json: bool,
+    /// Linear amplitude
+    tolerance: f32,
+    /// In milliseconds
+    margin: u32,
+    output: String,
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = match args.next() {
+        Some(ref arg) if arg == "-h" || arg == "--help" => {
+            println!("{}", USAGE);
+            return;
+        }
+        Some(command) => command,
+        None => usage_error("No command given"),
+    };
+    let mut options = Options {
+        samplerate: 48000,
+        blocksize: 1024,
+        json: false,
+        tolerance: 0.0,
+        margin: 100,
+        output: ".".into(),
+    };
+    let mut scenes = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-r" | "--samplerate" => options.samplerate = number_argument(&arg, args.next()),
+            "-b" | "--blocksize" => options.blocksize = number_argument(&arg, args.next()),
+            "--json" => options.json = true,
+            "--tolerance" => options.tolerance = decibel_argument(&arg, args.next()),
+            "--margin" => options.margin = number_argument(&arg, args.next()),
+            "-o" | "--output" => match args.next() {
+                Some(output) => options.output = output,
+                None => usage_error(&format!("{} requires a directory", arg)),
+            },
+            _ if arg.starts_with('-') => usage_error(&format!("Unknown option {:?}", arg)),
+            _ => scenes.push(arg),
+        }
+    }
+    if scenes.is_empty() {
+        usage_error("No scene file given");
+    }
+    if command == "compare" {
+        if scenes.len() != 2 {
+            usage_error("\"compare\" requires exactly two scene files");
+        }
+        if !compare(&scenes[0], &scenes[1], &options) {
+            process::exit(EXIT_INVALID);
+        }
+        return;
+    }
+    if command == "punch" {
+        if scenes.len() != 1 {
+            usage_error("\"punch\" requires exactly one scene file");
+        }
+        if !punch(&scenes[0], &options) {
+            process::exit(EXIT_INVALID);
+        }
+        return;
+    }
+    let run = match command.as_str() {
+        "validate" => validate,
+        "inspect" => inspect,
+        "probe" => probe,
+        _ => usage_error(&format!("Unknown command {:?}", command)),
+    };
+    let mut success = true;
+    for path in &scenes {
+        let scene = match Scene::load_dry_run(path, options.samplerate, options.blocksize) {
+            Ok(scene) => scene,
+            Err(e) => {
+                if let Some((line, column)) = e.position() {
+                    eprintln!("{}:{}:{}: error", path, line, column);
+                }
+                eprintln!("{}", e);
+                success = false;
+                continue;
+            }
+        };
+        success &= run(Path::new(path), &scene, &options);
+    }
+    if !success {
+        process::exit(EXIT_INVALID);
+    }
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    process::exit(EXIT_USAGE);
+}
+
+fn number_argument(option: &str, value: Option<String>) -> u32 {
+    match value.as_ref().map(|v| v.parse()) {
+        Some(Ok(value)) if value > 0 => value,
+        _ => usage_error(&format!("{} requires a positive number", option)),
+    }
+}
+
+fn decibel_argument(option: &str, value: Option<String>) -> f32 {
+    match value.as_ref().map(|v| v.parse::<f32>()) {
+        Some(Ok(value)) if value.is_finite() => 10.0f32.powf(value / 20.0),
+        _ => usage_error(&format!("{} requires a number (in dB)", option)),
+    }
+}
+
+fn validate(path: &Path, scene: &Scene, _options: &Options) -> bool {
+    let report = scene.report();
+    for warning in &report.warnings {
+        println!("{}: warning: {}", path.display(), warning);
+    }
+    if !report.missing_files.is_empty() {
+        return false;
+    }
+    println!("{}: OK", path.display());
+    true
+}
+
+fn inspect(path: &Path, scene: &Scene, options: &Options) -> bool {
+    let report = scene.report();
+    if options.json {
+        println!("{}", report.to_json());
+        return report.missing_files.is_empty();
+    }
+    println!("<!-- {} -->\n", path.display());
+    println!("{}", report.to_markdown());
+    println!("## Clips\n");
+    println!("| Source | Begin | Duration | File | Channel |");
+    println!("|--------|-------|----------|------|---------|");
+    let seconds = |frames: u64| frames as f64 / f64::from(options.samplerate);
+    for idx in 0..report.sources.len() {
+        for clip in scene.clips(idx) {
+            println!(
+                "| {} | {:.3} s | {:.3} s | `{}` | {} |",
+                idx + 1,
+                seconds(clip.begin),
+                seconds(clip.duration),
+                clip.file.display(),
+                clip.channel + 1
+            );
+        }
+    }
+    println!();
+    report.missing_files.is_empty()
+}
+
+fn probe(_path: &Path, scene: &Scene, _options: &Options) -> bool {
+    let report = scene.report();
+    for file in &report.files {
+        let status = if report.missing_files.contains(file) {
+            "missing"
+        } else {
+            "ok"
+        };
+        println!("{}\t{}", status, file.display());
+    }
+    report.missing_files.is_empty()
+}
+
+/// Loads a scene for offline rendering (errors are printed).
+fn load_for_rendering(path: &str, options: &Options) -> Option<Scene> {
+    let load_options = LoadOptions::new(options.samplerate, options.blocksize)
+        .buffer_blocks(8)
+        .sleeptime(Duration::from_millis(1))
+        .fade(Duration::from_secs(0), FadeShape::Linear);
+    Scene::with_options(path, &load_options)
+        .map_err(|e| eprintln!("{}", e))
+        .ok()
+}
+
+/// Returns `false` if the scenes differ (or cannot be loaded).
+fn compare(path_a: &str, path_b: &str, options: &Options) -> bool {
+    let load = |path: &str| load_for_rendering(path, options);
+    let (mut a, mut b) = match (load(path_a), load(path_b)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return false,
+    };
+    match a.compare(&mut b, options.tolerance) {
+        Ok(comparison) => {
+            println!("<!-- {} vs. {} -->\n", path_a, path_b);
+            println!("{}", comparison.to_markdown());
+            comparison.is_identical()
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+fn punch(path: &str, options: &Options) -> bool {
+    let mut scene = match load_for_rendering(path, options) {
+        Some(scene) => scene,
+        None => return false,
+    };
+    let margin = u64::from(options.margin) * u64::from(options.samplerate) / 1000;
+    match scene.render_punch_regions(margin, Path::new(&options.output)) {
+        Ok(render) => {
+            let boundaries: usize = render.regions.iter().map(|r| r.boundaries.len()).sum();
+            println!(
+                "{} clip boundaries in {} regions",
+                boundaries,
+                render.regions.len()
+            );
+            true
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,591 @@
+//! Encrypted bundles containing a scene and all its files,
+//! e.g. for distributing scenes to venues without exposing the raw stems.
+//!
+//! Bundles are created with [`BundleWriter`](struct.BundleWriter.html)
+//! and loaded with
+//! [`Scene::load_bundle()`](../struct.Scene.html#method.load_bundle),
+//! files are decrypted transparently while streaming.
+//!
+//! All files are split into chunks which are encrypted and authenticated
+//! separately (with XChaCha20-Poly1305), which allows seeking.
+//! Modified files (and wrong keys) are detected.
+//!
+//! File format (all numbers are little-endian):
+//!
+//! * magic bytes `ASDFBNDL`, format version (`u32`), bundle ID (16 random bytes)
+//! * all files, each as a sequence of encrypted chunks (with 16-byte tag)
+//! * encrypted index: number of files (`u32`), then for each file:
+//!   length of name (`u32`), name (UTF-8), offset (`u64`), size (`u64`)
+//! * offset (`u64`) and size (`u64`) of the encrypted index
+//!
+//! The first file in the index is the main scene.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Tag, XChaCha20Poly1305, XNonce};
+
+use crate::error::ResultExt;
+use crate::loader::{normalize, resolve_seek, ByteReader, OpenCallback};
+use crate::parser;
+use crate::{LoadError, LoadOptions, Scene};
+
+/// Size of the key in bytes
+pub const KEY_SIZE: usize = 32;
+
+const MAGIC: &[u8; 8] = b"ASDFBNDL";
+
+const VERSION: u32 = 1;
+
+/// Number of plaintext bytes per chunk
+const CHUNK_SIZE: usize = 1 << 16;
+
+const TAG_SIZE: usize = 16;
+
+const ID_SIZE: usize = 16;
+
+const HEADER_SIZE: u64 = (MAGIC.len() + 4 + ID_SIZE) as u64;
+
+/// Used instead of a file number for encrypting the index
+const INDEX_NUMBER: u32 = u32::MAX;
+
+struct Entry {
+    number: u32,
+    offset: u64,
+    size: u64,
+}
+
+/// Nonces consist of the (random) bundle ID, the file number and the chunk number.
+fn nonce(id: &[u8; ID_SIZE], number: u32, chunk: u32) -> XNonce {
+    let mut nonce = XNonce::default();
+    nonce[..ID_SIZE].copy_from_slice(id);
+    nonce[ID_SIZE..ID_SIZE + 4].copy_from_slice(&number.to_le_bytes());
+    nonce[ID_SIZE + 4..].copy_from_slice(&chunk.to_le_bytes());
+    nonce
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn encrypted_size(size: u64) -> u64 {
+    let chunks = (size + CHUNK_SIZE as u64 - 1) / CHUNK_SIZE as u64;
+    size + chunks * TAG_SIZE as u64
+}
+
+impl Scene {
+    /// Loads the main scene of an encrypted bundle (see `bundle` module),
+    /// see `Scene::with_options()`.
+    pub fn load_bundle<P: AsRef<Path>>(
+        path: P,
+        key: &[u8; KEY_SIZE],
+        options: &LoadOptions,
+    ) -> Result<Scene, LoadError> {
+        let path = path.as_ref();
+        let bundle = Bundle::open(path, key).context(path)?;
+        let main_scene = bundle.main_scene().to_path_buf();
+        parser::load_scene(
+            &main_scene,
+            options,
+            Some(bundle.into_open_callback()),
+            false,
+        )
+    }
+}
+
+/// An opened bundle (only the index is read).
+pub struct Bundle {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+    id: [u8; ID_SIZE],
+    entries: HashMap<PathBuf, Entry>,
+    main_scene: PathBuf,
+}
+
+impl Bundle {
+    /// Returns an error of kind `InvalidData` if the file is not a bundle,
+    /// if it has been modified or if `key` is wrong.
+    pub fn open<P: AsRef<Path>>(path: P, key: &[u8; KEY_SIZE]) -> io::Result<Bundle> {
+        let path = path.as_ref();
+        let mut file = fs::File::open(path)?;
+        let mut header = [0u8; HEADER_SIZE as usize];
+        file.read_exact(&mut header)?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("Not an ASDF bundle"));
+        }
+        let version = u32::from_le_bytes(header[MAGIC.len()..][..4].try_into().unwrap());
+        if version != VERSION {
+            return Err(invalid_data("Unsupported bundle version"));
+        }
+        let id: [u8; ID_SIZE] = header[MAGIC.len() + 4..].try_into().unwrap();
+
+        let mut trailer = [0u8; 16];
+        file.seek(SeekFrom::End(-(trailer.len() as i64)))?;
+        file.read_exact(&mut trailer)?;
+        let index_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let index_size = u64::from_le_bytes(trailer[8..].try_into().unwrap());
+        if index_size < TAG_SIZE as u64 || index_size > file.metadata()?.len() {
+            return Err(invalid_data("Invalid bundle index"));
+        }
+        let mut index = vec![0u8; index_size as usize];
+        file.seek(SeekFrom::Start(index_offset))?;
+        file.read_exact(&mut index)?;
+        let tag = Tag::clone_from_slice(&index[index.len() - TAG_SIZE..]);
+        index.truncate(index.len() - TAG_SIZE);
+        let cipher = XChaCha20Poly1305::new(key.into());
+        cipher
+            .decrypt_in_place_detached(&nonce(&id, INDEX_NUMBER, 0), b"", &mut index, &tag)
+            .map_err(|_| invalid_data("Wrong key or modified bundle"))?;
+
+        fn read_u32(index: &mut &[u8]) -> io::Result<u32> {
+            let mut bytes = [0u8; 4];
+            index.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+        let mut index = &index[..];
+        let count = read_u32(&mut index)?;
+        let mut entries = HashMap::new();
+        let mut main_scene = None;
+        for number in 0..count {
+            let mut name = vec![0u8; read_u32(&mut index)? as usize];
+            index.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid_data("Invalid file name"))?;
+            let mut numbers = [0u8; 16];
+            index.read_exact(&mut numbers)?;
+            let entry = Entry {
+                number,
+                offset: u64::from_le_bytes(numbers[..8].try_into().unwrap()),
+                size: u64::from_le_bytes(numbers[8..].try_into().unwrap()),
+            };
+            let name = normalize(Path::new(&name));
+            main_scene.get_or_insert_with(|| name.clone());
+            entries.insert(name, entry);
+        }
+        Ok(Bundle {
+            path: path.into(),
+            cipher,
+            id,
+            entries,
+            main_scene: main_scene.ok_or_else(|| invalid_data("Empty bundle"))?,
+        })
+    }
+
+    /// Path of the main scene file within the bundle.
+    pub fn main_scene(&self) -> &Path {
+        &self.main_scene
+    }
+
+    /// Names of all files in the bundle (in no particular order).
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.entries.keys().map(|p| p.as_path())
+    }
+
+    /// Opens a file within the bundle, returns an error of kind `NotFound`
+    /// if it doesn't exist.
+    pub fn open_file<P: AsRef<Path>>(&self, name: P) -> io::Result<EncryptedFile> {
+        let entry = self
+            .entries
+            .get(&normalize(name.as_ref()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found in bundle"))?;
+        Ok(EncryptedFile {
+            file: fs::File::open(&self.path)?,
+            cipher: self.cipher.clone(),
+            nonce_id: self.id,
+            number: entry.number,
+            offset: entry.offset,
+            size: entry.size,
+            buffer: Vec::with_capacity(CHUNK_SIZE + TAG_SIZE),
+            chunk: None,
+            position: 0,
+        })
+    }
+
+    /// Can be used with `Scene::load_with_reader()`.
+    pub fn into_open_callback(self) -> OpenCallback {
+        Arc::new(move |path: &Path| {
+            self.open_file(path)
+                .map(|file| Box::new(file) as Box<dyn ByteReader>)
+        })
+    }
+}
+
+/// A file within a bundle, decrypted on the fly.
+pub struct EncryptedFile {
+    file: fs::File,
+    cipher: XChaCha20Poly1305,
+    nonce_id: [u8; ID_SIZE],
+    number: u32,
+    /// Begin of the encrypted data within the bundle
+    offset: u64,
+    /// Size of the decrypted data
+    size: u64,
+    /// Decrypted data of the current chunk
+    buffer: Vec<u8>,
+    /// Number of the chunk in `buffer`
+    chunk: Option<u32>,
+    position: u64,
+}
+
+impl EncryptedFile {
+    fn load_chunk(&mut self, chunk: u32) -> io::Result<()> {
+        self.chunk = None;
+        let begin = u64::from(chunk) * CHUNK_SIZE as u64;
+        let plain_size = (self.size - begin).min(CHUNK_SIZE as u64) as usize;
+        self.buffer.resize(plain_size + TAG_SIZE, 0);
+        self.file.seek(SeekFrom::Start(
+            self.offset + u64::from(chunk) * (CHUNK_SIZE + TAG_SIZE) as u64,
+        ))?;
+        self.file.read_exact(&mut self.buffer)?;
+        let tag = Tag::clone_from_slice(&self.buffer[plain_size..]);
+        self.buffer.truncate(plain_size);
+        self.cipher
+            .decrypt_in_place_detached(
+                &nonce(&self.nonce_id, self.number, chunk),
+                b"",
+                &mut self.buffer,
+                &tag,
+            )
+            .map_err(|_| invalid_data("Modified bundle"))?;
+        self.chunk = Some(chunk);
+        Ok(())
+    }
+}
+
+impl Read for EncryptedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+        let chunk = (self.position / CHUNK_SIZE as u64) as u32;
+        if self.chunk != Some(chunk) {
+            self.load_chunk(chunk)?;
+        }
+        let start = (self.position % CHUNK_SIZE as u64) as usize;
+        let available = &self.buffer[start..];
+        let bytes = available.len().min(buf.len());
+        buf[..bytes].copy_from_slice(&available[..bytes]);
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl Seek for EncryptedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = resolve_seek(pos, self.position, self.size)?;
+        Ok(self.position)
+    }
+}
+
+/// Creates a new bundle.
+///
+/// The first added file is the main scene.
+/// All files referenced by it (audio files and sub-scenes) have to be added
+/// with names matching the (relative) paths used in the scene files.
+pub struct BundleWriter {
+    file: BufWriter<fs::File>,
+    cipher: XChaCha20Poly1305,
+    id: [u8; ID_SIZE],
+    /// Name, offset and size of all files
+    index: Vec<(String, u64, u64)>,
+    position: u64,
+}
+
+impl BundleWriter {
+    pub fn create<P: AsRef<Path>>(path: P, key: &[u8; KEY_SIZE]) -> io::Result<BundleWriter> {
+        let mut id = [0u8; ID_SIZE];
+        getrandom::getrandom(&mut id)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&id)?;
+        Ok(BundleWriter {
+            file,
+            cipher: XChaCha20Poly1305::new(key.into()),
+            id,
+            index: Vec::new(),
+            position: HEADER_SIZE,
+        })
+    }
+
+    /// Adds the contents of `source` with the given `name`
+    /// (a relative path with `/` as separator).
+    pub fn add_file<P: AsRef<Path>>(&mut self, name: &str, source: P) -> io::Result<()> {
+        if Path::new(name).is_absolute() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "File names in bundles must be relative",
+            ));
+        }
+        if self.index.len() >= INDEX_NUMBER as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Too many files in bundle",
+            ));
+        }
+        let number = self.index.len() as u32;
+        let mut source = fs::File::open(source)?;
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE + TAG_SIZE);
+        let mut size = 0;
+        let mut chunk = 0;
+        loop {
+            buffer.clear();
+            (&mut source)
+                .take(CHUNK_SIZE as u64)
+                .read_to_end(&mut buffer)?;
+            if buffer.is_empty() {
+                break;
+            }
+            size += buffer.len() as u64;
+            self.write_encrypted(&mut buffer, number, chunk)?;
+            chunk = chunk
+                .checked_add(1)
+                .ok_or_else(|| invalid_data("File too large"))?;
+        }
+        self.index.push((name.into(), self.position, size));
+        self.position += encrypted_size(size);
+        Ok(())
+    }
+
+    fn write_encrypted(&mut self, data: &mut [u8], number: u32, chunk: u32) -> io::Result<()> {
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(&nonce(&self.id, number, chunk), b"", data)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Encryption failed"))?;
+        self.file.write_all(data)?;
+        self.file.write_all(&tag)
+    }
+
+    /// Writes the index, the bundle cannot be loaded without calling this.
+    pub fn finish(mut self) -> io::Result<()> {
+        let mut index = Vec::new();
+        index.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        for (name, offset, size) in &self.index {
+            index.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            index.extend_from_slice(name.as_bytes());
+            index.extend_from_slice(&offset.to_le_bytes());
+            index.extend_from_slice(&size.to_le_bytes());
+        }
+        let index_offset = self.position;
+        let index_size = (index.len() + TAG_SIZE) as u64;
+        self.write_encrypted(&mut index, INDEX_NUMBER, 0)?;
+        self.file.write_all(&index_offset.to_le_bytes())?;
+        self.file.write_all(&index_size.to_le_bytes())?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_SIZE] = [42; KEY_SIZE];
+
+    /// Size of `a.bin` and `b.bin`, three chunks (the last one is partial)
+    const FILE_SIZE: usize = 2 * CHUNK_SIZE + 1000;
+
+    /// Temporary directory which is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir =
+                std::env::temp_dir().join(format!("asdf-bundle-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn contents(name: &str) -> Vec<u8> {
+        match name {
+            "scene.asd" => b"<asdf version=\"0.4\"/>".to_vec(),
+            "a.bin" => (0..FILE_SIZE).map(|i| (i % 251) as u8).collect(),
+            "b.bin" => (0..FILE_SIZE).map(|i| (i % 241) as u8).collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Creates `bundle.asdb` in `dir`, modifies it and opens it again.
+    fn create(dir: &TempDir, modify: impl FnOnce(&mut Vec<u8>, &Bundle)) -> io::Result<Bundle> {
+        let path = dir.0.join("bundle.asdb");
+        let mut writer = BundleWriter::create(&path, &KEY).unwrap();
+        for &name in &["scene.asd", "a.bin", "b.bin"] {
+            let source = dir.0.join(name);
+            fs::write(&source, contents(name)).unwrap();
+            writer.add_file(name, &source).unwrap();
+        }
+        writer.finish().unwrap();
+        let bundle = Bundle::open(&path, &KEY).unwrap();
+        let mut data = fs::read(&path).unwrap();
+        modify(&mut data, &bundle);
+        fs::write(&path, data).unwrap();
+        Bundle::open(&path, &KEY)
+    }
+
+    fn read(bundle: &Bundle, name: &str) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        bundle.open_file(name)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Position of the given chunk of a file within the bundle.
+    fn chunk_offset(bundle: &Bundle, name: &str, chunk: usize) -> usize {
+        bundle.entries[Path::new(name)].offset as usize + chunk * (CHUNK_SIZE + TAG_SIZE)
+    }
+
+    fn assert_invalid<T>(result: io::Result<T>) {
+        match result {
+            Ok(_) => panic!("modification was not detected"),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("round-trip");
+        let bundle = create(&dir, |_, _| {}).unwrap();
+        assert_eq!(bundle.main_scene(), Path::new("scene.asd"));
+        assert_eq!(bundle.files().count(), 3);
+        for &name in &["scene.asd", "a.bin", "b.bin"] {
+            assert_eq!(read(&bundle, name).unwrap(), contents(name));
+        }
+        assert_eq!(
+            bundle.open_file("c.bin").err().unwrap().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_invalid(Bundle::open(dir.0.join("bundle.asdb"), &[0; KEY_SIZE]));
+    }
+
+    #[test]
+    fn seeking() {
+        let dir = TempDir::new("seeking");
+        let bundle = create(&dir, |_, _| {}).unwrap();
+        let expected = contents("a.bin");
+        let mut file = bundle.open_file("a.bin").unwrap();
+        let mut buffer = [0u8; 10];
+        // Across a chunk boundary
+        let start = CHUNK_SIZE as u64 - 5;
+        assert_eq!(file.seek(SeekFrom::Start(start)).unwrap(), start);
+        file.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, expected[start as usize..][..10]);
+        assert_eq!(file.seek(SeekFrom::Current(-20)).unwrap(), start - 10);
+        file.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, expected[start as usize - 10..][..10]);
+        let end = FILE_SIZE as u64 - 10;
+        assert_eq!(file.seek(SeekFrom::End(-10)).unwrap(), end);
+        file.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, expected[end as usize..]);
+        assert_eq!(file.read(&mut buffer).unwrap(), 0);
+        assert_eq!(
+            file.seek(SeekFrom::Current(-(FILE_SIZE as i64) - 1))
+                .err()
+                .unwrap()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn flipped_byte() {
+        let dir = TempDir::new("flipped-byte");
+        let bundle = create(&dir, |data, bundle| {
+            data[chunk_offset(bundle, "a.bin", 1) + 100] ^= 1;
+        })
+        .unwrap();
+        assert_invalid(read(&bundle, "a.bin"));
+        // Other files are not affected
+        assert_eq!(read(&bundle, "b.bin").unwrap(), contents("b.bin"));
+
+        let dir = TempDir::new("flipped-index-byte");
+        assert_invalid(create(&dir, |data, _| {
+            let len = data.len();
+            data[len - 20] ^= 1;
+        }));
+    }
+
+    #[test]
+    fn truncated_chunk() {
+        let dir = TempDir::new("truncated-chunk");
+        let bundle = create(&dir, |data, bundle| {
+            // The last chunk of the last file, the index is moved accordingly
+            let end = chunk_offset(bundle, "b.bin", 2) + 1000 + TAG_SIZE;
+            data.drain(end - 10..end);
+            let len = data.len();
+            let trailer = &mut data[len - 16..len - 8];
+            let index_offset = u64::from_le_bytes((&*trailer).try_into().unwrap());
+            trailer.copy_from_slice(&(index_offset - 10).to_le_bytes());
+        })
+        .unwrap();
+        assert_eq!(read(&bundle, "a.bin").unwrap(), contents("a.bin"));
+        assert_invalid(read(&bundle, "b.bin"));
+
+        let dir = TempDir::new("truncated-bundle");
+        assert!(create(&dir, |data, _| data.truncate(data.len() - 10)).is_err());
+    }
+
+    #[test]
+    fn reordered_chunks() {
+        let dir = TempDir::new("reordered-chunks");
+        let bundle = create(&dir, |data, bundle| {
+            let first = chunk_offset(bundle, "a.bin", 0);
+            let (chunk0, rest) = data[first..].split_at_mut(CHUNK_SIZE + TAG_SIZE);
+            chunk0.swap_with_slice(&mut rest[..CHUNK_SIZE + TAG_SIZE]);
+        })
+        .unwrap();
+        assert_invalid(read(&bundle, "a.bin"));
+    }
+
+    #[test]
+    fn reordered_files() {
+        let dir = TempDir::new("reordered-files");
+        let bundle = create(&dir, |data, bundle| {
+            let a = chunk_offset(bundle, "a.bin", 0);
+            let b = chunk_offset(bundle, "b.bin", 0);
+            let (first, second) = data[a..].split_at_mut(b - a);
+            first.swap_with_slice(&mut second[..b - a]);
+        })
+        .unwrap();
+        assert_invalid(read(&bundle, "a.bin"));
+        assert_invalid(read(&bundle, "b.bin"));
+    }
+
+    #[test]
+    fn reordered_index() {
+        let dir = TempDir::new("reordered-index");
+        // Even with the correct key, files can't be re-assigned
+        let bundle = create(&dir, |data, bundle| {
+            let mut index = Vec::new();
+            index.extend_from_slice(&3u32.to_le_bytes());
+            for &name in &["scene.asd", "b.bin", "a.bin"] {
+                let entry = &bundle.entries[Path::new(name)];
+                index.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                index.extend_from_slice(name.as_bytes());
+                index.extend_from_slice(&entry.offset.to_le_bytes());
+                index.extend_from_slice(&entry.size.to_le_bytes());
+            }
+            let tag = bundle
+                .cipher
+                .encrypt_in_place_detached(&nonce(&bundle.id, INDEX_NUMBER, 0), b"", &mut index)
+                .unwrap();
+            index.extend_from_slice(&tag);
+            let len = data.len();
+            let begin = len - 16 - index.len();
+            data[begin..len - 16].copy_from_slice(&index);
+        })
+        .unwrap();
+        assert_eq!(read(&bundle, "scene.asd").unwrap(), contents("scene.asd"));
+        assert_invalid(read(&bundle, "a.bin"));
+        assert_invalid(read(&bundle, "b.bin"));
+    }
+}
@@ -4,13 +4,26 @@
 use std::cell::RefCell;
 use std::ffi::{CStr, CString};
 use std::fmt::Display;
+use std::os::raw::{c_char, c_void};
 use std::panic::{catch_unwind, AssertUnwindSafe, UnwindSafe};
 use std::time::Duration;
 
-use libc::c_char;
-
+#[cfg(feature = "threads")]
+use crate::chain::{SceneChain, SceneLoader};
+use crate::monitor::{TransformFeed, TransformMonitor};
+#[cfg(feature = "osc")]
+use crate::osc::OscServer;
+use crate::recorder::Recorder;
+#[cfg(feature = "remote")]
+use crate::remote::RemoteServer;
+use crate::split::{SceneAudio, SceneControl};
 use crate::transform::{Quat, Transform, Vec3};
-use crate::{Scene, Source};
+#[cfg(any(feature = "osc", feature = "remote"))]
+use crate::TransportRequest;
+use crate::{
+    AmbisonicsGroup, Area, AreaPolicy, BoundingBox, ClipInfo, Event, FadeShape, LoadOptions,
+    Marker, Scene, ScheduledSeek, ScheduledSeekState, Source, TransformModifier, TransportState,
+};
 
 #[repr(C)]
 #[derive(Default)]
@@ -21,6 +34,9 @@ pub struct AsdfTransform {
     rot_v: [f32; 3],
     /// Scalar part of quaternion
     rot_s: f32,
+    /// Whether the position has been moved into the area
+    /// (see asdf_scene_set_area_box())
+    clamped: bool,
 }
 
 impl From<Option<Transform>> for AsdfTransform {
@@ -32,6 +48,7 @@ impl From<Option<Transform>> for AsdfTransform {
                 pos: t.translation.unwrap_or_else(Vec3::zeros).into(),
                 rot_v: rot.vector().into(),
                 rot_s: rot.scalar(),
+                clamped: t.clamped,
             }
         } else {
             AsdfTransform::default()
@@ -39,11 +56,78 @@ impl From<Option<Transform>> for AsdfTransform {
     }
 }
 
+/// Axis-aligned bounding box, see asdf_scene_extent().
+#[repr(C)]
+#[derive(Default)]
+pub struct AsdfBoundingBox {
+    /// `false` if there are no positions (i.e. no active sources)
+    valid: bool,
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl From<Option<BoundingBox>> for AsdfBoundingBox {
+    fn from(extent: Option<BoundingBox>) -> AsdfBoundingBox {
+        match extent {
+            Some(extent) => AsdfBoundingBox {
+                valid: true,
+                min: extent.min,
+                max: extent.max,
+            },
+            None => AsdfBoundingBox::default(),
+        }
+    }
+}
+
+/// See asdf_scene_scheduled_seek().
+#[repr(C)]
+pub struct AsdfScheduledSeek {
+    /// `false` if no seek was scheduled (the other fields are meaningless)
+    valid: bool,
+    at: u64,
+    target: u64,
+    state: ScheduledSeekState,
+}
+
+impl From<Option<ScheduledSeek>> for AsdfScheduledSeek {
+    fn from(seek: Option<ScheduledSeek>) -> AsdfScheduledSeek {
+        match seek {
+            Some(seek) => AsdfScheduledSeek {
+                valid: true,
+                at: seek.at,
+                target: seek.target,
+                state: seek.state,
+            },
+            None => AsdfScheduledSeek {
+                valid: false,
+                at: 0,
+                target: 0,
+                state: ScheduledSeekState::Missed,
+            },
+        }
+    }
+}
+
 #[repr(C)]
 pub struct AsdfSource {
     id: *const c_char,
     name: *const c_char,
     model: *const c_char,
+    port: *const c_char,
+    /// Whether `color` is valid (i.e. a `color` attribute was given)
+    has_color: bool,
+    /// RGB values
+    color: [u8; 3],
+    icon: *const c_char,
+    comment: *const c_char,
+    /// Separated by spaces (empty if there are no tags)
+    tags: *const c_char,
+    /// Expected length of reverb/decay tail after the end of each clip (in frames)
+    tail: u64,
+    /// Whether the polarity is inverted (already applied to the audio data)
+    inverted: bool,
+    /// Static delay in frames (already applied to the audio data)
+    delay: u32,
 }
 
 impl AsdfSource {
@@ -57,6 +141,15 @@ impl AsdfSource {
             id: char_ptr(&source.id),
             name: char_ptr(&source.name),
             model: char_ptr(&source.model),
+            port: char_ptr(&source.port),
+            has_color: source.color.is_some(),
+            color: source.color.unwrap_or_default(),
+            icon: char_ptr(&source.icon),
+            comment: char_ptr(&source.comment),
+            tags: CString::new(source.tags.join(" ")).unwrap().into_raw(),
+            tail: source.tail,
+            inverted: source.inverted,
+            delay: source.delay,
         }
     }
 }
@@ -67,16 +160,188 @@ impl Drop for AsdfSource {
             CString::from_raw(self.id as *mut _);
             CString::from_raw(self.name as *mut _);
             CString::from_raw(self.model as *mut _);
+            CString::from_raw(self.port as *mut _);
+            CString::from_raw(self.icon as *mut _);
+            CString::from_raw(self.comment as *mut _);
+            CString::from_raw(self.tags as *mut _);
+        }
+    }
+}
+
+#[repr(C)]
+pub struct AsdfAttribute {
+    name: *const c_char,
+    value: *const c_char,
+}
+
+impl AsdfAttribute {
+    fn new(name: &str, value: &str) -> AsdfAttribute {
+        AsdfAttribute {
+            name: CString::new(name).unwrap().into_raw(),
+            value: CString::new(value).unwrap().into_raw(),
+        }
+    }
+}
+
+impl Drop for AsdfAttribute {
+    fn drop(&mut self) {
+        unsafe {
+            CString::from_raw(self.name as *mut _);
+            CString::from_raw(self.value as *mut _);
+        }
+    }
+}
+
+#[repr(C)]
+pub struct AsdfClipInfo {
+    begin: u64,
+    duration: u64,
+    file: *const c_char,
+    /// Zero-based channel number within the file
+    channel: u32,
+    speed: f64,
+}
+
+impl AsdfClipInfo {
+    fn new(clip: &ClipInfo) -> AsdfClipInfo {
+        AsdfClipInfo {
+            begin: clip.begin,
+            duration: clip.duration,
+            file: CString::new(clip.file.to_string_lossy().as_bytes())
+                .unwrap()
+                .into_raw(),
+            channel: clip.channel,
+            speed: clip.speed,
+        }
+    }
+}
+
+impl Drop for AsdfClipInfo {
+    fn drop(&mut self) {
+        unsafe {
+            CString::from_raw(self.file as *mut _);
+        }
+    }
+}
+
+#[repr(C)]
+pub struct AsdfEvent {
+    frame: u64,
+    /// Zero-based source index, -1 if the event doesn't belong to a source
+    source: i64,
+    /// NB: This is *not* NUL-terminated!
+    data: *const c_char,
+    data_len: usize,
+}
+
+impl AsdfEvent {
+    fn new(frame: u64, event: &Event) -> AsdfEvent {
+        AsdfEvent {
+            frame,
+            source: event.source.map(|idx| idx as i64).unwrap_or(-1),
+            data: event.data.as_ptr() as *const c_char,
+            data_len: event.data.len(),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct AsdfMarker {
+    frame: u64,
+    name: *const c_char,
+}
+
+impl AsdfMarker {
+    fn new(marker: &Marker) -> AsdfMarker {
+        AsdfMarker {
+            frame: marker.frame,
+            name: CString::new(marker.name.as_str()).unwrap().into_raw(),
+        }
+    }
+}
+
+impl Drop for AsdfMarker {
+    fn drop(&mut self) {
+        unsafe {
+            CString::from_raw(self.name as *mut _);
         }
     }
 }
 
+pub type AsdfEventCallback = extern "C" fn(event: *const AsdfEvent, user_data: *mut c_void);
+
 impl Scene {
     pub fn get_source(&self, index: usize) -> AsdfSource {
         AsdfSource::new(&self.sources[index])
     }
 }
 
+/// Maximum amount of memory (in bytes) used for caching decoded audio data
+/// of compressed files (`0`, the default, disables the cache).
+///
+/// This only affects scenes which are loaded afterwards.
+#[no_mangle]
+pub extern "C" fn asdf_set_decode_cache_budget(bytes: usize) {
+    crate::cache::set_memory_budget(bytes);
+}
+
+/// Options for asdf_scene_new_with_options() and similar functions,
+/// asdf_scene_options_default() provides the default values.
+#[repr(C)]
+pub struct AsdfSceneOptions {
+    /// Size of the buffer between the reader thread(s) and
+    /// asdf_get_audio_data() (in blocks)
+    buffer_blocks: u32,
+    /// Time the reader thread sleeps when the buffer is full
+    usleeptime: u64,
+    /// Number of threads used for reading audio files
+    /// (`0`: audio data is read in asdf_get_audio_data(), only for offline use)
+    reader_threads: u32,
+    /// Clips shorter than this are completely loaded into memory
+    /// (unless overridden by their `preload` attribute)
+    preload_threshold_ms: u64,
+    /// WAV files of at least this size (in bytes) bypass the page cache
+    /// of the operating system (`0` disables this)
+    direct_io_threshold: u64,
+    /// Maximum acceptable execution time of asdf_get_audio_data()
+    /// (`0`: no limit), loading fails if it cannot be met
+    /// (i.e. with `reader_threads == 0`), see asdf_scene_latency_violations()
+    max_latency_us: u64,
+}
+
+impl AsdfSceneOptions {
+    fn load_options(&self, samplerate: u32, blocksize: u32) -> LoadOptions {
+        let mut options = LoadOptions::new(samplerate, blocksize)
+            .buffer_blocks(self.buffer_blocks)
+            .sleeptime(Duration::from_micros(self.usleeptime))
+            .reader_threads(self.reader_threads)
+            .preload_threshold(Duration::from_millis(self.preload_threshold_ms));
+        if self.direct_io_threshold > 0 {
+            options = options.direct_io_threshold(self.direct_io_threshold);
+        }
+        if self.max_latency_us > 0 {
+            options = options.max_latency(Duration::from_micros(self.max_latency_us));
+        }
+        options
+    }
+}
+
+/// 16 buffer blocks, 10 ms sleep time, one reader thread,
+/// no preloading, no direct I/O and no latency limit.
+#[no_mangle]
+pub extern "C" fn asdf_scene_options_default() -> AsdfSceneOptions {
+    AsdfSceneOptions {
+        buffer_blocks: 16,
+        usleeptime: 10_000,
+        reader_threads: 1,
+        preload_threshold_ms: 0,
+        direct_io_threshold: 0,
+        max_latency_us: 0,
+    }
+}
+
+/// Like asdf_scene_new_with_options(), with default values for all other
+/// options (see asdf_scene_options_default()).
 #[no_mangle]
 pub unsafe extern "C" fn asdf_scene_new(
     filename: *const c_char,
@@ -85,18 +350,59 @@ pub unsafe extern "C" fn asdf_scene_new(
     buffer_blocks: u32,
     usleeptime: u64,
 ) -> *mut Scene {
+    let options = AsdfSceneOptions {
+        buffer_blocks,
+        usleeptime,
+        ..asdf_scene_options_default()
+    };
+    asdf_scene_new_with_options(filename, samplerate, blocksize, &options)
+}
+
+/// Loads a scene, the fade (see asdf_scene_set_fade()) is one block long.
+///
+/// Returns NULL on error (see asdf_scene_last_error()).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_new_with_options(
+    filename: *const c_char,
+    samplerate: u32,
+    blocksize: u32,
+    options: *const AsdfSceneOptions,
+) -> *mut Scene {
+    assert!(!options.is_null());
+    let options = (*options).load_options(samplerate, blocksize);
+    handle_errors(
+        || {
+            let filename = CStr::from_ptr(filename).to_str().unwrap_display();
+            Box::into_raw(Box::new(
+                Scene::with_options(filename, &options).unwrap_display(),
+            ))
+        },
+        std::ptr::null_mut(),
+    )
+}
+
+/// Loads the main scene of an encrypted bundle.
+///
+/// `key` must point to 32 bytes.
+/// The other arguments are the same as in asdf_scene_new_with_options().
+#[cfg(feature = "bundle")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_new_from_bundle(
+    filename: *const c_char,
+    key: *const u8,
+    samplerate: u32,
+    blocksize: u32,
+    options: *const AsdfSceneOptions,
+) -> *mut Scene {
+    assert!(!key.is_null());
+    assert!(!options.is_null());
+    let options = (*options).load_options(samplerate, blocksize);
     handle_errors(
         || {
             let filename = CStr::from_ptr(filename).to_str().unwrap_display();
+            let key = &*(key as *const [u8; crate::bundle::KEY_SIZE]);
             Box::into_raw(Box::new(
-                Scene::new(
-                    filename,
-                    samplerate,
-                    blocksize,
-                    buffer_blocks,
-                    Duration::from_micros(usleeptime),
-                )
-                .unwrap_display(),
+                Scene::load_bundle(filename, key, &options).unwrap_display(),
             ))
         },
         std::ptr::null_mut(),
@@ -110,6 +416,287 @@ pub unsafe extern "C" fn asdf_scene_free(ptr: *mut Scene) {
     }
 }
 
+/// Plays the scenes given by `filenames` (an array of `count` strings)
+/// one after another, see the `chain` module.
+///
+/// The scenes are loaded (in a background thread) like with
+/// asdf_scene_new_with_options(), scenes which cannot be loaded
+/// (or have more than `channels` sources) are skipped.
+/// Consecutive scenes overlap by `crossfade_ms` milliseconds,
+/// see asdf_scene_chain_set_crossfade().
+/// The fade is one block long, see asdf_scene_chain_set_fade().
+///
+/// Returns NULL on error (see asdf_scene_last_error()).
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_new(
+    filenames: *const *const c_char,
+    count: usize,
+    channels: u32,
+    samplerate: u32,
+    blocksize: u32,
+    options: *const AsdfSceneOptions,
+    crossfade_ms: u32,
+    crossfade_shape: FadeShape,
+) -> *mut SceneChain {
+    assert!(!options.is_null());
+    let sleeptime = Duration::from_micros((*options).usleeptime);
+    let options = (*options).load_options(samplerate, blocksize);
+    handle_errors(
+        || {
+            assert!(count == 0 || !filenames.is_null());
+            let loaders = (0..count)
+                .map(|i| {
+                    let filename = CStr::from_ptr(*filenames.add(i))
+                        .to_string_lossy()
+                        .into_owned();
+                    let options = options.clone();
+                    Box::new(move || Scene::with_options(&filename, &options)) as SceneLoader
+                })
+                .collect();
+            let crossfade = match crossfade_frames(crossfade_ms, samplerate) {
+                Some(frames) => frames,
+                None => return std::ptr::null_mut(),
+            };
+            let mut chain = SceneChain::new(loaders, channels, samplerate, blocksize, sleeptime);
+            let fade = Duration::from_secs_f64(f64::from(blocksize) / f64::from(samplerate));
+            chain.set_fade(fade, FadeShape::Linear);
+            chain.set_crossfade(crossfade, crossfade_shape);
+            Box::into_raw(Box::new(chain))
+        },
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_free(ptr: *mut SceneChain) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+    }
+}
+
+/// Sets duration (in milliseconds) and shape of the overlap between
+/// consecutive scenes (`0` means back-to-back playback).
+///
+/// A crossfade which is already running is not affected.
+/// Returns `false` (see asdf_scene_last_error()) if the crossfade is too long.
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_set_crossfade(
+    ptr: *mut SceneChain,
+    milliseconds: u32,
+    shape: FadeShape,
+) -> bool {
+    assert!(!ptr.is_null());
+    let chain = &mut *ptr;
+    match crossfade_frames(milliseconds, chain.samplerate()) {
+        Some(frames) => {
+            chain.set_crossfade(frames, shape);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sets duration (in milliseconds) and shape of the fade-in and fade-out
+/// when starting and stopping playback, see asdf_scene_set_fade().
+///
+/// By default, a linear fade over one block is used.
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_set_fade(
+    ptr: *mut SceneChain,
+    milliseconds: f32,
+    shape: FadeShape,
+) {
+    assert!(!ptr.is_null());
+    let chain = &mut *ptr;
+    chain.set_fade(
+        Duration::from_secs_f32(milliseconds.max(0.0) / 1000.0),
+        shape,
+    );
+}
+
+/// Writes one block of audio data into each of the `channels` given in
+/// asdf_scene_chain_new(), see asdf_scene_get_audio_data().
+///
+/// Return value of `false` means un-recoverable error
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_get_audio_data(
+    ptr: *mut SceneChain,
+    data: *const *mut f32,
+    rolling: bool,
+) -> bool {
+    // TODO: remove AssertUnwindSafe once ring buffer is UnwindSafe
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let chain = &mut *ptr;
+            let data = if chain.channels() == 0 {
+                &[]
+            } else {
+                assert!(!data.is_null());
+                std::slice::from_raw_parts(data, chain.channels() as usize)
+            };
+            chain.get_audio_data(data, rolling)
+        }),
+        false,
+    )
+}
+
+/// Zero-based index of the scene which is currently playing,
+/// `-1` if none has been loaded yet.
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_current_index(ptr: *mut SceneChain) -> i64 {
+    assert!(!ptr.is_null());
+    let chain = &*ptr;
+    chain.current_index().map_or(-1, |idx| idx as i64)
+}
+
+/// The scene which is currently playing (during a crossfade, this is the
+/// incoming scene), `NULL` if none has been loaded yet.
+///
+/// It can be used with the asdf_scene_*() functions (e.g. for seeking within
+/// the scene), except asdf_scene_free() and asdf_scene_get_audio_data().
+/// The pointer is only valid until the next call to
+/// asdf_scene_chain_get_audio_data().
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_current_scene(ptr: *mut SceneChain) -> *mut Scene {
+    assert!(!ptr.is_null());
+    let chain = &mut *ptr;
+    chain
+        .current_scene_mut()
+        .map_or(std::ptr::null_mut(), |scene| scene as *mut Scene)
+}
+
+/// Whether the last scene has been played until its end.
+#[cfg(feature = "threads")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_chain_is_finished(ptr: *mut SceneChain) -> bool {
+    assert!(!ptr.is_null());
+    let chain = &*ptr;
+    chain.is_finished()
+}
+
+/// Sets duration (in milliseconds) and shape of the fade-in and fade-out
+/// when starting and stopping playback.
+///
+/// By default, a linear fade over one block is used.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_fade(ptr: *mut Scene, milliseconds: f32, shape: FadeShape) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.set_fade(
+        Duration::from_secs_f32(milliseconds.max(0.0) / 1000.0),
+        shape,
+    );
+}
+
+#[repr(C)]
+pub struct AsdfStreamingStats {
+    /// Current size of the buffer (in blocks)
+    buffer_blocks: u32,
+    /// 0 if nothing has been measured yet
+    suggested_buffer_blocks: u32,
+    /// Number of frames currently available in the buffer
+    buffered_frames: u64,
+    /// Number of buffer underruns since the scene was loaded
+    underruns: u64,
+    /// Worst-case time the reader thread needed for reading one block
+    max_block_read_time_us: u64,
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_streaming_stats(ptr: *mut Scene) -> AsdfStreamingStats {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    let stats = scene.streaming_stats();
+    AsdfStreamingStats {
+        buffer_blocks: stats.buffer_blocks,
+        suggested_buffer_blocks: stats.suggested_buffer_blocks.unwrap_or(0),
+        buffered_frames: stats.buffered_frames,
+        underruns: stats.underruns,
+        max_block_read_time_us: stats.max_block_read_time.as_micros() as u64,
+    }
+}
+
+#[repr(C)]
+pub struct AsdfLatencyViolations {
+    /// Number of calls to asdf_get_audio_data() which exceeded the limit
+    count: u64,
+    /// Execution time of the slowest call
+    longest_us: u64,
+}
+
+/// Calls which exceeded `max_latency_us` (see asdf_scene_new_with_options()).
+///
+/// This is only measured in debug builds.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_latency_violations(ptr: *mut Scene) -> AsdfLatencyViolations {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    let violations = scene.latency_violations();
+    AsdfLatencyViolations {
+        count: violations.count,
+        longest_us: violations.longest.as_micros() as u64,
+    }
+}
+
+/// Enables automatic growing of the buffer (up to `max_blocks`)
+/// based on the measured refill performance, 0 disables it.
+///
+/// The buffer is only re-allocated when seeking.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_buffer_growth_limit(ptr: *mut Scene, max_blocks: u32) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.set_buffer_growth_limit(if max_blocks == 0 {
+        None
+    } else {
+        Some(max_blocks)
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_frames(ptr: *mut Scene) -> u64 {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.frames()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_lead_in(ptr: *mut Scene) -> u64 {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.lead_in()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_lead_out(ptr: *mut Scene) -> u64 {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.lead_out()
+}
+
+/// Changes the lead-out (in frames), this also changes asdf_scene_frames().
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_lead_out(ptr: *mut Scene, frames: u64) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.set_lead_out(frames);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_is_finished(ptr: *mut Scene, frame: u64) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.is_finished(frame)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn asdf_scene_file_sources(ptr: *mut Scene) -> u32 {
     assert!(!ptr.is_null());
@@ -131,67 +718,1640 @@ pub unsafe extern "C" fn asdf_source_free(ptr: *mut AsdfSource) {
     }
 }
 
+/// Language tags in order of preference, separated by commas
+/// (e.g. `"de-AT,de,en"`), see `Scene::set_preferred_languages()`.
 #[no_mangle]
-pub unsafe extern "C" fn asdf_scene_get_source_transform(
+pub unsafe extern "C" fn asdf_scene_set_preferred_languages(
     ptr: *mut Scene,
-    source_idx: usize,
-    frame: u64,
-) -> AsdfTransform {
+    languages: *const c_char,
+) {
     assert!(!ptr.is_null());
+    assert!(!languages.is_null());
     let scene = &mut *ptr;
-    scene.get_source_transform(source_idx, frame).into()
+    let languages = CStr::from_ptr(languages).to_string_lossy();
+    let languages: Vec<_> = languages
+        .split(',')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    scene.set_preferred_languages(&languages);
 }
 
-/// Reference transform is always "active".
+/// Content of the `<meta>` element `name` in the preferred language,
+/// see `Scene::get_metadata()`.
+///
+/// Returns NULL if there is no such element.
+/// The result must be freed with `asdf_string_free()`.
 #[no_mangle]
-pub unsafe extern "C" fn asdf_scene_get_reference_transform(
+pub unsafe extern "C" fn asdf_scene_get_metadata(
     ptr: *mut Scene,
-    frame: u64,
-) -> AsdfTransform {
+    name: *const c_char,
+) -> *mut c_char {
     assert!(!ptr.is_null());
-    let scene = &mut *ptr;
-    scene.get_reference_transform(frame).into()
+    assert!(!name.is_null());
+    let scene = &*ptr;
+    let name = CStr::from_ptr(name).to_string_lossy();
+    match scene.get_metadata(&name) {
+        Some(content) => CString::new(content).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
 }
 
-// TODO: possibility to report errors?
+/// File name of the `<poster>` image, see `Scene::poster()`.
+///
+/// Returns NULL if there is no poster.
+/// The result must be freed with `asdf_string_free()`.
 #[no_mangle]
-pub unsafe extern "C" fn asdf_scene_seek(ptr: *mut Scene, frame: u64) -> bool {
+pub unsafe extern "C" fn asdf_scene_poster(ptr: *mut Scene) -> *mut c_char {
     assert!(!ptr.is_null());
-    let scene = &mut *ptr;
-    scene.seek(frame)
+    let scene = &*ptr;
+    match scene.poster() {
+        Some(path) => CString::new(path.to_string_lossy().as_bytes())
+            .unwrap()
+            .into_raw(),
+        None => std::ptr::null_mut(),
+    }
 }
 
-/// Return value of `false` means un-recoverable error
+/// Whether the given source has the tag `tag` (see `SourceInfo::tags`).
 #[no_mangle]
-pub unsafe extern "C" fn asdf_scene_get_audio_data(
+pub unsafe extern "C" fn asdf_scene_source_has_tag(
     ptr: *mut Scene,
-    data: *const *mut f32,
-    rolling: bool,
+    source_index: usize,
+    tag: *const c_char,
 ) -> bool {
-    // TODO: remove AssertUnwindSafe once ring buffer is UnwindSafe
-    handle_errors(
-        AssertUnwindSafe(|| {
-            assert!(!ptr.is_null());
-            let scene = &mut *ptr;
-            assert!(!data.is_null());
-            let data = std::slice::from_raw_parts(data, scene.file_sources() as usize);
-            let success = scene.get_audio_data(data, rolling);
-            if !success {
-                // TODO: get more error details from streamer
-                set_error("Unrecoverable error getting audio file data");
-            }
-            success
-        }),
-        false,
-    )
+    assert!(!ptr.is_null());
+    assert!(!tag.is_null());
+    let scene = &*ptr;
+    let tag = CStr::from_ptr(tag).to_string_lossy();
+    scene
+        .get_source_info(source_index)
+        .tags
+        .iter()
+        .any(|t| *t == tag)
 }
 
-/// The error message will be freed if another error occurs. It is the caller's
-/// responsibility to make sure they're no longer using the string before
-/// calling any other function which may fail.
+/// Number of extra attributes given in the `<source>` element.
 #[no_mangle]
-pub extern "C" fn asdf_scene_last_error() -> *const c_char {
-    LAST_ERROR.with(|cell| cell.borrow().as_ptr())
+pub unsafe extern "C" fn asdf_scene_source_attribute_count(
+    ptr: *mut Scene,
+    source_index: usize,
+) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.get_source_info(source_index).extra_attributes.len()
+}
+
+/// Returns NULL if `attribute_index` is out of range.
+/// The result must be freed with `asdf_attribute_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_source_attribute(
+    ptr: *mut Scene,
+    source_index: usize,
+    attribute_index: usize,
+) -> *mut AsdfAttribute {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    match scene
+        .get_source_info(source_index)
+        .extra_attributes
+        .get(attribute_index)
+    {
+        Some((name, value)) => Box::into_raw(Box::new(AsdfAttribute::new(name, value))),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_attribute_free(ptr: *mut AsdfAttribute) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Returns 0 (and sets an error message) if `source_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_clip_count(ptr: *mut Scene, source_idx: usize) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if !check_source_idx(scene, source_idx) {
+        return 0;
+    }
+    scene.clip_count(source_idx)
+}
+
+/// Clips are sorted by begin time.
+/// Returns NULL if `clip_idx` is out of range.
+/// If `source_idx` is out of range, an error message is set as well.
+/// The result must be freed with `asdf_clip_info_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_clip_info(
+    ptr: *mut Scene,
+    source_idx: usize,
+    clip_idx: usize,
+) -> *mut AsdfClipInfo {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if !check_source_idx(scene, source_idx) {
+        return std::ptr::null_mut();
+    }
+    match scene.clips(source_idx).nth(clip_idx) {
+        Some(clip) => Box::into_raw(Box::new(AsdfClipInfo::new(&clip))),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_clip_info_free(ptr: *mut AsdfClipInfo) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Number of (merged) activity ranges of the given source.
+/// Returns 0 (and sets an error message) if `source_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_source_activity_count(
+    ptr: *mut Scene,
+    source_idx: usize,
+) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if !check_source_idx(scene, source_idx) {
+        return 0;
+    }
+    scene.source_activity(source_idx).len()
+}
+
+/// Writes begin and end frame of the given activity range.
+/// Returns `false` if `range_idx` is out of range.
+/// If `source_idx` is out of range, an error message is set as well.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_source_activity(
+    ptr: *mut Scene,
+    source_idx: usize,
+    range_idx: usize,
+    begin: *mut u64,
+    end: *mut u64,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!begin.is_null());
+    assert!(!end.is_null());
+    let scene = &mut *ptr;
+    if !check_source_idx(scene, source_idx) {
+        return false;
+    }
+    if let Some(&(b, e)) = scene.source_activity(source_idx).get(range_idx) {
+        *begin = b;
+        *end = e;
+        true
+    } else {
+        false
+    }
+}
+
+/// Mutes (or unmutes) the given source, see `Scene::set_source_mute()`.
+/// Does nothing (and sets an error message) if `source_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_source_mute(
+    ptr: *mut Scene,
+    source_idx: usize,
+    mute: bool,
+) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if check_source_idx(scene, source_idx) {
+        scene.set_source_mute(source_idx, mute);
+    }
+}
+
+/// Returns `false` (and sets an error message) if `source_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_is_source_muted(ptr: *mut Scene, source_idx: usize) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    check_source_idx(scene, source_idx) && scene.is_source_muted(source_idx)
+}
+
+/// Solos (or un-solos) the given source, see `Scene::set_source_solo()`.
+/// Does nothing (and sets an error message) if `source_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_source_solo(
+    ptr: *mut Scene,
+    source_idx: usize,
+    solo: bool,
+) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if check_source_idx(scene, source_idx) {
+        scene.set_source_solo(source_idx, solo);
+    }
+}
+
+/// Returns `false` (and sets an error message) if `source_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_is_source_soloed(ptr: *mut Scene, source_idx: usize) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    check_source_idx(scene, source_idx) && scene.is_source_soloed(source_idx)
+}
+
+/// Sets the linear gain of the given source, see `Scene::set_source_gain()`.
+///
+/// Returns `false` (see asdf_scene_last_error()) if `source_idx` is
+/// out of range or if `gain` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_source_gain(
+    ptr: *mut Scene,
+    source_idx: usize,
+    gain: f32,
+) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if !check_source_idx(scene, source_idx) || !check_gain(gain) {
+        return false;
+    }
+    scene.set_source_gain(source_idx, gain)
+}
+
+/// Sets the linear gain of all sources with the tag `name` or within the
+/// group with the ID `name`, see `Scene::set_group_gain()`.
+///
+/// The gain ramps start at frame `offset` of the next call to
+/// asdf_get_audio_data() (or one of its variants).
+///
+/// Returns the number of affected sources.
+/// If `gain` is negative or not finite, nothing is changed and `0` is returned
+/// (see asdf_scene_last_error()).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_group_gain(
+    ptr: *mut Scene,
+    name: *const c_char,
+    gain: f32,
+    offset: u32,
+) -> usize {
+    assert!(!ptr.is_null());
+    assert!(!name.is_null());
+    if !check_gain(gain) {
+        return 0;
+    }
+    let scene = &mut *ptr;
+    let name = CStr::from_ptr(name).to_string_lossy();
+    scene.set_group_gain(&name, gain, offset)
+}
+
+/// Like asdf_scene_set_group_gain(), but for muting.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_group_mute(
+    ptr: *mut Scene,
+    name: *const c_char,
+    mute: bool,
+) -> usize {
+    assert!(!ptr.is_null());
+    assert!(!name.is_null());
+    let scene = &mut *ptr;
+    let name = CStr::from_ptr(name).to_string_lossy();
+    scene.set_group_mute(&name, mute)
+}
+
+/// Like asdf_scene_set_group_gain(), but for soloing.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_group_solo(
+    ptr: *mut Scene,
+    name: *const c_char,
+    solo: bool,
+) -> usize {
+    assert!(!ptr.is_null());
+    assert!(!name.is_null());
+    let scene = &mut *ptr;
+    let name = CStr::from_ptr(name).to_string_lossy();
+    scene.set_group_solo(&name, solo)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_ambisonics_group_count(ptr: *mut Scene) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    scene.ambisonics_groups().len()
+}
+
+/// Writes the given Ambisonics group (see `Scene::ambisonics_groups()`) to `group`.
+/// Returns `false` if `group_idx` is out of range.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_ambisonics_group(
+    ptr: *mut Scene,
+    group_idx: usize,
+    group: *mut AmbisonicsGroup,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!group.is_null());
+    let scene = &*ptr;
+    if let Some(g) = scene.ambisonics_groups().get(group_idx) {
+        *group = *g;
+        true
+    } else {
+        false
+    }
+}
+
+/// Converts `frame` to the number of beats since the begin of the first bar.
+/// Returns `false` if the scene has no tempo map.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_frames_to_beats(
+    ptr: *mut Scene,
+    frame: u64,
+    beats: *mut f64,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!beats.is_null());
+    let scene = &mut *ptr;
+    if let Some(tempo) = scene.tempo_map() {
+        *beats = tempo.frames_to_beats(frame);
+        true
+    } else {
+        false
+    }
+}
+
+/// Inverse of `asdf_scene_frames_to_beats()`.
+/// Returns `false` if the scene has no tempo map.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_beats_to_frames(
+    ptr: *mut Scene,
+    beats: f64,
+    frame: *mut u64,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!frame.is_null());
+    let scene = &mut *ptr;
+    if let Some(tempo) = scene.tempo_map() {
+        *frame = tempo.beats_to_frames(beats);
+        true
+    } else {
+        false
+    }
+}
+
+/// Writes the (one-based) bar and beat at the given `frame`.
+/// Returns `false` if the scene has no tempo map.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_musical_position(
+    ptr: *mut Scene,
+    frame: u64,
+    bar: *mut i64,
+    beat: *mut f64,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!bar.is_null());
+    assert!(!beat.is_null());
+    let scene = &mut *ptr;
+    if let Some(tempo) = scene.tempo_map() {
+        let position = tempo.position(frame);
+        *bar = position.bar;
+        *beat = position.beat;
+        true
+    } else {
+        false
+    }
+}
+
+/// Called with the transform of an active source (which can be modified),
+/// see `asdf_scene_add_transform_modifier()`.
+///
+/// Setting `active` to `false` deactivates the source.
+/// Position and rotation are only changed if they have been modified
+/// (or if the source already had one).
+/// A rotation which cannot be normalized (e.g. all zeros) is ignored.
+pub type AsdfTransformModifierCallback = extern "C" fn(
+    user_data: *mut c_void,
+    source_idx: usize,
+    frame: u64,
+    transform: *mut AsdfTransform,
+);
+
+struct CallbackModifier {
+    callback: AsdfTransformModifierCallback,
+    user_data: *mut c_void,
+}
+
+// NB: The caller is responsible for the thread-safety of `user_data`
+unsafe impl Send for CallbackModifier {}
+unsafe impl Sync for CallbackModifier {}
+
+impl TransformModifier for CallbackModifier {
+    fn modify(&self, source_idx: usize, frame: u64, transform: &mut Transform) -> bool {
+        let mut t = AsdfTransform::from(Some(transform.clone()));
+        let (pos, rot_v, rot_s) = (t.pos, t.rot_v, t.rot_s);
+        (self.callback)(self.user_data, source_idx, frame, &mut t);
+        if !t.active {
+            return false;
+        }
+        if transform.translation.is_some() || t.pos != pos {
+            transform.translation = Some(t.pos.into());
+        }
+        if transform.rotation.is_some() || t.rot_v != rot_v || t.rot_s != rot_s {
+            let q = nalgebra::Quaternion::new(t.rot_s, t.rot_v[0], t.rot_v[1], t.rot_v[2]);
+            if q.norm().is_finite() {
+                if let Some(rotation) = Quat::try_new(q, 0.0) {
+                    transform.rotation = Some(rotation);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Registers a `callback` which is applied to all source transforms
+/// (in `asdf_scene_get_source_transform()` and everywhere else).
+///
+/// The callback must be realtime-safe, `user_data` is passed to it unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_add_transform_modifier(
+    ptr: *mut Scene,
+    callback: AsdfTransformModifierCallback,
+    user_data: *mut c_void,
+) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.add_transform_modifier(Box::new(CallbackModifier {
+        callback,
+        user_data,
+    }));
+}
+
+/// Removes all callbacks added with `asdf_scene_add_transform_modifier()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_clear_transform_modifiers(ptr: *mut Scene) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.clear_transform_modifiers();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_source_transform(
+    ptr: *mut Scene,
+    source_idx: usize,
+    frame: u64,
+) -> AsdfTransform {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.get_source_transform(source_idx, frame).into()
+}
+
+/// Transforms of all sources at `frame`, written to `transforms`
+/// (which must have space for `asdf_scene_file_sources()` elements).
+///
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_source_transforms(
+    ptr: *mut Scene,
+    frame: u64,
+    transforms: *mut AsdfTransform,
+) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    let source_transforms = scene.get_source_transforms(frame);
+    if source_transforms.is_empty() {
+        // NB: `transforms` may be NULL if there are no sources
+        return;
+    }
+    assert!(!transforms.is_null());
+    let target = std::slice::from_raw_parts_mut(transforms, source_transforms.len());
+    for (target, transform) in target.iter_mut().zip(source_transforms) {
+        *target = transform.clone().into();
+    }
+}
+
+/// Bounding box of all source positions while the sources are active
+/// (computed while loading the scene).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_extent(ptr: *mut Scene) -> AsdfBoundingBox {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.extent().into()
+}
+
+/// Like asdf_scene_extent(), but only for the given source.
+///
+/// If `source_idx` is out of range, the box is not valid
+/// (and an error message is set).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_source_extent(
+    ptr: *mut Scene,
+    source_idx: usize,
+) -> AsdfBoundingBox {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if !check_source_idx(scene, source_idx) {
+        return AsdfBoundingBox::default();
+    }
+    scene.source_extent(source_idx).into()
+}
+
+/// Confines all source positions to the box given by `min` and `max`
+/// (arrays of 3 values), see `Scene::set_area()`.
+///
+/// With `project`, positions outside of the box are moved towards its
+/// center onto its surface, otherwise the nearest point inside is used.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_area_box(
+    ptr: *mut Scene,
+    min: *const f32,
+    max: *const f32,
+    project: bool,
+) {
+    assert!(!ptr.is_null());
+    assert!(!min.is_null());
+    assert!(!max.is_null());
+    let scene = &mut *ptr;
+    let extent = BoundingBox {
+        min: *(min as *const [f32; 3]),
+        max: *(max as *const [f32; 3]),
+    };
+    scene.set_area(Some(Area::from_box(extent)), area_policy(project));
+}
+
+/// Like asdf_scene_set_area_box(), but with a sphere.
+///
+/// Returns `false` if `radius` is negative.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_area_sphere(
+    ptr: *mut Scene,
+    center: *const f32,
+    radius: f32,
+    project: bool,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!center.is_null());
+    let scene = &mut *ptr;
+    if radius.is_nan() || radius < 0.0 {
+        set_error("Negative radius");
+        return false;
+    }
+    let area = Area::sphere(*(center as *const [f32; 3]), radius);
+    scene.set_area(Some(area), area_policy(project));
+    true
+}
+
+/// Like asdf_scene_set_area_box(), but with a convex polygon in the x/y plane
+/// (`vertices` contains `count` pairs of x and y coordinates).
+///
+/// Returns `false` if the polygon is invalid.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_area_polygon(
+    ptr: *mut Scene,
+    vertices: *const f32,
+    count: usize,
+    project: bool,
+) -> bool {
+    assert!(!ptr.is_null());
+    assert!(!vertices.is_null());
+    let scene = &mut *ptr;
+    let vertices = std::slice::from_raw_parts(vertices as *const [f32; 2], count);
+    match Area::polygon(vertices) {
+        Some(area) => {
+            scene.set_area(Some(area), area_policy(project));
+            true
+        }
+        None => {
+            set_error("Invalid polygon");
+            false
+        }
+    }
+}
+
+/// Removes the area set with asdf_scene_set_area_box() (or similar).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_clear_area(ptr: *mut Scene) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.set_area(None, AreaPolicy::default());
+}
+
+fn area_policy(project: bool) -> AreaPolicy {
+    if project {
+        AreaPolicy::Project
+    } else {
+        AreaPolicy::Clamp
+    }
+}
+
+/// Reference transform is always "active".
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_reference_transform(
+    ptr: *mut Scene,
+    frame: u64,
+) -> AsdfTransform {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.get_reference_transform(frame).into()
+}
+
+/// `source_idx` is `-1` for the reference.
+pub type AsdfTransformQueryCallback = extern "C" fn(
+    source_idx: i64,
+    frame: u64,
+    transform: *const AsdfTransform,
+    user_data: *mut c_void,
+);
+
+/// Starts recording all transform queries, see `Scene::enable_transform_trace()`.
+///
+/// A `capacity` of `0` stops recording.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_enable_transform_trace(ptr: *mut Scene, capacity: usize) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    if capacity == 0 {
+        scene.take_transform_trace();
+    } else {
+        scene.enable_transform_trace(capacity);
+    }
+}
+
+/// Calls `callback` for each recorded transform query (oldest first)
+/// and returns the number of queries.
+/// Recording continues, this is not realtime-safe.
+///
+/// If `dropped` is not NULL, it is set to the number of queries
+/// which were not recorded (or have been discarded).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_transform_trace(
+    ptr: *mut Scene,
+    callback: AsdfTransformQueryCallback,
+    user_data: *mut c_void,
+    dropped: *mut u64,
+) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    if !dropped.is_null() {
+        *dropped = 0;
+    }
+    let trace = match scene.transform_trace() {
+        Some(trace) => trace,
+        None => return 0,
+    };
+    if !dropped.is_null() {
+        *dropped = trace.dropped();
+    }
+    let mut count = 0;
+    for query in trace.queries() {
+        let source_idx = query.source.map_or(-1, |idx| idx as i64);
+        let transform = AsdfTransform::from(query.transform.clone());
+        callback(source_idx, query.frame, &transform, user_data);
+        count += 1;
+    }
+    count
+}
+
+/// Calls `callback` for each event within the block starting at `frame`
+/// (see `Scene::get_events()` for the order) and returns the number of events.
+/// The event data is only valid during the callback.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_events(
+    ptr: *mut Scene,
+    frame: u64,
+    frames: u32,
+    callback: AsdfEventCallback,
+    user_data: *mut c_void,
+) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    let mut count = 0;
+    for (frame, event) in scene.get_events(frame, frames) {
+        callback(&AsdfEvent::new(frame, event), user_data);
+        count += 1;
+    }
+    count
+}
+
+// TODO: possibility to report errors?
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_seek(ptr: *mut Scene, frame: u64) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.seek(frame)
+}
+
+/// Schedules a seek to `target` which takes place exactly at frame `at`,
+/// see `Scene::seek_at()`.
+///
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_seek_at(ptr: *mut Scene, at: u64, target: u64) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.seek_at(at, target)
+}
+
+/// The seek scheduled with asdf_scene_seek_at(), see `Scene::scheduled_seek()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_scheduled_seek(ptr: *mut Scene) -> AsdfScheduledSeek {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    scene.scheduled_seek().into()
+}
+
+/// Returns to frame 0 immediately (without fade-out), discarding buffered data,
+/// see `Scene::reset()`.
+///
+/// Same return value as `asdf_scene_seek()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_reset(ptr: *mut Scene) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.reset()
+}
+
+/// Current playback position (in frames), see `Scene::position()`.
+///
+/// If `rolling` is not NULL, it is set to whether audio data was delivered
+/// in the most recent call to `asdf_scene_get_audio_data()`.
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_position(ptr: *mut Scene, rolling: *mut bool) -> u64 {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    let position = scene.position();
+    if !rolling.is_null() {
+        *rolling = position.rolling;
+    }
+    position.frame
+}
+
+/// Current state of playback, see `Scene::transport_state()`.
+///
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_transport_state(ptr: *mut Scene) -> TransportState {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    scene.transport_state()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_marker_count(ptr: *mut Scene) -> usize {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.markers().len()
+}
+
+/// Markers are sorted by time.
+/// Returns NULL if `index` is out of range.
+/// The result must be freed with `asdf_marker_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_marker(ptr: *mut Scene, index: usize) -> *mut AsdfMarker {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    match scene.markers().get(index) {
+        Some(marker) => Box::into_raw(Box::new(AsdfMarker::new(marker))),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_marker_free(ptr: *mut AsdfMarker) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Same return value as `asdf_scene_seek()`.
+/// If there is no marker with the given name, `false` is returned
+/// and an error message is available via `asdf_scene_last_error()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_seek_to_marker(ptr: *mut Scene, name: *const c_char) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    assert!(!name.is_null());
+    let name = CStr::from_ptr(name).to_string_lossy();
+    if let Some(result) = scene.seek_to_marker(&name) {
+        result
+    } else {
+        set_error(format!("Unknown marker: {:?}", name));
+        false
+    }
+}
+
+/// Releases the next `<wait-for-trigger>` element with the given `id`
+/// (UTF-8 encoded), see `Scene::trigger()`.
+///
+/// Returns `false` if there is no such element
+/// (no error message is set in this case).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_trigger(ptr: *mut Scene, id: *const c_char) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    assert!(!id.is_null());
+    let id = CStr::from_ptr(id).to_string_lossy();
+    scene.trigger(&id)
+}
+
+/// Sets the value of the live input `name` (UTF-8 encoded).
+///
+/// This is realtime-safe, it can be called from any thread.
+/// Returns `false` if there is no live input with the given name
+/// (no error message is set in this case).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_input(
+    ptr: *mut Scene,
+    name: *const c_char,
+    value: f32,
+) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    assert!(!name.is_null());
+    match CStr::from_ptr(name).to_str() {
+        Ok(name) => scene.set_input(name, value),
+        Err(_) => false,
+    }
+}
+
+#[cfg(any(feature = "osc", feature = "remote"))]
+#[repr(C)]
+pub struct AsdfTransportRequest {
+    seek: bool,
+    /// Only valid if `seek` is `true`
+    seek_frame: u64,
+    change_rolling: bool,
+    /// Only valid if `change_rolling` is `true`
+    rolling: bool,
+}
+
+#[cfg(any(feature = "osc", feature = "remote"))]
+impl From<TransportRequest> for AsdfTransportRequest {
+    fn from(request: TransportRequest) -> AsdfTransportRequest {
+        AsdfTransportRequest {
+            seek: request.seek.is_some(),
+            seek_frame: request.seek.unwrap_or_default(),
+            change_rolling: request.rolling.is_some(),
+            rolling: request.rolling.unwrap_or_default(),
+        }
+    }
+}
+
+/// `address`: e.g. "0.0.0.0:9000"
+///
+/// The scene must outlive the OSC server.
+/// Returns NULL on error (see `asdf_scene_last_error()`).
+#[cfg(feature = "osc")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_osc_server_new(
+    scene: *mut Scene,
+    address: *const c_char,
+) -> *mut OscServer {
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!scene.is_null());
+            let scene = &*scene;
+            let address = CStr::from_ptr(address).to_str().unwrap_display();
+            Box::into_raw(Box::new(OscServer::new(scene, address).unwrap_display()))
+        }),
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(feature = "osc")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_osc_server_free(ptr: *mut OscServer) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Applies received live inputs and source overrides to `scene`,
+/// transport commands have to be handled by the caller.
+///
+/// This is realtime-safe, it should be called from the audio thread.
+#[cfg(feature = "osc")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_osc_server_process(
+    ptr: *mut OscServer,
+    scene: *mut Scene,
+) -> AsdfTransportRequest {
+    assert!(!ptr.is_null());
+    let server = &*ptr;
+    assert!(!scene.is_null());
+    let scene = &mut *scene;
+    server.process(scene).into()
+}
+
+/// `address`: e.g. "0.0.0.0:9001"
+///
+/// The scene is only needed during construction.
+/// Returns NULL on error (see `asdf_scene_last_error()`).
+#[cfg(feature = "remote")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_remote_server_new(
+    scene: *mut Scene,
+    address: *const c_char,
+) -> *mut RemoteServer {
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!scene.is_null());
+            let scene = &*scene;
+            let address = CStr::from_ptr(address).to_str().unwrap_display();
+            Box::into_raw(Box::new(RemoteServer::new(scene, address).unwrap_display()))
+        }),
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(feature = "remote")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_remote_server_free(ptr: *mut RemoteServer) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Applies mute/solo commands to `scene` and publishes its current state
+/// to subscribed clients, transport commands have to be handled by the caller.
+///
+/// This is realtime-safe, it should be called from the audio thread.
+#[cfg(feature = "remote")]
+#[no_mangle]
+pub unsafe extern "C" fn asdf_remote_server_process(
+    ptr: *mut RemoteServer,
+    scene: *mut Scene,
+) -> AsdfTransportRequest {
+    assert!(!ptr.is_null());
+    let server = &*ptr;
+    assert!(!scene.is_null());
+    let scene = &mut *scene;
+    server.process(scene).into()
+}
+
+/// Allocates space for (at most) `capacity` breakpoints.
+/// The result must be freed with `asdf_recorder_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_recorder_new(scene: *mut Scene, capacity: usize) -> *mut Recorder {
+    assert!(!scene.is_null());
+    let scene = &*scene;
+    Box::into_raw(Box::new(Recorder::new(scene, capacity)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_recorder_free(ptr: *mut Recorder) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Returns `false` if the capacity is exhausted.
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_recorder_capture(
+    ptr: *mut Recorder,
+    scene: *mut Scene,
+    frame: u64,
+) -> bool {
+    assert!(!ptr.is_null());
+    let recorder = &mut *ptr;
+    assert!(!scene.is_null());
+    let scene = &*scene;
+    recorder.capture(scene, frame)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_recorder_clear(ptr: *mut Recorder) {
+    assert!(!ptr.is_null());
+    let recorder = &mut *ptr;
+    recorder.clear();
+}
+
+/// Returns the recorded trajectories as ASDF XML (empty if nothing was recorded).
+/// The result must be freed with `asdf_string_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_recorder_to_asdf(ptr: *mut Recorder) -> *mut c_char {
+    assert!(!ptr.is_null());
+    let recorder = &*ptr;
+    CString::new(recorder.to_asdf()).unwrap().into_raw()
+}
+
+/// Creates a feed/monitor pair with `rate` snapshots of all transforms per second.
+///
+/// The feed (which is written to `feed`) is used in the audio thread,
+/// the monitor can be used from another thread.
+/// Both must be freed with `asdf_transform_feed_free()`
+/// and `asdf_transform_monitor_free()`, respectively.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_monitor_new(
+    scene: *mut Scene,
+    rate: f32,
+    feed: *mut *mut TransformFeed,
+) -> *mut TransformMonitor {
+    assert!(!scene.is_null());
+    let scene = &*scene;
+    assert!(!feed.is_null());
+    let (f, monitor) = crate::monitor::new(scene, rate);
+    *feed = Box::into_raw(Box::new(f));
+    Box::into_raw(Box::new(monitor))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_feed_free(ptr: *mut TransformFeed) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_monitor_free(ptr: *mut TransformMonitor) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Takes snapshots of all transforms which are due within the given block.
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_feed_process(
+    ptr: *mut TransformFeed,
+    scene: *mut Scene,
+    frame: u64,
+    frames: u32,
+) {
+    assert!(!ptr.is_null());
+    let feed = &mut *ptr;
+    assert!(!scene.is_null());
+    let scene = &*scene;
+    feed.process(scene, frame, frames);
+}
+
+/// Fetches the most recent snapshot.
+/// Returns `false` if there was no new snapshot since the last call.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_monitor_update(ptr: *mut TransformMonitor) -> bool {
+    assert!(!ptr.is_null());
+    let monitor = &mut *ptr;
+    monitor.update()
+}
+
+/// Frame number of the most recent snapshot.
+/// Returns `false` if there is no snapshot yet.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_monitor_frame(
+    ptr: *mut TransformMonitor,
+    frame: *mut u64,
+) -> bool {
+    assert!(!ptr.is_null());
+    let monitor = &*ptr;
+    assert!(!frame.is_null());
+    if let Some(snapshot) = monitor.latest() {
+        *frame = snapshot.frame;
+        true
+    } else {
+        false
+    }
+}
+
+/// Source transform of the most recent snapshot
+/// (inactive if there is no snapshot yet or if `source_idx` is out of range).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_monitor_get_source_transform(
+    ptr: *mut TransformMonitor,
+    source_idx: usize,
+) -> AsdfTransform {
+    assert!(!ptr.is_null());
+    let monitor = &*ptr;
+    monitor
+        .latest()
+        .and_then(|snapshot| snapshot.sources.get(source_idx).cloned().flatten())
+        .into()
+}
+
+/// Reference transform of the most recent snapshot
+/// (inactive if there is no snapshot yet).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_transform_monitor_get_reference_transform(
+    ptr: *mut TransformMonitor,
+) -> AsdfTransform {
+    assert!(!ptr.is_null());
+    let monitor = &*ptr;
+    monitor
+        .latest()
+        .and_then(|snapshot| snapshot.reference.clone())
+        .into()
+}
+
+/// Summary of the scene as Markdown text.
+/// The result must be freed with `asdf_string_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_report_markdown(ptr: *mut Scene) -> *mut c_char {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    CString::new(scene.report().to_markdown())
+        .unwrap()
+        .into_raw()
+}
+
+/// Summary of the scene as JSON object.
+/// The result must be freed with `asdf_string_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_report_json(ptr: *mut Scene) -> *mut c_char {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    CString::new(scene.report().to_json()).unwrap().into_raw()
+}
+
+/// Starts recording a session log with space for `capacity` entries,
+/// see `Scene::enable_session_log()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_enable_session_log(ptr: *mut Scene, capacity: usize) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.enable_session_log(capacity);
+}
+
+/// Writes the session log (as JSON Lines) to `filename`.
+///
+/// Returns `false` on error (see `asdf_scene_last_error()`),
+/// e.g. if the session log is not enabled.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_write_session_log(
+    ptr: *mut Scene,
+    filename: *const c_char,
+) -> bool {
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let scene = &*ptr;
+            let filename = CStr::from_ptr(filename).to_str().unwrap_display();
+            scene
+                .session_log()
+                .ok_or("Session log is not enabled")
+                .unwrap_display()
+                .write(filename)
+                .unwrap_display();
+            true
+        }),
+        false,
+    )
+}
+
+/// Starts watching for sources which are silent for (at least) `seconds`
+/// although they have an active clip, see `Scene::enable_silence_watchdog()`.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_enable_silence_watchdog(ptr: *mut Scene, seconds: f32) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.enable_silence_watchdog(Duration::from_secs_f32(seconds));
+}
+
+/// Returns `false` if the silence watchdog is not enabled
+/// or if `source_idx` is out of range.
+///
+/// This is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_is_source_silent(ptr: *mut Scene, source_idx: usize) -> bool {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    match scene.silence_watchdog() {
+        // NB: No error message is set, because that would allocate
+        Some(watchdog) if source_idx < scene.file_sources() as usize => {
+            watchdog.is_silent(source_idx)
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_string_free(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        CString::from_raw(ptr);
+    }
+}
+
+/// Writes audio and transforms from `begin` to `end` (in frames)
+/// into the existing `directory`, see `Scene::export_range()`.
+///
+/// Must not be called during playback.
+/// Returns `false` on error (see `asdf_scene_last_error()`).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_export_range(
+    ptr: *mut Scene,
+    begin: u64,
+    end: u64,
+    directory: *const c_char,
+) -> bool {
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let scene = &mut *ptr;
+            let directory = CStr::from_ptr(directory).to_str().unwrap_display();
+            scene
+                .export_range(begin, end, directory.as_ref())
+                .unwrap_display();
+            true
+        }),
+        false,
+    )
+}
+
+/// Test mode: checks whether all clips are placed sample-accurately,
+/// see `Scene::verify_clip_alignment()`.
+///
+/// The number of misaligned clips is written to `misaligned`.
+/// Must not be called during playback.
+/// Returns `false` on error (see `asdf_scene_last_error()`).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_verify_clip_alignment(
+    ptr: *mut Scene,
+    misaligned: *mut usize,
+) -> bool {
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            assert!(!misaligned.is_null());
+            let scene = &mut *ptr;
+            let results = scene.verify_clip_alignment().unwrap_display();
+            *misaligned = results.iter().filter(|clip| !clip.is_aligned()).count();
+            true
+        }),
+        false,
+    )
+}
+
+/// Routes sources to arbitrary output channels, see `Scene::set_output_map()`.
+///
+/// `sources` and `outputs` are arrays of `count` zero-based indices,
+/// source `sources[i]` is written to output channel `outputs[i]`.
+/// Afterwards, the asdf_scene_get_audio_data*() functions expect
+/// `channels` channels.
+///
+/// Returns `false` on error (see `asdf_scene_last_error()`).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_set_output_map(
+    ptr: *mut Scene,
+    channels: u32,
+    sources: *const usize,
+    outputs: *const usize,
+    count: usize,
+) -> bool {
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let scene = &mut *ptr;
+            let routes: Vec<_> = if count == 0 {
+                Vec::new()
+            } else {
+                assert!(!sources.is_null() && !outputs.is_null());
+                let sources = std::slice::from_raw_parts(sources, count);
+                let outputs = std::slice::from_raw_parts(outputs, count);
+                sources
+                    .iter()
+                    .copied()
+                    .zip(outputs.iter().copied())
+                    .collect()
+            };
+            scene.set_output_map(channels, &routes);
+            true
+        }),
+        false,
+    )
+}
+
+/// Goes back to one output channel per source.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_clear_output_map(ptr: *mut Scene) {
+    assert!(!ptr.is_null());
+    let scene = &mut *ptr;
+    scene.clear_output_map();
+}
+
+/// Number of channels expected by the asdf_scene_get_audio_data*() functions.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_output_channels(ptr: *mut Scene) -> u32 {
+    assert!(!ptr.is_null());
+    let scene = &*ptr;
+    scene.output_channels()
+}
+
+/// Return value of `false` means un-recoverable error
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_audio_data(
+    ptr: *mut Scene,
+    data: *const *mut f32,
+    rolling: bool,
+) -> bool {
+    // TODO: remove AssertUnwindSafe once ring buffer is UnwindSafe
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let scene = &mut *ptr;
+            let data = if scene.output_channels() == 0 {
+                // NB: `data` may be NULL if there are no channels
+                &[]
+            } else {
+                assert!(!data.is_null());
+                std::slice::from_raw_parts(data, scene.output_channels() as usize)
+            };
+            let success = scene.get_audio_data(data, rolling);
+            if let Some(error) = scene.streaming_error() {
+                set_error(error);
+            }
+            success
+        }),
+        false,
+    )
+}
+
+/// Like asdf_scene_get_audio_data(), but with an arbitrary number of frames
+/// (which doesn't have to be equal to the blocksize given in asdf_scene_new()).
+///
+/// Return value of `false` means un-recoverable error
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_audio_data_frames(
+    ptr: *mut Scene,
+    data: *const *mut f32,
+    frames: u32,
+    rolling: bool,
+) -> bool {
+    // TODO: remove AssertUnwindSafe once ring buffer is UnwindSafe
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let scene = &mut *ptr;
+            let data = if scene.output_channels() == 0 {
+                // NB: `data` may be NULL if there are no channels
+                &[]
+            } else {
+                assert!(!data.is_null());
+                std::slice::from_raw_parts(data, scene.output_channels() as usize)
+            };
+            let success = scene.get_audio_data_frames(data, frames, rolling);
+            if let Some(error) = scene.streaming_error() {
+                set_error(error);
+            }
+            success
+        }),
+        false,
+    )
+}
+
+/// Like asdf_scene_get_audio_data_frames(), but a change of `rolling` only
+/// takes effect at frame `offset` within the block (e.g. at the host's
+/// sample-accurate transport change), see `Scene::get_audio_data_with_ramp()`.
+///
+/// `offset` must not be larger than `frames`.
+///
+/// Return value of `false` means un-recoverable error
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_get_audio_data_with_ramp(
+    ptr: *mut Scene,
+    data: *const *mut f32,
+    frames: u32,
+    rolling: bool,
+    offset: u32,
+) -> bool {
+    // TODO: remove AssertUnwindSafe once ring buffer is UnwindSafe
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let scene = &mut *ptr;
+            let data = if scene.output_channels() == 0 {
+                // NB: `data` may be NULL if there are no channels
+                &[]
+            } else {
+                assert!(!data.is_null());
+                std::slice::from_raw_parts(data, scene.output_channels() as usize)
+            };
+            let success = scene.get_audio_data_with_ramp(data, frames, rolling, offset);
+            if let Some(error) = scene.streaming_error() {
+                set_error(error);
+            }
+            success
+        }),
+        false,
+    )
+}
+
+/// Splits the scene into a part for the audio thread (which is returned)
+/// and a thread-safe part for control threads (which is written to `control`),
+/// see `Scene::split()`.
+///
+/// The scene is consumed, `ptr` must not be used (or freed) afterwards.
+///
+/// The `asdf_scene_audio_*()` functions must only be called from one thread
+/// at a time (typically the audio thread), they are realtime-safe.
+/// The `asdf_scene_control_*()` functions can be called from any thread,
+/// also concurrently, they never block the audio thread.
+/// Both parts must be freed with `asdf_scene_audio_free()`
+/// and `asdf_scene_control_free()`, respectively.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_split(
+    ptr: *mut Scene,
+    control: *mut *mut SceneControl,
+) -> *mut SceneAudio {
+    assert!(!ptr.is_null());
+    let scene = Box::from_raw(ptr);
+    assert!(!control.is_null());
+    let (audio, c) = scene.split();
+    *control = Box::into_raw(Box::new(c));
+    Box::into_raw(Box::new(audio))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_audio_free(ptr: *mut SceneAudio) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_free(ptr: *mut SceneControl) {
+    if !ptr.is_null() {
+        Box::from_raw(ptr);
+    }
+}
+
+/// Number of channels expected by asdf_scene_audio_get_audio_data().
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_audio_channels(ptr: *mut SceneAudio) -> u32 {
+    assert!(!ptr.is_null());
+    let audio = &*ptr;
+    audio.channels()
+}
+
+/// Like asdf_scene_get_audio_data_frames(), transport commands from
+/// the control part are applied before the audio data is delivered.
+///
+/// Return value of `false` means un-recoverable error
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_audio_get_audio_data(
+    ptr: *mut SceneAudio,
+    data: *const *mut f32,
+    frames: u32,
+    rolling: bool,
+) -> bool {
+    // TODO: remove AssertUnwindSafe once ring buffer is UnwindSafe
+    handle_errors(
+        AssertUnwindSafe(|| {
+            assert!(!ptr.is_null());
+            let audio = &mut *ptr;
+            let data = if audio.channels() == 0 {
+                // NB: `data` may be NULL if there are no channels
+                &[]
+            } else {
+                assert!(!data.is_null());
+                std::slice::from_raw_parts(data, audio.channels() as usize)
+            };
+            let success = audio.get_audio_data_frames(data, frames, rolling);
+            if let Some(error) = audio.streaming_error() {
+                set_error(error);
+            }
+            success
+        }),
+        false,
+    )
+}
+
+/// Like asdf_scene_get_source_transform(), this is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_get_source_transform(
+    ptr: *const SceneControl,
+    source_idx: usize,
+    frame: u64,
+) -> AsdfTransform {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    control.get_source_transform(source_idx, frame).into()
+}
+
+/// Like asdf_scene_get_reference_transform(), this is realtime-safe.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_get_reference_transform(
+    ptr: *const SceneControl,
+    frame: u64,
+) -> AsdfTransform {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    control.get_reference_transform(frame).into()
+}
+
+/// See asdf_scene_set_input().
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_set_input(
+    ptr: *const SceneControl,
+    name: *const c_char,
+    value: f32,
+) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    assert!(!name.is_null());
+    match CStr::from_ptr(name).to_str() {
+        Ok(name) => control.set_input(name, value),
+        Err(_) => false,
+    }
+}
+
+/// Requests a seek, which is completed in asdf_scene_audio_get_audio_data(),
+/// see asdf_scene_control_is_seeking().
+///
+/// Returns `false` if too many commands are pending.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_seek(ptr: *const SceneControl, frame: u64) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    control.seek(frame)
+}
+
+/// Requests a reset (see asdf_scene_reset()), like asdf_scene_control_seek().
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_reset(ptr: *const SceneControl) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    control.reset()
+}
+
+/// Whether a seek (or reset) has been requested but not yet completed.
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_is_seeking(ptr: *const SceneControl) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    control.is_seeking()
+}
+
+/// Requests the release of a `<wait-for-trigger>` element, see asdf_scene_trigger().
+///
+/// Returns `false` if there is no element with the given `id`
+/// or if too many commands are pending (no error message is set in this case).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_trigger(
+    ptr: *const SceneControl,
+    id: *const c_char,
+) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    assert!(!id.is_null());
+    let id = CStr::from_ptr(id).to_string_lossy();
+    control.trigger(&id)
+}
+
+/// Requests muting (or unmuting) the given source.
+/// Returns `false` if too many commands are pending
+/// or if `source_idx` is out of range (which sets an error message).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_set_source_mute(
+    ptr: *const SceneControl,
+    source_idx: usize,
+    mute: bool,
+) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    check_source_count(source_idx, control.file_sources())
+        && control.set_source_mute(source_idx, mute)
+}
+
+/// Requests soloing (or un-soloing) the given source.
+/// Returns `false` if too many commands are pending
+/// or if `source_idx` is out of range (which sets an error message).
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_set_source_solo(
+    ptr: *const SceneControl,
+    source_idx: usize,
+    solo: bool,
+) -> bool {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    check_source_count(source_idx, control.file_sources())
+        && control.set_source_solo(source_idx, solo)
+}
+
+/// Playback position after the most recent call to
+/// asdf_scene_audio_get_audio_data(), see asdf_scene_position().
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_position(
+    ptr: *const SceneControl,
+    rolling: *mut bool,
+) -> u64 {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    let position = control.position();
+    if !rolling.is_null() {
+        *rolling = position.rolling;
+    }
+    position.frame
+}
+
+/// State of playback after the most recent call to
+/// asdf_scene_audio_get_audio_data().
+#[no_mangle]
+pub unsafe extern "C" fn asdf_scene_control_transport_state(
+    ptr: *const SceneControl,
+) -> TransportState {
+    assert!(!ptr.is_null());
+    let control = &*ptr;
+    control.transport_state()
+}
+
+/// The error message will be freed if another error occurs. It is the caller's
+/// responsibility to make sure they're no longer using the string before
+/// calling any other function which may fail.
+#[no_mangle]
+pub extern "C" fn asdf_scene_last_error() -> *const c_char {
+    LAST_ERROR.with(|cell| cell.borrow().as_ptr())
 }
 
 thread_local! {
@@ -204,6 +2364,49 @@ fn set_error<D: Display>(error: D) {
     });
 }
 
+/// Sets an error message if `source_idx` is out of range.
+fn check_source_idx(scene: &Scene, source_idx: usize) -> bool {
+    check_source_count(source_idx, scene.file_sources())
+}
+
+/// Like `check_source_idx()`, given the number of `sources`.
+fn check_source_count(source_idx: usize, sources: u32) -> bool {
+    let sources = sources as usize;
+    if source_idx < sources {
+        true
+    } else {
+        set_error(format!(
+            "Source index {} out of range (number of sources: {})",
+            source_idx, sources
+        ));
+        false
+    }
+}
+
+/// Sets an error message if `gain` is negative or not finite.
+fn check_gain(gain: f32) -> bool {
+    if crate::streamer::is_valid_gain(gain) {
+        true
+    } else {
+        set_error(format!(
+            "Invalid gain: {} (must be finite and non-negative)",
+            gain
+        ));
+        false
+    }
+}
+
+/// Sets an error message if the crossfade doesn't fit into `u32` frames.
+#[cfg(feature = "threads")]
+fn crossfade_frames(milliseconds: u32, samplerate: u32) -> Option<u32> {
+    let frames = u64::from(milliseconds) * u64::from(samplerate) / 1000;
+    if frames > u64::from(u32::MAX) {
+        set_error(format!("Crossfade too long: {} ms", milliseconds));
+        return None;
+    }
+    Some(frames as u32)
+}
+
 fn handle_errors<F, T>(f: F, optb: T) -> T
 where
     F: FnOnce() -> T + UnwindSafe,
@@ -0,0 +1,404 @@
+//! Playback of several scenes one after another, e.g. for concert programs
+//! consisting of several pieces.
+//!
+//! While one scene is playing, the next one is loaded in a background thread
+//! (which means that its reader thread can already buffer its beginning),
+//! so that the transition is gapless.
+//! Consecutive scenes can also be crossfaded, see `SceneChain::set_crossfade()`.
+//!
+//! Source `n` of each scene is written to output channel `n` of the chain,
+//! channels beyond the number of sources of the current scene are silent.
+//! During a crossfade, only sources of the outgoing scene which have the same
+//! ID as a source of the incoming scene are audible (in the channel
+//! of the latter), all other sources of the outgoing scene stop
+//! at the beginning of the crossfade.
+//! All scenes must use the same sample rate and block size as the chain.
+
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::queue;
+
+use crate::{FadeShape, LoadError, Scene};
+
+/// Maximum number of finished scenes waiting to be dropped in the background
+const RETIRED_SCENES: usize = 4;
+
+/// Called in the background thread, see `SceneChain::new()`.
+pub type SceneLoader = Box<dyn FnOnce() -> Result<Scene, LoadError> + Send>;
+
+type ChainError = Box<dyn Error + Send + Sync>;
+
+/// Previous scene during a crossfade.
+struct Outgoing {
+    scene: Scene,
+    /// Crossfade settings at the beginning of the crossfade
+    /// (later calls to `SceneChain::set_crossfade()` don't affect it)
+    length: u32,
+    shape: FadeShape,
+}
+
+pub struct SceneChain {
+    channels: u32,
+    samplerate: u32,
+    blocksize: u32,
+    scenes: usize,
+    /// Scene which is currently playing, with its index in the chain
+    current: Option<(usize, Scene)>,
+    outgoing: Option<Outgoing>,
+    /// For each source of `outgoing`, the source of the current scene
+    /// with the same ID (if any)
+    crossfade_map: Box<[Option<usize>]>,
+    crossfade_length: u32,
+    crossfade_shape: FadeShape,
+    /// Number of frames of the crossfade which have already been delivered
+    crossfade_position: u32,
+    /// Used when starting and stopping playback
+    fade: Duration,
+    fade_shape: FadeShape,
+    /// Whether the fade has to be applied to the current scene after it was started
+    restore_fade: bool,
+    ready_consumer: queue::spsc::Consumer<(usize, Scene)>,
+    retire_producer: queue::spsc::Producer<Scene>,
+    loading_finished: Arc<AtomicBool>,
+    load_errors: Arc<Mutex<Vec<(usize, ChainError)>>>,
+    keep_running: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+    /// Audio data of the outgoing scene during a crossfade
+    buffers: Box<[Box<[f32]>]>,
+    buffer_pointers: Box<[*mut f32]>,
+    /// Target pointers shifted to the beginning of the current segment
+    target_pointers: Box<[*mut f32]>,
+}
+
+// NB: The pointers are only used within get_audio_data()
+unsafe impl Send for SceneChain {}
+
+impl SceneChain {
+    /// Starts a background thread which calls the `loaders` in order.
+    ///
+    /// The first scene is loaded immediately, each following scene
+    /// as soon as the previous one has started playing.
+    /// Scenes which cannot be loaded (or have more than `channels` sources,
+    /// or a different `samplerate` or `blocksize`) are skipped,
+    /// see `take_load_errors()`.
+    ///
+    /// `sleeptime` is the polling interval of the background thread.
+    pub fn new(
+        loaders: Vec<SceneLoader>,
+        channels: u32,
+        samplerate: u32,
+        blocksize: u32,
+        sleeptime: Duration,
+    ) -> SceneChain {
+        let scenes = loaders.len();
+        let (ready_producer, ready_consumer) = queue::spsc::new(1);
+        let (retire_producer, retire_consumer) = queue::spsc::new::<Scene>(RETIRED_SCENES);
+        let loading_finished = Arc::new(AtomicBool::new(false));
+        let load_errors = Arc::new(Mutex::new(Vec::new()));
+        let keep_running = Arc::new(AtomicBool::new(true));
+        let worker = {
+            let loading_finished = Arc::clone(&loading_finished);
+            let load_errors = Arc::clone(&load_errors);
+            let keep_running = Arc::clone(&keep_running);
+            thread::spawn(move || {
+                let mut loaders = loaders.into_iter().enumerate().peekable();
+                let mut pending = None;
+                while keep_running.load(Ordering::Acquire) {
+                    while let Ok(scene) = retire_consumer.pop() {
+                        drop(scene);
+                    }
+                    // NB: At most one scene is loaded in advance,
+                    //     it is taken from the queue when it starts playing
+                    if pending.is_none() && ready_producer.len() == 0 {
+                        if let Some((idx, loader)) = loaders.next() {
+                            match loader() {
+                                Ok(scene) if scene.file_sources() > channels => {
+                                    load_errors.lock().unwrap().push((
+                                        idx,
+                                        "Scene has more sources than the chain has channels".into(),
+                                    ));
+                                }
+                                Ok(scene)
+                                    if scene.samplerate() != samplerate
+                                        || scene.blocksize() != blocksize =>
+                                {
+                                    load_errors.lock().unwrap().push((
+                                        idx,
+                                        format!(
+                                            "Scene has samplerate {} and blocksize {}, \
+                                             the chain has {} and {}",
+                                            scene.samplerate(),
+                                            scene.blocksize(),
+                                            samplerate,
+                                            blocksize
+                                        )
+                                        .into(),
+                                    ));
+                                }
+                                Ok(scene) => pending = Some((idx, scene)),
+                                Err(e) => load_errors.lock().unwrap().push((idx, e.into())),
+                            }
+                        }
+                    }
+                    if let Some(item) = pending.take() {
+                        if let Err(queue::spsc::PushError(item)) = ready_producer.push(item) {
+                            pending = Some(item);
+                        }
+                    }
+                    if pending.is_none() && loaders.peek().is_none() {
+                        loading_finished.store(true, Ordering::Release);
+                    }
+                    thread::sleep(sleeptime);
+                }
+            })
+        };
+        let mut buffers: Box<[Box<[f32]>]> = (0..channels)
+            .map(|_| vec![0.0; blocksize as usize].into())
+            .collect();
+        SceneChain {
+            channels,
+            samplerate,
+            blocksize,
+            scenes,
+            current: None,
+            outgoing: None,
+            crossfade_map: vec![None; channels as usize].into(),
+            crossfade_length: 0,
+            crossfade_shape: FadeShape::default(),
+            crossfade_position: 0,
+            fade: Duration::from_secs(0),
+            fade_shape: FadeShape::default(),
+            restore_fade: false,
+            ready_consumer,
+            retire_producer,
+            loading_finished,
+            load_errors,
+            keep_running,
+            worker: Some(worker),
+            buffer_pointers: buffers.iter_mut().map(|b| b.as_mut_ptr()).collect(),
+            buffers,
+            target_pointers: vec![std::ptr::null_mut(); channels as usize].into(),
+        }
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn samplerate(&self) -> u32 {
+        self.samplerate
+    }
+
+    pub fn blocksize(&self) -> u32 {
+        self.blocksize
+    }
+
+    /// Total number of scenes (including those which couldn't be loaded).
+    pub fn scenes(&self) -> usize {
+        self.scenes
+    }
+
+    /// Number of frames during which consecutive scenes overlap
+    /// (`0`, the default, means back-to-back playback).
+    ///
+    /// The next scene starts `frames` before the end of the current one
+    /// (including its lead-out).
+    /// A crossfade which is already running is not affected.
+    pub fn set_crossfade(&mut self, frames: u32, shape: FadeShape) {
+        self.crossfade_length = frames;
+        self.crossfade_shape = shape;
+    }
+
+    /// Fade-in and fade-out when starting and stopping playback
+    /// (see `Scene::set_fade()`), the transitions between scenes are not faded.
+    pub fn set_fade(&mut self, fade: Duration, shape: FadeShape) {
+        self.fade = fade;
+        self.fade_shape = shape;
+        if let Some((_, scene)) = &mut self.current {
+            scene.set_fade(fade, shape);
+        }
+    }
+
+    /// Zero-based index of the scene which is currently playing
+    /// (during a crossfade, this is the incoming scene).
+    pub fn current_index(&self) -> Option<usize> {
+        self.current.as_ref().map(|(idx, _)| *idx)
+    }
+
+    pub fn current_scene(&self) -> Option<&Scene> {
+        self.current.as_ref().map(|(_, scene)| scene)
+    }
+
+    /// Can be used e.g. for seeking within the current scene.
+    pub fn current_scene_mut(&mut self) -> Option<&mut Scene> {
+        self.current.as_mut().map(|(_, scene)| scene)
+    }
+
+    /// Whether the last scene has been played until its end.
+    pub fn is_finished(&self) -> bool {
+        // NB: The flag has to be checked before the queue
+        self.loading_finished.load(Ordering::Acquire)
+            && self.ready_consumer.len() == 0
+            && self.outgoing.is_none()
+            && match &self.current {
+                Some((_, scene)) => scene.is_finished(scene.streamer.next_frame()),
+                None => true,
+            }
+    }
+
+    /// Errors of scenes which have been skipped (with their zero-based index)
+    /// since the last call.
+    pub fn take_load_errors(&self) -> Vec<(usize, ChainError)> {
+        std::mem::take(&mut *self.load_errors.lock().unwrap())
+    }
+
+    /// Writes `blocksize` frames into each of the `channels` target channels,
+    /// see `Scene::get_audio_data()`.
+    ///
+    /// Before the first scene has been loaded, silence is delivered.
+    ///
+    /// This is realtime-safe, scenes are loaded and dropped
+    /// in the background thread.
+    ///
+    /// Return value of `false` means un-recoverable error
+    #[must_use]
+    pub unsafe fn get_audio_data(&mut self, target: &[*mut f32], rolling: bool) -> bool {
+        assert_eq!(target.len(), self.channels as usize);
+        let frames = self.blocksize;
+        for &ptr in target {
+            std::slice::from_raw_parts_mut(ptr, frames as usize).fill(0.0);
+        }
+        let mut result = true;
+        let mut offset = 0;
+        while offset < frames {
+            let until_crossfade = self.current.as_ref().map_or(0, |(_, scene)| {
+                let remaining = scene
+                    .end_frame()
+                    .saturating_sub(scene.streamer.next_frame());
+                remaining.saturating_sub(u64::from(self.crossfade_length))
+            });
+            if (rolling || self.current.is_none())
+                && until_crossfade == 0
+                && self.outgoing.is_none()
+            {
+                if let Ok(next) = self.ready_consumer.pop() {
+                    self.start_next(next);
+                    continue;
+                }
+            }
+            let (_, scene) = match &mut self.current {
+                Some(current) => current,
+                None => break,
+            };
+            let mut n = frames - offset;
+            if let Some(outgoing) = &self.outgoing {
+                n = n.min(outgoing.length - self.crossfade_position);
+            } else if until_crossfade > 0 {
+                n = n.min(until_crossfade.min(u64::from(u32::MAX)) as u32);
+            }
+            let sources = scene.file_sources() as usize;
+            for (pointer, &ptr) in self.target_pointers.iter_mut().zip(target) {
+                *pointer = ptr.add(offset as usize);
+            }
+            result &= scene.get_source_data_frames(&self.target_pointers[..sources], n, rolling);
+            if self.restore_fade {
+                scene.set_fade(self.fade, self.fade_shape);
+                self.restore_fade = false;
+            }
+            if let Some(outgoing) = &mut self.outgoing {
+                let sources = outgoing.scene.file_sources() as usize;
+                result &= outgoing.scene.get_source_data_frames(
+                    &self.buffer_pointers[..sources],
+                    n,
+                    rolling,
+                );
+                let length = outgoing.length;
+                let shape = outgoing.shape;
+                for i in 0..n {
+                    let position = if rolling {
+                        self.crossfade_position + i + 1
+                    } else {
+                        self.crossfade_position
+                    };
+                    let gain_in = shape.gain(position, length);
+                    let gain_out = shape.gain(length - position, length);
+                    for &ptr in self.target_pointers.iter() {
+                        *ptr.add(i as usize) *= gain_in;
+                    }
+                    for (buffer, &channel) in
+                        self.buffers.iter().zip(&self.crossfade_map[..sources])
+                    {
+                        if let Some(channel) = channel {
+                            *self.target_pointers[channel].add(i as usize) +=
+                                gain_out * buffer[i as usize];
+                        }
+                    }
+                }
+                if rolling {
+                    self.crossfade_position += n;
+                }
+                if self.crossfade_position == length {
+                    let outgoing = self.outgoing.take().unwrap();
+                    self.retire(outgoing.scene);
+                }
+            }
+            offset += n;
+        }
+        result
+    }
+
+    /// Makes the next scene the current one, the previous one is
+    /// crossfaded (or dropped in the background thread).
+    fn start_next(&mut self, next: (usize, Scene)) {
+        let (idx, mut scene) = next;
+        match self.current.take() {
+            Some((_, previous)) => {
+                // NB: The fade (at least one frame) is applied after the first block
+                scene.set_fade(Duration::from_secs(0), self.fade_shape);
+                self.restore_fade = true;
+                if self.crossfade_length > 0 {
+                    // NB: This doesn't allocate
+                    let sources = previous.file_sources() as usize;
+                    for (source, channel) in self.crossfade_map[..sources].iter_mut().enumerate() {
+                        *channel = previous.get_source_id(source).and_then(|id| {
+                            (0..scene.file_sources() as usize)
+                                .find(|&idx| scene.get_source_id(idx) == Some(id))
+                        });
+                    }
+                    self.outgoing = Some(Outgoing {
+                        scene: previous,
+                        length: self.crossfade_length,
+                        shape: self.crossfade_shape,
+                    });
+                    self.crossfade_position = 0;
+                } else {
+                    self.retire(previous);
+                }
+            }
+            None => scene.set_fade(self.fade, self.fade_shape),
+        }
+        self.current = Some((idx, scene));
+    }
+
+    fn retire(&mut self, scene: Scene) {
+        if let Err(queue::spsc::PushError(scene)) = self.retire_producer.push(scene) {
+            // NB: This should never happen, but if it does, it's not realtime-safe
+            drop(scene);
+        }
+    }
+}
+
+impl Drop for SceneChain {
+    fn drop(&mut self) {
+        self.keep_running.store(false, Ordering::Release);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::error!("Scene chain thread panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Sample-accurate comparison of two scenes, e.g. to verify that nothing
+//! audible has changed after upgrading this crate or after editing a scene.
+
+use std::error::Error;
+use std::fmt::Write;
+
+use crate::export::wait_for;
+use crate::Scene;
+
+pub struct Comparison {
+    pub samplerate: u32,
+    /// Number of compared frames (the duration of the longer scene)
+    pub frames: u64,
+    pub sources: Vec<SourceComparison>,
+}
+
+/// Sources are matched by their ID, sources without ID by their position.
+///
+/// A source which only exists in one of the scenes is compared with silence.
+pub struct SourceComparison {
+    pub id: Option<String>,
+    /// Zero-based index of the source in the first scene
+    pub index_a: Option<usize>,
+    /// Zero-based index of the source in the second scene
+    pub index_b: Option<usize>,
+    /// First frame where the difference exceeds the tolerance
+    /// (`None` if there is no such frame)
+    pub first_difference: Option<u64>,
+    /// Maximum absolute difference between corresponding samples
+    pub max_deviation: f32,
+}
+
+impl Comparison {
+    /// Whether no source differs by more than the tolerance.
+    pub fn is_identical(&self) -> bool {
+        self.sources.iter().all(|s| s.first_difference.is_none())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let seconds = |frames: u64| frames as f64 / f64::from(self.samplerate);
+        let index = |idx: Option<usize>| idx.map_or("-".to_string(), |idx| (idx + 1).to_string());
+        let mut out = String::new();
+        writeln!(out, "# Scene Comparison\n").unwrap();
+        writeln!(out, "Compared duration: {:.3} s\n", seconds(self.frames)).unwrap();
+        writeln!(out, "| A | B | ID | First difference | Max. deviation |").unwrap();
+        writeln!(out, "|---|---|----|------------------|----------------|").unwrap();
+        for source in &self.sources {
+            let first_difference = match source.first_difference {
+                Some(frame) => format!("{} ({:.3} s)", frame, seconds(frame)),
+                None => "-".to_string(),
+            };
+            let deviation = if source.max_deviation > 0.0 {
+                format!("{:.1} dB", 20.0 * source.max_deviation.log10())
+            } else {
+                "-".to_string()
+            };
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                index(source.index_a),
+                index(source.index_b),
+                source.id.as_deref().unwrap_or(""),
+                first_difference,
+                deviation
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+impl Scene {
+    /// Renders this scene and `other` offline (from the beginning to the end
+    /// of the longer one) and compares the audio data of each source.
+    ///
+    /// Differences up to `tolerance` (in linear amplitude) are ignored,
+    /// `0.0` only accepts identical samples.
+    ///
+    /// Both scenes must have the same sample rate.
+    /// Like `export_range()`, this must not be called while one of the
+    /// scenes is used for playback.
+    /// Afterwards, playback is stopped and the scenes have to be seeked before
+    /// they can be used again.
+    pub fn compare(
+        &mut self,
+        other: &mut Scene,
+        tolerance: f32,
+    ) -> Result<Comparison, Box<dyn Error + Send + Sync>> {
+        if self.samplerate != other.samplerate {
+            return Err("Scenes with different sample rates cannot be compared".into());
+        }
+        let mut sources: Vec<_> = pair_sources(self, other)
+            .into_iter()
+            .map(|(index_a, index_b)| SourceComparison {
+                id: index_a
+                    .and_then(|idx| self.get_source_id(idx))
+                    .or_else(|| index_b.and_then(|idx| other.get_source_id(idx)))
+                    .cloned(),
+                index_a,
+                index_b,
+                first_difference: None,
+                max_deviation: 0.0,
+            })
+            .collect();
+        let frames = self.frames.max(other.frames);
+        // NB: Both scenes are rendered in lockstep
+        let blocksize = self.streamer.blocksize().min(other.streamer.blocksize());
+        let mut buffers_a = vec![vec![0.0f32; blocksize as usize]; self.sources.len()];
+        let mut buffers_b = vec![vec![0.0f32; blocksize as usize]; other.sources.len()];
+        let pointers_a: Vec<_> = buffers_a.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let pointers_b: Vec<_> = buffers_b.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let silence = vec![0.0f32; blocksize as usize];
+
+        // Stop playback (if needed), without fade-out
+        self.streamer.stop();
+        other.streamer.stop();
+        // NB: <wait-for-trigger> elements are ignored
+        let _holds = (
+            self.streamer.suspend_holds(),
+            other.streamer.suspend_holds(),
+        );
+        wait_for(|| self.seek(0))?;
+        wait_for(|| other.seek(0))?;
+        let mut frame = 0;
+        while frame < frames {
+            render_block(self, &pointers_a, blocksize)?;
+            render_block(other, &pointers_b, blocksize)?;
+            let len = (frames - frame).min(u64::from(blocksize)) as usize;
+            for source in &mut sources {
+                let a = source.index_a.map_or(&silence, |idx| &buffers_a[idx]);
+                let b = source.index_b.map_or(&silence, |idx| &buffers_b[idx]);
+                for (i, (x, y)) in a[..len].iter().zip(&b[..len]).enumerate() {
+                    let deviation = (x - y).abs();
+                    if deviation > tolerance && source.first_difference.is_none() {
+                        source.first_difference = Some(frame + i as u64);
+                    }
+                    source.max_deviation = source.max_deviation.max(deviation);
+                }
+            }
+            frame += u64::from(blocksize);
+        }
+
+        self.streamer.stop();
+        other.streamer.stop();
+        Ok(Comparison {
+            samplerate: self.samplerate,
+            frames,
+            sources,
+        })
+    }
+}
+
+fn render_block(
+    scene: &mut Scene,
+    pointers: &[*mut f32],
+    frames: u32,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    wait_for(|| scene.streamer.frames_available() >= frames as usize)?;
+    if !unsafe { scene.get_source_data_frames(pointers, frames, true) } {
+        return Err(scene.streaming_error().unwrap().clone().into());
+    }
+    Ok(())
+}
+
+/// Pairs of zero-based source indices in `a` and `b`.
+fn pair_sources(a: &Scene, b: &Scene) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut used = vec![false; b.sources.len()];
+    let mut pairs = Vec::new();
+    for (idx, source) in a.sources.iter().enumerate() {
+        let other = match &source.id {
+            Some(id) => b.sources.iter().position(|s| s.id.as_ref() == Some(id)),
+            None => Some(idx).filter(|&i| i < b.sources.len() && b.sources[i].id.is_none()),
+        };
+        let other = other.filter(|&i| !used[i]);
+        if let Some(i) = other {
+            used[i] = true;
+        }
+        pairs.push((Some(idx), other));
+    }
+    for (idx, used) in used.into_iter().enumerate() {
+        if !used {
+            pairs.push((None, Some(idx)));
+        }
+    }
+    pairs
+}
@@ -0,0 +1,79 @@
+//! Support for additional audio file formats.
+//!
+//! Decoders implementing [`AudioDecoder`](trait.AudioDecoder.html) can be
+//! registered with [`register_decoder()`](fn.register_decoder.html).
+//! They are tried (in the order of registration) for all files that cannot be
+//! opened by the built-in decoders (Ogg Vorbis and WAV).
+//! Ogg Vorbis files are only supported with the `vorbis` feature (enabled by default).
+//! Resampling and repetition are handled like for the built-in formats.
+
+use std::error::Error;
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::audiofile::custom::{self, Decoded};
+
+/// An opened audio file of a user-defined format.
+pub trait AudioDecoder: Send + Sync {
+    /// Used in error messages
+    const NAME: &'static str;
+
+    /// Returns an error if the file has an unsupported format.
+    fn open(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>>
+    where
+        Self: Sized;
+
+    fn channels(&self) -> u32;
+    fn frames(&self) -> u64;
+    fn samplerate(&self) -> u32;
+    fn seek(&mut self, frame: u64) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Writes up to `frames` frames (starting at the current position)
+    /// into `channels` (one slice with a length of at least `frames`
+    /// per channel in the file).
+    ///
+    /// Returns the number of written frames, `0` means end of file.
+    fn fill_channels(
+        &mut self,
+        channels: &mut [Box<[f32]>],
+        frames: u32,
+    ) -> Result<u32, Box<dyn Error + Send + Sync>>;
+}
+
+type OpenFunction = fn(&Path) -> Result<Box<dyn Decoded>, Box<dyn Error + Send + Sync>>;
+
+lazy_static! {
+    static ref DECODERS: RwLock<Vec<(&'static str, OpenFunction)>> = RwLock::new(Vec::new());
+}
+
+/// Makes the decoder `D` available for all scenes loaded afterwards.
+pub fn register_decoder<D: AudioDecoder + 'static>() {
+    DECODERS.write().unwrap().push((D::NAME, open::<D>));
+}
+
+fn open<D: AudioDecoder + 'static>(
+    path: &Path,
+) -> Result<Box<dyn Decoded>, Box<dyn Error + Send + Sync>> {
+    Ok(Box::new(D::open(path)?))
+}
+
+/// Tries all registered decoders.
+///
+/// Returns `Ok(None)` if none is registered, otherwise the errors
+/// of all decoders (if none of them can open the file).
+pub(crate) fn open_registered(
+    path: &Path,
+) -> Result<Option<custom::File>, Vec<(&'static str, Box<dyn Error + Send + Sync>)>> {
+    let decoders = DECODERS.read().unwrap();
+    if decoders.is_empty() {
+        return Ok(None);
+    }
+    let mut errors = Vec::new();
+    for &(name, open) in decoders.iter() {
+        match open(path) {
+            Ok(decoder) => return Ok(Some(custom::File::new(decoder))),
+            Err(e) => errors.push((name, e)),
+        }
+    }
+    Err(errors)
+}
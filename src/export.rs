@@ -0,0 +1,183 @@
+//! Export of a time range of a scene (audio and transforms),
+//! e.g. for handing an excerpt to collaborators who use different tools.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+#[cfg(feature = "threads")]
+use std::thread;
+#[cfg(feature = "threads")]
+use std::time::Duration;
+
+use crate::transform::{Quat, Transform, Vec3};
+use crate::Scene;
+
+/// Time between checks whether the reader thread has provided data
+#[cfg(feature = "threads")]
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Maximum time to wait for the reader thread
+#[cfg(feature = "threads")]
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Name of the multichannel audio file (one channel per source)
+const AUDIO_FILE_NAME: &str = "audio.wav";
+
+/// Name of the CSV file containing the transforms of all sources and the reference
+const TRANSFORMS_FILE_NAME: &str = "transforms.csv";
+
+impl Scene {
+    /// Writes the audio data and the transforms from `begin` to `end`
+    /// (in frames) into the (existing) `directory`.
+    ///
+    /// Audio is stored as 32-bit float WAV file with one channel per source
+    /// (if there are any sources),
+    /// transforms are sampled once per block and stored as CSV file with
+    /// the columns `frame,time,source,active,x,y,z,qx,qy,qz,qw`.
+    /// The reference is stored with the source name "reference".
+    ///
+    /// This uses the same streaming mechanism as playback, it must not be
+    /// called while the scene is used for playback.
+    /// The beginning is faded in (see `set_fade()`).
+    /// Afterwards, playback is stopped and the scene has to be seeked before
+    /// it can be used again.
+    pub fn export_range(
+        &mut self,
+        begin: u64,
+        end: u64,
+        directory: &Path,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if end <= begin {
+            return Err("End of export range must be after begin".into());
+        }
+        let blocksize = self.streamer.blocksize();
+        let channels = self.file_sources();
+        let mut buffers = vec![vec![0.0f32; blocksize as usize]; channels as usize];
+        let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+
+        // Stop playback (if needed), without fade-out
+        self.streamer.stop();
+        // NB: <wait-for-trigger> elements are ignored
+        let _holds = self.streamer.suspend_holds();
+        wait_for(|| self.seek(begin))?;
+
+        // NB: Without any channels, no audio file is written
+        let mut audio = if channels > 0 {
+            Some(hound::WavWriter::create(
+                directory.join(AUDIO_FILE_NAME),
+                hound::WavSpec {
+                    channels: channels as u16,
+                    sample_rate: self.samplerate,
+                    bits_per_sample: 32,
+                    sample_format: hound::SampleFormat::Float,
+                },
+            )?)
+        } else {
+            None
+        };
+        let mut transforms = BufWriter::new(File::create(directory.join(TRANSFORMS_FILE_NAME))?);
+        writeln!(transforms, "frame,time,source,active,x,y,z,qx,qy,qz,qw")?;
+
+        let mut frame = begin;
+        while frame < end {
+            wait_for(|| self.streamer.frames_available() >= blocksize as usize)?;
+            if !unsafe { self.get_source_data_frames(&pointers, blocksize, true) } {
+                return Err(self.streaming_error().unwrap().clone().into());
+            }
+            let frames = (end - frame).min(u64::from(blocksize)) as usize;
+            if let Some(audio) = &mut audio {
+                for i in 0..frames {
+                    for &ptr in &pointers {
+                        audio.write_sample(unsafe { *ptr.add(i) })?;
+                    }
+                }
+            }
+            let time = frame as f64 / f64::from(self.samplerate);
+            for idx in 0..self.sources.len() {
+                let name = match self.get_source_id(idx) {
+                    Some(id) => csv_field(id),
+                    None => (idx + 1).to_string(),
+                };
+                let transform = self.get_source_transform(idx, frame);
+                write_row(&mut transforms, frame, time, &name, transform)?;
+            }
+            let transform = self.get_reference_transform(frame);
+            write_row(&mut transforms, frame, time, "reference", transform)?;
+            frame += u64::from(blocksize);
+        }
+
+        self.streamer.stop();
+        if let Some(audio) = audio {
+            audio.finalize()?;
+        }
+        transforms.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "threads")]
+pub(crate) fn wait_for<F: FnMut() -> bool>(
+    mut condition: F,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut waited = Duration::from_secs(0);
+    while !condition() {
+        if waited > TIMEOUT {
+            return Err("Timeout while waiting for audio data".into());
+        }
+        thread::sleep(POLL_INTERVAL);
+        waited += POLL_INTERVAL;
+    }
+    Ok(())
+}
+
+/// Without reader threads, audio data is read on demand,
+/// waiting (and sleeping) wouldn't change anything.
+#[cfg(not(feature = "threads"))]
+pub(crate) fn wait_for<F: FnMut() -> bool>(
+    mut condition: F,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if condition() {
+        Ok(())
+    } else {
+        Err("Audio data is not available".into())
+    }
+}
+
+fn write_row(
+    writer: &mut dyn Write,
+    frame: u64,
+    time: f64,
+    source: &str,
+    transform: Option<Transform>,
+) -> std::io::Result<()> {
+    let active = transform.is_some();
+    let transform = transform.unwrap_or_default();
+    let pos = transform.translation.unwrap_or_else(Vec3::zeros);
+    let rot = transform.rotation.unwrap_or_else(Quat::identity);
+    let v = rot.vector();
+    writeln!(
+        writer,
+        "{},{},{},{},{},{},{},{},{},{},{}",
+        frame,
+        time,
+        source,
+        active,
+        pos.x,
+        pos.y,
+        pos.z,
+        v[0],
+        v[1],
+        v[2],
+        rot.scalar()
+    )
+}
+
+/// Quotes the value if necessary
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,478 @@
+//! Simple arithmetic expressions depending on the time `t` (in seconds).
+//!
+//! Supported are numbers, the constants `pi` and `e`, the variable `t`,
+//! the operators `+`, `-`, `*`, `/` and `^` (power), parentheses and the
+//! functions `sin`, `cos`, `tan`, `asin`, `acos`, `atan`, `sqrt`, `abs`,
+//! `exp`, `ln`, `floor` and `ceil`.
+//!
+//! Live inputs can be referenced with `$input.<name>`,
+//! their values are set by the host at runtime (see `LiveInputs`).
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Current values of all live inputs.
+///
+/// Reading and writing is lock-free (and therefore realtime-safe).
+pub struct LiveInputs {
+    /// `f32` values stored as bits
+    values: Box<[AtomicU32]>,
+}
+
+impl LiveInputs {
+    /// All values are initialized to zero.
+    pub fn new(count: usize) -> LiveInputs {
+        LiveInputs {
+            values: (0..count)
+                .map(|_| AtomicU32::new(0.0f32.to_bits()))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> f32 {
+        f32::from_bits(self.values[idx].load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, idx: usize, value: f32) {
+        self.values[idx].store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+pub enum Expression {
+    Constant(f32),
+    Time,
+    /// Index of live input
+    Input(usize),
+    Negate(Box<Expression>),
+    Binary(BinaryOp, Box<Expression>, Box<Expression>),
+    Call(Function, Box<Expression>),
+}
+
+#[derive(Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Clone, Copy)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sqrt,
+    Abs,
+    Exp,
+    Ln,
+    Floor,
+    Ceil,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        use Function::*;
+        Some(match name {
+            "sin" => Sin,
+            "cos" => Cos,
+            "tan" => Tan,
+            "asin" => Asin,
+            "acos" => Acos,
+            "atan" => Atan,
+            "sqrt" => Sqrt,
+            "abs" => Abs,
+            "exp" => Exp,
+            "ln" => Ln,
+            "floor" => Floor,
+            "ceil" => Ceil,
+            _ => return None,
+        })
+    }
+
+    fn call(self, x: f32) -> f32 {
+        use Function::*;
+        match self {
+            Sin => x.sin(),
+            Cos => x.cos(),
+            Tan => x.tan(),
+            Asin => x.asin(),
+            Acos => x.acos(),
+            Atan => x.atan(),
+            Sqrt => x.sqrt(),
+            Abs => x.abs(),
+            Exp => x.exp(),
+            Ln => x.ln(),
+            Floor => x.floor(),
+            Ceil => x.ceil(),
+        }
+    }
+}
+
+impl Expression {
+    /// Parses a single expression, the error contains a human-readable message.
+    ///
+    /// Names of live inputs that are not yet in `inputs` are appended.
+    pub fn parse(text: &str, inputs: &mut Vec<String>) -> Result<Expression, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+            inputs,
+        };
+        let result = parser.expression()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(format!("Unexpected {} in expression", token));
+        }
+        Ok(result)
+    }
+
+    /// Whether the expression depends on any live input.
+    pub fn uses_inputs(&self) -> bool {
+        use Expression::*;
+        match self {
+            Constant(_) | Time => false,
+            Input(_) => true,
+            Negate(x) | Call(_, x) => x.uses_inputs(),
+            Binary(_, a, b) => a.uses_inputs() || b.uses_inputs(),
+        }
+    }
+
+    /// `t`: time in seconds
+    pub fn evaluate(&self, t: f32, inputs: &LiveInputs) -> f32 {
+        // NB: This function is supposed to be realtime-safe!
+        use Expression::*;
+        match self {
+            Constant(value) => *value,
+            Time => t,
+            Input(idx) => inputs.get(*idx),
+            Negate(x) => -x.evaluate(t, inputs),
+            Binary(op, a, b) => {
+                let a = a.evaluate(t, inputs);
+                let b = b.evaluate(t, inputs);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Subtract => a - b,
+                    BinaryOp::Multiply => a * b,
+                    BinaryOp::Divide => a / b,
+                    BinaryOp::Power => a.powf(b),
+                }
+            }
+            Call(function, x) => function.call(x.evaluate(t, inputs)),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Number(f32),
+    Identifier(String),
+    /// Name of live input (without "$input." prefix)
+    Input(String),
+    Operator(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number {}", value),
+            Token::Identifier(name) => write!(f, "name {:?}", name),
+            Token::Input(name) => write!(f, "input {:?}", name),
+            Token::Operator(c) => write!(f, "{:?}", c),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            let mut previous = ' ';
+            while let Some(&(idx, c)) = chars.peek() {
+                let exponent_sign = (c == '+' || c == '-') && previous == 'e';
+                if c.is_ascii_digit() || c == '.' || c == 'e' || exponent_sign {
+                    end = idx + c.len_utf8();
+                    previous = c;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number = &text[start..end];
+            // NB: `f32::from_str()` would accept "1." and "1.e3"
+            let dangling_point = number
+                .match_indices('.')
+                .any(|(idx, _)| !number[idx + 1..].starts_with(|c: char| c.is_ascii_digit()));
+            let value = f32::from_str(number)
+                .ok()
+                .filter(|_| !dangling_point)
+                .ok_or_else(|| format!("Invalid number: {:?}", number))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '$' {
+            let is_input = c == '$';
+            let mut end = start + c.len_utf8();
+            chars.next();
+            while let Some(&(idx, c)) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' || (is_input && c == '.') {
+                    end = idx + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let name = &text[start..end];
+            if is_input {
+                const PREFIX: &str = "$input.";
+                if !name.starts_with(PREFIX) || name.len() == PREFIX.len() {
+                    return Err(format!(
+                        "Invalid live input {:?} (expected \"{}<name>\")",
+                        name, PREFIX
+                    ));
+                }
+                tokens.push(Token::Input(name[PREFIX.len()..].into()));
+            } else {
+                tokens.push(Token::Identifier(name.into()));
+            }
+        } else if "+-*/^()".contains(c) {
+            tokens.push(Token::Operator(c));
+            chars.next();
+        } else {
+            return Err(format!("Invalid character in expression: {:?}", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    inputs: &'a mut Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn next_is(&mut self, op: char) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Operator(op)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), String> {
+        if self.next_is(op) {
+            Ok(())
+        } else {
+            Err(format!("Expected {:?} in expression", op))
+        }
+    }
+
+    /// expression := term (("+" | "-") term)*
+    fn expression(&mut self) -> Result<Expression, String> {
+        let mut result = self.term()?;
+        loop {
+            let op = if self.next_is('+') {
+                BinaryOp::Add
+            } else if self.next_is('-') {
+                BinaryOp::Subtract
+            } else {
+                return Ok(result);
+            };
+            result = Expression::Binary(op, Box::new(result), Box::new(self.term()?));
+        }
+    }
+
+    /// term := unary (("*" | "/") unary)*
+    fn term(&mut self) -> Result<Expression, String> {
+        let mut result = self.unary()?;
+        loop {
+            let op = if self.next_is('*') {
+                BinaryOp::Multiply
+            } else if self.next_is('/') {
+                BinaryOp::Divide
+            } else {
+                return Ok(result);
+            };
+            result = Expression::Binary(op, Box::new(result), Box::new(self.unary()?));
+        }
+    }
+
+    /// unary := "-" unary | power
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.next_is('-') {
+            Ok(Expression::Negate(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    /// power := atom ("^" unary)?
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.atom()?;
+        if self.next_is('^') {
+            let exponent = self.unary()?;
+            Ok(Expression::Binary(
+                BinaryOp::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// atom := number | "t" | "pi" | "e" | input | function "(" expression ")"
+    ///       | "(" expression ")"
+    fn atom(&mut self) -> Result<Expression, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or("Unexpected end of expression")?;
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(Expression::Constant(*value)),
+            Token::Input(name) => {
+                let idx = match self.inputs.iter().position(|n| n == name) {
+                    Some(idx) => idx,
+                    None => {
+                        self.inputs.push(name.clone());
+                        self.inputs.len() - 1
+                    }
+                };
+                Ok(Expression::Input(idx))
+            }
+            Token::Operator('(') => {
+                let result = self.expression()?;
+                self.expect(')')?;
+                Ok(result)
+            }
+            Token::Identifier(name) => match name.as_str() {
+                "t" => Ok(Expression::Time),
+                "pi" => Ok(Expression::Constant(std::f32::consts::PI)),
+                "e" => Ok(Expression::Constant(std::f32::consts::E)),
+                name => {
+                    let function = Function::from_name(name)
+                        .ok_or_else(|| format!("Unknown name in expression: {:?}", name))?;
+                    self.expect('(')?;
+                    let argument = self.expression()?;
+                    self.expect(')')?;
+                    Ok(Expression::Call(function, Box::new(argument)))
+                }
+            },
+            token => Err(format!("Unexpected {} in expression", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str) -> f32 {
+        let mut inputs = Vec::new();
+        let expression = Expression::parse(text, &mut inputs).unwrap();
+        expression.evaluate(0.0, &LiveInputs::new(inputs.len()))
+    }
+
+    fn error(text: &str) -> String {
+        match Expression::parse(text, &mut Vec::new()) {
+            Ok(_) => panic!("{:?} should be invalid", text),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("1 + 2 * 3"), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3"), 9.0);
+        assert_eq!(evaluate("2 * 3 ^ 2"), 18.0);
+        assert_eq!(evaluate("-2^2"), -4.0);
+        assert_eq!(evaluate("2^-1"), 0.5);
+        assert_eq!(evaluate("--2"), 2.0);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(evaluate("2^3^2"), 512.0);
+        assert_eq!(evaluate("8 - 4 - 2"), 2.0);
+        assert_eq!(evaluate("8 / 4 / 2"), 1.0);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(evaluate("1.5"), 1.5);
+        assert_eq!(evaluate(".5"), 0.5);
+        assert_eq!(evaluate("2e3"), 2000.0);
+        assert_eq!(evaluate("2.5e-1"), 0.25);
+        assert_eq!(evaluate("2 * e"), 2.0 * std::f32::consts::E);
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        for text in &["2e", "1.", ".", "1.e3", "1..2", "1.2.3", "2e+"] {
+            assert!(error(text).starts_with("Invalid number"), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn time_and_functions() {
+        let mut inputs = Vec::new();
+        let expression = Expression::parse("2 * sin(t) + floor(t)", &mut inputs).unwrap();
+        let inputs = LiveInputs::new(0);
+        assert_eq!(expression.evaluate(0.0, &inputs), 0.0);
+        assert_eq!(expression.evaluate(1.5, &inputs), 2.0 * 1.5f32.sin() + 1.0);
+        assert!(error("foo(t)").starts_with("Unknown name"));
+        assert!(error("sin t").starts_with("Expected '('"));
+        assert!(error("sin(t").starts_with("Expected ')'"));
+    }
+
+    #[test]
+    fn inputs() {
+        let mut names = Vec::new();
+        let expression =
+            Expression::parse("$input.a + 10 * $input.b - $input.a", &mut names).unwrap();
+        assert_eq!(names, ["a", "b"]);
+        assert!(expression.uses_inputs());
+        let inputs = LiveInputs::new(names.len());
+        inputs.set(0, 3.0);
+        inputs.set(1, 0.5);
+        assert_eq!(expression.evaluate(0.0, &inputs), 5.0);
+
+        // Names from previous expressions are re-used
+        let expression = Expression::parse("$input.b * $input.c", &mut names).unwrap();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(expression.evaluate(0.0, &LiveInputs::new(3)), 0.0);
+
+        assert!(!Expression::parse("t", &mut names).unwrap().uses_inputs());
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        for text in &["$input.", "$input", "$inputs.a", "$foo.a", "$"] {
+            assert!(error(text).starts_with("Invalid live input"), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        assert_eq!(error("1 2"), "Unexpected number 2 in expression");
+        assert_eq!(error("(1))"), "Unexpected ')' in expression");
+        assert_eq!(error("t t"), "Unexpected name \"t\" in expression");
+        assert!(error("1 + 2 #").starts_with("Invalid character"));
+    }
+
+    #[test]
+    fn incomplete() {
+        assert_eq!(error(""), "Unexpected end of expression");
+        assert_eq!(error("1 +"), "Unexpected end of expression");
+        assert_eq!(error("(1 + 2"), "Expected ')' in expression");
+        assert_eq!(error("*2"), "Unexpected '*' in expression");
+    }
+}
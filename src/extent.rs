@@ -0,0 +1,106 @@
+//! Spatial extent of the source trajectories, see `Scene::extent()`.
+//!
+//! The extents are computed once while loading the scene, by sampling
+//! the position of each source while it is active.
+//! Transforms depending on live inputs are evaluated with the initial values
+//! of the inputs, overrides and transform modifiers are not considered.
+
+use crate::transform::Vec3;
+use crate::Scene;
+
+/// Interval between sampled positions (in seconds)
+const SAMPLING_INTERVAL: f64 = 0.05;
+
+/// Axis-aligned bounding box (in scene coordinates).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl BoundingBox {
+    /// Box containing only `point`.
+    pub fn from_point(point: [f32; 3]) -> BoundingBox {
+        BoundingBox {
+            min: point,
+            max: point,
+        }
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        let mut result = [0.0; 3];
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = 0.5 * (self.min[i] + self.max[i]);
+        }
+        result
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        let mut result = [0.0; 3];
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = self.max[i] - self.min[i];
+        }
+        result
+    }
+
+    /// Whether `point` is inside the box (or on its surface).
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    /// Whether `other` is completely inside this box,
+    /// e.g. to check if a scene fits into the area reproducible by a renderer.
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains(other.min) && self.contains(other.max)
+    }
+
+    /// Enlarges the box (if necessary) to contain `point`.
+    pub fn include(&mut self, point: [f32; 3]) {
+        for ((min, max), value) in self.min.iter_mut().zip(&mut self.max).zip(&point) {
+            *min = min.min(*value);
+            *max = max.max(*value);
+        }
+    }
+
+    /// Smallest box containing both boxes.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let mut result = *self;
+        result.include(other.min);
+        result.include(other.max);
+        result
+    }
+}
+
+/// Extents of all sources (`None` for sources without active clips).
+pub(crate) fn source_extents(scene: &Scene) -> Box<[Option<BoundingBox>]> {
+    let step = ((SAMPLING_INTERVAL * f64::from(scene.samplerate)) as u64).max(1);
+    let view = scene.transform_view();
+    scene
+        .sources
+        .iter()
+        .enumerate()
+        .map(|(idx, source)| {
+            let mut extent: Option<BoundingBox> = None;
+            for &(begin, end) in source.active_ranges.iter() {
+                if begin >= end {
+                    continue;
+                }
+                // NB: The last frame of each range is always included
+                let frames = (begin..end).step_by(step as usize).chain(Some(end - 1));
+                for frame in frames {
+                    let transform = match view.get_unmodified_source_transform(idx, frame) {
+                        Some(transform) => transform,
+                        None => continue,
+                    };
+                    let position: [f32; 3] =
+                        transform.translation.unwrap_or_else(Vec3::zeros).into();
+                    match &mut extent {
+                        Some(extent) => extent.include(position),
+                        None => extent = Some(BoundingBox::from_point(position)),
+                    }
+                }
+            }
+            extent
+        })
+        .collect()
+}
@@ -28,6 +28,86 @@ cargo cinstall --release
 
 Further options (like `--destdir` and `--prefix`) are available.
 
+# Command line tool
+
+A tool for validating and inspecting scenes (e.g. in CI checks)
+is available with the `cli` feature:
+
+```text
+cargo run --release --features cli --bin asdf-cli -- validate my-scene.asdf
+```
+
+Run `asdf-cli --help` for further commands and options.
+
+# Python bindings
+
+Python bindings (with audio data as NumPy arrays) are available with the
+`python` feature.
+Using [maturin](https://www.maturin.rs/) (`pip install maturin`),
+the Python module `asdf` can be installed into the current environment with:
+
+```text
+maturin develop --release
+```
+
+```python
+import asdf
+scene = asdf.Scene('my-scene.asdf', samplerate=48000, blocksize=1024)
+audio = scene.render(0, scene.frames)  # one row per file source
+print(scene.get_source_transform(0, 0))
+```
+
+# WebAssembly
+
+For targets without threads and without a file system
+(like `wasm32-unknown-unknown`), the default features have to be disabled
+(this includes `threads` and the C libraries used for decoding and
+resampling):
+
+```text
+cargo build --release --target wasm32-unknown-unknown --no-default-features
+```
+
+Scenes can then be loaded with `Scene::load_with_reader()`,
+which gets the contents of all files (scene files and audio files)
+from a user-supplied callback.
+Audio data is read on demand in `get_audio_data()`.
+Only WAV files are supported (unless other decoders are registered,
+see `decoder` module) and their sample rate has to match the sample rate
+of the scene.
+If libvorbis and libsamplerate are compiled for the target,
+the features `vorbis` and `resampling` can be enabled.
+
+# Scene archives
+
+With the `archive` feature, a scene and all its audio files can be
+distributed as a single zip file with the extension `.asdz`, which can be
+loaded like a scene file.
+Audio files should be stored without compression, e.g.:
+
+```text
+zip -0 -r my-piece.asdz my-piece.asdf audio/
+```
+
+# Remote audio files
+
+With the `http` feature, clip files can be given as URLs
+(e.g. `<clip file="https://example.org/audio.ogg"/>`),
+which are streamed (using HTTP range requests) instead of being downloaded
+beforehand.
+Further sources can be registered with `media::register_media_source()`.
+
+# Test signals
+
+Instead of an audio file, a clip can contain a generated test signal
+(sine tone, pink noise, sine sweep or Dirac impulses),
+e.g. `<clip file="signal:pink duration=30 level=-30"/>`, see `signal` module.
+
+# Streaming without scenes
+
+The real-time file streaming engine can also be used without ASDF scenes
+(e.g. with a different scene description format), see `streaming` module.
+
 # Updating `README.md`
 
 Using [cargo-readme](https://github.com/livioribeiro/cargo-readme)
@@ -38,41 +118,276 @@ cargo readme -o README.md
 ```
 */
 use std::collections::HashMap;
-use std::path::Path;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
+use superslice::Ext; // for slice::lower_bound_by_key()
+
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod area;
 mod audiofile;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+#[cfg(feature = "threads")]
+pub mod chain;
+pub mod compare;
+pub mod decoder;
 mod error;
+mod export;
+mod expression;
+pub mod extent;
+pub mod loader;
+pub mod monitor;
+mod output;
 mod parser;
+pub mod punch;
+pub mod recorder;
+pub mod report;
+pub mod session;
+pub mod split;
 mod streamer;
+pub mod streaming;
+pub mod tempo;
+pub mod trace;
 mod transform;
+mod verify;
+pub mod watchdog;
+pub mod writer;
 
 #[cfg(cargo_c)]
 mod capi;
 
-use crate::parser::error::LoadError;
-use crate::streamer::FileStreamer;
-use crate::transform::Transform;
+#[cfg(feature = "osc")]
+pub mod osc;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "remote")]
+pub mod remote;
+
+pub use crate::area::{Area, AreaPolicy};
+pub use crate::audiofile::cache;
+pub use crate::audiofile::media;
+pub use crate::audiofile::signal;
+use crate::expression::LiveInputs;
+pub use crate::extent::BoundingBox;
+pub use crate::parser::error::LoadError;
+use crate::streamer::{FileStreamer, HoldState};
+use crate::tempo::TempoMap;
+
+pub use crate::streamer::{
+    Backoff, FadeShape, Hold, Position, ScheduledSeek, ScheduledSeekState, StreamerBuilder,
+    StreamingError, StreamingStats, TransportState,
+};
+pub use crate::transform::{Easing, Quat, Transform, Vec3};
+pub use crate::verify::ClipAlignment;
 
 const REFERENCE_ID: &str = "reference";
 
-type TransformerStorage = Box<[(Box<dyn Transformer>, Box<[(u64, u64)]>)]>;
+/// Transformers with their activity ranges (begin, end, hold),
+/// see `PlaylistEntry::hold`
+type TransformerStorage = Box<[(Box<dyn Transformer>, Box<[(u64, u64, Option<usize>)]>)]>;
 
 pub struct Scene {
+    samplerate: u32,
+    /// Total duration in frames (including lead-in and lead-out)
+    frames: u64,
+    /// Silence before the first element (in frames)
+    lead_in: u64,
+    /// Silence after the last element (in frames)
+    lead_out: u64,
     sources: Vec<Source>,
+    /// Referenced by `Source::group` and `Group::parent`
+    groups: Box<[Group]>,
+    /// See `ambisonics_groups()`
+    ambisonics_groups: Box<[AmbisonicsGroup]>,
     streamer: FileStreamer,
     /// Transformers with list of activity
     transformers: TransformerStorage,
     /// Map from ID to list of transformers directly applying to this ID
     transformer_map: HashMap<String, Box<[usize]>>,
     reference_transform: Transform,
+    /// File names of all clips, indexed by `Clip::file`
+    clip_files: Box<[PathBuf]>,
+    /// Playback speed of all clips, indexed by `Clip::file`
+    clip_speeds: Box<[f64]>,
+    /// Used instead of the file system (see `load_with_reader()`)
+    open: Option<loader::OpenCallback>,
+    /// Defined by `<tempo>` elements (if any)
+    tempo: Option<TempoMap>,
+    /// Given by `<meta>` elements (in the main scene)
+    metadata: Box<[Metadata]>,
+    /// Given by the `<poster>` element (in the main scene)
+    poster: Option<PathBuf>,
+    /// See `set_preferred_languages()`
+    preferred_languages: Vec<String>,
+    /// Applied to all source transforms
+    transform_modifiers: Vec<Arc<dyn TransformModifier>>,
+    /// Indexed like `sources`, see `get_source_transforms()`
+    source_transforms: Box<[Option<Transform>]>,
+    /// Indexed like `sources`, see `set_source_override()`
+    position_overrides: Box<[Option<Vec3>]>,
+    /// Grouped by hold (see `event_groups`), each group sorted by frame number
+    events: Box<[Event]>,
+    /// Hold (see `PlaylistEntry::hold`) and range within `events` of each group
+    event_groups: Box<[(Option<usize>, Range<usize>)]>,
+    /// Sorted by frame number
+    markers: Box<[Marker]>,
+    /// Sorted by frame number, each one starts the hold with the same index
+    triggers: Box<[Trigger]>,
+    /// End of the contents of each hold (without waiting and lead-out),
+    /// indexed like `triggers`
+    hold_ends: Box<[u64]>,
+    inputs: LiveInputs,
+    /// Map from live input name to index in `inputs`
+    input_map: HashMap<String, usize>,
+    session_log: Option<session::SessionLog>,
+    /// See `enable_silence_watchdog()`
+    silence_watchdog: Option<watchdog::SilenceWatchdog>,
+    /// See `streaming_error()`
+    streaming_error: Option<StreamingError>,
+    /// See `enable_transform_trace()`
+    transform_tracer: Option<trace::Tracer>,
+    /// See `set_output_map()`
+    output_map: Option<output::OutputMap>,
+    /// See `LoadOptions::max_latency()`
+    max_latency: Option<Duration>,
+    /// See `latency_violations()`
+    latency_violations: LatencyViolations,
+    /// Indexed like `sources`, see `source_extent()`
+    source_extents: Box<[Option<BoundingBox>]>,
+    /// See `set_area()`
+    area: Option<(Area, AreaPolicy)>,
+}
+
+/// See `Scene::latency_violations()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyViolations {
+    /// Number of calls which exceeded the limit
+    pub count: u64,
+    /// Execution time of the slowest call
+    pub longest: Duration,
+}
+
+/// Options for loading a scene, see `Scene::with_options()`.
+///
+/// ```text
+/// let options = LoadOptions::new(samplerate, blocksize)
+///     .buffer_blocks(32)
+///     .reader_threads(2)
+///     .preload_threshold(Duration::from_secs(5));
+/// let scene = Scene::with_options(path, &options)?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    pub(crate) samplerate: u32,
+    pub(crate) blocksize: u32,
+    pub(crate) buffer_blocks: u32,
+    pub(crate) sleeptime: Duration,
+    /// `None`: one block, linear
+    pub(crate) fade: Option<(Duration, FadeShape)>,
+    pub(crate) reader_threads: u32,
+    pub(crate) preload_threshold: Duration,
+    pub(crate) direct_io_threshold: Option<u64>,
+    pub(crate) max_latency: Option<Duration>,
+}
+
+impl LoadOptions {
+    /// The defaults are 16 buffer blocks, a sleep time of 10 ms,
+    /// a linear fade over one block, one reader thread, no preloading,
+    /// no direct I/O and no latency limit.
+    pub fn new(samplerate: u32, blocksize: u32) -> LoadOptions {
+        LoadOptions {
+            samplerate,
+            blocksize,
+            buffer_blocks: 16,
+            sleeptime: Duration::from_millis(10),
+            fade: None,
+            reader_threads: 1,
+            preload_threshold: Duration::from_secs(0),
+            direct_io_threshold: None,
+            max_latency: None,
+        }
+    }
+
+    /// Size of the buffer between the reader thread(s) and `get_audio_data()`
+    /// (in blocks), see `Scene::streaming_stats()`.
+    pub fn buffer_blocks(mut self, blocks: u32) -> LoadOptions {
+        self.buffer_blocks = blocks;
+        self
+    }
+
+    /// Time the reader thread sleeps when the buffer is full.
+    pub fn sleeptime(mut self, sleeptime: Duration) -> LoadOptions {
+        self.sleeptime = sleeptime;
+        self
+    }
+
+    /// Used when starting and stopping playback, see `Scene::set_fade()`.
+    pub fn fade(mut self, fade: Duration, shape: FadeShape) -> LoadOptions {
+        self.fade = Some((fade, shape));
+        self
+    }
+
+    /// With `threads > 1`, reading of audio files is distributed
+    /// among multiple threads, which can help with scenes containing many
+    /// simultaneously playing files.
+    /// The threads read one block at a time (in lock-step),
+    /// see `StreamerBuilder::reader_threads()`.
+    /// With `threads == 0` (or without the `threads` feature),
+    /// audio data is read in `get_audio_data()`, see `Scene::load_with_reader()`.
+    pub fn reader_threads(mut self, threads: u32) -> LoadOptions {
+        self.reader_threads = threads;
+        self
+    }
+
+    /// Clips shorter than `threshold` are completely decoded into
+    /// memory while loading, all other clips are streamed from disk.
+    /// This can be overridden for each clip with the `preload` attribute
+    /// (`"true"` or `"false"`).
+    pub fn preload_threshold(mut self, threshold: Duration) -> LoadOptions {
+        self.preload_threshold = threshold;
+        self
+    }
+
+    /// WAV files with a size of at least `bytes` are read without using
+    /// the page cache of the operating system, which would otherwise be
+    /// thrashed by huge scenes.
+    ///
+    /// For audio files on network shares (SMB, NFS, ...), the attribute
+    /// `storage="network"` can be used in the `<asdf>` element of a scene.
+    /// Its files are then read in larger chunks (further ahead)
+    /// and failed reads are retried, this threshold is ignored.
+    pub fn direct_io_threshold(mut self, bytes: u64) -> LoadOptions {
+        self.direct_io_threshold = Some(bytes);
+        self
+    }
+
+    /// Maximum acceptable execution time of `get_audio_data()` (and its
+    /// variants), e.g. a fraction of the block duration.
+    ///
+    /// With a limit, loading fails if audio data would have to be read in
+    /// `get_audio_data()` (i.e. with `reader_threads(0)` or without the
+    /// `threads` feature), all other features are realtime-safe.
+    /// In debug builds, each call is measured and calls exceeding the limit
+    /// are reported, see `Scene::latency_violations()`.
+    pub fn max_latency(mut self, latency: Duration) -> LoadOptions {
+        self.max_latency = Some(latency);
+        self
+    }
 }
 
 impl Scene {
     /// Loads an ASDF scene from a file.
+    ///
+    /// All other options have their default values, see `LoadOptions::new()`.
+    #[deprecated(note = "use `Scene::with_options()` instead")]
     pub fn new<P: AsRef<Path>>(
         path: P,
         samplerate: u32,
@@ -80,13 +395,236 @@ impl Scene {
         buffer_blocks: u32,
         sleeptime: Duration,
     ) -> Result<Scene, LoadError> {
-        parser::load_scene(
-            path.as_ref(),
-            samplerate,
-            blocksize,
-            buffer_blocks,
-            sleeptime,
-        )
+        let options = LoadOptions::new(samplerate, blocksize)
+            .buffer_blocks(buffer_blocks)
+            .sleeptime(sleeptime);
+        Scene::with_options(path, &options)
+    }
+
+    /// Loads an ASDF scene from a file, see `LoadOptions`.
+    ///
+    /// With the `archive` feature, `path` can also be a zip archive with the
+    /// extension `.asdz` (see `archive` module).
+    pub fn with_options<P: AsRef<Path>>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<Scene, LoadError> {
+        parser::load_scene(path.as_ref(), options, None, false)
+    }
+
+    /// Maximum execution time of `get_audio_data()`, see `LoadOptions::max_latency()`.
+    pub fn max_latency(&self) -> Option<Duration> {
+        self.max_latency
+    }
+
+    /// Calls of `get_audio_data()` (and its variants) which took longer
+    /// than `max_latency()`.
+    ///
+    /// This is only measured in debug builds,
+    /// in release builds no violations are reported.
+    pub fn latency_violations(&self) -> LatencyViolations {
+        self.latency_violations
+    }
+
+    /// Loads an ASDF scene for validation and inspection (e.g. with `report()`),
+    /// without starting playback.
+    ///
+    /// All audio files are opened, but no audio data is read and no streaming
+    /// buffers are allocated.
+    /// Missing files don't cause an error, they are listed in
+    /// `Report::missing_files` (and their clips have a duration of zero).
+    /// `get_audio_data()` always provides silence.
+    pub fn load_dry_run<P: AsRef<Path>>(
+        path: P,
+        samplerate: u32,
+        blocksize: u32,
+    ) -> Result<Scene, LoadError> {
+        let options = LoadOptions::new(samplerate, blocksize).buffer_blocks(1);
+        parser::load_scene(path.as_ref(), &options, None, true)
+    }
+
+    /// Loads an ASDF scene using `open` instead of the file system,
+    /// e.g. for WebAssembly in web browsers.
+    ///
+    /// No reader thread is started (`LoadOptions::reader_threads()` is ignored),
+    /// audio data is read in `get_audio_data()`,
+    /// which is fine for offline rendering, but might cause dropouts
+    /// in real-time applications.
+    /// The scene is ready for playback from the beginning, no seeking is needed.
+    pub fn load_with_reader<P: AsRef<Path>>(
+        path: P,
+        open: loader::OpenCallback,
+        options: &LoadOptions,
+    ) -> Result<Scene, LoadError> {
+        let options = options.clone().reader_threads(0);
+        parser::load_scene(path.as_ref(), &options, Some(open), false)
+    }
+
+    /// Statistics about streaming audio data from disk,
+    /// e.g. for tuning `LoadOptions::buffer_blocks()`.
+    ///
+    /// This is realtime-safe, it can be called from the audio thread.
+    pub fn streaming_stats(&self) -> StreamingStats {
+        self.streamer.stats()
+    }
+
+    /// Enables an adaptive mode where the buffer is automatically grown
+    /// (up to `max_blocks`) to the size suggested by `streaming_stats()`.
+    /// `None` disables it (which is the default).
+    ///
+    /// The buffer is only re-allocated when seeking, because this is the only
+    /// time when it is not in use by the audio thread.
+    pub fn set_buffer_growth_limit(&mut self, max_blocks: Option<u32>) {
+        self.streamer.set_buffer_growth_limit(max_blocks);
+    }
+
+    /// Sets duration and shape of the fade-in and fade-out
+    /// when starting and stopping playback.
+    ///
+    /// The fade is at least one frame long, it may span multiple blocks.
+    /// Seeking is delayed until the fade-out is finished.
+    pub fn set_fade(&mut self, fade: Duration, shape: FadeShape) {
+        let frames = (fade.as_secs_f64() * f64::from(self.samplerate)).round() as u32;
+        self.streamer.set_fade(frames, shape);
+    }
+
+    /// Total duration of the scene in frames.
+    ///
+    /// This includes lead-in and lead-out.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Length of the silence before the first element (in frames).
+    ///
+    /// This is specified with the `lead-in` attribute of the `<asdf>` element.
+    /// All elements of the scene are shifted by this amount.
+    pub fn lead_in(&self) -> u64 {
+        self.lead_in
+    }
+
+    /// Length of the silence after the last element (in frames).
+    pub fn lead_out(&self) -> u64 {
+        self.lead_out
+    }
+
+    /// Changes the silence after the last element (in frames), e.g. to allow
+    /// reverb tails to decay.
+    ///
+    /// The initial value is specified with the `lead-out` attribute
+    /// of the `<asdf>` element.
+    pub fn set_lead_out(&mut self, frames: u64) {
+        self.frames = self.frames - self.lead_out + frames;
+        self.lead_out = frames;
+    }
+
+    /// Whether `frame` is at (or after) the end of the scene,
+    /// i.e. after the lead-out has passed.
+    ///
+    /// The end is delayed by the waiting time of `<wait-for-trigger>`
+    /// elements, the scene is never finished while one of them is waiting.
+    pub fn is_finished(&self, frame: u64) -> bool {
+        let holds = self.streamer.hold_state();
+        frame >= self.end_frame() && !(0..holds.len()).any(|idx| holds.is_waiting(idx))
+    }
+
+    /// Frame number of the end of the scene,
+    /// including the current waiting time of `<wait-for-trigger>` elements.
+    pub(crate) fn end_frame(&self) -> u64 {
+        let holds = self.streamer.hold_state();
+        self.hold_ends
+            .iter()
+            .enumerate()
+            .map(|(idx, end)| end + holds.delay(Some(idx)) + self.lead_out)
+            .fold(self.frames, u64::max)
+    }
+
+    /// Tempo map for converting between frames and bars/beats,
+    /// `None` if the scene doesn't have any `<tempo>` elements.
+    pub fn tempo_map(&self) -> Option<&TempoMap> {
+        self.tempo.as_ref()
+    }
+
+    /// All `<meta>` elements of the main scene, in document order.
+    pub fn metadata(&self) -> &[Metadata] {
+        &self.metadata
+    }
+
+    /// Image representing the scene (e.g. for scene browsers),
+    /// given by `<poster file="..."/>` in the `<head>` of the main scene.
+    ///
+    /// Relative paths are resolved relative to the scene file,
+    /// the file itself is not checked.
+    pub fn poster(&self) -> Option<&Path> {
+        self.poster.as_deref()
+    }
+
+    /// Contents of the `poster()` image file, `None` if there is no poster.
+    ///
+    /// The file is read like the audio files of the scene,
+    /// i.e. also from archives or with `load_with_reader()`.
+    pub fn read_poster(&self) -> io::Result<Option<Vec<u8>>> {
+        let path = match &self.poster {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let data = match &self.open {
+            Some(open) => {
+                let mut data = Vec::new();
+                open(path)?.read_to_end(&mut data)?;
+                data
+            }
+            None => std::fs::read(path)?,
+        };
+        Ok(Some(data))
+    }
+
+    /// Language tags (e.g. `["de-AT", "en"]`) in order of preference,
+    /// used by `get_metadata()`.
+    pub fn set_preferred_languages<S: AsRef<str>>(&mut self, languages: &[S]) {
+        self.preferred_languages = languages.iter().map(|l| l.as_ref().into()).collect();
+    }
+
+    /// Content of the `<meta>` element with the given `name`
+    /// (e.g. `"title"`) in the best-matching language.
+    ///
+    /// For each preferred language (see `set_preferred_languages()`),
+    /// an exact match is tried (case-insensitive), then a match of the
+    /// primary language (e.g. `"de"` for `"de-AT"`).
+    /// If none of them is available, the element without `xml:lang` is used,
+    /// if there is no such element, the first one with the given name.
+    pub fn get_metadata(&self, name: &str) -> Option<&str> {
+        let primary = |tag: &str| tag.split('-').next().unwrap_or("").to_lowercase();
+        let candidates = || self.metadata.iter().filter(move |m| m.name == name);
+        let language_matches = self.preferred_languages.iter().flat_map(|language| {
+            let exact = candidates().find(move |m| match &m.lang {
+                Some(lang) => lang.eq_ignore_ascii_case(language),
+                None => false,
+            });
+            let similar = candidates().find(move |m| match &m.lang {
+                Some(lang) => primary(lang) == primary(language),
+                None => false,
+            });
+            exact.into_iter().chain(similar)
+        });
+        language_matches
+            .chain(candidates().filter(|m| m.lang.is_none()))
+            .chain(candidates())
+            .next()
+            .map(|m| m.content.as_str())
+    }
+
+    /// Total duration of the scene.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames as f64 / f64::from(self.samplerate))
+    }
+
+    pub fn samplerate(&self) -> u32 {
+        self.samplerate
+    }
+
+    pub fn blocksize(&self) -> u32 {
+        self.streamer.blocksize()
     }
 
     pub fn file_sources(&self) -> u32 {
@@ -105,20 +643,884 @@ impl Scene {
         self.sources[index].model.as_ref()
     }
 
+    /// Summary of the scene, including warnings about possible problems.
+    pub fn report(&self) -> report::Report {
+        report::Report::new(self)
+    }
+
+    /// All metadata given in the `<source>` element.
+    /// Panics if `index` is out of range.
+    pub fn get_source_info(&self, index: usize) -> SourceInfo {
+        let source = &self.sources[index];
+        SourceInfo {
+            id: source.id.as_deref(),
+            name: source.name.as_deref(),
+            model: source.model.as_deref(),
+            port: source.port.as_deref(),
+            color: source.color,
+            icon: source.icon.as_deref(),
+            comment: source.comment.as_deref(),
+            tags: &source.tags,
+            tail: source.tail,
+            inverted: source.inverted,
+            delay: source.delay,
+            extra_attributes: &source.extra_attributes,
+        }
+    }
+
+    /// All tags used by the sources of the scene (see `SourceInfo::tags`),
+    /// in order of their first appearance.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut result: Vec<&str> = Vec::new();
+        for tag in self.sources.iter().flat_map(|s| s.tags.iter()) {
+            if !result.contains(&tag.as_str()) {
+                result.push(tag);
+            }
+        }
+        result
+    }
+
+    /// Zero-based indices of all sources with the given tag,
+    /// e.g. for muting all sources tagged `"ambience"`.
+    pub fn sources_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.sources
+            .iter()
+            .enumerate()
+            .filter(move |(_, source)| source.tags.iter().any(|t| t == tag))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Number of clips in the given source.
+    /// Panics if `source_idx` is out of range.
+    pub fn clip_count(&self, source_idx: usize) -> usize {
+        self.sources[source_idx].clips.len()
+    }
+
+    /// Iterates over all clips of the given source, sorted by begin time.
+    /// Panics if `source_idx` is out of range.
+    pub fn clips(&self, source_idx: usize) -> impl Iterator<Item = ClipInfo> {
+        self.sources[source_idx]
+            .clips
+            .iter()
+            .map(move |clip| ClipInfo {
+                begin: clip.begin,
+                duration: clip.duration,
+                file: &self.clip_files[clip.file],
+                channel: clip.channel,
+                speed: self.clip_speeds[clip.file],
+            })
+    }
+
+    /// Returns all `<event>`s within the block starting at `frame`,
+    /// each with the frame where it actually happens.
+    ///
+    /// Events after a `<wait-for-trigger>` element are delayed by the
+    /// waiting time (as of the most recent call to `get_audio_data()`),
+    /// `Event::frame` is the time without waiting.
+    /// The events are sorted by time, except that delayed events
+    /// come after the others.
+    pub fn get_events(&self, frame: u64, frames: u32) -> impl Iterator<Item = (u64, &Event)> {
+        // NB: This function is supposed to be realtime-safe!
+        let holds = self.streamer.hold_state();
+        self.event_groups.iter().flat_map(move |(hold, range)| {
+            let delay = holds.delay(*hold);
+            let events = &self.events[range.clone()];
+            let begin = events.lower_bound_by_key(&frame.saturating_sub(delay), |e| e.frame);
+            let end = events
+                .lower_bound_by_key(&(frame + u64::from(frames)).saturating_sub(delay), |e| {
+                    e.frame
+                });
+            events[begin..end.max(begin)]
+                .iter()
+                .map(move |event| (event.frame + delay, event))
+        })
+    }
+
+    /// Bounding box of the positions of all sources while they are active,
+    /// `None` if no source has any clips (see `extent` module).
+    ///
+    /// This can be used e.g. for automatically framing the scene in a
+    /// visualizer or for warning if sources leave the area a renderer can
+    /// reproduce.
+    pub fn extent(&self) -> Option<BoundingBox> {
+        self.source_extents
+            .iter()
+            .flatten()
+            .fold(None, |result: Option<BoundingBox>, extent| match result {
+                Some(result) => Some(result.union(extent)),
+                None => Some(*extent),
+            })
+    }
+
+    /// Bounding box of the positions of the given source while it is active,
+    /// see `extent()`.
+    /// Panics if `source_idx` is out of range.
+    pub fn source_extent(&self, source_idx: usize) -> Option<BoundingBox> {
+        self.source_extents[source_idx]
+    }
+
+    /// List of non-overlapping (begin, end) frame ranges where the given
+    /// source produces audio, sorted by begin time.
+    /// Adjacent clips are merged into a single range.
+    /// Waiting times of `<wait-for-trigger>` elements are not included.
+    /// Panics if `source_idx` is out of range.
+    pub fn source_activity(&self, source_idx: usize) -> &[(u64, u64)] {
+        &self.sources[source_idx].active_ranges
+    }
+
+    /// Requests playback to continue from `frame`.
+    ///
+    /// Returns `true` if the scene is ready to play from there
+    /// (i.e. `transport_state()` is `Primed` or `Finished`).
+    /// Otherwise, this has to be called again (with the same `frame`,
+    /// e.g. once per audio block) until it returns `true`:
+    ///
+    /// * While playing, `get_audio_data()` has to be called with
+    ///   `rolling` set to `false` until the fade-out is finished
+    ///   (`FadingOutForSeek`).
+    ///   If `rolling` is set to `true` again before that,
+    ///   the seek is cancelled.
+    /// * Afterwards, the reader thread needs some time to provide
+    ///   audio data at the new position (`Seeking`).
     pub fn seek(&mut self, frame: u64) -> bool {
-        self.streamer.seek(frame)
+        let result = self.streamer.seek(frame);
+        self.after_seek(frame, result);
+        result
+    }
+
+    fn after_seek(&mut self, frame: u64, result: bool) {
+        if let Some(log) = &mut self.session_log {
+            log.seek(frame, result);
+        }
+        if let Some(watchdog) = &mut self.silence_watchdog {
+            watchdog.reset();
+        }
+    }
+
+    /// Schedules a seek to frame `target`, which takes place exactly when
+    /// frame `at` is reached during playback (even within a block),
+    /// without fade-out and without waiting for the reader thread.
+    /// This way, cue jumps can be prepared in advance and executed
+    /// in sync with external systems.
+    ///
+    /// Returns `false` if `at` has already been played, if another
+    /// scheduled seek hasn't taken place yet or if the scene is currently
+    /// seeking (see `seek()`).
+    /// Only one seek can be scheduled at a time, a regular `seek()`
+    /// (or `reset()`) cancels it.
+    ///
+    /// The reader thread has to prepare the audio data at the target
+    /// before it reads frame `at`, see `scheduled_seek()`.
+    /// The jump is not faded, the audio data should be silent
+    /// (or continuous) at both positions.
+    /// Releases of `<wait-for-trigger>` elements (see `trigger()`)
+    /// are kept, elements before `target` don't wait.
+    ///
+    /// This is realtime-safe.
+    pub fn seek_at(&mut self, at: u64, target: u64) -> bool {
+        self.streamer.seek_at(at, target)
+    }
+
+    /// The seek scheduled with `seek_at()` and its current state
+    /// (`None` if there was none since the most recent regular seek).
+    ///
+    /// The state is updated during `get_audio_data()`.
+    /// If it is `Missed`, the reader thread was too far ahead,
+    /// the seek has to be scheduled earlier next time
+    /// (at least the buffer size, see `streaming_stats()`).
+    pub fn scheduled_seek(&self) -> Option<ScheduledSeek> {
+        self.streamer.scheduled_seek()
+    }
+
+    /// Returns to the state right after loading (primed at frame 0),
+    /// e.g. when the same scene is used for multiple performances.
+    ///
+    /// Unlike `seek(0)`, this takes effect immediately, without fade-out:
+    /// buffered audio data is discarded, fades are cancelled, all triggers
+    /// are armed again and the underrun counter is cleared.
+    /// Settings like mute/solo, fade length and output map are kept.
+    /// Since the output is not faded, this should only be called while
+    /// the output is not audible.
+    ///
+    /// Same return value as `seek()`, if it is `false`,
+    /// `seek(0)` has to be called until it returns `true`.
+    pub fn reset(&mut self) -> bool {
+        self.streamer.reset();
+        if let Some(log) = &mut self.session_log {
+            log.reset();
+        }
+        self.streaming_error = None;
+        self.seek(0)
+    }
+
+    /// Starts recording transport actions, seeks, underruns and errors
+    /// (see `session` module), with space for (at most) `capacity` entries.
+    ///
+    /// A previously recorded log is discarded.
+    pub fn enable_session_log(&mut self, capacity: usize) {
+        self.session_log = Some(session::SessionLog::new(self.samplerate, capacity));
+    }
+
+    pub fn session_log(&self) -> Option<&session::SessionLog> {
+        self.session_log.as_ref()
+    }
+
+    /// Can be used to add custom entries to the log.
+    pub fn session_log_mut(&mut self) -> Option<&mut session::SessionLog> {
+        self.session_log.as_mut()
+    }
+
+    /// Stops logging and returns the log recorded so far.
+    pub fn take_session_log(&mut self) -> Option<session::SessionLog> {
+        self.session_log.take()
+    }
+
+    /// Starts recording all calls to `get_source_transform()` and
+    /// `get_reference_transform()` (see `trace` module), keeping (at most)
+    /// the `capacity` most recent ones.
+    ///
+    /// A previously recorded trace is discarded.
+    /// Recording is realtime-safe, queries made while the trace is
+    /// retrieved with `transform_trace()` are counted as dropped.
+    pub fn enable_transform_trace(&mut self, capacity: usize) {
+        self.transform_tracer = Some(trace::Tracer::new(capacity));
+    }
+
+    /// Copy of the queries recorded so far, recording continues.
+    pub fn transform_trace(&self) -> Option<trace::TransformTrace> {
+        self.transform_tracer.as_ref().map(trace::Tracer::snapshot)
+    }
+
+    /// Stops recording and returns the trace recorded so far.
+    pub fn take_transform_trace(&mut self) -> Option<trace::TransformTrace> {
+        self.transform_tracer.take().map(trace::Tracer::into_trace)
+    }
+
+    /// Starts watching for sources which have an active clip but deliver
+    /// only silence for (at least) `duration` (see `watchdog` module).
+    ///
+    /// Such sources are reported with a `Silence` event in the session log
+    /// (if enabled) and can be queried with `silence_watchdog()`.
+    pub fn enable_silence_watchdog(&mut self, duration: Duration) {
+        let limit = (duration.as_secs_f64() * f64::from(self.samplerate)).round() as u64;
+        self.silence_watchdog = Some(watchdog::SilenceWatchdog::new(
+            self.sources.len(),
+            limit.max(1),
+        ));
+    }
+
+    pub fn silence_watchdog(&self) -> Option<&watchdog::SilenceWatchdog> {
+        self.silence_watchdog.as_ref()
+    }
+
+    /// Current playback position, based on the audio data actually delivered
+    /// by `get_audio_data()` (e.g. underruns don't advance the position).
+    ///
+    /// This is realtime-safe, it can be called from the audio thread.
+    pub fn position(&self) -> Position {
+        self.streamer.position()
+    }
+
+    /// Reason why the most recent call to `get_audio_data()` (or one of its
+    /// variants) returned `false`, `None` if it was successful.
+    ///
+    /// After `StreamingError::ReaderDied`, the scene only delivers silence.
+    pub fn streaming_error(&self) -> Option<&StreamingError> {
+        self.streaming_error.as_ref()
+    }
+
+    /// Current state of playback, see `seek()`.
+    ///
+    /// This is realtime-safe, it can be called from the audio thread.
+    pub fn transport_state(&self) -> TransportState {
+        match self.streamer.transport_state() {
+            TransportState::Primed if self.is_finished(self.position().frame) => {
+                TransportState::Finished
+            }
+            state => state,
+        }
+    }
+
+    /// All `<wait-for-trigger>` elements, sorted by time (without waiting).
+    pub fn triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    /// Releases the next `<wait-for-trigger>` element with the given `id`
+    /// (which is waiting or which is at or after the current playback
+    /// position of its `<seq>`).
+    ///
+    /// If it is currently waiting, its `<seq>` continues after the
+    /// audio data which has already been buffered (see `streaming_stats()`),
+    /// otherwise it will not wait there.
+    /// Seeking cancels all releases.
+    ///
+    /// Returns `false` if there is no such element.
+    pub fn trigger(&mut self, id: &str) -> bool {
+        let next_frame = self.streamer.next_frame();
+        let holds = self.streamer.hold_state();
+        let pending = self.triggers.iter().enumerate().position(|(idx, trigger)| {
+            trigger.id == id
+                && !holds.is_released(idx)
+                && (holds.is_waiting(idx)
+                    || trigger.frame + holds.delay(trigger.parent) >= next_frame)
+        });
+        match pending {
+            Some(idx) => {
+                self.streamer.release_hold(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ID of the first `<wait-for-trigger>` element which is currently
+    /// waiting (as of the most recent call to `get_audio_data()`).
+    ///
+    /// Only the `<seq>` (or `<par>`) containing the element waits,
+    /// everything else continues to play.
+    /// Multiple elements can be waiting at the same time.
+    pub fn waiting_for_trigger(&self) -> Option<&str> {
+        let holds = self.streamer.hold_state();
+        self.triggers
+            .iter()
+            .enumerate()
+            .find(|&(idx, _)| holds.is_waiting(idx))
+            .map(|(_, trigger)| trigger.id.as_str())
+    }
+
+    /// All Ambisonics sources, in order of their appearance in the scene.
+    pub fn ambisonics_groups(&self) -> &[AmbisonicsGroup] {
+        &self.ambisonics_groups
+    }
+
+    /// The Ambisonics group of the given source (if any).
+    /// Panics if `source_idx` is out of range.
+    pub fn get_ambisonics_group(&self, source_idx: usize) -> Option<&AmbisonicsGroup> {
+        assert!(source_idx < self.sources.len());
+        self.ambisonics_groups.iter().find(|group| {
+            group.first_source <= source_idx
+                && source_idx < group.first_source + group.channels() as usize
+        })
+    }
+
+    /// All `<marker>`s, sorted by time.
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Seeks to the first marker with the given name (see `seek()`).
+    /// Returns `None` if there is no such marker.
+    pub fn seek_to_marker(&mut self, name: &str) -> Option<bool> {
+        let frame = self.markers.iter().find(|m| m.name == name)?.frame;
+        Some(self.seek(frame))
+    }
+
+    /// Writes the sources to arbitrary output channels of the host
+    /// (e.g. with a fixed patchbay) instead of one channel per source.
+    ///
+    /// `routes` contains pairs of zero-based source index and output channel.
+    /// A source can be routed to multiple outputs, sources which are routed
+    /// to the same output are summed, outputs without sources are silent.
+    /// Afterwards, `get_audio_data()` (and its variants) expect `channels`
+    /// target channels, see `output_channels()`.
+    ///
+    /// Panics if a source index or output channel is out of range.
+    pub fn set_output_map(&mut self, channels: u32, routes: &[(usize, usize)]) {
+        self.output_map = Some(output::OutputMap::new(
+            channels,
+            routes,
+            self.file_sources() as usize,
+            self.streamer.blocksize(),
+        ));
+    }
+
+    /// Goes back to one output channel per source.
+    pub fn clear_output_map(&mut self) {
+        self.output_map = None;
+    }
+
+    /// Number of target channels expected by `get_audio_data()`,
+    /// see `set_output_map()`.
+    pub fn output_channels(&self) -> u32 {
+        match &self.output_map {
+            Some(map) => map.channels(),
+            None => self.file_sources(),
+        }
     }
 
     /// Return value of `false` means un-recoverable error
     #[must_use]
     pub unsafe fn get_audio_data(&mut self, target: &[*mut f32], rolling: bool) -> bool {
-        self.streamer.get_data(target, rolling)
+        let blocksize = self.streamer.blocksize();
+        self.get_audio_data_frames(target, blocksize, rolling)
+    }
+
+    /// Like `get_audio_data()`, but with an arbitrary number of `frames`
+    /// (for hosts with varying buffer sizes).
+    ///
+    /// Audio data is still read from disk in chunks of `blocksize`
+    /// (see `LoadOptions::new()`), remaining frames are kept for the next call.
+    ///
+    /// Return value of `false` means un-recoverable error
+    #[must_use]
+    pub unsafe fn get_audio_data_frames(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+    ) -> bool {
+        self.measure_latency(|scene| scene.render_frames(target, frames, rolling))
+    }
+
+    #[must_use]
+    unsafe fn render_frames(&mut self, target: &[*mut f32], frames: u32, rolling: bool) -> bool {
+        let map = match self.output_map.take() {
+            Some(map) => map,
+            None => return self.get_source_data_frames(target, frames, rolling),
+        };
+        let mut result = true;
+        let mut done = 0;
+        while done < frames {
+            let n = (frames - done).min(map.blocksize());
+            result &= self.get_source_data_frames(map.source_pointers(), n, rolling);
+            map.write(target, done, n);
+            done += n;
+        }
+        self.output_map = Some(map);
+        result
+    }
+
+    /// Like `get_audio_data_frames()`, but always with one channel per source
+    /// (ignoring `set_output_map()`).
+    #[must_use]
+    pub(crate) unsafe fn get_source_data_frames(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+    ) -> bool {
+        let frame = self.streamer.next_frame();
+        let result = self.streamer.get_data(target, frames, rolling);
+        self.after_audio_data(target, frame, frames, rolling, result)
+    }
+
+    /// Like `get_audio_data_frames()`, but a change of `rolling` only takes
+    /// effect at frame `offset` within the block (e.g. the sample-accurate
+    /// transport change of the host), the frames before that are processed
+    /// with the previous state.
+    ///
+    /// When starting, the first `offset` frames are silent and playback
+    /// (including the fade-in) starts exactly at `offset`.
+    /// When stopping, the fade-out starts at `offset`.
+    /// With an `offset` of `0`, this is the same as `get_audio_data_frames()`.
+    ///
+    /// Panics if `offset` is larger than `frames`.
+    ///
+    /// Return value of `false` means un-recoverable error
+    #[must_use]
+    pub unsafe fn get_audio_data_with_ramp(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+        offset: u32,
+    ) -> bool {
+        assert!(offset <= frames);
+        self.measure_latency(|scene| scene.render_with_ramp(target, frames, rolling, offset))
+    }
+
+    #[must_use]
+    unsafe fn render_with_ramp(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+        offset: u32,
+    ) -> bool {
+        let map = match self.output_map.take() {
+            Some(map) => map,
+            None => return self.get_source_data_with_ramp(target, frames, rolling, offset),
+        };
+        // NB: The data is processed in chunks, the ramp is in (at most) one of them
+        let previous = self.streamer.previous_rolling();
+        let mut result = true;
+        let mut done = 0;
+        while done < frames {
+            let n = (frames - done).min(map.blocksize());
+            let pointers = map.source_pointers();
+            result &= if offset >= done + n {
+                self.get_source_data_frames(pointers, n, previous)
+            } else if offset > done {
+                self.get_source_data_with_ramp(pointers, n, rolling, offset - done)
+            } else {
+                self.get_source_data_frames(pointers, n, rolling)
+            };
+            map.write(target, done, n);
+            done += n;
+        }
+        self.output_map = Some(map);
+        result
+    }
+
+    #[must_use]
+    unsafe fn get_source_data_with_ramp(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+        offset: u32,
+    ) -> bool {
+        let frame = self.streamer.next_frame();
+        let result = self
+            .streamer
+            .get_data_with_ramp(target, frames, rolling, offset);
+        self.after_audio_data(target, frame, frames, rolling, result)
+    }
+
+    /// Calls `f`, in debug builds its execution time is checked against
+    /// `max_latency` (see `LoadOptions::max_latency()`).
+    fn measure_latency(&mut self, f: impl FnOnce(&mut Scene) -> bool) -> bool {
+        #[cfg(debug_assertions)]
+        {
+            if let Some(limit) = self.max_latency {
+                let start = std::time::Instant::now();
+                let result = f(self);
+                let elapsed = start.elapsed();
+                if elapsed > limit {
+                    let violations = &mut self.latency_violations;
+                    if violations.count == 0 {
+                        log::warn!("get_audio_data() took {:?} (limit: {:?})", elapsed, limit);
+                    }
+                    violations.count += 1;
+                    violations.longest = violations.longest.max(elapsed);
+                }
+                return result;
+            }
+        }
+        f(self)
+    }
+
+    /// `frame` is the frame number of the first requested frame.
+    /// Returns whether `result` was successful.
+    unsafe fn after_audio_data(
+        &mut self,
+        target: &[*mut f32],
+        frame: u64,
+        frames: u32,
+        rolling: bool,
+        result: Result<(), StreamingError>,
+    ) -> bool {
+        if let Some(log) = &mut self.session_log {
+            let underruns = self.streamer.stats().underruns;
+            log.audio_data(frames, rolling, underruns, result.as_ref().err());
+        }
+        self.streaming_error = result.err();
+        let result = self.streaming_error.is_none();
+        if let Some(target) = self.streamer.executed_seek() {
+            self.after_seek(target, true);
+        }
+        // NB: Only complete blocks of regular playback are checked
+        let end = frame + u64::from(frames);
+        if let Some(watchdog) = &mut self.silence_watchdog {
+            if result && self.streamer.next_frame() == end {
+                let sources = &self.sources;
+                let streamer = &self.streamer;
+                let holds = streamer.hold_state();
+                let log = &mut self.session_log;
+                watchdog.process(
+                    target,
+                    frames,
+                    |idx| {
+                        let source = &sources[idx];
+                        // NB: Muted sources are silent on purpose
+                        let has_clip = source
+                            .active_ranges
+                            .iter()
+                            .zip(source.active_holds.iter())
+                            .any(|(&(begin, stop), &hold)| {
+                                let delay = holds.delay(hold);
+                                begin + delay <= frame && end <= stop + delay
+                            });
+                        has_clip && streamer.is_audible(idx)
+                    },
+                    |idx| {
+                        if let Some(log) = log {
+                            log.log(session::SessionEvent::Silence(idx));
+                        }
+                    },
+                );
+            }
+        }
+        result
+    }
+
+    /// Names of all live inputs used in the scene (`$input.<name>`).
+    pub fn input_names(&self) -> impl Iterator<Item = &String> {
+        self.input_map.keys()
+    }
+
+    /// Sets the value of a live input, this is realtime-safe and lock-free.
+    /// Returns `false` if there is no live input with the given name.
+    pub fn set_input(&self, name: &str, value: f32) -> bool {
+        if let Some(&idx) = self.input_map.get(name) {
+            self.inputs.set(idx, value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Overrides the position of the given source (e.g. from a live controller).
+    /// The scene's own transforms are ignored as long as the override is set,
+    /// the source is still only active while it has an active clip.
+    /// `None` removes the override.
+    /// Panics if `source_idx` is out of range.
+    pub fn set_source_override(&mut self, source_idx: usize, position: Option<[f32; 3]>) {
+        self.position_overrides[source_idx] = position.map(Vec3::from);
+    }
+
+    /// Mutes (or unmutes) the given source, e.g. for auditioning sources in a GUI.
+    ///
+    /// The change is applied with a short gain ramp (to avoid clicks)
+    /// during the following calls to `get_audio_data()`.
+    /// Panics if `source_idx` is out of range.
+    pub fn set_source_mute(&mut self, source_idx: usize, mute: bool) {
+        self.streamer.set_mute(source_idx, mute);
+    }
+
+    /// Panics if `source_idx` is out of range.
+    pub fn is_source_muted(&self, source_idx: usize) -> bool {
+        self.streamer.is_muted(source_idx)
+    }
+
+    /// Solos (or un-solos) the given source.
+    ///
+    /// As long as any source is soloed, all other sources are muted
+    /// (with a short gain ramp, like in `set_source_mute()`).
+    /// Muting takes precedence over soloing.
+    /// Panics if `source_idx` is out of range.
+    pub fn set_source_solo(&mut self, source_idx: usize, solo: bool) {
+        self.streamer.set_solo(source_idx, solo);
+    }
+
+    /// Panics if `source_idx` is out of range.
+    pub fn is_source_soloed(&self, source_idx: usize) -> bool {
+        self.streamer.is_soloed(source_idx)
+    }
+
+    /// Sets the (linear) gain of the given source, e.g. for riding levels
+    /// during a show. The default is `1.0`.
+    ///
+    /// The change is applied with a short gain ramp, like in `set_source_mute()`.
+    /// Returns `false` (and leaves the gain unchanged) if `gain` is negative
+    /// or not finite.
+    /// Panics if `source_idx` is out of range.
+    pub fn set_source_gain(&mut self, source_idx: usize, gain: f32) -> bool {
+        self.streamer.set_gain(source_idx, gain)
+    }
+
+    /// Panics if `source_idx` is out of range.
+    pub fn source_gain(&self, source_idx: usize) -> f32 {
+        self.streamer.gain(source_idx)
+    }
+
+    /// Zero-based indices of all sources which have the tag `name`
+    /// (see `SourceInfo::tags`) or which are (directly or indirectly)
+    /// contained in the `<group>` with the ID `name`.
+    pub fn sources_in_group<'a>(&'a self, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.sources
+            .iter()
+            .enumerate()
+            .filter(move |(_, source)| is_in_group(source, &self.groups, name))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Sets the gain of all sources in the given tag or group
+    /// (see `sources_in_group()` and `set_source_gain()`),
+    /// e.g. `scene.set_group_gain("ambience", 0.5, 0)`.
+    ///
+    /// The gain ramps start exactly at frame `offset` of the next call to
+    /// `get_audio_data()` (or one of its variants), e.g. at the time of a cue
+    /// within the block. Offsets beyond the next block refer to later blocks.
+    ///
+    /// Returns the number of affected sources.
+    /// If `gain` is negative or not finite, nothing is changed and `0` is returned.
+    /// This is realtime-safe.
+    pub fn set_group_gain(&mut self, name: &str, gain: f32, offset: u32) -> usize {
+        if !streamer::is_valid_gain(gain) {
+            return 0;
+        }
+        self.apply_to_group(name, |streamer, idx| {
+            streamer.set_gain_at(idx, gain, offset);
+        })
+    }
+
+    /// Mutes (or unmutes) all sources in the given tag or group,
+    /// see `set_group_gain()`.
+    pub fn set_group_mute(&mut self, name: &str, mute: bool) -> usize {
+        self.apply_to_group(name, |streamer, idx| streamer.set_mute(idx, mute))
+    }
+
+    /// Solos (or un-solos) all sources in the given tag or group,
+    /// see `set_group_gain()`.
+    pub fn set_group_solo(&mut self, name: &str, solo: bool) -> usize {
+        self.apply_to_group(name, |streamer, idx| streamer.set_solo(idx, solo))
+    }
+
+    fn apply_to_group(&mut self, name: &str, mut f: impl FnMut(&mut FileStreamer, usize)) -> usize {
+        let mut count = 0;
+        for (idx, source) in self.sources.iter().enumerate() {
+            if is_in_group(source, &self.groups, name) {
+                f(&mut self.streamer, idx);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Confines all source positions to `area` (e.g. the area which can be
+    /// reproduced by a renderer), `None` removes the limitation.
+    ///
+    /// Positions outside of the area are moved onto its boundary according
+    /// to `policy`, their transforms are marked as `clamped`.
+    /// This is applied in `get_source_transform()`
+    /// after all transform modifiers, see `area` module.
+    pub fn set_area(&mut self, area: Option<Area>, policy: AreaPolicy) {
+        self.area = area.map(|area| (area, policy));
+    }
+
+    /// Adds a post-processing step for all source transforms,
+    /// see `TransformModifier`.
+    ///
+    /// Modifiers are applied in the order they have been added.
+    pub fn add_transform_modifier(&mut self, modifier: Box<dyn TransformModifier>) {
+        self.transform_modifiers.push(modifier.into());
+    }
+
+    /// Removes all modifiers added with `add_transform_modifier()`.
+    pub fn clear_transform_modifiers(&mut self) {
+        self.transform_modifiers.clear();
     }
 
     /// `source_idx`: Zero-based source number
     /// Panics if `source_idx` is out of range.
+    ///
+    /// All modifiers added with `add_transform_modifier()` are applied.
     pub fn get_source_transform(&self, source_idx: usize, frame: u64) -> Option<Transform> {
+        self.transform_view()
+            .get_source_transform(source_idx, frame)
+    }
+
+    /// Transforms of all sources (indexed like the sources) at `frame`,
+    /// see `get_source_transform()`.
+    ///
+    /// This is realtime-safe, the result is stored in memory which is
+    /// allocated when loading the scene.
+    pub fn get_source_transforms(&mut self, frame: u64) -> &[Option<Transform>] {
+        // NB: `transform_view()` would borrow `source_transforms` as well
+        let view = TransformView {
+            sources: &self.sources,
+            groups: &self.groups,
+            transformers: &self.transformers,
+            transformer_map: &self.transformer_map,
+            reference_transform: &self.reference_transform,
+            transform_modifiers: &self.transform_modifiers,
+            position_overrides: &self.position_overrides,
+            inputs: &self.inputs,
+            transform_tracer: self.transform_tracer.as_ref(),
+            area: self.area.as_ref(),
+            holds: self.streamer.hold_state(),
+        };
+        view.get_source_transforms(&mut self.source_transforms, frame);
+        &self.source_transforms
+    }
+
+    pub fn get_reference_transform(&self, frame: u64) -> Option<Transform> {
+        self.transform_view().get_reference_transform(frame)
+    }
+
+    fn get_unmodified_source_transform(&self, source_idx: usize, frame: u64) -> Option<Transform> {
+        self.transform_view()
+            .get_unmodified_source_transform(source_idx, frame)
+    }
+
+    /// Whether the position of the given source can be changed live,
+    /// i.e. by an override or by a transform depending on live inputs.
+    /// Panics if `source_idx` is out of range.
+    fn is_source_live(&self, source_idx: usize) -> bool {
+        self.transform_view().is_source_live(source_idx)
+    }
+
+    fn transform_view(&self) -> TransformView {
+        TransformView {
+            sources: &self.sources,
+            groups: &self.groups,
+            transformers: &self.transformers,
+            transformer_map: &self.transformer_map,
+            reference_transform: &self.reference_transform,
+            transform_modifiers: &self.transform_modifiers,
+            position_overrides: &self.position_overrides,
+            inputs: &self.inputs,
+            transform_tracer: self.transform_tracer.as_ref(),
+            area: self.area.as_ref(),
+            holds: self.streamer.hold_state(),
+        }
+    }
+}
+
+/// Everything needed for transform queries,
+/// borrowed from a `Scene` or from a `split::SceneControl`.
+struct TransformView<'a> {
+    sources: &'a [Source],
+    groups: &'a [Group],
+    transformers: &'a TransformerStorage,
+    transformer_map: &'a HashMap<String, Box<[usize]>>,
+    reference_transform: &'a Transform,
+    transform_modifiers: &'a [Arc<dyn TransformModifier>],
+    /// Indexed like `sources`
+    position_overrides: &'a [Option<Vec3>],
+    inputs: &'a LiveInputs,
+    transform_tracer: Option<&'a trace::Tracer>,
+    area: Option<&'a (Area, AreaPolicy)>,
+    /// Transforms after `<wait-for-trigger>` elements are delayed
+    holds: &'a HoldState,
+}
+
+impl<'a> TransformView<'a> {
+    fn get_source_transform(&self, source_idx: usize, frame: u64) -> Option<Transform> {
         // NB: This function is supposed to be realtime-safe!
+        let transform = self
+            .get_unmodified_source_transform(source_idx, frame)
+            .and_then(|mut transform| {
+                for modifier in self.transform_modifiers {
+                    if !modifier.modify(source_idx, frame, &mut transform) {
+                        return None;
+                    }
+                }
+                if let Some((area, policy)) = self.area {
+                    let position = transform.translation.unwrap_or_else(Vec3::zeros);
+                    if let Some(position) = area.confine(position.into(), *policy) {
+                        transform.translation = Some(position.into());
+                        transform.clamped = true;
+                    }
+                }
+                Some(transform)
+            });
+        if let Some(tracer) = self.transform_tracer {
+            tracer.record(Some(source_idx), frame, transform.as_ref());
+        }
+        transform
+    }
+
+    /// `result` is indexed like the sources.
+    fn get_source_transforms(&self, result: &mut [Option<Transform>], frame: u64) {
+        for (idx, transform) in result.iter_mut().enumerate() {
+            *transform = self.get_source_transform(idx, frame);
+        }
+    }
+
+    fn get_unmodified_source_transform(&self, source_idx: usize, frame: u64) -> Option<Transform> {
         let source = &self.sources[source_idx];
 
         // Transforms applied to <clip> (and its <channel> elements)
@@ -128,18 +1530,36 @@ impl Scene {
         // If source is not active, we don't need to check other transforms
         clip_transform.as_ref()?;
 
+        // NB: The override is given in world coordinates, groups are ignored
+        if let Some(position) = self.position_overrides[source_idx] {
+            return Some(Transform {
+                translation: Some(position),
+                ..source.transform.clone()
+            });
+        }
+
         let mut source_transform = source.transform.clone();
         source_transform.apply(Transform::merge(
             clip_transform,
             self.get_transform_applying_to(source.id.as_ref(), frame),
         ));
+
+        // Each <group> is applied in the coordinate frame of its parent
+        for group in self.groups_of(source) {
+            let mut group_transform = group.transform.clone();
+            group_transform.apply(self.get_transform_applying_to(group.id.as_ref(), frame));
+            source_transform.apply(Some(group_transform));
+        }
         Some(source_transform)
     }
 
-    pub fn get_reference_transform(&self, frame: u64) -> Option<Transform> {
+    fn get_reference_transform(&self, frame: u64) -> Option<Transform> {
         let mut reference_transform = self.reference_transform.clone();
         reference_transform
             .apply(self.get_transform_applying_to(Some(&REFERENCE_ID.into()), frame));
+        if let Some(tracer) = self.transform_tracer {
+            tracer.record(None, frame, Some(&reference_transform));
+        }
         Some(reference_transform)
     }
 
@@ -161,9 +1581,13 @@ impl Scene {
 
         let (transformer, activity) = &self.transformers[idx];
 
-        for &(begin, end) in activity.iter() {
-            if begin <= frame && frame < end {
-                let mut result = transformer.get_transform(frame - begin);
+        for &(begin, end, hold) in activity.iter() {
+            let local = match frame.checked_sub(self.holds.delay(hold)) {
+                Some(local) => local,
+                None => continue,
+            };
+            if begin <= local && local < end {
+                let mut result = transformer.get_transform(local - begin, self.inputs);
                 let id = transformer.id();
                 // TODO: Establish recursion limit! There might be circular dependencies!
                 result.apply(self.get_transform_applying_to(id, frame));
@@ -173,6 +1597,51 @@ impl Scene {
         None
     }
 
+    fn is_source_live(&self, source_idx: usize) -> bool {
+        let source = &self.sources[source_idx];
+        self.position_overrides[source_idx].is_some()
+            || source
+                .activity
+                .iter()
+                .any(|&idx| self.transformer_uses_inputs(idx, 0))
+            || self.any_transformer_applying_to_uses_inputs(source.id.as_ref(), 0)
+            || self
+                .groups_of(source)
+                .any(|group| self.any_transformer_applying_to_uses_inputs(group.id.as_ref(), 0))
+    }
+
+    /// Enclosing groups of the given source, innermost first.
+    fn groups_of(&self, source: &Source) -> impl Iterator<Item = &'a Group> {
+        let groups = self.groups;
+        let mut group_idx = source.group;
+        std::iter::from_fn(move || {
+            let group = &groups[group_idx?];
+            group_idx = group.parent;
+            Some(group)
+        })
+    }
+
+    /// Circular dependencies are stopped at `MAX_DEPTH`
+    fn transformer_uses_inputs(&self, idx: usize, depth: usize) -> bool {
+        const MAX_DEPTH: usize = 100;
+        if depth > MAX_DEPTH {
+            return false;
+        }
+        let transformer = &self.transformers[idx].0;
+        transformer.uses_inputs()
+            || self.any_transformer_applying_to_uses_inputs(transformer.id(), depth + 1)
+    }
+
+    fn any_transformer_applying_to_uses_inputs(&self, id: Option<&String>, depth: usize) -> bool {
+        id.and_then(|id| self.transformer_map.get(id))
+            .map(|transformers| {
+                transformers
+                    .iter()
+                    .any(|&idx| self.transformer_uses_inputs(idx, depth))
+            })
+            .unwrap_or(false)
+    }
+
     fn get_transform_applying_to(&self, id: Option<&String>, frame: u64) -> Option<Transform> {
         let transformers = self.transformer_map.get(id?)?;
         // TODO: Establish recursion limit! There might be circular dependencies!
@@ -182,20 +1651,227 @@ impl Scene {
     }
 }
 
-trait Transformer {
+/// Metadata of a source, see `Scene::get_source_info()`.
+pub struct SourceInfo<'a> {
+    pub id: Option<&'a str>,
+    pub name: Option<&'a str>,
+    /// Rendering model, e.g. "point", "plane" or "binaural"
+    pub model: Option<&'a str>,
+    pub port: Option<&'a str>,
+    /// RGB color, given by the `color` attribute (as `#rrggbb`).
+    ///
+    /// This and the following attributes are not used by the library,
+    /// they are meant for presenting the source in user interfaces.
+    pub color: Option<[u8; 3]>,
+    /// Name (or path) of an icon, given by the `icon` attribute
+    pub icon: Option<&'a str>,
+    /// Free-form text, given by the `comment` attribute
+    pub comment: Option<&'a str>,
+    /// Categories of the source (e.g. `"dialog"` or `"ambience"`),
+    /// given as a whitespace-separated list by the `tags` attribute
+    pub tags: &'a [String],
+    /// Expected length of the reverb/decay tail after the end of each clip
+    /// (in frames), i.e. how long a renderer should keep processing the source.
+    /// This is given by the `tail` attribute (in seconds), the default is 0.
+    pub tail: u64,
+    /// Whether the polarity of the audio data is inverted
+    /// (`polarity="inverted"`), this is applied when reading the data.
+    pub inverted: bool,
+    /// Static delay (in frames) which is applied when reading the audio data,
+    /// given by the `delay` attribute (in samples or with the suffix `ms`),
+    /// e.g. for aligning microphone signals.
+    /// After seeking, the first `delay` frames of the source are silent.
+    pub delay: u32,
+    /// Attributes of the `<source>` element which are not used by the library
+    /// (as `(name, value)` pairs in the order they appear in the file)
+    pub extra_attributes: &'a [(String, String)],
+}
+
+/// A clip as seen from one of the sources it feeds.
+pub struct ClipInfo<'a> {
+    /// Begin time in frames
+    pub begin: u64,
+    /// Duration in frames
+    pub duration: u64,
+    pub file: &'a Path,
+    /// Zero-based channel number within the file
+    pub channel: u32,
+    /// Playback speed (given by the `speed` attribute of the `<clip>`)
+    pub speed: f64,
+}
+
+/// A timed `<event>` with arbitrary data, e.g. a cue for video or lights.
+pub struct Event {
+    pub frame: u64,
+    /// Zero-based source index (if the event was given a "source" attribute)
+    pub source: Option<usize>,
+    pub data: String,
+}
+
+/// Transport commands requested by a remote controller, to be handled by the host.
+///
+/// If multiple commands of the same kind arrived since the last query,
+/// only the latest one is kept.
+#[derive(Default)]
+pub struct TransportRequest {
+    /// Frame number to seek to (see `Scene::seek()`)
+    pub seek: Option<u64>,
+    pub rolling: Option<bool>,
+}
+
+/// A named position on the timeline, given by a `<marker>` element.
+pub struct Marker {
+    pub frame: u64,
+    pub name: String,
+}
+
+/// Normalization of Ambisonics signals.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmbisonicsNormalization {
+    /// Schmidt semi-normalized (as used in AmbiX)
+    Sn3d,
+    /// Fully normalized
+    N3d,
+    /// Furse-Malham (with FuMa channel ordering, only up to third order)
+    Fuma,
+}
+
+/// Consecutive sources which together form an Ambisonics signal,
+/// given by the `ambisonics-order` attribute of a `<clip>`.
+///
+/// Each source holds one Ambisonics channel (in ACN order, unless the
+/// normalization is `Fuma`), a renderer should decode them together
+/// instead of treating them as point sources.
+/// Their `model` is `"ambisonics"`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmbisonicsGroup {
+    /// Zero-based index of the first source
+    pub first_source: usize,
+    pub order: u32,
+    pub normalization: AmbisonicsNormalization,
+}
+
+impl AmbisonicsGroup {
+    /// Number of sources (i.e. channels) in the group
+    pub fn channels(&self) -> u32 {
+        (self.order + 1) * (self.order + 1)
+    }
+}
+
+/// A `<meta>` element, e.g. the title or a description of the scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metadata {
+    pub name: String,
+    pub content: String,
+    /// Language tag given by the `xml:lang` attribute (e.g. `"en"`)
+    pub lang: Option<String>,
+}
+
+/// A position on the timeline where playback waits for `Scene::trigger()`,
+/// given by a `<wait-for-trigger>` element.
+///
+/// While waiting, only the rest of the enclosing `<seq>` (or `<par>`)
+/// is paused, everything else continues to play.
+/// When a `<seq>` is repeated, each iteration has its own triggers.
+pub struct Trigger {
+    /// Time without waiting (i.e. not including the waiting time of `parent`)
+    pub frame: u64,
+    pub id: String,
+    /// Index of the trigger which delays this one, if it is in the part
+    /// of the timeline which is paused by another trigger
+    pub parent: Option<usize>,
+}
+
+/// Post-processing of source transforms (e.g. coordinate conversion
+/// or warping the scene to fit a room), see `Scene::add_transform_modifier()`.
+pub trait TransformModifier: Send + Sync {
+    /// Modifies the transform of the (active) source `source_idx` at `frame`.
+    ///
+    /// Returning `false` deactivates the source (and skips the remaining modifiers).
+    ///
+    /// This is called from `Scene::get_source_transform()`,
+    /// therefore it has to be realtime-safe.
+    fn modify(&self, source_idx: usize, frame: u64, transform: &mut Transform) -> bool;
+}
+
+trait Transformer: Send + Sync {
     fn id(&self) -> Option<&String>;
     /// begin and end is checked before calling this
-    fn get_transform(&self, frame: u64) -> Transform;
+    fn get_transform(&self, frame: u64, inputs: &LiveInputs) -> Transform;
+    /// Whether the result depends on live inputs
+    fn uses_inputs(&self) -> bool {
+        false
+    }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct Source {
     id: Option<String>,
     name: Option<String>,
     model: Option<String>,
+    port: Option<String>,
+    /// RGB color (for display in user interfaces)
+    color: Option<[u8; 3]>,
+    icon: Option<String>,
+    comment: Option<String>,
+    /// Given by the `tags` attribute (without duplicates)
+    tags: Box<[String]>,
+    /// Expected length of reverb/decay tail after the end of each clip (in frames)
+    tail: u64,
+    /// Given by the `polarity` attribute
+    inverted: bool,
+    /// Given by the `delay` attribute (in frames)
+    delay: u32,
+    /// Any further attributes given in the `<source>` element
+    extra_attributes: Box<[(String, String)]>,
     /// Transform given in <head> element
     transform: Transform,
     /// List of transforms that define when source is active
     activity: Box<[usize]>,
+    /// Playlist entries (of file sources) sorted by begin time
+    clips: Box<[Clip]>,
+    /// Union of all clip ranges (begin, end)
+    active_ranges: Box<[(u64, u64)]>,
+    /// Hold of each range in `active_ranges`, see `PlaylistEntry::hold`
+    active_holds: Box<[Option<usize>]>,
+    /// Index of innermost enclosing <group> (if any)
+    group: Option<usize>,
     // TODO: live or file source?
 }
+
+/// A <group> of sources (and nested groups), given in the <head> element
+#[derive(Clone)]
+struct Group {
+    id: Option<String>,
+    transform: Transform,
+    /// Index of enclosing <group> (if any)
+    parent: Option<usize>,
+}
+
+/// Whether `source` has the tag `name` or is within the group with ID `name`.
+fn is_in_group(source: &Source, groups: &[Group], name: &str) -> bool {
+    if source.tags.iter().any(|tag| tag == name) {
+        return true;
+    }
+    let mut group_idx = source.group;
+    while let Some(idx) = group_idx {
+        if groups[idx].id.as_deref() == Some(name) {
+            return true;
+        }
+        group_idx = groups[idx].parent;
+    }
+    false
+}
+
+#[derive(Clone)]
+struct Clip {
+    begin: u64,
+    duration: u64,
+    /// Index into `Scene::clip_files`
+    file: usize,
+    channel: u32,
+    /// See `PlaylistEntry::hold`
+    hold: Option<usize>,
+}
@@ -0,0 +1,68 @@
+//! Loading scenes without a file system (e.g. in web browsers),
+//! see [`Scene::load_with_reader()`](../struct.Scene.html#method.load_with_reader).
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+#[cfg(any(feature = "archive", feature = "bundle"))]
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+
+/// Contents of a file, e.g. `std::io::Cursor<Vec<u8>>`.
+pub trait ByteReader: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> ByteReader for T {}
+
+/// Provides the contents of the scene file, all included sub-scenes
+/// and all audio files.
+///
+/// Relative paths are joined with the directory of the (sub-)scene file
+/// they are used in, but they are not resolved in any other way
+/// (e.g. `..` is not removed).
+pub type OpenCallback = Arc<dyn Fn(&Path) -> io::Result<Box<dyn ByteReader>> + Send + Sync>;
+
+pub(crate) fn read_to_string(open: &OpenCallback, path: &Path) -> io::Result<String> {
+    let mut data = String::new();
+    open(path)?.read_to_string(&mut data)?;
+    Ok(data)
+}
+
+/// New position after seeking to `pos` in a stream of `len` bytes, starting
+/// at `position` (for implementations of `Seek` which don't seek eagerly).
+pub(crate) fn resolve_seek(pos: SeekFrom, position: u64, len: u64) -> io::Result<u64> {
+    match pos {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::End(offset) => offset_position(len, offset),
+        SeekFrom::Current(offset) => offset_position(position, offset),
+    }
+    .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative position",
+        )
+    })
+}
+
+/// `None` if the result would be negative (or too large).
+pub(crate) fn offset_position(base: u64, offset: i64) -> Option<u64> {
+    if offset < 0 {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    } else {
+        base.checked_add(offset as u64)
+    }
+}
+
+/// Removes `.` and resolves `..` (without accessing the file system).
+#[cfg(any(feature = "archive", feature = "bundle"))]
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
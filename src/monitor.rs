@@ -0,0 +1,135 @@
+//! Decimated stream of transforms (e.g. 30 times per second) for visualization.
+//!
+//! The [`TransformFeed`](struct.TransformFeed.html) is used in the audio thread
+//! (typically once per block), the
+//! [`TransformMonitor`](struct.TransformMonitor.html) can be used from any
+//! other thread (e.g. a GUI thread) to get the most recent transforms.
+
+use crossbeam::queue;
+
+use crate::transform::Transform;
+use crate::Scene;
+
+/// Number of snapshots which can be in flight at the same time
+const QUEUE_CAPACITY: usize = 16;
+
+/// Transforms of all sources and of the reference at a given time.
+pub struct TransformSnapshot {
+    pub frame: u64,
+    /// Indexed by source number, `None` if the source is inactive
+    pub sources: Box<[Option<Transform>]>,
+    pub reference: Option<Transform>,
+}
+
+/// Sending side (to be used in the audio thread), see `new()`.
+pub struct TransformFeed {
+    /// Time between snapshots (in frames)
+    interval: f64,
+    /// Number of the next snapshot (counted from the beginning of the scene)
+    next_snapshot: u64,
+    producer: queue::spsc::Producer<TransformSnapshot>,
+    recycling_consumer: queue::spsc::Consumer<TransformSnapshot>,
+}
+
+/// Receiving side (to be used in a non-audio thread), see `new()`.
+pub struct TransformMonitor {
+    consumer: queue::spsc::Consumer<TransformSnapshot>,
+    recycling_producer: queue::spsc::Producer<TransformSnapshot>,
+    latest: Option<TransformSnapshot>,
+}
+
+/// Creates a feed/monitor pair with `rate` snapshots per second.
+///
+/// All memory is allocated in advance.
+/// Panics if `rate` is not positive.
+pub fn new(scene: &Scene, rate: f32) -> (TransformFeed, TransformMonitor) {
+    assert!(rate > 0.0);
+    let (producer, consumer) = queue::spsc::new(QUEUE_CAPACITY);
+    let (recycling_producer, recycling_consumer) = queue::spsc::new(QUEUE_CAPACITY);
+    // NB: One snapshot is kept in `TransformMonitor::latest`
+    for _ in 0..QUEUE_CAPACITY - 1 {
+        recycling_producer
+            .push(TransformSnapshot {
+                frame: 0,
+                sources: vec![None; scene.sources.len()].into(),
+                reference: None,
+            })
+            .unwrap();
+    }
+    (
+        TransformFeed {
+            interval: f64::from(scene.samplerate) / f64::from(rate),
+            next_snapshot: 0,
+            producer,
+            recycling_consumer,
+        },
+        TransformMonitor {
+            consumer,
+            recycling_producer,
+            latest: None,
+        },
+    )
+}
+
+impl TransformFeed {
+    /// Takes snapshots of all transforms which are due within the block
+    /// starting at `frame` with the length `frames`.
+    ///
+    /// After seeking, the snapshots continue at the new position.
+    /// If the monitor doesn't keep up, snapshots are dropped.
+    pub fn process(&mut self, scene: &Scene, frame: u64, frames: u32) {
+        // NB: This function is supposed to be realtime-safe!
+        let end = frame + u64::from(frames);
+        let interval = self.interval;
+        let snapshot_frame = |n: u64| (n as f64 * interval).round() as u64;
+        let first_snapshot = (frame as f64 / interval).ceil() as u64;
+        if snapshot_frame(self.next_snapshot) < frame || self.next_snapshot > first_snapshot + 1 {
+            // After seeking (or dropped blocks)
+            self.next_snapshot = first_snapshot;
+        }
+        loop {
+            let current = snapshot_frame(self.next_snapshot);
+            if current >= end {
+                break;
+            }
+            self.next_snapshot += 1;
+            if current < frame {
+                continue;
+            }
+            let mut snapshot = match self.recycling_consumer.pop() {
+                Ok(snapshot) => snapshot,
+                // The monitor doesn't keep up
+                Err(_) => continue,
+            };
+            snapshot.frame = current;
+            for (idx, transform) in snapshot.sources.iter_mut().enumerate() {
+                *transform = scene.get_source_transform(idx, current);
+            }
+            snapshot.reference = scene.get_reference_transform(current);
+            // NB: There are never more snapshots than the queue capacity
+            self.producer.push(snapshot).unwrap();
+        }
+    }
+}
+
+impl TransformMonitor {
+    /// Fetches the snapshots which were taken since the last call
+    /// and keeps the most recent one.
+    ///
+    /// Returns `false` if there was no new snapshot.
+    pub fn update(&mut self) -> bool {
+        let mut updated = false;
+        while let Ok(snapshot) = self.consumer.pop() {
+            if let Some(previous) = self.latest.replace(snapshot) {
+                self.recycling_producer.push(previous).unwrap();
+            }
+            updated = true;
+        }
+        updated
+    }
+
+    /// The most recent snapshot (`None` before the first `update()`).
+    pub fn latest(&self) -> Option<&TransformSnapshot> {
+        self.latest.as_ref()
+    }
+}
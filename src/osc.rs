@@ -0,0 +1,202 @@
+//! OSC server for controlling a [`Scene`](../struct.Scene.html) from
+//! show-control systems (requires the `osc` feature).
+//!
+//! Supported messages (all values can be given as int, float or double):
+//!
+//! * `/asdf/input/<name> <value>`: sets the live input `$input.<name>`
+//! * `/asdf/seek <seconds>`: requests a seek
+//! * `/asdf/cue <marker name>`: requests a seek to the given `<marker>`
+//! * `/asdf/rolling <bool or int>`: requests start/stop of playback
+//! * `/asdf/source/<id>/pos <x> <y> [<z>]`: overrides the source position
+//! * `/asdf/source/<id>/release`: removes the position override
+//!
+//! Messages are received and decoded in a separate thread, they are applied
+//! to the scene by calling [`OscServer::process()`](struct.OscServer.html#method.process)
+//! (e.g. once per audio block).
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::queue;
+use rosc::{OscMessage, OscPacket, OscType};
+
+use crate::{Scene, TransportRequest};
+
+/// Maximum number of messages between two calls to `process()`
+const QUEUE_CAPACITY: usize = 256;
+
+enum Command {
+    /// Index of live input, value
+    Input(usize, f32),
+    Seek(u64),
+    Rolling(bool),
+    /// Source index, position (`None` removes the override)
+    SourcePosition(usize, Option<[f32; 3]>),
+}
+
+pub struct OscServer {
+    commands: queue::spsc::Consumer<Command>,
+    keep_receiving: Arc<AtomicBool>,
+    receiver_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl OscServer {
+    /// Starts listening for UDP packets on `address` (e.g. `"0.0.0.0:9000"`).
+    ///
+    /// Names of live inputs, source IDs and markers are taken from `scene`.
+    pub fn new<A: ToSocketAddrs>(
+        scene: &Scene,
+        address: A,
+    ) -> Result<OscServer, Box<dyn Error + Send + Sync>> {
+        let socket = UdpSocket::bind(address)?;
+        // NB: The timeout is needed to regularly check `keep_receiving`
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        let names = Names {
+            samplerate: scene.samplerate,
+            inputs: scene.input_map.clone(),
+            sources: scene
+                .sources
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, source)| source.id.clone().map(|id| (id, idx)))
+                .collect(),
+            markers: scene
+                .markers
+                .iter()
+                .map(|m| (m.name.clone(), m.frame))
+                .collect(),
+        };
+        let (producer, consumer) = queue::spsc::new(QUEUE_CAPACITY);
+        let keep_receiving = Arc::new(AtomicBool::new(true));
+        let keep_receiving_clone = Arc::clone(&keep_receiving);
+        let receiver_thread = thread::spawn(move || {
+            let mut buffer = [0u8; rosc::decoder::MTU];
+            while keep_receiving_clone.load(Ordering::Acquire) {
+                // NB: Timeouts and other errors are ignored
+                if let Ok((size, _)) = socket.recv_from(&mut buffer) {
+                    if let Ok(packet) = rosc::decoder::decode(&buffer[..size]) {
+                        names.handle_packet(packet, &producer);
+                    }
+                }
+            }
+        });
+        Ok(OscServer {
+            commands: consumer,
+            keep_receiving,
+            receiver_thread: Some(receiver_thread),
+        })
+    }
+
+    /// Applies all live inputs and source overrides received since the
+    /// last call.
+    ///
+    /// Transport commands are not applied, they are returned to the caller.
+    pub fn process(&self, scene: &mut Scene) -> TransportRequest {
+        // NB: This function is supposed to be realtime-safe!
+        let mut request = TransportRequest::default();
+        while let Ok(command) = self.commands.pop() {
+            match command {
+                Command::Input(idx, value) => scene.inputs.set(idx, value),
+                Command::Seek(frame) => request.seek = Some(frame),
+                Command::Rolling(rolling) => request.rolling = Some(rolling),
+                Command::SourcePosition(idx, position) => scene.set_source_override(idx, position),
+            }
+        }
+        request
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.keep_receiving.store(false, Ordering::Release);
+        // NB: The thread terminates after the next read timeout
+        let _ = self.receiver_thread.take().unwrap().join();
+    }
+}
+
+/// Lookup tables used in the receiver thread
+struct Names {
+    samplerate: u32,
+    inputs: HashMap<String, usize>,
+    sources: HashMap<String, usize>,
+    markers: Vec<(String, u64)>,
+}
+
+impl Names {
+    fn handle_packet(&self, packet: OscPacket, producer: &queue::spsc::Producer<Command>) {
+        match packet {
+            OscPacket::Message(message) => {
+                if let Some(command) = self.get_command(message) {
+                    // NB: If the queue is full, the message is dropped
+                    let _ = producer.push(command);
+                }
+            }
+            OscPacket::Bundle(bundle) => {
+                for packet in bundle.content {
+                    self.handle_packet(packet, producer);
+                }
+            }
+        }
+    }
+
+    fn get_command(&self, message: OscMessage) -> Option<Command> {
+        let path = message.addr.strip_prefix("/asdf/")?;
+        let args = &message.args;
+        if let Some(name) = path.strip_prefix("input/") {
+            let idx = *self.inputs.get(name)?;
+            Some(Command::Input(idx, get_float(args.first()?)?))
+        } else if let Some(rest) = path.strip_prefix("source/") {
+            let (id, action) = rest.split_at(rest.rfind('/')?);
+            let idx = *self.sources.get(id)?;
+            match action {
+                "/pos" => {
+                    let x = get_float(args.first()?)?;
+                    let y = get_float(args.get(1)?)?;
+                    let z = args.get(2).and_then(get_float).unwrap_or(0.0);
+                    Some(Command::SourcePosition(idx, Some([x, y, z])))
+                }
+                "/release" => Some(Command::SourcePosition(idx, None)),
+                _ => None,
+            }
+        } else {
+            match path {
+                "seek" => {
+                    let seconds = get_float(args.first()?)?;
+                    if seconds < 0.0 {
+                        return None;
+                    }
+                    let frame = (f64::from(seconds) * f64::from(self.samplerate)).round();
+                    Some(Command::Seek(frame as u64))
+                }
+                "cue" => {
+                    let name = match args.first()? {
+                        OscType::String(name) => name,
+                        _ => return None,
+                    };
+                    let &(_, frame) = self.markers.iter().find(|(n, _)| n == name)?;
+                    Some(Command::Seek(frame))
+                }
+                "rolling" => Some(Command::Rolling(match args.first()? {
+                    OscType::Bool(value) => *value,
+                    OscType::Int(value) => *value != 0,
+                    _ => return None,
+                })),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn get_float(arg: &OscType) -> Option<f32> {
+    match *arg {
+        OscType::Float(value) => Some(value),
+        OscType::Double(value) => Some(value as f32),
+        OscType::Int(value) => Some(value as f32),
+        _ => None,
+    }
+}
@@ -0,0 +1,78 @@
+//! Routing of sources to output channels of the host,
+//! see `Scene::set_output_map()`.
+
+pub(crate) struct OutputMap {
+    /// Number of output channels
+    channels: u32,
+    /// For each source, the output channels it is written to
+    outputs: Box<[Box<[usize]>]>,
+    /// Audio data of each source, at most `blocksize` frames
+    buffers: Box<[Box<[f32]>]>,
+    pointers: Box<[*mut f32]>,
+}
+
+// NB: The pointers point into `buffers`, which are never shared
+unsafe impl Send for OutputMap {}
+
+impl OutputMap {
+    /// `routes` contains pairs of zero-based source index and output channel.
+    ///
+    /// All memory is allocated in advance.
+    pub(crate) fn new(
+        channels: u32,
+        routes: &[(usize, usize)],
+        sources: usize,
+        blocksize: u32,
+    ) -> OutputMap {
+        let mut outputs = vec![Vec::new(); sources];
+        for &(source, output) in routes {
+            assert!(source < sources, "Source index out of range");
+            assert!(output < channels as usize, "Output channel out of range");
+            outputs[source].push(output);
+        }
+        let mut buffers: Box<[Box<[f32]>]> = (0..sources)
+            .map(|_| vec![0.0; blocksize as usize].into())
+            .collect();
+        OutputMap {
+            channels,
+            outputs: outputs.into_iter().map(Vec::into_boxed_slice).collect(),
+            pointers: buffers.iter_mut().map(|b| b.as_mut_ptr()).collect(),
+            buffers,
+        }
+    }
+
+    pub(crate) fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    /// Maximum number of frames which can be processed at once.
+    pub(crate) fn blocksize(&self) -> u32 {
+        self.buffers.first().map_or(u32::MAX, |b| b.len() as u32)
+    }
+
+    /// One channel per source, with space for `blocksize()` frames.
+    pub(crate) fn source_pointers(&self) -> &[*mut f32] {
+        &self.pointers
+    }
+
+    /// Writes `frames` frames of all sources to the `target` output channels,
+    /// starting at frame `offset`.
+    /// Sources routed to the same output are summed, unused outputs are silent.
+    pub(crate) unsafe fn write(&self, target: &[*mut f32], offset: u32, frames: u32) {
+        assert_eq!(target.len(), self.channels as usize);
+        for &ptr in target {
+            std::slice::from_raw_parts_mut(ptr.add(offset as usize), frames as usize).fill(0.0);
+        }
+        for (buffer, outputs) in self.buffers.iter().zip(self.outputs.iter()) {
+            for &output in outputs.iter() {
+                let data = std::slice::from_raw_parts_mut(
+                    target[output].add(offset as usize),
+                    frames as usize,
+                );
+                for (out, value) in data.iter_mut().zip(buffer.iter()) {
+                    *out += value;
+                }
+            }
+        }
+    }
+}
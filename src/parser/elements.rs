@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::collections::HashMap;
 use std::num::NonZeroU64;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -7,18 +8,29 @@ use asdfspline::AsdfSpline;
 use xmlparser as xml;
 
 use crate::audiofile::dynamic::{load_audio_file, AudioFile};
+use crate::audiofile::{faded, looped, media, memory, signal};
 use crate::error::ResultExt;
-use crate::streamer::FileStreamer;
-use crate::transform::{get_length, parse_pos, parse_transform, Transform, Vec3};
-use crate::{Source, Transformer, REFERENCE_ID};
+use crate::expression::Expression;
+use crate::tempo::TempoMap;
+use crate::transform::{
+    get_length, parse_easing, parse_pos, parse_pos_expression, parse_transform, Easing, Transform,
+    Vec3,
+};
+use crate::{
+    AmbisonicsGroup, AmbisonicsNormalization, Group, Metadata, Source, Transformer, REFERENCE_ID,
+};
 
 use super::error::ParseError;
 use super::time::{frames2seconds, seconds2frames, Seconds};
 use super::{
-    Attributes, ConstantTransformer, GetAttributeValue, PlaylistEntry, SceneInitializer,
-    SplineTransformer, TransformerInstance,
+    canonical_path, Attributes, ConstantTransformer, EventData, EventInstance,
+    ExpressionTransformer, GetAttributeValue, LinearTransformer, PlaylistEntry, SceneInitializer,
+    SplineTransformer, SubScene, TransformerInstance,
 };
 
+/// Maximum value of the `delay` attribute of `<source>` (in seconds)
+const MAX_SOURCE_DELAY: f32 = 0.1;
+
 pub trait AsAny {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
@@ -53,16 +65,29 @@ pub trait Element<'a>: AsAny {
         ))
     }
 
+    /// Begin of the next child element (in frames),
+    /// relative to the begin of this element.
+    fn child_offset(&self) -> u64 {
+        0
+    }
+
     fn add_files_and_transformers(
         &mut self,
         _files: Vec<PlaylistEntry>,
         _transformers: Vec<TransformerInstance>,
+        _events: Vec<EventInstance>,
         _duration: u64,
         _span: xml::StrSpan,
     ) -> Result<(), ParseError> {
         unreachable!("This has to be implemented for all container elements");
     }
 
+    /// All following child elements are held by the given hold
+    /// (of a <wait-for-trigger> element).
+    fn start_hold(&mut self, _hold: usize) {
+        unreachable!("This has to be implemented for all container elements");
+    }
+
     // NB: "span" is stored in "scene", therefore they must have matching lifetimes.
     // TODO: can the borrowed Box be avoided?
     // TODO: see https://github.com/rust-lang/rust-clippy/issues/3971
@@ -80,6 +105,10 @@ pub struct AsdfElement {
     version: String,
     seq: SeqElement,
     previous_child: String,
+    /// Silence before the first element (in frames)
+    lead_in: u64,
+    /// Silence after the last element (in frames)
+    lead_out: u64,
 }
 
 impl AsdfElement {
@@ -89,6 +118,8 @@ impl AsdfElement {
                 version: String::new(),
                 seq: SeqElement::new(),
                 previous_child: String::new(),
+                lead_in: 0,
+                lead_out: 0,
             })
         } else {
             Err(ParseError::new(
@@ -104,7 +135,7 @@ impl<'a> Element<'a> for AsdfElement {
         &mut self,
         attributes: &mut Attributes,
         span: xml::StrSpan,
-        _scene: &mut SceneInitializer,
+        scene: &mut SceneInitializer,
     ) -> Result<(), ParseError> {
         if let Some(value) = attributes.get_value("version") {
             if value.as_str().trim() == "0.4" {
@@ -121,9 +152,42 @@ impl<'a> Element<'a> for AsdfElement {
                 span,
             ));
         }
+        for (name, target) in &mut [
+            ("lead-in", &mut self.lead_in),
+            ("lead-out", &mut self.lead_out),
+        ] {
+            if let Some(value) = attributes.get_value(name) {
+                let time = scene.parse_time(value)?;
+                if time.0 < 0.0 {
+                    return Err(ParseError::new(
+                        format!("\"{}\" must not be negative", name),
+                        value,
+                    ));
+                }
+                **target = seconds2frames(time, scene.samplerate);
+            }
+        }
+        if let Some(value) = attributes.get_value("storage") {
+            scene.network_storage = match value.as_str().trim() {
+                "local" => false,
+                "network" => true,
+                _ => {
+                    return Err(ParseError::new(
+                        "\"storage\" must be either \"local\" or \"network\"",
+                        value,
+                    ))
+                }
+            };
+        }
+        // NB: This is needed for the tempo map
+        scene.lead_in = self.lead_in;
         Ok(())
     }
 
+    fn child_offset(&self) -> u64 {
+        self.lead_in + self.seq.end
+    }
+
     fn open_child_element(
         &mut self,
         name: xml::StrSpan,
@@ -167,11 +231,16 @@ impl<'a> Element<'a> for AsdfElement {
         &mut self,
         files: Vec<PlaylistEntry>,
         transformers: Vec<TransformerInstance>,
+        events: Vec<EventInstance>,
         duration: u64,
         span: xml::StrSpan,
     ) -> Result<(), ParseError> {
         self.seq
-            .add_files_and_transformers(files, transformers, duration, span)
+            .add_files_and_transformers(files, transformers, events, duration, span)
+    }
+
+    fn start_hold(&mut self, hold: usize) {
+        self.seq.start_hold(hold);
     }
 
     fn close(
@@ -181,26 +250,53 @@ impl<'a> Element<'a> for AsdfElement {
         scene: &mut SceneInitializer<'a>,
     ) -> Result<(), ParseError> {
         assert!(parent.is_none());
-        scene.transformer_instances = self.seq.transformers;
-        scene.streamer = Some(FileStreamer::new(
-            self.seq.files,
-            scene.file_storage.split_off(0),
-            scene.blocksize,
-            scene.sources.len() as u32,
-            scene.buffer_blocks,
-            scene.sleeptime,
-        ));
+        let lead_in = self.lead_in;
+        scene.frames = lead_in + self.seq.end + self.lead_out;
+        scene.lead_in = lead_in;
+        scene.lead_out = self.lead_out;
+        scene.transformer_instances = self
+            .seq
+            .transformers
+            .into_iter()
+            .map(|instance| TransformerInstance {
+                begin: instance.begin + lead_in,
+                ..instance
+            })
+            .collect();
+        scene.event_instances = self
+            .seq
+            .events
+            .into_iter()
+            .map(|instance| EventInstance {
+                begin: instance.begin + lead_in,
+                ..instance
+            })
+            .collect();
+        scene.playlist = Some(
+            self.seq
+                .files
+                .into_iter()
+                .map(|entry| PlaylistEntry {
+                    begin: entry.begin + lead_in,
+                    ..entry
+                })
+                .collect(),
+        );
         Ok(())
     }
 }
 
 struct HeadElement {
     reference: bool,
+    poster: bool,
 }
 
 impl HeadElement {
     pub fn new() -> HeadElement {
-        HeadElement { reference: false }
+        HeadElement {
+            reference: false,
+            poster: false,
+        }
     }
 }
 
@@ -221,8 +317,18 @@ impl<'a> Element<'a> for HeadElement {
         _parent_span: xml::StrSpan,
     ) -> Result<Box<dyn Element<'a>>, ParseError> {
         match name.as_str() {
-            "meta" => Err(ParseError::new("TODO: implement <meta> tags", name)),
+            "meta" => Ok(Box::new(MetaElement::new())),
             "source" => Ok(Box::new(SourceElement::new())),
+            "group" => Ok(Box::new(GroupElement::new())),
+            "tempo" => Ok(Box::new(TempoElement::new())),
+            "poster" => {
+                if self.poster {
+                    Err(ParseError::new("Only one <poster> is allowed", name))
+                } else {
+                    self.poster = true;
+                    Ok(Box::new(PosterElement::new()))
+                }
+            }
             "reference" => {
                 if self.reference {
                     Err(ParseError::new("Only one <reference> is allowed", name))
@@ -248,6 +354,117 @@ impl<'a> Element<'a> for HeadElement {
     }
 }
 
+/// Scene metadata, e.g. `<meta name="title" content="..." xml:lang="en"/>`
+struct MetaElement {}
+
+impl MetaElement {
+    pub fn new() -> MetaElement {
+        MetaElement {}
+    }
+}
+
+impl<'a> Element<'a> for MetaElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        let name = match attributes.get_value("name") {
+            Some(value) if value.as_str().is_empty() => {
+                return Err(ParseError::new("Empty metadata name", value));
+            }
+            Some(value) => value.to_string(),
+            None => {
+                return Err(ParseError::new(
+                    "\"name\" attribute is required in <meta> element",
+                    span,
+                ))
+            }
+        };
+        let content = match attributes.get_value("content") {
+            Some(value) => value.to_string(),
+            None => {
+                return Err(ParseError::new(
+                    "\"content\" attribute is required in <meta> element",
+                    span,
+                ))
+            }
+        };
+        // NB: This is given as "xml:lang"
+        let lang = match attributes.get_value("lang") {
+            Some(value) if value.as_str().is_empty() => {
+                return Err(ParseError::new("Empty language tag", value));
+            }
+            Some(value) => Some(value.to_string()),
+            None => None,
+        };
+        scene.metadata.push(Metadata {
+            name,
+            content,
+            lang,
+        });
+        Ok(())
+    }
+
+    fn close(
+        self: Box<Self>,
+        _span: xml::StrSpan<'a>,
+        _parent: Option<&mut Box<dyn Element>>,
+        _scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        Ok(())
+    }
+}
+
+/// Image representing the scene (e.g. in a scene browser),
+/// e.g. `<poster file="cover.png"/>`
+struct PosterElement {}
+
+impl PosterElement {
+    pub fn new() -> PosterElement {
+        PosterElement {}
+    }
+}
+
+impl<'a> Element<'a> for PosterElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        let file_value = match attributes.get_value("file") {
+            Some(value) => value,
+            None => {
+                return Err(ParseError::new(
+                    "\"file\" attribute is required in <poster> element",
+                    span,
+                ))
+            }
+        };
+        let mut path = PathBuf::from(file_value.as_str());
+        if path == Path::new("") {
+            return Err(ParseError::new("Empty file name", file_value));
+        }
+        // NB: The image is not opened, it is only used by the host
+        if path.is_relative() {
+            path = scene.dir.join(path);
+        }
+        scene.poster = Some(path);
+        Ok(())
+    }
+
+    fn close(
+        self: Box<Self>,
+        _span: xml::StrSpan<'a>,
+        _parent: Option<&mut Box<dyn Element>>,
+        _scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        Ok(())
+    }
+}
+
 struct SourceElement {}
 
 impl SourceElement {
@@ -267,15 +484,107 @@ impl<'a> Element<'a> for SourceElement {
 
         let name = attributes.get_value("name").map(|v| v.to_string());
         let model = attributes.get_value("model").map(|v| v.to_string());
+        let port = attributes.get_value("port").map(|v| v.to_string());
+        let color =
+            match attributes.get_value("color") {
+                Some(value) => Some(parse_color(value.as_str()).ok_or_else(|| {
+                    ParseError::new("\"color\" must be given as \"#rrggbb\"", value)
+                })?),
+                None => None,
+            };
+        let icon = attributes.get_value("icon").map(|v| v.to_string());
+        let comment = attributes.get_value("comment").map(|v| v.to_string());
+        let mut tags: Vec<String> = Vec::new();
+        if let Some(value) = attributes.get_value("tags") {
+            for tag in value.as_str().split_whitespace() {
+                if !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.into());
+                }
+            }
+            if tags.is_empty() {
+                return Err(ParseError::new("Empty list of tags", value));
+            }
+        }
+        let tail = match attributes.get_value("tail") {
+            Some(value) => {
+                let time = scene.parse_time(value)?;
+                if time.0 < 0.0 {
+                    return Err(ParseError::new("\"tail\" must not be negative", value));
+                }
+                seconds2frames(time, scene.samplerate)
+            }
+            None => 0,
+        };
+        let inverted = match attributes.get_value("polarity") {
+            Some(value) => match value.as_str() {
+                "normal" => false,
+                "inverted" => true,
+                _ => {
+                    return Err(ParseError::new(
+                        "\"polarity\" must be \"normal\" or \"inverted\"",
+                        value,
+                    ))
+                }
+            },
+            None => false,
+        };
+        let delay = match attributes.get_value("delay") {
+            Some(value) => {
+                // NB: Either in milliseconds or in samples
+                let delay = match value.as_str().strip_suffix("ms") {
+                    Some(ms) => {
+                        let ms = f32::from_str(ms.trim()).context(value)?;
+                        if ms.is_nan() || ms < 0.0 {
+                            return Err(ParseError::new("\"delay\" must not be negative", value));
+                        }
+                        seconds2frames(Seconds(ms / 1000.0), scene.samplerate)
+                    }
+                    None => u64::from_str(value.as_str()).context(value)?,
+                };
+                let max = seconds2frames(Seconds(MAX_SOURCE_DELAY), scene.samplerate);
+                if delay > max {
+                    return Err(ParseError::new(
+                        format!(
+                            "\"delay\" must not be longer than {} ms ({} samples)",
+                            MAX_SOURCE_DELAY * 1000.0,
+                            max
+                        ),
+                        value,
+                    ));
+                }
+                delay as u32
+            }
+            None => 0,
+        };
 
         // TODO: source without ID is only allowed for live sources!
 
         let transform = parse_transform(attributes)?.unwrap_or_default();
+
+        // NB: All remaining attributes are stored for use by the renderer
+        let extra_attributes = attributes
+            .drain(..)
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
         scene.sources.push(Source {
             id,
             name,
             model,
+            port,
+            color,
+            icon,
+            comment,
+            tags: tags.into(),
+            tail,
+            inverted,
+            delay,
+            extra_attributes,
             activity: Default::default(),
+            clips: Default::default(),
+            active_ranges: Default::default(),
+            active_holds: Default::default(),
+            group: scene.group_stack.last().cloned(),
             transform,
         });
         Ok(())
@@ -292,6 +601,62 @@ impl<'a> Element<'a> for SourceElement {
     }
 }
 
+struct GroupElement {}
+
+impl GroupElement {
+    pub fn new() -> GroupElement {
+        GroupElement {}
+    }
+}
+
+impl<'a> Element<'a> for GroupElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        _span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        let id = scene.get_id(attributes)?;
+        let transform = parse_transform(attributes)?.unwrap_or_default();
+        let parent = scene.group_stack.last().cloned();
+        scene.group_stack.push(scene.groups.len());
+        scene.groups.push(Group {
+            id,
+            transform,
+            parent,
+        });
+        Ok(())
+    }
+
+    fn open_child_element(
+        &mut self,
+        name: xml::StrSpan,
+        _parent_span: xml::StrSpan,
+    ) -> Result<Box<dyn Element<'a>>, ParseError> {
+        match name.as_str() {
+            "source" => Ok(Box::new(SourceElement::new())),
+            "group" => Ok(Box::new(GroupElement::new())),
+            _ => Err(ParseError::new(
+                format!(
+                    "Only <source> and <group> elements are allowed in <group>, not <{}>",
+                    name.as_str()
+                ),
+                name,
+            )),
+        }
+    }
+
+    fn close(
+        self: Box<Self>,
+        _span: xml::StrSpan<'a>,
+        _parent: Option<&mut Box<dyn Element>>,
+        scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        scene.group_stack.pop().unwrap();
+        Ok(())
+    }
+}
+
 struct ReferenceElement {}
 
 impl ReferenceElement {
@@ -330,6 +695,92 @@ impl<'a> Element<'a> for ReferenceElement {
     }
 }
 
+struct TempoElement {}
+
+impl TempoElement {
+    pub fn new() -> TempoElement {
+        TempoElement {}
+    }
+}
+
+impl<'a> Element<'a> for TempoElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        let bpm = if let Some(value) = attributes.get_value("bpm") {
+            let bpm = f64::from_str(value.as_str()).context(value)?;
+            if !bpm.is_finite() || bpm <= 0.0 {
+                return Err(ParseError::new("\"bpm\" must be positive", value));
+            }
+            bpm
+        } else {
+            return Err(ParseError::new(
+                "\"bpm\" attribute is required in <tempo> element",
+                span,
+            ));
+        };
+        let beats_per_bar = if let Some(value) = attributes.get_value("beats-per-bar") {
+            let beats = f64::from_str(value.as_str()).context(value)?;
+            if !beats.is_finite() || beats <= 0.0 {
+                return Err(ParseError::new("\"beats-per-bar\" must be positive", value));
+            }
+            Some(beats)
+        } else {
+            None
+        };
+        // NB: Bars are counted from one
+        let bar = if let Some(value) = attributes.get_value("bar") {
+            let bar = u64::from_str(value.as_str()).context(value)?;
+            if bar == 0 {
+                return Err(ParseError::new("Bars are counted from 1", value));
+            }
+            Some((bar, value))
+        } else {
+            None
+        };
+        match (&mut scene.tempo, bar) {
+            (None, None) | (None, Some((1, _))) => {
+                scene.tempo = Some(TempoMap::new(
+                    scene.samplerate,
+                    scene.lead_in,
+                    bpm,
+                    beats_per_bar.unwrap_or(4.0),
+                ));
+            }
+            (None, Some((_, value))) => {
+                return Err(ParseError::new(
+                    "The first <tempo> element must start at bar 1",
+                    value,
+                ));
+            }
+            (Some(_), None) => {
+                return Err(ParseError::new(
+                    "\"bar\" attribute is required for tempo changes",
+                    span,
+                ));
+            }
+            (Some(tempo), Some((bar, value))) => {
+                tempo
+                    .add_change(bar - 1, bpm, beats_per_bar)
+                    .map_err(|e| ParseError::new(e, value))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(
+        self: Box<Self>,
+        _span: xml::StrSpan<'a>,
+        _parent: Option<&mut Box<dyn Element>>,
+        _scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        Ok(())
+    }
+}
+
 struct BodyElement {
     seq: SeqElement,
 }
@@ -361,15 +812,24 @@ impl<'a> Element<'a> for BodyElement {
         self.seq.open_child_element(name, parent_span)
     }
 
+    fn child_offset(&self) -> u64 {
+        self.seq.end
+    }
+
     fn add_files_and_transformers(
         &mut self,
         files: Vec<PlaylistEntry>,
         transformers: Vec<TransformerInstance>,
+        events: Vec<EventInstance>,
         duration: u64,
         span: xml::StrSpan,
     ) -> Result<(), ParseError> {
         self.seq
-            .add_files_and_transformers(files, transformers, duration, span)
+            .add_files_and_transformers(files, transformers, events, duration, span)
+    }
+
+    fn start_hold(&mut self, hold: usize) {
+        self.seq.start_hold(hold);
     }
 
     fn close(
@@ -385,8 +845,11 @@ impl<'a> Element<'a> for BodyElement {
 struct SeqElement {
     files: Vec<PlaylistEntry>,
     transformers: Vec<TransformerInstance>,
+    events: Vec<EventInstance>,
     end: u64,
     iterations: NonZeroU64,
+    /// Hold of the most recent <wait-for-trigger> element (if any)
+    hold: Option<usize>,
 }
 
 impl SeqElement {
@@ -394,8 +857,10 @@ impl SeqElement {
         SeqElement {
             files: Vec::new(),
             transformers: Vec::new(),
+            events: Vec::new(),
             end: 0,
             iterations: NonZeroU64::new(1).unwrap(),
+            hold: None,
         }
     }
 }
@@ -421,17 +886,24 @@ impl<'a> Element<'a> for SeqElement {
         child_in_container(name, parent_span)
     }
 
+    fn child_offset(&self) -> u64 {
+        self.end
+    }
+
     fn add_files_and_transformers(
         &mut self,
         files: Vec<PlaylistEntry>,
         transformers: Vec<TransformerInstance>,
+        events: Vec<EventInstance>,
         duration: u64,
         _span: xml::StrSpan,
     ) -> Result<(), ParseError> {
         let end = self.end;
+        let hold = self.hold;
         self.files
             .extend(files.into_iter().map(|entry| PlaylistEntry {
                 begin: end + entry.begin,
+                hold: entry.hold.or(hold),
                 ..entry
             }));
         self.transformers.extend(
@@ -439,44 +911,116 @@ impl<'a> Element<'a> for SeqElement {
                 .into_iter()
                 .map(|instance| TransformerInstance {
                     begin: end + instance.begin,
+                    hold: instance.hold.or(hold),
                     ..instance
                 }),
         );
+        self.events
+            .extend(events.into_iter().map(|instance| EventInstance {
+                begin: end + instance.begin,
+                hold: instance.hold.or(hold),
+                ..instance
+            }));
         self.end += duration;
         Ok(())
     }
 
+    fn start_hold(&mut self, hold: usize) {
+        self.hold = Some(hold);
+    }
+
     fn close(
         self: Box<Self>,
         span: xml::StrSpan<'a>,
         parent: Option<&mut Box<dyn Element>>,
-        _scene: &mut SceneInitializer<'a>,
+        scene: &mut SceneInitializer<'a>,
     ) -> Result<(), ParseError> {
-        let mut files = Vec::new();
-        let mut transformers = Vec::new();
-        for i in 0..self.iterations.get() {
-            files.extend(self.files.iter().cloned().map(|entry| PlaylistEntry {
-                begin: i * self.end + entry.begin,
-                ..entry
-            }));
-            transformers.extend(self.transformers.iter().cloned().map(|instance| {
-                TransformerInstance {
-                    begin: i * self.end + instance.begin,
-                    ..instance
-                }
-            }));
-        }
+        let (files, transformers, events) = repeat_contents(
+            &self.files,
+            &self.transformers,
+            &self.events,
+            self.end,
+            self.iterations,
+            self.hold,
+            scene,
+        );
         parent
             .unwrap()
-            .add_files_and_transformers(files, transformers, self.end, span)
+            .add_files_and_transformers(files, transformers, events, self.end, span)
+    }
+}
+
+/// Repeats the contents of a <seq> or <par> element `iterations` times,
+/// each iteration is `duration` frames long.
+///
+/// Each iteration gets its own holds (one per <wait-for-trigger> element),
+/// the contents of an iteration which are not held by one of them
+/// are held by the last hold of the previous iteration (`last_hold`).
+fn repeat_contents(
+    files: &[PlaylistEntry],
+    transformers: &[TransformerInstance],
+    events: &[EventInstance],
+    duration: u64,
+    iterations: NonZeroU64,
+    last_hold: Option<usize>,
+    scene: &mut SceneInitializer,
+) -> (
+    Vec<PlaylistEntry>,
+    Vec<TransformerInstance>,
+    Vec<EventInstance>,
+) {
+    // Maps the holds of the first iteration to the holds of the current one
+    let mut holds = HashMap::new();
+    let mut previous = None;
+    let map_hold = |holds: &HashMap<usize, usize>, previous, hold: Option<usize>| match hold {
+        Some(hold) => holds.get(&hold).copied().or(Some(hold)),
+        None => previous,
+    };
+    let mut result = (Vec::new(), Vec::new(), Vec::new());
+    for i in 0..iterations.get() {
+        if i > 0 {
+            previous = map_hold(&holds, previous, last_hold);
+            holds = events
+                .iter()
+                .filter_map(|instance| instance.starts_hold)
+                .map(|hold| {
+                    scene.hold_count += 1;
+                    (hold, scene.hold_count - 1)
+                })
+                .collect();
+        }
+        let offset = i * duration;
+        result.0.extend(files.iter().map(|entry| PlaylistEntry {
+            begin: offset + entry.begin,
+            hold: map_hold(&holds, previous, entry.hold),
+            ..entry.clone()
+        }));
+        result
+            .1
+            .extend(transformers.iter().map(|instance| TransformerInstance {
+                begin: offset + instance.begin,
+                hold: map_hold(&holds, previous, instance.hold),
+                ..instance.clone()
+            }));
+        result.2.extend(events.iter().map(|instance| EventInstance {
+            begin: offset + instance.begin,
+            hold: map_hold(&holds, previous, instance.hold),
+            starts_hold: map_hold(&holds, None, instance.starts_hold),
+            ..instance.clone()
+        }));
     }
+    result
 }
 
 struct ParElement {
     files: Vec<PlaylistEntry>,
     transformers: Vec<TransformerInstance>,
+    events: Vec<EventInstance>,
     duration_frames: Option<u64>,
     iterations: NonZeroU64,
+    /// Hold of the most recent <wait-for-trigger> element (if any),
+    /// it holds all following child elements
+    hold: Option<usize>,
 }
 
 impl ParElement {
@@ -484,8 +1028,10 @@ impl ParElement {
         ParElement {
             files: Vec::new(),
             transformers: Vec::new(),
+            events: Vec::new(),
             duration_frames: None,
             iterations: NonZeroU64::new(1).unwrap(),
+            hold: None,
         }
     }
 }
@@ -515,9 +1061,11 @@ impl<'a> Element<'a> for ParElement {
         &mut self,
         files: Vec<PlaylistEntry>,
         transformers: Vec<TransformerInstance>,
+        events: Vec<EventInstance>,
         duration: u64,
         span: xml::StrSpan,
     ) -> Result<(), ParseError> {
+        // NB: Elements without duration (e.g. <event>) don't count as "first element"
         if let Some(self_duration) = self.duration_frames {
             if duration > self_duration {
                 // NB: This is a limitation to simplify parsing, it may be lifted at some point.
@@ -526,39 +1074,54 @@ impl<'a> Element<'a> for ParElement {
                     span,
                 ));
             }
-        } else {
+        } else if duration > 0 {
             self.duration_frames = Some(duration);
         }
-        self.files.extend(files);
-        self.transformers.extend(transformers);
+        let hold = self.hold;
+        self.files
+            .extend(files.into_iter().map(|entry| PlaylistEntry {
+                hold: entry.hold.or(hold),
+                ..entry
+            }));
+        self.transformers.extend(
+            transformers
+                .into_iter()
+                .map(|instance| TransformerInstance {
+                    hold: instance.hold.or(hold),
+                    ..instance
+                }),
+        );
+        self.events
+            .extend(events.into_iter().map(|instance| EventInstance {
+                hold: instance.hold.or(hold),
+                ..instance
+            }));
         Ok(())
     }
 
-    // TODO: better code re-use w.r.t. SeqElement?
-    fn close(
+    fn start_hold(&mut self, hold: usize) {
+        self.hold = Some(hold);
+    }
+
+    fn close(
         self: Box<Self>,
         span: xml::StrSpan<'a>,
         parent: Option<&mut Box<dyn Element>>,
-        _scene: &mut SceneInitializer<'a>,
+        scene: &mut SceneInitializer<'a>,
     ) -> Result<(), ParseError> {
         let duration = self.duration_frames.unwrap_or_default();
-        let mut files = Vec::new();
-        let mut transformers = Vec::new();
-        for i in 0..self.iterations.get() {
-            files.extend(self.files.iter().cloned().map(|entry| PlaylistEntry {
-                begin: i * duration + entry.begin,
-                ..entry
-            }));
-            transformers.extend(self.transformers.iter().cloned().map(|instance| {
-                TransformerInstance {
-                    begin: i * duration + instance.begin,
-                    ..instance
-                }
-            }));
-        }
+        let (files, transformers, events) = repeat_contents(
+            &self.files,
+            &self.transformers,
+            &self.events,
+            duration,
+            self.iterations,
+            self.hold,
+            scene,
+        );
         parent
             .unwrap()
-            .add_files_and_transformers(files, transformers, duration, span)?;
+            .add_files_and_transformers(files, transformers, events, duration, span)?;
         Ok(())
     }
 }
@@ -568,19 +1131,74 @@ struct ClipElement {
     clip_id: Option<String>,
     source_id: Option<String>,
     file: Option<Box<dyn AudioFile + Send + Sync>>,
+    path: PathBuf,
+    /// The file doesn't exist (only in a dry run, see `Scene::report()`)
+    missing: bool,
     channels: Vec<ChannelElement>,
-    /// An optional 0-based source index for each channel in the file.
-    /// The channel_map can be shorter than the number of channels in the file
-    /// (but not longer!).
-    channel_map: Vec<Option<usize>>,
+    /// 0-based file channels given in the "channels" attribute
+    /// (in the order of the <channel> elements they are used for)
+    selected_channels: Option<Vec<u32>>,
     channel_ids: Vec<String>,
     transform: Option<Transform>,
+    speed: f64,
+    /// Order and normalization (if the clip contains an Ambisonics signal)
+    ambisonics: Option<(u32, AmbisonicsNormalization)>,
 }
 
 impl ClipElement {
     fn new() -> ClipElement {
         Default::default()
     }
+
+    /// Opens the audio file at `self.path` (and applies looping, fades and preloading).
+    #[allow(clippy::too_many_arguments)]
+    fn load_file(
+        &self,
+        file_value: xml::StrSpan,
+        span: xml::StrSpan,
+        scene: &SceneInitializer,
+        iterations: NonZeroU64,
+        loop_crossfade: Option<u64>,
+        fades: [u64; 2],
+        preload: Option<bool>,
+    ) -> Result<Box<dyn AudioFile + Send + Sync>, ParseError> {
+        let mut file = load_audio_file(
+            &self.path,
+            scene.samplerate,
+            self.speed,
+            // NB: With a loop crossfade, the file is repeated below
+            if loop_crossfade.is_some() {
+                NonZeroU64::new(1).unwrap()
+            } else {
+                iterations
+            },
+            scene.direct_io_threshold,
+            scene.network_storage,
+            scene.open.as_ref(),
+        )
+        .context(file_value)?;
+        if let Some(crossfade) = loop_crossfade {
+            let looped = looped::File::new(file, iterations.get(), crossfade)
+                .map_err(|e| ParseError::new(format!("Error analyzing loop: {}", e), file_value))?;
+            log::debug!("Loop end of {:?}: {} frames", self.path, looped.loop_end());
+            file = Box::new(looped);
+        }
+        if fades != [0, 0] {
+            if fades[0] + fades[1] > file.frames() {
+                return Err(ParseError::new(
+                    "\"fade-in\" and \"fade-out\" together must not be longer than the file",
+                    span,
+                ));
+            }
+            file = Box::new(faded::File::new(file, fades[0], fades[1]));
+        }
+        if !scene.dry_run && preload.unwrap_or_else(|| file.frames() < scene.preload_frames) {
+            file = Box::new(memory::File::load(file).map_err(|e| {
+                ParseError::new(format!("Error preloading file: {}", e), file_value)
+            })?);
+        }
+        Ok(file)
+    }
 }
 
 impl<'a> Element<'a> for ClipElement {
@@ -604,24 +1222,147 @@ impl<'a> Element<'a> for ClipElement {
             NonZeroU64::new(1).unwrap()
         };
 
+        let preload = match attributes.get_value("preload") {
+            Some(value) => Some(bool::from_str(value.as_str()).context(value)?),
+            None => None,
+        };
+
+        let order = match attributes.get_value("ambisonics-order") {
+            Some(value) => {
+                if self.source_id.is_some() {
+                    return Err(ParseError::new(
+                        "\"source\" is not allowed in Ambisonics <clip> elements",
+                        value,
+                    ));
+                }
+                Some(u32::from_str(value.as_str()).context(value)?)
+            }
+            None => None,
+        };
+        if let Some((key, value)) = attributes.get_item("ambisonics-normalization") {
+            let order = order.ok_or_else(|| {
+                ParseError::new(
+                    "\"ambisonics-normalization\" requires \"ambisonics-order\"",
+                    key,
+                )
+            })?;
+            let normalization = match value.as_str() {
+                "sn3d" => AmbisonicsNormalization::Sn3d,
+                "n3d" => AmbisonicsNormalization::N3d,
+                "fuma" if order <= 3 => AmbisonicsNormalization::Fuma,
+                "fuma" => {
+                    return Err(ParseError::new(
+                        "FuMa normalization is only defined up to third order",
+                        value,
+                    ))
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        "\"ambisonics-normalization\" must be \"sn3d\", \"n3d\" or \"fuma\"",
+                        value,
+                    ))
+                }
+            };
+            self.ambisonics = Some((order, normalization));
+        } else if let Some(order) = order {
+            self.ambisonics = Some((order, AmbisonicsNormalization::Sn3d));
+        }
+
+        let speed = match attributes.get_value("speed") {
+            Some(value) => {
+                let speed = f64::from_str(value.as_str()).context(value)?;
+                if !(speed > 0.0 && speed.is_finite()) {
+                    return Err(ParseError::new(
+                        "\"speed\" must be a positive number",
+                        value,
+                    ));
+                }
+                speed
+            }
+            None => 1.0,
+        };
+
+        let loop_crossfade = match attributes.get_value("loop-crossfade") {
+            Some(value) => {
+                if iterations.get() < 2 {
+                    return Err(ParseError::new(
+                        "\"loop-crossfade\" requires \"repeat\" (with at least 2 iterations)",
+                        value,
+                    ));
+                }
+                Some(seconds2frames(scene.parse_time(value)?, scene.samplerate))
+            }
+            None => None,
+        };
+
+        // Fade-in and fade-out (in frames)
+        let mut fades = [0; 2];
+        for (name, fade) in ["fade-in", "fade-out"].iter().zip(&mut fades) {
+            if let Some(value) = attributes.get_value(name) {
+                *fade = seconds2frames(scene.parse_time(value)?, scene.samplerate);
+            }
+        }
+
         if let Some(file_value) = attributes.get_value("file") {
             let mut path = PathBuf::from(file_value.as_str());
 
             if path == Path::new("") {
                 return Err(ParseError::new("Empty file name", file_value));
             }
-            if path.is_relative() {
+            // NB: Locations handled by a media source (e.g. URLs) are used as-is
+            let is_file_path = media::find(file_value.as_str()).is_none()
+                && !signal::is_location(file_value.as_str());
+            if path.is_relative() && is_file_path {
                 path = scene.dir.join(path);
             }
-
-            self.file =
-                Some(load_audio_file(path, scene.samplerate, iterations).context(file_value)?);
+            self.path = path;
+            self.speed = speed;
+            // NB: In a dry run, the rest of the scene is still checked,
+            //     missing files are reported by Scene::report()
+            self.missing =
+                scene.dry_run && scene.open.is_none() && is_file_path && !self.path.exists();
+            if !self.missing {
+                self.file = Some(self.load_file(
+                    file_value,
+                    span,
+                    scene,
+                    iterations,
+                    loop_crossfade,
+                    fades,
+                    preload,
+                )?);
+            }
         } else {
             return Err(ParseError::new(
                 "\"file\" attribute is required in <clip> element",
                 span,
             ));
         }
+        if let Some(value) = attributes.get_value("channels") {
+            // NB: Any channel number is allowed for missing files
+            let file_channels = self.file.as_ref().map_or(u32::MAX, |f| f.channels());
+            let mut selection = Vec::new();
+            for number in value.as_str().split_whitespace() {
+                let number: u32 = number.parse().context(value)?;
+                if number == 0 || number > file_channels {
+                    return Err(ParseError::new(
+                        format!(
+                            "Channel numbers must be between 1 and {} (the number of channels in the file)",
+                            file_channels
+                        ),
+                        value,
+                    ));
+                }
+                selection.push(number - 1);
+            }
+            if selection.is_empty() {
+                return Err(ParseError::new(
+                    "\"channels\" must contain at least one channel number",
+                    value,
+                ));
+            }
+            self.selected_channels = Some(selection);
+        }
         self.transform = parse_transform(attributes)?;
         Ok(())
     }
@@ -632,13 +1373,25 @@ impl<'a> Element<'a> for ClipElement {
         _parent_span: xml::StrSpan,
     ) -> Result<Box<dyn Element<'a>>, ParseError> {
         if name.as_str() == "channel" {
-            if self.source_id.is_none() {
-                let file_channels = self.file.as_ref().unwrap().channels();
-                if self.channel_map.len() >= file_channels as usize {
+            if self.ambisonics.is_some() {
+                Err(ParseError::new(
+                    "No <channel> elements are allowed in Ambisonics <clip> elements",
+                    name,
+                ))
+            } else if self.source_id.is_none() {
+                let available = match &self.selected_channels {
+                    Some(selection) => selection.len(),
+                    None => self
+                        .file
+                        .as_ref()
+                        .map_or(usize::MAX, |f| f.channels() as usize),
+                };
+                if self.channels.len() >= available {
                     return Err(ParseError::new(
                         format!(
-                        "Only as many <channel> elements as channels in the file are allowed ({})",
-                        file_channels),
+                            "Only as many <channel> elements as available channels are allowed ({})",
+                            available
+                        ),
                         name,
                     ));
                 }
@@ -666,13 +1419,47 @@ impl<'a> Element<'a> for ClipElement {
         parent: Option<&mut Box<dyn Element>>,
         scene: &mut SceneInitializer<'a>,
     ) -> Result<(), ParseError> {
-        let file = self.file.take().unwrap();
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => {
+                assert!(self.missing);
+                // Silent placeholder with as many channels as needed
+                let channels = if let Some(selection) = &self.selected_channels {
+                    selection.iter().max().unwrap() + 1
+                } else if let Some((order, _)) = self.ambisonics {
+                    (order + 1) * (order + 1)
+                } else {
+                    self.channels.iter().map(|c| c.skip.unwrap_or(1)).sum()
+                };
+                Box::new(memory::File::empty(channels.max(1), scene.samplerate))
+            }
+        };
         let duration = file.frames();
         let file_channels = file.channels();
-        if self.channels.is_empty() {
-            if file_channels != 1 {
+        let selection = self
+            .selected_channels
+            .take()
+            .unwrap_or_else(|| (0..file_channels).collect());
+        if let Some((order, _)) = self.ambisonics {
+            let expected = (order as usize + 1) * (order as usize + 1);
+            if selection.len() != expected {
                 return Err(ParseError::new(
-                    "Multi-channel <clip> must have at least one <channel> sub-element",
+                    format!(
+                        "Ambisonics order {} requires {} channels, not {}",
+                        order,
+                        expected,
+                        selection.len()
+                    ),
+                    span,
+                ));
+            }
+            // One new source per Ambisonics channel
+            self.channels = (0..expected).map(|_| ChannelElement::default()).collect();
+        } else if self.channels.is_empty() {
+            if selection.len() != 1 {
+                return Err(ParseError::new(
+                    "Multi-channel <clip> must have at least one <channel> sub-element \
+                     (or select a single channel with the \"channels\" attribute)",
                     span,
                 ));
             }
@@ -682,35 +1469,52 @@ impl<'a> Element<'a> for ClipElement {
             assert!(channel.transform.is_none());
             self.channels.push(channel);
         }
+        let first_new_source = scene.sources.len();
         let mut transformers = Vec::new();
+        let mut channel_map = vec![Vec::new(); file_channels as usize];
+        // Index into `selection`
+        let mut next = 0;
         for channel in self.channels {
             if let Some(skip) = channel.skip {
-                for _ in 0..skip {
-                    self.channel_map.push(None);
-                }
+                next += skip as usize;
             } else {
-                if self.channel_map.len() >= file_channels as usize {
-                    return Err(ParseError::new(
-                        format!(
-                            "Too many <channel> elements (file has only {} channels)",
-                            file_channels
-                        ),
-                        span,
-                    ));
-                }
+                let file_channel = match selection.get(next) {
+                    Some(&file_channel) => file_channel as usize,
+                    None => {
+                        return Err(ParseError::new(
+                            format!(
+                                "Too many <channel> elements (only {} channels are available)",
+                                selection.len()
+                            ),
+                            span,
+                        ));
+                    }
+                };
+                next += 1;
                 let source_number = if let Some(source_id) = channel.source_id {
                     // Source must already exist
-                    scene
+                    let source_number = scene
                         .sources
                         .iter()
                         .filter_map(|s| s.id.as_ref())
                         .position(|id| *id == source_id)
-                        .unwrap()
+                        .unwrap();
+                    // NB: A source can only play one channel at a time
+                    if channel_map.iter().any(|t| t.contains(&source_number)) {
+                        return Err(ParseError::new(
+                            format!(
+                                "Source {:?} is used by more than one <channel> of this <clip>",
+                                source_id
+                            ),
+                            span,
+                        ));
+                    }
+                    source_number
                 } else {
                     scene.sources.push(Default::default());
                     scene.sources.len() - 1
                 };
-                self.channel_map.push(Some(source_number));
+                channel_map[file_channel].push(source_number);
 
                 // IDs are required for the parent transformer to work
                 let channel_id = channel.channel_id.unwrap_or_else(|| scene.create_new_id());
@@ -733,11 +1537,25 @@ impl<'a> Element<'a> for ClipElement {
             }
         }
 
+        if let Some((order, normalization)) = self.ambisonics {
+            for source in &mut scene.sources[first_new_source..] {
+                source.model = Some("ambisonics".into());
+            }
+            scene.ambisonics_groups.push(AmbisonicsGroup {
+                first_source: first_new_source,
+                order,
+                normalization,
+            });
+        }
+
         // <clip> transformer that applies to all <channel> elements
 
+        // IDs are required for the transform of a sub-scene to work
+        let clip_id = self.clip_id.unwrap_or_else(|| scene.create_new_id());
+        scene.root_transformer_ids.push(clip_id.clone());
         scene.add_transformer(
             Box::new(ConstantTransformer {
-                id: self.clip_id,
+                id: Some(clip_id),
                 transform: self.transform.unwrap_or_default(),
             }),
             0,
@@ -746,15 +1564,19 @@ impl<'a> Element<'a> for ClipElement {
             &mut transformers,
         );
 
-        scene.file_storage.push((file, self.channel_map.into()));
+        let channel_map = channel_map.into_iter().map(Vec::into_boxed_slice).collect();
+        scene.file_storage.push((file, channel_map));
+        scene.file_paths.push(self.path);
+        scene.file_speeds.push(self.speed);
         let files = vec![PlaylistEntry {
             begin: 0,
             duration,
             idx: scene.file_storage.len() - 1,
+            hold: None,
         }];
         parent
             .unwrap()
-            .add_files_and_transformers(files, transformers, duration, span)
+            .add_files_and_transformers(files, transformers, vec![], duration, span)
     }
 }
 
@@ -812,9 +1634,28 @@ struct TransformElement {
     duration: Option<Seconds>,
     targets: Vec<String>,
     transform: Option<Transform>,
+    /// Time-dependent position given as comma-separated expressions
+    pos_expression: Option<[Expression; 3]>,
+    interpolation: Interpolation,
+    /// Default tension for all nodes (only for `Interpolation::Spline`)
+    tension: Option<f32>,
     nodes: Vec<TransformNodeElement>,
 }
 
+/// Interpolation between positions of <o> nodes
+#[derive(PartialEq)]
+enum Interpolation {
+    /// Kochanek-Bartels spline (with tension, continuity and bias)
+    Spline,
+    Linear,
+}
+
+impl Default for Interpolation {
+    fn default() -> Interpolation {
+        Interpolation::Spline
+    }
+}
+
 impl TransformElement {
     fn new() -> TransformElement {
         Default::default()
@@ -833,7 +1674,7 @@ impl<'a> Element<'a> for TransformElement {
             assert!(self.targets.is_empty());
             for s in apply_to.as_str().split_whitespace() {
                 // TODO: make sure IDs are valid?
-                self.targets.push(s.into());
+                self.targets.push(scene.prefixed(s));
             }
             if self.targets.is_empty() {
                 return Err(ParseError::new(
@@ -847,6 +1688,56 @@ impl<'a> Element<'a> for TransformElement {
                 span,
             ));
         }
+        if attributes
+            .iter()
+            .any(|&(k, v)| k.as_str() == "pos" && v.as_str().contains(','))
+        {
+            let pos_value = attributes.get_value("pos").unwrap();
+            self.pos_expression = Some(parse_pos_expression(pos_value, &mut scene.input_names)?);
+        }
+        // Separate expressions per component, e.g. x="$input.fader1"
+        let has_pos = attributes.iter().any(|&(k, _)| k.as_str() == "pos");
+        let mut components = [None, None, None];
+        for (component, name) in components.iter_mut().zip(&["x", "y", "z"]) {
+            if let Some((key, value)) = attributes.get_item(name) {
+                if has_pos || self.pos_expression.is_some() {
+                    return Err(ParseError::new(
+                        format!("\"{}\" cannot be combined with \"pos\"", name),
+                        key,
+                    ));
+                }
+                *component = Some(
+                    Expression::parse(value.as_str(), &mut scene.input_names)
+                        .map_err(|msg| ParseError::new(msg, value))?,
+                );
+            }
+        }
+        if components.iter().any(Option::is_some) {
+            let [x, y, z] = components;
+            let or_zero = |c: Option<Expression>| c.unwrap_or(Expression::Constant(0.0));
+            self.pos_expression = Some([or_zero(x), or_zero(y), or_zero(z)]);
+        }
+        if let Some(value) = attributes.get_value("interpolation") {
+            self.interpolation = match value.as_str() {
+                "spline" => Interpolation::Spline,
+                "linear" => Interpolation::Linear,
+                _ => {
+                    return Err(ParseError::new(
+                        "Invalid interpolation (expected \"spline\" or \"linear\")",
+                        value,
+                    ))
+                }
+            };
+        }
+        if let Some((tension_key, tension_value)) = attributes.get_item("tension") {
+            if self.interpolation != Interpolation::Spline {
+                return Err(ParseError::new(
+                    "\"tension\" is only allowed with interpolation=\"spline\"",
+                    tension_key,
+                ));
+            }
+            self.tension = Some(f32::from_str(tension_value.as_str()).context(tension_value)?);
+        }
         self.transform = parse_transform(attributes)?;
 
         // TODO: allow specifying duration?
@@ -891,6 +1782,13 @@ impl<'a> Element<'a> for TransformElement {
                 None
             };
 
+        if self.pos_expression.is_some() && !self.nodes.is_empty() {
+            return Err(ParseError::new(
+                "<o> elements are not allowed if <transform> has a \"pos\" expression",
+                span,
+            ));
+        }
+
         if self.nodes.is_empty() {
             self.nodes.push(TransformNodeElement {
                 transform: self.transform.unwrap_or_default(),
@@ -904,6 +1802,13 @@ impl<'a> Element<'a> for TransformElement {
         }
         assert!(!self.nodes.is_empty());
 
+        if self.nodes.last().unwrap().easing.is_some() {
+            return Err(ParseError::new(
+                "\"easing\" is not allowed in the last <transform> node",
+                span,
+            ));
+        }
+
         if self.nodes[0].time.unwrap_or_default() != Seconds(0.0) {
             // TODO: This is a temporary restriction until "begin" semantics are sorted out:
             return Err(ParseError::new(
@@ -912,7 +1817,14 @@ impl<'a> Element<'a> for TransformElement {
             ));
         }
 
-        let transformer = if self.nodes.len() == 1 {
+        let transformer = if let Some(position) = self.pos_expression.take() {
+            Box::new(ExpressionTransformer {
+                id: self.id,
+                position,
+                rotation: self.nodes.pop().unwrap().transform.rotation,
+                samplerate: scene.samplerate,
+            }) as Box<dyn Transformer>
+        } else if self.nodes.len() == 1 {
             let node = self.nodes.pop().unwrap();
             if node.closed {
                 return Err(ParseError::new(
@@ -931,10 +1843,12 @@ impl<'a> Element<'a> for TransformElement {
             let mut tensions = Vec::<Option<f32>>::new();
             let mut continuities = Vec::<Option<f32>>::new();
             let mut biases = Vec::<Option<f32>>::new();
+            let mut easings = Vec::<Easing>::new();
             let mut closed = false;
 
             for node in self.nodes {
                 times.push(node.time.map(|t| t.0));
+                easings.push(node.easing.unwrap_or_default());
                 if node.closed {
                     // This was checked during parsing:
                     assert!(node.speed.is_none());
@@ -956,6 +1870,18 @@ impl<'a> Element<'a> for TransformElement {
 
                 // TODO: handle rotation, volume, ...
             }
+            if self.interpolation == Interpolation::Linear
+                && (speeds.iter().any(Option::is_some)
+                    || tensions.iter().any(Option::is_some)
+                    || continuities.iter().any(Option::is_some)
+                    || biases.iter().any(Option::is_some))
+            {
+                return Err(ParseError::new(
+                    "speed/tension/continuity/bias are only allowed \
+                     with interpolation=\"spline\"",
+                    span,
+                ));
+            }
             if let Some(last_time) = times.last().unwrap() {
                 if self.duration.is_some() {
                     return Err(ParseError::new(
@@ -975,44 +1901,76 @@ impl<'a> Element<'a> for TransformElement {
                     return Err(ParseError::new("Unable to infer time of last node", span));
                 };
             }
-            if !closed {
-                if tensions.remove(0).is_some()
-                    || continuities.remove(0).is_some()
-                    || biases.remove(0).is_some()
-                {
-                    return Err(ParseError::new(
-                        "tension/continuity/bias are not allowed in the first node \
-                         (except if pos=\"closed\")",
-                        span,
-                    ));
+            // NB: The last node was checked above
+            easings.pop();
+            if self.interpolation == Interpolation::Linear {
+                if closed {
+                    positions.push(positions[0]);
                 }
-                if tensions.pop().unwrap().is_some()
-                    || continuities.pop().unwrap().is_some()
-                    || biases.pop().unwrap().is_some()
-                {
-                    return Err(ParseError::new(
-                        "tension/continuity/bias are not allowed in the last node",
-                        span,
-                    ));
+                Box::new(
+                    LinearTransformer::new(self.id, positions, &times, easings, scene.samplerate)
+                        .map_err(|msg| ParseError::new(msg, span))?,
+                ) as Box<dyn Transformer>
+            } else {
+                if !closed {
+                    if tensions.remove(0).is_some()
+                        || continuities.remove(0).is_some()
+                        || biases.remove(0).is_some()
+                    {
+                        return Err(ParseError::new(
+                            "tension/continuity/bias are not allowed in the first node \
+                             (except if pos=\"closed\")",
+                            span,
+                        ));
+                    }
+                    if tensions.pop().unwrap().is_some()
+                        || continuities.pop().unwrap().is_some()
+                        || biases.pop().unwrap().is_some()
+                    {
+                        return Err(ParseError::new(
+                            "tension/continuity/bias are not allowed in the last node",
+                            span,
+                        ));
+                    }
+                }
+                assert!(tensions.len() == continuities.len());
+                assert!(tensions.len() == biases.len());
+                let default_tension = self.tension;
+                let tcb: Vec<_> = (0..tensions.len())
+                    .map(|i| {
+                        [
+                            tensions[i].or(default_tension).unwrap_or_default(),
+                            continuities[i].unwrap_or_default(),
+                            biases[i].unwrap_or_default(),
+                        ]
+                    })
+                    .collect();
+                let mut eased_ranges = Vec::new();
+                for (i, &easing) in easings.iter().enumerate() {
+                    if easing == Easing::Linear {
+                        continue;
+                    }
+                    // NB: The first time has been checked above
+                    let begin = if i == 0 { Some(0.0) } else { times[i] };
+                    match (begin, times[i + 1]) {
+                        (Some(begin), Some(end)) => eased_ranges.push((begin, end, easing)),
+                        _ => {
+                            return Err(ParseError::new(
+                                "\"easing\" requires \"time\" in both nodes of the segment \
+                                 with interpolation=\"spline\"",
+                                span,
+                            ))
+                        }
+                    }
                 }
+                Box::new(SplineTransformer {
+                    id: self.id,
+                    spline: AsdfSpline::new(&positions, &times, &speeds, &tcb, closed, get_length)
+                        .context(span)?,
+                    easings: eased_ranges.into(),
+                    samplerate: scene.samplerate,
+                }) as Box<dyn Transformer>
             }
-            assert!(tensions.len() == continuities.len());
-            assert!(tensions.len() == biases.len());
-            let tcb: Vec<_> = (0..tensions.len())
-                .map(|i| {
-                    [
-                        tensions[i].unwrap_or_default(),
-                        continuities[i].unwrap_or_default(),
-                        biases[i].unwrap_or_default(),
-                    ]
-                })
-                .collect();
-            Box::new(SplineTransformer {
-                id: self.id,
-                spline: AsdfSpline::new(&positions, &times, &speeds, &tcb, closed, get_length)
-                    .context(span)?,
-                samplerate: scene.samplerate,
-            }) as Box<dyn Transformer>
         };
 
         if let Some(duration) = self
@@ -1022,7 +1980,7 @@ impl<'a> Element<'a> for TransformElement {
         {
             let mut transformers = Vec::new();
             scene.add_transformer(transformer, 0, duration, &self.targets, &mut transformers);
-            parent.add_files_and_transformers(vec![], transformers, duration, span)
+            parent.add_files_and_transformers(vec![], transformers, vec![], duration, span)
         } else {
             Err(ParseError::new(
                 "Unable to infer <transform> duration",
@@ -1041,6 +1999,8 @@ struct TransformNodeElement {
     tension: Option<f32>,
     continuity: Option<f32>,
     bias: Option<f32>,
+    /// Easing of the segment towards the following node
+    easing: Option<Easing>,
 }
 
 impl<'a> Element<'a> for TransformNodeElement {
@@ -1048,10 +2008,10 @@ impl<'a> Element<'a> for TransformNodeElement {
         &mut self,
         attributes: &mut Attributes,
         span: xml::StrSpan,
-        _scene: &mut SceneInitializer,
+        scene: &mut SceneInitializer,
     ) -> Result<(), ParseError> {
         if let Some(time_value) = attributes.get_value("time") {
-            self.time = Some(Seconds::from_str(time_value.as_str()).context(time_value)?);
+            self.time = Some(scene.parse_time(time_value)?);
         }
         let mut position = None;
         if let Some(pos_value) = attributes.get_value("pos") {
@@ -1107,6 +2067,9 @@ impl<'a> Element<'a> for TransformNodeElement {
             }
             self.bias = Some(f32::from_str(bias_value.as_str()).context(bias_value)?);
         }
+        if let Some(easing_value) = attributes.get_value("easing") {
+            self.easing = Some(parse_easing(easing_value)?);
+        }
 
         if !self.closed && self.transform.translation.is_none() {
             return Err(ParseError::new("\"pos\" must be given (for now)", span));
@@ -1138,6 +2101,277 @@ impl<'a> Element<'a> for TransformNodeElement {
     }
 }
 
+#[derive(Default)]
+struct EventElement {
+    time: Seconds,
+    source_id: Option<String>,
+    data: String,
+}
+
+impl EventElement {
+    fn new() -> EventElement {
+        Default::default()
+    }
+}
+
+impl<'a> Element<'a> for EventElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        if let Some(time_value) = attributes.get_value("time") {
+            self.time = scene.parse_time(time_value)?;
+        }
+        self.source_id = scene.get_existing_source_id(attributes)?;
+        if let Some(data_value) = attributes.get_value("data") {
+            self.data = data_value.to_string();
+        } else {
+            return Err(ParseError::new(
+                "\"data\" attribute is required in <event> element",
+                span,
+            ));
+        }
+        Ok(())
+    }
+
+    fn close(
+        self: Box<Self>,
+        span: xml::StrSpan<'a>,
+        parent: Option<&mut Box<dyn Element>>,
+        scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        let source = self.source_id.map(|source_id| {
+            // Source must already exist
+            scene
+                .sources
+                .iter()
+                .position(|s| s.id.as_ref() == Some(&source_id))
+                .unwrap()
+        });
+        scene
+            .event_storage
+            .push(EventData::Event(source, self.data));
+        let events = vec![EventInstance {
+            begin: seconds2frames(self.time, scene.samplerate),
+            idx: scene.event_storage.len() - 1,
+            hold: None,
+            starts_hold: None,
+        }];
+        let parent = parent.ok_or_else(|| {
+            ParseError::new("<event> must be inside of <body>, <seq> or <par>", span)
+        })?;
+        // NB: Events don't have a duration, they don't advance the time in <seq>
+        parent.add_files_and_transformers(vec![], vec![], events, 0, span)
+    }
+}
+
+#[derive(Default)]
+struct MarkerElement {
+    time: Seconds,
+    name: String,
+}
+
+impl MarkerElement {
+    fn new() -> MarkerElement {
+        Default::default()
+    }
+}
+
+impl<'a> Element<'a> for MarkerElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        if let Some(time_value) = attributes.get_value("time") {
+            self.time = scene.parse_time(time_value)?;
+        }
+        if let Some(name_value) = attributes.get_value("name") {
+            if name_value.as_str().is_empty() {
+                return Err(ParseError::new("Empty marker name", name_value));
+            }
+            self.name = name_value.to_string();
+        } else {
+            return Err(ParseError::new(
+                "\"name\" attribute is required in <marker> element",
+                span,
+            ));
+        }
+        Ok(())
+    }
+
+    fn close(
+        self: Box<Self>,
+        span: xml::StrSpan<'a>,
+        parent: Option<&mut Box<dyn Element>>,
+        scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        scene.event_storage.push(EventData::Marker(self.name));
+        let events = vec![EventInstance {
+            begin: seconds2frames(self.time, scene.samplerate),
+            idx: scene.event_storage.len() - 1,
+            hold: None,
+            starts_hold: None,
+        }];
+        let parent = parent.ok_or_else(|| {
+            ParseError::new("<marker> must be inside of <body>, <seq> or <par>", span)
+        })?;
+        // NB: Markers don't have a duration, they don't advance the time in <seq>
+        parent.add_files_and_transformers(vec![], vec![], events, 0, span)
+    }
+}
+
+/// Playback is paused until the host calls `Scene::trigger()` with the given ID
+#[derive(Default)]
+struct WaitForTriggerElement {
+    id: String,
+}
+
+impl WaitForTriggerElement {
+    fn new() -> WaitForTriggerElement {
+        Default::default()
+    }
+}
+
+impl<'a> Element<'a> for WaitForTriggerElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        _scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        // NB: The ID is not registered with get_id(),
+        //     the same trigger ID can be used multiple times
+        if let Some(id_value) = attributes.get_value("id") {
+            if id_value.as_str().is_empty() {
+                return Err(ParseError::new("Empty trigger ID", id_value));
+            }
+            self.id = id_value.to_string();
+        } else {
+            return Err(ParseError::new(
+                "\"id\" attribute is required in <wait-for-trigger> element",
+                span,
+            ));
+        }
+        Ok(())
+    }
+
+    fn close(
+        self: Box<Self>,
+        span: xml::StrSpan<'a>,
+        parent: Option<&mut Box<dyn Element>>,
+        scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        let parent = parent.ok_or_else(|| {
+            ParseError::new(
+                "<wait-for-trigger> must be inside of <body>, <seq> or <par>",
+                span,
+            )
+        })?;
+        let hold = scene.hold_count;
+        scene.hold_count += 1;
+        scene.event_storage.push(EventData::Trigger(self.id));
+        let events = vec![EventInstance {
+            begin: 0,
+            idx: scene.event_storage.len() - 1,
+            hold: None,
+            starts_hold: Some(hold),
+        }];
+        // NB: The waiting time is not known in advance, it doesn't advance the time in <seq>
+        parent.add_files_and_transformers(vec![], vec![], events, 0, span)?;
+        // NB: Only the rest of the parent element waits, not the enclosing elements
+        parent.start_hold(hold);
+        Ok(())
+    }
+}
+
+/// Another ASDF scene embedded in the current one
+#[derive(Default)]
+struct SceneElement {
+    id: Option<String>,
+    sub_scene: Option<SubScene>,
+    transform: Option<Transform>,
+}
+
+impl SceneElement {
+    fn new() -> SceneElement {
+        Default::default()
+    }
+}
+
+impl<'a> Element<'a> for SceneElement {
+    fn parse_attributes(
+        &mut self,
+        attributes: &mut Attributes,
+        span: xml::StrSpan,
+        scene: &mut SceneInitializer,
+    ) -> Result<(), ParseError> {
+        self.id = scene.get_id(attributes)?;
+        if let Some(file_value) = attributes.get_value("file") {
+            let mut path = PathBuf::from(file_value.as_str());
+
+            if path == Path::new("") {
+                return Err(ParseError::new("Empty file name", file_value));
+            }
+            if path.is_relative() {
+                path = scene.dir.join(path);
+            }
+            if let Ok(canonical) = canonical_path(&path, scene.open.as_ref()) {
+                if scene.include_stack.contains(&canonical) {
+                    return Err(ParseError::new(
+                        "Circular reference to scene file",
+                        file_value,
+                    ));
+                }
+            }
+            let id_prefix = format!(
+                "{}/",
+                self.id.clone().unwrap_or_else(|| scene.create_new_id())
+            );
+            self.sub_scene = Some(scene.load_sub_scene(&path, id_prefix).context(file_value)?);
+        } else {
+            return Err(ParseError::new(
+                "\"file\" attribute is required in <scene> element",
+                span,
+            ));
+        }
+        self.transform = parse_transform(attributes)?;
+        Ok(())
+    }
+
+    fn close(
+        mut self: Box<Self>,
+        span: xml::StrSpan<'a>,
+        parent: Option<&mut Box<dyn Element>>,
+        scene: &mut SceneInitializer<'a>,
+    ) -> Result<(), ParseError> {
+        let sub_scene = self.sub_scene.take().unwrap();
+        let duration = sub_scene.frames;
+        let (files, mut transformers, events, targets) = scene.add_sub_scene(sub_scene, span);
+
+        // <scene> transformer that applies to all <clip> and <scene> elements of the sub-scene
+
+        let scene_id = self.id.unwrap_or_else(|| scene.create_new_id());
+        scene.root_transformer_ids.push(scene_id.clone());
+        scene.add_transformer(
+            Box::new(ConstantTransformer {
+                id: Some(scene_id),
+                transform: self.transform.unwrap_or_default(),
+            }),
+            0,
+            duration,
+            &targets,
+            &mut transformers,
+        );
+        parent
+            .unwrap()
+            .add_files_and_transformers(files, transformers, events, duration, span)
+    }
+}
+
 fn child_in_container<'a>(
     name: xml::StrSpan,
     parent_span: xml::StrSpan,
@@ -1147,6 +2381,10 @@ fn child_in_container<'a>(
         "par" => Ok(Box::new(ParElement::new())),
         "clip" => Ok(Box::new(ClipElement::new())),
         "transform" => Ok(Box::new(TransformElement::new())),
+        "event" => Ok(Box::new(EventElement::new())),
+        "scene" => Ok(Box::new(SceneElement::new())),
+        "marker" => Ok(Box::new(MarkerElement::new())),
+        "wait-for-trigger" => Ok(Box::new(WaitForTriggerElement::new())),
         _ => Err(ParseError::new(
             format!(
                 "No <{}> element allowed in <{}>",
@@ -1157,3 +2395,13 @@ fn child_in_container<'a>(
         )),
     }
 }
+
+/// Parses a color given as `#rrggbb` (hexadecimal, case-insensitive).
+fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([component(0)?, component(2)?, component(4)?])
+}
@@ -12,6 +12,10 @@ use crate::error::FromSourceAndContext;
 #[derive(Debug)]
 pub struct ParseError {
     msg: String,
+    /// One-based line number
+    line: usize,
+    /// One-based column number (in characters)
+    column: usize,
     context: String,
 }
 
@@ -80,8 +84,12 @@ impl ParseError {
             context.extend(iter::repeat(MARKER).take(chars));
             line_start = line_end;
         }
+        let before = &source[..span.start()];
+        let line_begin = before.rfind('\n').map_or(0, |idx| idx + 1);
         ParseError {
             msg: msg.as_ref().into(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_begin..].chars().count() + 1,
             context,
         }
     }
@@ -89,7 +97,11 @@ impl ParseError {
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}\n---{}", self.msg, self.context)
+        write!(
+            f,
+            "{} (line {}, column {})\n---{}",
+            self.msg, self.line, self.column, self.context
+        )
     }
 }
 
@@ -106,6 +118,25 @@ pub enum LoadErrorKind {
     ReadFile(io::Error),
     Tokenize(xml::Error),
     Parse(ParseError),
+    /// The scene cannot be loaded with the requested options
+    Unsupported(&'static str),
+}
+
+impl LoadError {
+    pub(crate) fn unsupported(path: &Path, msg: &'static str) -> LoadError {
+        LoadError {
+            path: path.into(),
+            kind: LoadErrorKind::Unsupported(msg),
+        }
+    }
+
+    /// One-based line and column number of a parse error (if available).
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match &self.kind {
+            LoadErrorKind::Parse(e) => Some((e.line, e.column)),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for LoadError {
@@ -116,6 +147,7 @@ impl fmt::Display for LoadError {
             // TODO: Show offending lines with TextPos (row/col)?
             Tokenize(e) => write!(f, "Error tokenizing {:?}: {}", self.path, e),
             Parse(e) => write!(f, "Error parsing {:?}: {}", self.path, e),
+            Unsupported(msg) => write!(f, "Cannot load {:?}: {}", self.path, msg),
         }
     }
 }
@@ -175,6 +207,12 @@ impl FromSourceAndContext<AudioFileLoadError, xml::StrSpan<'_>> for ParseError {
     }
 }
 
+impl FromSourceAndContext<LoadError, xml::StrSpan<'_>> for ParseError {
+    fn from_source_and_context(source: LoadError, context: xml::StrSpan) -> ParseError {
+        ParseError::new(format!("Error loading sub-scene: {}", source), context)
+    }
+}
+
 impl FromSourceAndContext<std::num::ParseIntError, xml::StrSpan<'_>> for ParseError {
     fn from_source_and_context(
         source: std::num::ParseIntError,
@@ -199,6 +237,21 @@ impl FromSourceAndContext<std::num::ParseFloatError, xml::StrSpan<'_>> for Parse
     }
 }
 
+impl FromSourceAndContext<std::str::ParseBoolError, xml::StrSpan<'_>> for ParseError {
+    fn from_source_and_context(
+        source: std::str::ParseBoolError,
+        context: xml::StrSpan,
+    ) -> ParseError {
+        ParseError::new(
+            format!(
+                "Error parsing attribute as boolean (\"true\" or \"false\"): {}",
+                source
+            ),
+            context,
+        )
+    }
+}
+
 impl FromSourceAndContext<asdfspline::Error, xml::StrSpan<'_>> for ParseError {
     fn from_source_and_context(source: asdfspline::Error, context: xml::StrSpan) -> ParseError {
         ParseError::new(format!("Error creating ASDF spline: {}", source), context)
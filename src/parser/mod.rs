@@ -1,18 +1,28 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use asdfspline::AsdfSpline;
 use regex::Regex;
-use superslice::Ext; // for slice::lower_bound_by_key()
+use superslice::Ext; // for slice::lower_bound_by_key() and upper_bound_by()
 use xmlparser as xml;
 
 use crate::audiofile::dynamic::AudioFile;
+use crate::audiofile::ChannelMap;
 use crate::error::ResultExt;
-use crate::streamer::FileStreamer;
-use crate::transform::{get_length, Transform, Vec3};
-use crate::{Scene, Source, Transformer, REFERENCE_ID};
+use crate::expression::{Expression, LiveInputs};
+use crate::extent;
+use crate::loader::{self, OpenCallback};
+use crate::streamer::{ChannelTrim, Hold, StreamerBuilder};
+use crate::tempo::TempoMap;
+use crate::transform::{get_length, Easing, Quat, Transform, Vec3};
+use crate::{
+    AmbisonicsGroup, Clip, Event, Group, LoadOptions, Marker, Metadata, Scene, Source, Transformer,
+    Trigger, REFERENCE_ID,
+};
 
 mod elements;
 pub mod error;
@@ -20,9 +30,14 @@ mod time;
 
 use elements::{AsdfElement, Element};
 use error::{LoadError, ParseError};
-use time::frames2seconds;
+use time::{frames2seconds, Seconds, Time};
 
-pub type FileStorage = Vec<(Box<dyn AudioFile + Send + Sync>, Box<[Option<usize>]>)>;
+/// Name of the reader thread (worker threads get a numeric suffix)
+const READER_THREAD_NAME: &str = "asdf-reader";
+
+/// All audio files of a scene, each with the target channel(s)
+/// of each of its channels, see the `streaming` module.
+pub type FileStorage = Vec<(Box<dyn AudioFile + Send + Sync>, ChannelMap)>;
 
 #[derive(Default)]
 pub struct SceneInitializer<'a> {
@@ -31,20 +46,220 @@ pub struct SceneInitializer<'a> {
     blocksize: u32,
     buffer_blocks: u32,
     sleeptime: Duration,
+    /// Clips shorter than this (in frames) are completely loaded into memory
+    preload_frames: u64,
+    /// WAV files of at least this size (in bytes) bypass the OS page cache
+    direct_io_threshold: Option<u64>,
+    /// Audio files are read from a network share (`storage="network"`)
+    network_storage: bool,
+    /// Used instead of the file system (if given)
+    open: Option<OpenCallback>,
+    /// Audio files are only opened, no audio data is read
+    dry_run: bool,
     all_ids: HashSet<String>,
+    /// Prepended to all IDs of a sub-scene (empty in the main scene)
+    id_prefix: String,
+    /// Canonical paths of the current scene file and all its parents
+    include_stack: Vec<PathBuf>,
     sources: Vec<Source>,
+    groups: Vec<Group>,
+    /// Sources created by Ambisonics <clip> elements
+    ambisonics_groups: Vec<AmbisonicsGroup>,
+    /// Indices of currently open <group> elements
+    group_stack: Vec<usize>,
     current_id_suffix: u32,
     file_storage: FileStorage,
+    /// File names, same order as `file_storage`
+    file_paths: Vec<PathBuf>,
+    /// Playback speed of each file, same order as `file_storage`
+    file_speeds: Vec<f64>,
     transformer_storage: Vec<Box<dyn Transformer>>,
     transformer_instances: Vec<TransformerInstance>,
+    event_storage: Vec<EventData>,
+    event_instances: Vec<EventInstance>,
     /// transformer index, source index, span (of closing <clip> tag)
     channel_transformers: Vec<(usize, usize, xml::StrSpan<'a>)>,
     transformer_map: HashMap<String, Vec<usize>>,
-    streamer: Option<FileStreamer>,
+    /// IDs of <clip> and <scene> transformers, used for applying a sub-scene transform
+    root_transformer_ids: Vec<String>,
+    playlist: Option<Vec<PlaylistEntry>>,
     reference_transform: Transform,
+    /// Duration of the whole scene (including lead-in and lead-out)
+    frames: u64,
+    /// Silence before the first element (in frames)
+    lead_in: u64,
+    /// Silence after the last element (in frames)
+    lead_out: u64,
+    /// Names of live inputs, shared with all sub-scenes
+    input_names: Vec<String>,
+    /// Defined by <tempo> elements, needed for musical time values
+    tempo: Option<TempoMap>,
+    /// Given by <meta> elements
+    metadata: Vec<Metadata>,
+    /// Given by the <poster> element
+    poster: Option<PathBuf>,
+    /// Begin (in frames) of the element whose attributes are currently parsed
+    current_frame: u64,
+    /// Number of holds created by <wait-for-trigger> elements so far
+    hold_count: usize,
 }
 
 impl<'a> SceneInitializer<'a> {
+    /// Loads a sub-scene, all its IDs will get the prefix `id_prefix`.
+    ///
+    /// The <reference> of the sub-scene is ignored.
+    fn load_sub_scene(&mut self, path: &Path, id_prefix: String) -> Result<SubScene, LoadError> {
+        let file_data = read_scene_file(path, self.open.as_ref()).context(path)?;
+        let mut include_stack = self.include_stack.clone();
+        include_stack.push(canonical_path(path, self.open.as_ref()).context(path)?);
+        let mut sub = SceneInitializer {
+            dir: path.parent().unwrap().into(),
+            samplerate: self.samplerate,
+            blocksize: self.blocksize,
+            buffer_blocks: self.buffer_blocks,
+            sleeptime: self.sleeptime,
+            preload_frames: self.preload_frames,
+            direct_io_threshold: self.direct_io_threshold,
+            network_storage: self.network_storage,
+            open: self.open.clone(),
+            dry_run: self.dry_run,
+            id_prefix,
+            include_stack,
+            input_names: std::mem::replace(&mut self.input_names, Vec::new()),
+            ..Default::default()
+        };
+        let result = parse_file(path, &file_data, &mut sub);
+        self.input_names = std::mem::replace(&mut sub.input_names, Vec::new());
+        result?;
+        Ok(SubScene {
+            sources: sub.sources,
+            groups: sub.groups,
+            ambisonics_groups: sub.ambisonics_groups,
+            file_storage: sub.file_storage,
+            file_paths: sub.file_paths,
+            file_speeds: sub.file_speeds,
+            transformer_storage: sub.transformer_storage,
+            transformer_map: sub.transformer_map,
+            channel_transformers: sub
+                .channel_transformers
+                .into_iter()
+                .map(|(transformer, source, _)| (transformer, source))
+                .collect(),
+            root_transformer_ids: sub.root_transformer_ids,
+            event_storage: sub.event_storage,
+            playlist: sub.playlist.unwrap(),
+            transformer_instances: sub.transformer_instances,
+            event_instances: sub.event_instances,
+            frames: sub.frames,
+            hold_count: sub.hold_count,
+        })
+    }
+
+    /// Moves everything from `sub` to `self`, adjusting all indices.
+    ///
+    /// Returns playlist entries, transformer instances and event instances
+    /// (relative to the begin of the sub-scene) and the IDs of the
+    /// sub-scene's root transformers.
+    fn add_sub_scene(
+        &mut self,
+        sub: SubScene,
+        span: xml::StrSpan<'a>,
+    ) -> (
+        Vec<PlaylistEntry>,
+        Vec<TransformerInstance>,
+        Vec<EventInstance>,
+        Vec<String>,
+    ) {
+        let source_offset = self.sources.len();
+        let group_offset = self.groups.len();
+        let file_offset = self.file_storage.len();
+        let transformer_offset = self.transformer_storage.len();
+        let event_offset = self.event_storage.len();
+        let hold_offset = self.hold_count;
+        self.hold_count += sub.hold_count;
+        let offset_hold = |hold: Option<usize>| hold.map(|h| h + hold_offset);
+
+        // TODO: <group> transforms of the sub-scene are applied after the <scene> transform
+        self.sources
+            .extend(sub.sources.into_iter().map(|source| Source {
+                group: source.group.map(|g| g + group_offset),
+                ..source
+            }));
+        self.groups
+            .extend(sub.groups.into_iter().map(|group| Group {
+                parent: group.parent.map(|p| p + group_offset),
+                ..group
+            }));
+        self.ambisonics_groups
+            .extend(
+                sub.ambisonics_groups
+                    .into_iter()
+                    .map(|group| AmbisonicsGroup {
+                        first_source: group.first_source + source_offset,
+                        ..group
+                    }),
+            );
+        for (file, channel_map) in sub.file_storage {
+            let channel_map = channel_map
+                .iter()
+                .map(|sources| sources.iter().map(|s| s + source_offset).collect())
+                .collect();
+            self.file_storage.push((file, channel_map));
+        }
+        self.file_paths.extend(sub.file_paths);
+        self.file_speeds.extend(sub.file_speeds);
+        self.transformer_storage.extend(sub.transformer_storage);
+        for (id, transformers) in sub.transformer_map {
+            self.transformer_map
+                .entry(id)
+                .or_default()
+                .extend(transformers.into_iter().map(|idx| idx + transformer_offset));
+        }
+        for (transformer, source) in sub.channel_transformers {
+            self.channel_transformers.push((
+                transformer + transformer_offset,
+                source + source_offset,
+                span,
+            ));
+        }
+        self.event_storage
+            .extend(sub.event_storage.into_iter().map(|data| match data {
+                EventData::Event(source, data) => {
+                    EventData::Event(source.map(|s| s + source_offset), data)
+                }
+                marker => marker,
+            }));
+        let files = sub
+            .playlist
+            .into_iter()
+            .map(|entry| PlaylistEntry {
+                idx: entry.idx + file_offset,
+                hold: offset_hold(entry.hold),
+                ..entry
+            })
+            .collect();
+        let transformers = sub
+            .transformer_instances
+            .into_iter()
+            .map(|instance| TransformerInstance {
+                idx: instance.idx + transformer_offset,
+                hold: offset_hold(instance.hold),
+                ..instance
+            })
+            .collect();
+        let events = sub
+            .event_instances
+            .into_iter()
+            .map(|instance| EventInstance {
+                idx: instance.idx + event_offset,
+                hold: offset_hold(instance.hold),
+                starts_hold: offset_hold(instance.starts_hold),
+                ..instance
+            })
+            .collect();
+        (files, transformers, events, sub.root_transformer_ids)
+    }
+
     fn add_transformer(
         &mut self,
         transformer: Box<dyn Transformer>,
@@ -65,16 +280,24 @@ impl<'a> SceneInitializer<'a> {
             begin,
             duration,
             idx,
+            hold: None,
         });
         idx
     }
 }
 
-#[derive(Clone)]
+/// One playback of a file (from its beginning), see the `streaming` module.
+#[derive(Clone, Debug)]
 pub struct PlaylistEntry {
+    /// In frames
     pub begin: u64,
+    /// In frames, normally the length of the file
     pub duration: u64,
+    /// Index into the `FileStorage`
     pub idx: usize,
+    /// Index of the hold which delays this entry (see `StreamerBuilder::holds()`),
+    /// `None` if it is never delayed
+    pub hold: Option<usize>,
 }
 
 #[derive(Clone)]
@@ -82,6 +305,27 @@ pub struct TransformerInstance {
     pub begin: u64,
     pub duration: u64,
     pub idx: usize,
+    /// See `PlaylistEntry::hold`
+    pub hold: Option<usize>,
+}
+
+/// Contents of <event>, <marker> and <wait-for-trigger> elements
+pub enum EventData {
+    /// Optional source index and data string
+    Event(Option<usize>, String),
+    Marker(String),
+    /// Trigger ID
+    Trigger(String),
+}
+
+#[derive(Clone)]
+pub struct EventInstance {
+    pub begin: u64,
+    pub idx: usize,
+    /// See `PlaylistEntry::hold`
+    pub hold: Option<usize>,
+    /// The hold started by a <wait-for-trigger> element
+    pub starts_hold: Option<usize>,
 }
 
 struct ConstantTransformer {
@@ -94,7 +338,7 @@ impl Transformer for ConstantTransformer {
         self.id.as_ref()
     }
 
-    fn get_transform(&self, _frame: u64) -> Transform {
+    fn get_transform(&self, _frame: u64, _inputs: &LiveInputs) -> Transform {
         self.transform.clone()
     }
 }
@@ -102,6 +346,8 @@ impl Transformer for ConstantTransformer {
 struct SplineTransformer {
     id: Option<String>,
     spline: AsdfSpline<f32, Vec3>,
+    /// Time ranges (begin, end) with non-linear easing, sorted and non-overlapping
+    easings: Box<[(f32, f32, Easing)]>,
     samplerate: u32,
 }
 
@@ -110,36 +356,513 @@ impl Transformer for SplineTransformer {
         self.id.as_ref()
     }
 
-    fn get_transform(&self, frame: u64) -> Transform {
-        let time = frames2seconds(frame, self.samplerate).0;
+    fn get_transform(&self, frame: u64, _inputs: &LiveInputs) -> Transform {
+        let mut time = frames2seconds(frame, self.samplerate).0;
+        let idx = self
+            .easings
+            .upper_bound_by(|(begin, _, _)| begin.partial_cmp(&time).unwrap());
+        if idx > 0 {
+            let (t0, t1, easing) = self.easings[idx - 1];
+            if time < t1 {
+                time = t0 + (t1 - t0) * easing.apply((time - t0) / (t1 - t0));
+            }
+        }
         Transform {
             translation: Some(self.spline.evaluate(time, get_length)),
             // TODO: proper rotation
             rotation: None,
+            clamped: false,
         }
     }
 }
 
+/// Contents of a sub-scene, ready to be merged into the parent scene
+pub struct SubScene {
+    sources: Vec<Source>,
+    groups: Vec<Group>,
+    ambisonics_groups: Vec<AmbisonicsGroup>,
+    file_storage: FileStorage,
+    file_paths: Vec<PathBuf>,
+    file_speeds: Vec<f64>,
+    transformer_storage: Vec<Box<dyn Transformer>>,
+    transformer_map: HashMap<String, Vec<usize>>,
+    /// transformer index, source index
+    channel_transformers: Vec<(usize, usize)>,
+    root_transformer_ids: Vec<String>,
+    event_storage: Vec<EventData>,
+    playlist: Vec<PlaylistEntry>,
+    transformer_instances: Vec<TransformerInstance>,
+    event_instances: Vec<EventInstance>,
+    frames: u64,
+    hold_count: usize,
+}
+
+/// Piecewise-linear movement between nodes
+struct LinearTransformer {
+    id: Option<String>,
+    positions: Box<[Vec3]>,
+    /// Strictly increasing, same length as `positions`
+    times: Box<[f32]>,
+    /// One less than `positions`
+    easings: Box<[Easing]>,
+    samplerate: u32,
+}
+
+impl LinearTransformer {
+    /// Missing times (except first and last) are inferred
+    /// by assuming constant speed between the neighboring given times.
+    fn new(
+        id: Option<String>,
+        positions: Vec<Vec3>,
+        times: &[Option<f32>],
+        easings: Vec<Easing>,
+        samplerate: u32,
+    ) -> Result<LinearTransformer, String> {
+        assert!(positions.len() == times.len());
+        assert!(positions.len() == easings.len() + 1);
+        assert!(positions.len() >= 2);
+        let mut result = Vec::with_capacity(times.len());
+        result.push(times[0].unwrap_or_default());
+        let mut start = 0;
+        for (end, &time) in times.iter().enumerate().skip(1) {
+            let end_time = match time {
+                Some(time) => time,
+                None => {
+                    assert!(end < times.len() - 1, "last time must be given");
+                    continue;
+                }
+            };
+            let start_time = result[start];
+            if end_time <= start_time {
+                return Err("Times must be strictly increasing".into());
+            }
+            let lengths: Vec<f32> = (start..end)
+                .map(|i| get_length(positions[i + 1] - positions[i]))
+                .collect();
+            let total: f32 = lengths.iter().sum();
+            let mut length = 0.0;
+            for (i, segment) in lengths.iter().enumerate() {
+                length += segment;
+                result.push(if i == lengths.len() - 1 {
+                    end_time
+                } else if total > 0.0 {
+                    start_time + (end_time - start_time) * length / total
+                } else {
+                    start_time + (end_time - start_time) * (i + 1) as f32 / lengths.len() as f32
+                });
+            }
+            start = end;
+        }
+        Ok(LinearTransformer {
+            id,
+            positions: positions.into(),
+            times: result.into(),
+            easings: easings.into(),
+            samplerate,
+        })
+    }
+}
+
+impl Transformer for LinearTransformer {
+    fn id(&self) -> Option<&String> {
+        self.id.as_ref()
+    }
+
+    fn get_transform(&self, frame: u64, _inputs: &LiveInputs) -> Transform {
+        let time = frames2seconds(frame, self.samplerate).0;
+        let idx = self.times.upper_bound_by(|t| t.partial_cmp(&time).unwrap());
+        let position = if idx == 0 {
+            self.positions[0]
+        } else if idx == self.times.len() {
+            self.positions[idx - 1]
+        } else {
+            let (t0, t1) = (self.times[idx - 1], self.times[idx]);
+            let (p0, p1) = (self.positions[idx - 1], self.positions[idx]);
+            p0 + (p1 - p0) * self.easings[idx - 1].apply((time - t0) / (t1 - t0))
+        };
+        Transform {
+            translation: Some(position),
+            // TODO: proper rotation
+            rotation: None,
+            clamped: false,
+        }
+    }
+}
+
+struct ExpressionTransformer {
+    id: Option<String>,
+    position: [Expression; 3],
+    rotation: Option<Quat>,
+    samplerate: u32,
+}
+
+impl Transformer for ExpressionTransformer {
+    fn id(&self) -> Option<&String> {
+        self.id.as_ref()
+    }
+
+    fn uses_inputs(&self) -> bool {
+        self.position.iter().any(Expression::uses_inputs)
+    }
+
+    fn get_transform(&self, frame: u64, inputs: &LiveInputs) -> Transform {
+        let t = frames2seconds(frame, self.samplerate).0;
+        Transform {
+            translation: Some(Vec3::new(
+                self.position[0].evaluate(t, inputs),
+                self.position[1].evaluate(t, inputs),
+                self.position[2].evaluate(t, inputs),
+            )),
+            rotation: self.rotation,
+            clamped: false,
+        }
+    }
+}
+
+/// With `dry_run`, audio files are opened (to get their metadata)
+/// but no audio data is read and no streaming buffers are allocated.
+///
+/// If `open` is given, it is used instead of the file system.
 pub fn load_scene(
     path: &Path,
-    samplerate: u32,
-    blocksize: u32,
-    buffer_blocks: u32,
-    sleeptime: Duration,
+    options: &LoadOptions,
+    open: Option<OpenCallback>,
+    dry_run: bool,
 ) -> Result<Scene, LoadError> {
-    let file_data = fs::read_to_string(path).context(path)?;
-    let mut element_stack = Vec::<(Box<dyn Element>, xml::StrSpan)>::new();
+    let reader_threads = options.reader_threads;
+    if options.max_latency.is_some() && (reader_threads == 0 || cfg!(not(feature = "threads"))) {
+        return Err(LoadError::unsupported(
+            path,
+            "Reading audio data in the audio thread cannot meet a latency limit",
+        ));
+    }
+    let samplerate = options.samplerate;
+    #[cfg(feature = "archive")]
+    let (main_scene, open) = crate::archive::resolve(path, open).context(path)?;
+    #[cfg(feature = "archive")]
+    let path = main_scene.as_path();
+    let file_data = read_scene_file(path, open.as_ref()).context(path)?;
     let mut scene = SceneInitializer {
         dir: path.parent().unwrap().into(),
         samplerate,
-        blocksize,
-        buffer_blocks,
-        sleeptime,
+        blocksize: options.blocksize,
+        buffer_blocks: options.buffer_blocks,
+        sleeptime: options.sleeptime,
+        preload_frames: (options.preload_threshold.as_secs_f64() * f64::from(samplerate)).round()
+            as u64,
+        include_stack: vec![canonical_path(path, open.as_ref()).context(path)?],
+        direct_io_threshold: options.direct_io_threshold,
+        open,
+        dry_run,
         ..Default::default()
     };
+    parse_file(path, &file_data, &mut scene)?;
+
+    // Each <wait-for-trigger> starts one hold, they are sorted by time
+    // (nested holds at the same time come after their parents)
+    let mut parents = vec![None; scene.hold_count];
+    for instance in &scene.event_instances {
+        if let Some(hold) = instance.starts_hold {
+            parents[hold] = instance.hold;
+        }
+    }
+    let depth = |mut hold: usize| {
+        let mut depth = 0;
+        while let Some(parent) = parents[hold] {
+            hold = parent;
+            depth += 1;
+        }
+        depth
+    };
+    let mut trigger_instances: Vec<_> = scene
+        .event_instances
+        .iter()
+        .filter_map(|instance| Some((instance.begin, instance.starts_hold?, instance.idx)))
+        .collect();
+    trigger_instances.sort_by_key(|&(begin, hold, _)| (begin, depth(hold)));
+    let mut hold_indices = vec![0; scene.hold_count];
+    for (idx, &(_, hold, _)) in trigger_instances.iter().enumerate() {
+        hold_indices[hold] = idx;
+    }
+    let map_hold = |hold: Option<usize>| hold.map(|hold| hold_indices[hold]);
+
+    let playlist: Vec<_> = scene
+        .playlist
+        .take()
+        .unwrap()
+        .into_iter()
+        .map(|entry| PlaylistEntry {
+            hold: map_hold(entry.hold),
+            ..entry
+        })
+        .collect();
+
+    // Latest end of the contents of each hold (not including any waiting)
+    let mut hold_ends: Vec<_> = trigger_instances
+        .iter()
+        .map(|&(begin, _, _)| begin)
+        .collect();
+    let ends = playlist
+        .iter()
+        .map(|entry| (entry.hold, entry.begin + entry.duration))
+        .chain(
+            scene
+                .transformer_instances
+                .iter()
+                .map(|instance| (map_hold(instance.hold), instance.begin + instance.duration)),
+        )
+        .chain(
+            scene
+                .event_instances
+                .iter()
+                .map(|instance| (map_hold(instance.hold), instance.begin)),
+        );
+    for (hold, end) in ends {
+        if let Some(hold) = hold {
+            hold_ends[hold] = hold_ends[hold].max(end);
+        }
+    }
+
+    let mut clips = vec![Vec::new(); scene.sources.len()];
+    for entry in &playlist {
+        let (_, channel_map) = &scene.file_storage[entry.idx];
+        for (channel, sources) in channel_map.iter().enumerate() {
+            for &source in sources.iter() {
+                clips[source].push(Clip {
+                    begin: entry.begin,
+                    duration: entry.duration,
+                    file: entry.idx,
+                    channel: channel as u32,
+                    hold: entry.hold,
+                });
+            }
+        }
+    }
+    for (source, mut clips) in scene.sources.iter_mut().zip(clips) {
+        // NB: Clips within a source cannot overlap
+        clips.sort_by_key(|clip| clip.begin);
+        let mut ranges = Vec::<(u64, u64)>::new();
+        let mut holds = Vec::new();
+        for clip in &clips {
+            let end = clip.begin + clip.duration;
+            match ranges.last_mut() {
+                // NB: Clips with different holds may be moved apart during playback
+                Some(last) if last.1 >= clip.begin && holds.last() == Some(&clip.hold) => {
+                    last.1 = last.1.max(end)
+                }
+                _ => {
+                    ranges.push((clip.begin, end));
+                    holds.push(clip.hold);
+                }
+            }
+        }
+        source.clips = clips.into();
+        source.active_ranges = ranges.into();
+        source.active_holds = holds.into();
+    }
+
+    let mut transformer_activity = Vec::new();
+    transformer_activity.resize(scene.transformer_storage.len(), Vec::new());
+
+    for instance in &scene.transformer_instances {
+        transformer_activity[instance.idx].push((
+            instance.begin,
+            instance.begin + instance.duration,
+            map_hold(instance.hold),
+        ));
+    }
+
+    // TODO: assert that transformer activities are sorted (they should be!?!)
+
+    let mut source_activity = Vec::<Vec<(u64, u64, usize)>>::new();
+    source_activity.resize(scene.sources.len(), Vec::new());
+
+    for (transform_idx, source_idx, span) in scene.channel_transformers {
+        let activity = &mut source_activity[source_idx];
+
+        for &(begin, end, _) in &transformer_activity[transform_idx] {
+            let idx = activity.lower_bound_by_key(&begin, |a| a.0);
+
+            if (idx > 0 && activity[idx - 1].1 > begin)
+                || (idx < activity.len() && activity[idx].0 < end)
+            {
+                return Err(ParseError::new(
+                    format!(
+                        "Clip overlap in source \"{}\"",
+                        scene.sources[source_idx]
+                            .id
+                            .as_ref()
+                            .expect("Overlap cannot happen in sources without ID")
+                    ),
+                    span,
+                ))
+                .context(path);
+            }
+            activity.insert(idx, (begin, end, transform_idx))
+        }
+    }
+    scene
+        .sources
+        .iter_mut()
+        .zip(source_activity)
+        .for_each(|(source, activity)| {
+            source.activity = activity.into_iter().map(|(_, _, idx)| idx).collect();
+        });
+    let mut events = Vec::new();
+    let mut markers = Vec::new();
+    for instance in &scene.event_instances {
+        match &scene.event_storage[instance.idx] {
+            EventData::Event(source, data) => events.push((
+                map_hold(instance.hold),
+                Event {
+                    frame: instance.begin,
+                    source: *source,
+                    data: data.clone(),
+                },
+            )),
+            EventData::Marker(name) => markers.push(Marker {
+                frame: instance.begin,
+                name: name.clone(),
+            }),
+            // NB: Triggers are handled above
+            EventData::Trigger(_) => {}
+        }
+    }
+    // NB: Events with the same time keep their document order
+    events.sort_by_key(|(hold, event)| (hold.map_or(0, |hold| hold + 1), event.frame));
+    let mut event_groups = Vec::<(Option<usize>, Range<usize>)>::new();
+    for (idx, &(hold, _)) in events.iter().enumerate() {
+        match event_groups.last_mut() {
+            Some((last, range)) if *last == hold => range.end = idx + 1,
+            _ => event_groups.push((hold, idx..idx + 1)),
+        }
+    }
+    markers.sort_by_key(|marker| marker.frame);
+    let mut holds = Vec::new();
+    let mut triggers = Vec::new();
+    for &(begin, hold, idx) in &trigger_instances {
+        let parent = map_hold(parents[hold]);
+        holds.push(Hold {
+            frame: begin,
+            parent,
+        });
+        if let EventData::Trigger(id) = &scene.event_storage[idx] {
+            triggers.push(Trigger {
+                frame: begin,
+                id: id.clone(),
+                parent,
+            });
+        }
+    }
+
+    let streamer = StreamerBuilder::new(scene.blocksize, scene.sources.len() as u32)
+        .trims(
+            scene
+                .sources
+                .iter()
+                .map(|source| ChannelTrim {
+                    inverted: source.inverted,
+                    delay: source.delay,
+                })
+                .collect(),
+        )
+        .buffer_blocks(scene.buffer_blocks)
+        .sleeptime(scene.sleeptime)
+        .reader_threads(reader_threads)
+        .thread_name(READER_THREAD_NAME)
+        .holds(holds)
+        .build(
+            // NB: Without a playlist, no reader thread is started
+            if dry_run { Vec::new() } else { playlist },
+            scene.file_storage,
+        );
+
+    let mut result = Scene {
+        samplerate: scene.samplerate,
+        frames: scene.frames,
+        lead_in: scene.lead_in,
+        lead_out: scene.lead_out,
+        source_transforms: vec![None; scene.sources.len()].into(),
+        position_overrides: vec![None; scene.sources.len()].into(),
+        sources: scene.sources,
+        groups: scene.groups.into(),
+        ambisonics_groups: scene.ambisonics_groups.into(),
+        streamer,
+        transformers: scene
+            .transformer_storage
+            .into_iter()
+            .zip(transformer_activity)
+            .map(|(t, a)| (t, a.into()))
+            .collect(),
+        transformer_map: scene
+            .transformer_map
+            .into_iter()
+            .map(|(k, v)| (k, v.into()))
+            .collect(),
+        reference_transform: scene.reference_transform,
+        clip_files: scene.file_paths.into(),
+        clip_speeds: scene.file_speeds.into(),
+        open: scene.open,
+        tempo: scene.tempo,
+        metadata: scene.metadata.into(),
+        poster: scene.poster,
+        preferred_languages: Vec::new(),
+        transform_modifiers: Vec::new(),
+        events: events.into_iter().map(|(_, event)| event).collect(),
+        event_groups: event_groups.into(),
+        markers: markers.into(),
+        triggers: triggers.into(),
+        hold_ends: hold_ends.into(),
+        inputs: LiveInputs::new(scene.input_names.len()),
+        input_map: scene
+            .input_names
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| (name, idx))
+            .collect(),
+        session_log: None,
+        silence_watchdog: None,
+        streaming_error: None,
+        transform_tracer: None,
+        output_map: None,
+        max_latency: options.max_latency,
+        latency_violations: Default::default(),
+        source_extents: Default::default(),
+        area: None,
+    };
+    result.source_extents = extent::source_extents(&result);
+    if let Some((fade, shape)) = options.fade {
+        result.set_fade(fade, shape);
+    }
+    Ok(result)
+}
+
+/// Parses the contents of `file_data` (which was read from `path`) into `scene`.
+fn read_scene_file(path: &Path, open: Option<&OpenCallback>) -> io::Result<String> {
+    match open {
+        Some(open) => loader::read_to_string(open, path),
+        None => fs::read_to_string(path),
+    }
+}
+
+/// Used for detecting circular references between scene files
+fn canonical_path(path: &Path, open: Option<&OpenCallback>) -> io::Result<PathBuf> {
+    match open {
+        // NB: Without a file system, paths cannot be resolved
+        Some(_) => Ok(path.into()),
+        None => path.canonicalize(),
+    }
+}
+
+fn parse_file<'a>(
+    path: &Path,
+    file_data: &'a str,
+    scene: &mut SceneInitializer<'a>,
+) -> Result<(), LoadError> {
+    let mut element_stack = Vec::<(Box<dyn Element>, xml::StrSpan)>::new();
     let mut attributes = Attributes::new();
 
-    for token in xml::Tokenizer::from(&file_data as &str) {
+    for token in xml::Tokenizer::from(file_data) {
         use xml::Token::*;
         match token.context(path)? {
             Declaration {
@@ -219,7 +942,18 @@ pub fn load_scene(
                 value,
                 ..
             } => {
-                no_namespaces(prefix).context(path)?;
+                // NB: "xml:lang" is the only allowed prefixed attribute, it is stored as "lang"
+                if name.as_str() == "lang" {
+                    if prefix.as_str() != "xml" {
+                        return Err(ParseError::new(
+                            "Use \"xml:lang\" instead of \"lang\"",
+                            name,
+                        ))
+                        .context(path);
+                    }
+                } else {
+                    no_namespaces(prefix).context(path)?;
+                }
                 if attributes.iter().any(|&(k, _)| k.as_str() == name.as_str()) {
                     return Err(ParseError::new(
                         format!("Duplicate attribute {:?}", name.as_str()),
@@ -233,9 +967,11 @@ pub fn load_scene(
                 use xml::ElementEnd::*;
                 match end {
                     Open => {
+                        let parents = &element_stack[..element_stack.len() - 1];
+                        scene.current_frame = parents.iter().map(|(e, _)| e.child_offset()).sum();
                         let (element, span) = element_stack.last_mut().unwrap();
                         element
-                            .parse_attributes(&mut attributes, *span, &mut scene)
+                            .parse_attributes(&mut attributes, *span, scene)
                             .context(path)?;
                     }
                     Close(prefix, name) => {
@@ -253,25 +989,23 @@ pub fn load_scene(
                             .context(path);
                         }
                         if let Some((parent, _)) = element_stack.last_mut() {
-                            element
-                                .close(name, Some(parent), &mut scene)
-                                .context(path)?;
+                            element.close(name, Some(parent), scene).context(path)?;
                         } else {
-                            element.close(name, None, &mut scene).context(path)?;
+                            element.close(name, None, scene).context(path)?;
                         }
                         assert!(attributes.is_empty());
                     }
                     Empty => {
                         let (mut element, span) = element_stack.pop().unwrap();
+                        scene.current_frame =
+                            element_stack.iter().map(|(e, _)| e.child_offset()).sum();
                         element
-                            .parse_attributes(&mut attributes, span, &mut scene)
+                            .parse_attributes(&mut attributes, span, scene)
                             .context(path)?;
                         if let Some((parent, _)) = element_stack.last_mut() {
-                            element
-                                .close(span, Some(parent), &mut scene)
-                                .context(path)?;
+                            element.close(span, Some(parent), scene).context(path)?;
                         } else {
-                            element.close(span, None, &mut scene).context(path)?;
+                            element.close(span, None, scene).context(path)?;
                         }
                     }
                 }
@@ -297,75 +1031,15 @@ pub fn load_scene(
         ))
         .context(path);
     }
-    if scene.streamer.is_none() {
+    if scene.playlist.is_none() {
         // See https://github.com/RazrFalcon/xmlparser/issues/8
         return Err(ParseError::new(
             "Missing XML root element",
-            file_data.as_str().into(),
+            file_data.into(),
         ))
         .context(path);
     }
-
-    let mut transformer_activity = Vec::new();
-    transformer_activity.resize(scene.transformer_storage.len(), Vec::new());
-
-    for instance in &scene.transformer_instances {
-        transformer_activity[instance.idx]
-            .push((instance.begin, instance.begin + instance.duration));
-    }
-
-    // TODO: assert that transformer activities are sorted (they should be!?!)
-
-    let mut source_activity = Vec::<Vec<(u64, u64, usize)>>::new();
-    source_activity.resize(scene.sources.len(), Vec::new());
-
-    for (transform_idx, source_idx, span) in scene.channel_transformers {
-        let activity = &mut source_activity[source_idx];
-
-        for &(begin, end) in &transformer_activity[transform_idx] {
-            let idx = activity.lower_bound_by_key(&begin, |a| a.0);
-
-            if (idx > 0 && activity[idx - 1].1 > begin)
-                || (idx < activity.len() && activity[idx].0 < end)
-            {
-                return Err(ParseError::new(
-                    format!(
-                        "Clip overlap in source \"{}\"",
-                        scene.sources[source_idx]
-                            .id
-                            .as_ref()
-                            .expect("Overlap cannot happen in sources without ID")
-                    ),
-                    span,
-                ))
-                .context(path);
-            }
-            activity.insert(idx, (begin, end, transform_idx))
-        }
-    }
-    scene
-        .sources
-        .iter_mut()
-        .zip(source_activity)
-        .for_each(|(source, activity)| {
-            source.activity = activity.into_iter().map(|(_, _, idx)| idx).collect();
-        });
-    Ok(Scene {
-        sources: scene.sources,
-        streamer: scene.streamer.unwrap(),
-        transformers: scene
-            .transformer_storage
-            .into_iter()
-            .zip(transformer_activity)
-            .map(|(t, a)| (t, a.into()))
-            .collect(),
-        transformer_map: scene
-            .transformer_map
-            .into_iter()
-            .map(|(k, v)| (k, v.into()))
-            .collect(),
-        reference_transform: scene.reference_transform,
-    })
+    Ok(())
 }
 
 pub type Attributes<'a> = Vec<(xml::StrSpan<'a>, xml::StrSpan<'a>)>;
@@ -401,6 +1075,35 @@ fn no_namespaces(prefix: xml::StrSpan) -> Result<(), ParseError> {
 }
 
 impl<'a> SceneInitializer<'a> {
+    /// Parses a time value, either in seconds or (if a tempo map is defined)
+    /// in beats (e.g. `"6b"`) or bars and beats (e.g. `"2:1.5"`).
+    ///
+    /// Musical time values are converted according to the tempo map,
+    /// starting at the begin of the current element.
+    fn parse_time(&self, value: xml::StrSpan) -> Result<Seconds, ParseError> {
+        let time = value.as_str().parse::<Time>().context(value)?;
+        let tempo = match (time, &self.tempo) {
+            (Time::Seconds(seconds), _) => return Ok(seconds),
+            (_, Some(tempo)) => tempo,
+            (_, None) => {
+                return Err(ParseError::new(
+                    "Musical time values require a preceding <tempo> element",
+                    value,
+                ));
+            }
+        };
+        let begin = tempo.frames_to_beats(self.current_frame);
+        let end = match time {
+            Time::Seconds(_) => unreachable!(),
+            Time::Beats(beats) => begin + beats,
+            Time::Bars(bars, beats) => {
+                tempo.bars_to_beats(tempo.beats_to_bars(begin) + bars) + beats
+            }
+        };
+        let frames = tempo.beats_to_fractional_frames(end) - self.current_frame as f64;
+        Ok(Seconds((frames / f64::from(self.samplerate)) as f32))
+    }
+
     /// https://www.w3.org/TR/xml-id/
     ///
     /// * the ID value matches the allowed lexical form,
@@ -450,13 +1153,18 @@ impl<'a> SceneInitializer<'a> {
         if !self.all_ids.insert(id.clone()) {
             return Err(ParseError::new(format!("Non-unique ID: \"{}\"", id), value));
         }
-        Ok(id)
+        Ok(self.prefixed(&id))
+    }
+
+    /// IDs in sub-scenes get a prefix to avoid collisions with the parent scene
+    fn prefixed(&self, id: &str) -> String {
+        format!("{}{}", self.id_prefix, id)
     }
 
     /// This creates intentionally invalid XML IDs for internal use
     fn create_new_id(&mut self) -> String {
         self.current_id_suffix += 1;
-        format!(".asdf:{}", self.current_id_suffix)
+        format!("{}.asdf:{}", self.id_prefix, self.current_id_suffix)
     }
 
     fn get_id(&mut self, attributes: &mut Attributes) -> Result<Option<String>, ParseError> {
@@ -469,7 +1177,7 @@ impl<'a> SceneInitializer<'a> {
 
     fn get_source_id(&mut self, attributes: &mut Attributes) -> Result<Option<String>, ParseError> {
         if let Some(value) = attributes.get_value("source") {
-            let id = value.to_string();
+            let id = self.prefixed(value.as_str());
             if self
                 .sources
                 .iter()
@@ -489,4 +1197,30 @@ impl<'a> SceneInitializer<'a> {
             Ok(None)
         }
     }
+
+    /// Like `get_source_id()`, but the source must already exist
+    /// (no new source is created).
+    fn get_existing_source_id(
+        &mut self,
+        attributes: &mut Attributes,
+    ) -> Result<Option<String>, ParseError> {
+        if let Some(value) = attributes.get_value("source") {
+            let id = self.prefixed(value.as_str());
+            if self
+                .sources
+                .iter()
+                .filter_map(|src| src.id.as_ref())
+                .any(|s| *s == id)
+            {
+                Ok(Some(id))
+            } else {
+                Err(ParseError::new(
+                    format!("Unknown source ID: {:?}", id),
+                    value,
+                ))
+            }
+        } else {
+            Ok(None)
+        }
+    }
 }
@@ -19,6 +19,35 @@ impl FromStr for Seconds {
     }
 }
 
+/// A time value which is either given in seconds or in musical units.
+#[derive(Clone, Copy)]
+pub enum Time {
+    Seconds(Seconds),
+    /// E.g. `"6b"`
+    Beats(f64),
+    /// E.g. `"2:1.5"` (bars and beats)
+    Bars(f64, f64),
+}
+
+impl FromStr for Time {
+    type Err = ParseSecondsError;
+
+    fn from_str(s: &str) -> Result<Self, ParseSecondsError> {
+        if let Some(beats) = s.strip_suffix('b') {
+            f64::from_str(beats)
+                .map(Time::Beats)
+                .map_err(|_| ParseSecondsError {})
+        } else if let Some(colon) = s.find(':') {
+            match (f64::from_str(&s[..colon]), f64::from_str(&s[colon + 1..])) {
+                (Ok(bars), Ok(beats)) => Ok(Time::Bars(bars, beats)),
+                _ => Err(ParseSecondsError {}),
+            }
+        } else {
+            Seconds::from_str(s).map(Time::Seconds)
+        }
+    }
+}
+
 impl Add for Seconds {
     type Output = Seconds;
 
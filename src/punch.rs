@@ -0,0 +1,228 @@
+//! Rendering of short regions around all clip boundaries ("punch regions"),
+//! e.g. for quickly auditioning all edits of a large scene for clicks.
+//!
+//! The regions are concatenated (separated by silence) into one audio file,
+//! the positions of the clip boundaries within this file are stored
+//! as label track (which can be imported into Audacity and similar tools).
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::export::wait_for;
+use crate::Scene;
+
+/// Name of the multichannel audio file (one channel per source)
+const AUDIO_FILE_NAME: &str = "punch.wav";
+
+/// Name of the label file, see `PunchRender::to_labels()`
+const LABELS_FILE_NAME: &str = "punch-labels.txt";
+
+/// Silence between regions (in seconds)
+const GAP: f64 = 0.25;
+
+/// Fade-in and fade-out at the edges of each region (in seconds)
+const EDGE_FADE: f64 = 0.005;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundaryKind {
+    ClipBegin,
+    ClipEnd,
+}
+
+/// Beginning or end of a clip.
+#[derive(Clone, Debug)]
+pub struct Boundary {
+    /// Position in the scene (in frames)
+    pub frame: u64,
+    /// Position in the rendered audio file (in frames)
+    pub offset: u64,
+    /// Zero-based source index (which is also the channel in the audio file)
+    pub source: usize,
+    pub source_id: Option<String>,
+    pub kind: BoundaryKind,
+}
+
+/// A rendered range of the scene,
+/// overlapping ranges around neighboring boundaries are merged.
+#[derive(Clone, Debug)]
+pub struct PunchRegion {
+    /// In frames
+    pub begin: u64,
+    /// In frames
+    pub end: u64,
+    /// Position of `begin` in the rendered audio file (in frames)
+    pub offset: u64,
+    pub boundaries: Vec<Boundary>,
+}
+
+pub struct PunchRender {
+    pub samplerate: u32,
+    pub regions: Vec<PunchRegion>,
+}
+
+impl PunchRender {
+    /// Label track with one (point) label per boundary,
+    /// one line per label with the tab-separated columns
+    /// `start`, `end` (both in seconds within the audio file) and `label`.
+    pub fn to_labels(&self) -> String {
+        let seconds = |frames: u64| frames as f64 / f64::from(self.samplerate);
+        let mut out = String::new();
+        for boundary in self.regions.iter().flat_map(|r| &r.boundaries) {
+            let source = match &boundary.source_id {
+                Some(id) => id.clone(),
+                None => (boundary.source + 1).to_string(),
+            };
+            let kind = match boundary.kind {
+                BoundaryKind::ClipBegin => "begin",
+                BoundaryKind::ClipEnd => "end",
+            };
+            let time = seconds(boundary.offset);
+            writeln!(
+                out,
+                "{:.6}\t{:.6}\tsource {}: clip {} at {:.3} s",
+                time,
+                time,
+                source,
+                kind,
+                seconds(boundary.frame)
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+impl Scene {
+    /// Renders `margin` frames before and after each clip boundary
+    /// into the (existing) `directory`.
+    ///
+    /// The audio data is stored as 32-bit float WAV file with one channel
+    /// per source, the label track (see `PunchRender::to_labels()`)
+    /// as text file.
+    /// Each region is faded in and out (over a few milliseconds)
+    /// to avoid clicks which are not part of the scene.
+    ///
+    /// Like `export_range()`, this must not be called while the scene
+    /// is used for playback.
+    /// Afterwards, playback is stopped and the scene has to be seeked before
+    /// it can be used again.
+    pub fn render_punch_regions(
+        &mut self,
+        margin: u64,
+        directory: &Path,
+    ) -> Result<PunchRender, Box<dyn Error + Send + Sync>> {
+        let channels = self.file_sources();
+        if channels == 0 {
+            return Err("The scene has no file sources".into());
+        }
+        let mut regions = self.punch_regions(margin);
+        let blocksize = self.streamer.blocksize();
+        let mut buffers = vec![vec![0.0f32; blocksize as usize]; channels as usize];
+        let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let gap = (GAP * f64::from(self.samplerate)) as u64;
+        let edge_fade = (EDGE_FADE * f64::from(self.samplerate)) as u64;
+
+        let mut audio = hound::WavWriter::create(
+            directory.join(AUDIO_FILE_NAME),
+            hound::WavSpec {
+                channels: channels as u16,
+                sample_rate: self.samplerate,
+                bits_per_sample: 32,
+                sample_format: hound::SampleFormat::Float,
+            },
+        )?;
+        // NB: <wait-for-trigger> elements are ignored
+        let _holds = self.streamer.suspend_holds();
+        let mut offset = 0;
+        for region in &mut regions {
+            region.offset = offset;
+            for boundary in &mut region.boundaries {
+                boundary.offset = offset + (boundary.frame - region.begin);
+            }
+            let length = region.end - region.begin;
+            let fade = edge_fade.min(length / 2);
+
+            // Stop playback (if needed), without fade-out
+            self.streamer.stop();
+            wait_for(|| self.seek(region.begin))?;
+            let mut position = 0;
+            while position < length {
+                wait_for(|| self.streamer.frames_available() >= blocksize as usize)?;
+                if !unsafe { self.get_source_data_frames(&pointers, blocksize, true) } {
+                    return Err(self.streaming_error().unwrap().clone().into());
+                }
+                let frames = (length - position).min(u64::from(blocksize));
+                for i in 0..frames {
+                    let distance = (position + i).min(length - 1 - position - i);
+                    let gain = if distance < fade {
+                        (distance + 1) as f32 / (fade + 1) as f32
+                    } else {
+                        1.0
+                    };
+                    for &ptr in &pointers {
+                        audio.write_sample(gain * unsafe { *ptr.add(i as usize) })?;
+                    }
+                }
+                position += frames;
+            }
+            for _ in 0..gap * u64::from(channels) {
+                audio.write_sample(0.0f32)?;
+            }
+            offset += length + gap;
+        }
+        self.streamer.stop();
+        audio.finalize()?;
+
+        let render = PunchRender {
+            samplerate: self.samplerate,
+            regions,
+        };
+        fs::write(directory.join(LABELS_FILE_NAME), render.to_labels())?;
+        Ok(render)
+    }
+
+    /// Merged regions around all clip boundaries, sorted by time
+    /// (the offsets are not yet known).
+    fn punch_regions(&self, margin: u64) -> Vec<PunchRegion> {
+        let mut boundaries = Vec::new();
+        for (idx, source) in self.sources.iter().enumerate() {
+            for clip in source.clips.iter() {
+                let end = clip.begin + clip.duration;
+                for (frame, kind) in [
+                    (clip.begin, BoundaryKind::ClipBegin),
+                    (end, BoundaryKind::ClipEnd),
+                ] {
+                    boundaries.push(Boundary {
+                        frame,
+                        offset: 0,
+                        source: idx,
+                        source_id: source.id.clone(),
+                        kind,
+                    });
+                }
+            }
+        }
+        boundaries.sort_by_key(|b| (b.frame, b.source));
+        let mut regions: Vec<PunchRegion> = Vec::new();
+        for boundary in boundaries {
+            let begin = boundary.frame.saturating_sub(margin);
+            // NB: After the end of the scene, there is only silence
+            let end = (boundary.frame + margin).min(self.frames).max(begin + 1);
+            match regions.last_mut() {
+                Some(region) if begin <= region.end => {
+                    region.end = region.end.max(end);
+                    region.boundaries.push(boundary);
+                }
+                _ => regions.push(PunchRegion {
+                    begin,
+                    end,
+                    offset: 0,
+                    boundaries: vec![boundary],
+                }),
+            }
+        }
+        regions
+    }
+}
@@ -0,0 +1,437 @@
+//! Python bindings (using PyO3), e.g. for scripting, analyzing and rendering
+//! scenes in Jupyter notebooks.
+//!
+//! Audio data is provided as NumPy arrays with one row per file source.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2};
+use pyo3::exceptions::{PyIndexError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::export::wait_for;
+use crate::{Area, AreaPolicy, BoundingBox, LoadOptions, Scene, ScheduledSeekState, Transform};
+
+fn area_policy(project: bool) -> AreaPolicy {
+    if project {
+        AreaPolicy::Project
+    } else {
+        AreaPolicy::Clamp
+    }
+}
+
+fn runtime_error<E: ToString>(e: E) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+fn check_gain(gain: f32) -> PyResult<()> {
+    if crate::streamer::is_valid_gain(gain) {
+        Ok(())
+    } else {
+        Err(PyValueError::new_err(
+            "gain must be finite and non-negative",
+        ))
+    }
+}
+
+/// Position and orientation of a source (or the reference).
+///
+/// `rotation` is a unit quaternion given as `(x, y, z, w)`.
+#[pyclass(name = "Transform", module = "asdf", frozen)]
+struct PyTransform {
+    #[pyo3(get)]
+    position: Option<(f32, f32, f32)>,
+    #[pyo3(get)]
+    rotation: Option<(f32, f32, f32, f32)>,
+    /// Whether the position has been moved into the area (see `set_area_box()`)
+    #[pyo3(get)]
+    clamped: bool,
+}
+
+impl From<Transform> for PyTransform {
+    fn from(t: Transform) -> PyTransform {
+        PyTransform {
+            position: t.translation.map(|v| (v[0], v[1], v[2])),
+            rotation: t.rotation.map(|q| {
+                let v = q.vector();
+                (v[0], v[1], v[2], q.scalar())
+            }),
+            clamped: t.clamped,
+        }
+    }
+}
+
+#[pymethods]
+impl PyTransform {
+    fn __repr__(&self) -> String {
+        format!(
+            "Transform(position={:?}, rotation={:?}, clamped={})",
+            self.position,
+            self.rotation,
+            if self.clamped { "True" } else { "False" }
+        )
+    }
+}
+
+/// An ASDF scene.
+///
+/// Times are given in seconds, positions in frames.
+/// The fade (after seeking) is one block long.
+// NB: Scene contains non-thread-safe transform modifiers
+#[pyclass(name = "Scene", module = "asdf", unsendable)]
+struct PyScene {
+    scene: Scene,
+}
+
+impl PyScene {
+    fn check_source(&self, source_idx: usize) -> PyResult<()> {
+        if source_idx >= self.scene.sources.len() {
+            return Err(PyIndexError::new_err("Source index out of range"));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyScene {
+    #[new]
+    #[pyo3(signature = (
+        path,
+        samplerate,
+        blocksize,
+        buffer_blocks = 16,
+        sleeptime = 0.01,
+        reader_threads = 1,
+        preload_threshold = 0.0,
+        direct_io_threshold = None,
+        max_latency = None,
+    ))]
+    #[allow(clippy::too_many_arguments)] // Python keyword arguments
+    fn new(
+        path: PathBuf,
+        samplerate: u32,
+        blocksize: u32,
+        buffer_blocks: u32,
+        sleeptime: f64,
+        reader_threads: u32,
+        preload_threshold: f64,
+        direct_io_threshold: Option<u64>,
+        max_latency: Option<f64>,
+    ) -> PyResult<PyScene> {
+        if samplerate == 0 || blocksize == 0 {
+            return Err(PyValueError::new_err(
+                "samplerate and blocksize must be positive",
+            ));
+        }
+        let seconds = |value: f64, name: &str| {
+            Duration::try_from_secs_f64(value)
+                .map_err(|_| PyValueError::new_err(format!("Invalid {}: {}", name, value)))
+        };
+        let mut options = LoadOptions::new(samplerate, blocksize)
+            .buffer_blocks(buffer_blocks)
+            .sleeptime(seconds(sleeptime, "sleeptime")?)
+            .reader_threads(reader_threads)
+            .preload_threshold(seconds(preload_threshold, "preload_threshold")?);
+        if let Some(bytes) = direct_io_threshold {
+            options = options.direct_io_threshold(bytes);
+        }
+        if let Some(value) = max_latency {
+            options = options.max_latency(seconds(value, "max_latency")?);
+        }
+        let scene = Scene::with_options(path, &options).map_err(runtime_error)?;
+        Ok(PyScene { scene })
+    }
+
+    /// Loads a scene without reading any audio data, see `Scene::load_dry_run()`.
+    #[staticmethod]
+    fn load_dry_run(path: PathBuf, samplerate: u32, blocksize: u32) -> PyResult<PyScene> {
+        let scene = Scene::load_dry_run(path, samplerate, blocksize).map_err(runtime_error)?;
+        Ok(PyScene { scene })
+    }
+
+    #[getter]
+    fn samplerate(&self) -> u32 {
+        self.scene.samplerate
+    }
+
+    #[getter]
+    fn blocksize(&self) -> u32 {
+        self.scene.streamer.blocksize()
+    }
+
+    /// Duration in frames (including lead-in and lead-out)
+    #[getter]
+    fn frames(&self) -> u64 {
+        self.scene.frames()
+    }
+
+    /// Duration in seconds
+    #[getter]
+    fn duration(&self) -> f64 {
+        self.scene.duration().as_secs_f64()
+    }
+
+    /// Number of calls exceeding `max_latency` and the longest execution time
+    /// (in seconds), only measured in debug builds
+    #[getter]
+    fn latency_violations(&self) -> (u64, f64) {
+        let violations = self.scene.latency_violations();
+        (violations.count, violations.longest.as_secs_f64())
+    }
+
+    /// File name of the `<poster>` image (if any)
+    #[getter]
+    fn poster(&self) -> Option<PathBuf> {
+        self.scene.poster().map(Into::into)
+    }
+
+    /// Contents of the poster image file, `None` if there is no poster
+    fn read_poster<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let data = self.scene.read_poster().map_err(runtime_error)?;
+        Ok(data.map(|data| PyBytes::new(py, &data)))
+    }
+
+    /// Number of sources (including live sources)
+    #[getter]
+    fn sources(&self) -> usize {
+        self.scene.sources.len()
+    }
+
+    /// Number of sources with audio data from files (i.e. rows of audio data)
+    #[getter]
+    fn file_sources(&self) -> u32 {
+        self.scene.file_sources()
+    }
+
+    fn get_source_id(&self, source_idx: usize) -> PyResult<Option<String>> {
+        self.check_source(source_idx)?;
+        Ok(self.scene.get_source_id(source_idx).cloned())
+    }
+
+    fn get_source_name(&self, source_idx: usize) -> PyResult<Option<String>> {
+        self.check_source(source_idx)?;
+        Ok(self.scene.get_source_name(source_idx).cloned())
+    }
+
+    fn get_source_tags(&self, source_idx: usize) -> PyResult<Vec<String>> {
+        self.check_source(source_idx)?;
+        Ok(self.scene.get_source_info(source_idx).tags.to_vec())
+    }
+
+    /// All tags used by the sources of the scene
+    #[getter]
+    fn tags(&self) -> Vec<String> {
+        self.scene.tags().into_iter().map(Into::into).collect()
+    }
+
+    /// Indices of all sources with the given tag
+    fn sources_with_tag(&self, tag: &str) -> Vec<usize> {
+        self.scene.sources_with_tag(tag).collect()
+    }
+
+    /// Linear gain of the given source (the default is 1.0)
+    fn set_source_gain(&mut self, source_idx: usize, gain: f32) -> PyResult<()> {
+        if source_idx >= self.scene.file_sources() as usize {
+            return Err(PyIndexError::new_err("Source index out of range"));
+        }
+        check_gain(gain)?;
+        self.scene.set_source_gain(source_idx, gain);
+        Ok(())
+    }
+
+    /// Sets the gain of all sources with the given tag (or in the given group),
+    /// starting at frame `offset` of the next block.
+    /// Returns the number of affected sources.
+    #[pyo3(signature = (name, gain, offset = 0))]
+    fn set_group_gain(&mut self, name: &str, gain: f32, offset: u32) -> PyResult<usize> {
+        check_gain(gain)?;
+        Ok(self.scene.set_group_gain(name, gain, offset))
+    }
+
+    fn set_group_mute(&mut self, name: &str, mute: bool) -> usize {
+        self.scene.set_group_mute(name, mute)
+    }
+
+    fn set_group_solo(&mut self, name: &str, solo: bool) -> usize {
+        self.scene.set_group_solo(name, solo)
+    }
+
+    /// Returns `None` if the source is inactive at the given frame.
+    fn get_source_transform(&self, source_idx: usize, frame: u64) -> PyResult<Option<PyTransform>> {
+        self.check_source(source_idx)?;
+        Ok(self
+            .scene
+            .get_source_transform(source_idx, frame)
+            .map(Into::into))
+    }
+
+    fn get_reference_transform(&self, frame: u64) -> Option<PyTransform> {
+        self.scene.get_reference_transform(frame).map(Into::into)
+    }
+
+    /// Confines all source positions to the given box, see `Scene::set_area()`.
+    ///
+    /// With `project=True`, positions are moved towards the center of the box,
+    /// otherwise the nearest point inside is used.
+    #[pyo3(signature = (min, max, project = false))]
+    fn set_area_box(&mut self, min: [f32; 3], max: [f32; 3], project: bool) {
+        let area = Area::from_box(BoundingBox { min, max });
+        self.scene.set_area(Some(area), area_policy(project));
+    }
+
+    /// Like `set_area_box()`, but with a sphere
+    #[pyo3(signature = (center, radius, project = false))]
+    fn set_area_sphere(&mut self, center: [f32; 3], radius: f32, project: bool) -> PyResult<()> {
+        if radius.is_nan() || radius < 0.0 {
+            return Err(PyValueError::new_err("radius must not be negative"));
+        }
+        let area = Area::sphere(center, radius);
+        self.scene.set_area(Some(area), area_policy(project));
+        Ok(())
+    }
+
+    /// Like `set_area_box()`, but with a convex polygon in the x/y plane
+    #[pyo3(signature = (vertices, project = false))]
+    fn set_area_polygon(&mut self, vertices: Vec<[f32; 2]>, project: bool) -> PyResult<()> {
+        let area =
+            Area::polygon(&vertices).ok_or_else(|| PyValueError::new_err("Invalid polygon"))?;
+        self.scene.set_area(Some(area), area_policy(project));
+        Ok(())
+    }
+
+    fn clear_area(&mut self) {
+        self.scene.set_area(None, AreaPolicy::default());
+    }
+
+    /// Bounding box `(min, max)` of all active source positions,
+    /// `None` if there are no active sources
+    #[getter]
+    fn extent(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.scene.extent().map(|extent| (extent.min, extent.max))
+    }
+
+    /// Bounding box `(min, max)` of the positions of the given source
+    fn get_source_extent(&self, source_idx: usize) -> PyResult<Option<([f32; 3], [f32; 3])>> {
+        self.check_source(source_idx)?;
+        Ok(self
+            .scene
+            .source_extent(source_idx)
+            .map(|extent| (extent.min, extent.max)))
+    }
+
+    /// Returns `False` if seeking is not yet possible
+    /// (because the previous seek is still in progress).
+    fn seek(&mut self, frame: u64) -> bool {
+        self.scene.seek(frame)
+    }
+
+    /// Seeks to `target` exactly when frame `at` is reached during playback,
+    /// returns `False` if the seek cannot be scheduled (see `Scene::seek_at()`).
+    fn seek_at(&mut self, at: u64, target: u64) -> bool {
+        self.scene.seek_at(at, target)
+    }
+
+    /// `(at, target, state)` of the seek scheduled with `seek_at()`,
+    /// where `state` is one of `"pending"`, `"armed"`, `"done"` and `"missed"`
+    #[getter]
+    fn scheduled_seek(&self) -> Option<(u64, u64, &'static str)> {
+        self.scene.scheduled_seek().map(|seek| {
+            let state = match seek.state {
+                ScheduledSeekState::Pending => "pending",
+                ScheduledSeekState::Armed => "armed",
+                ScheduledSeekState::Done => "done",
+                ScheduledSeekState::Missed => "missed",
+            };
+            (seek.at, seek.target, state)
+        })
+    }
+
+    /// Returns to frame 0 without fade-out (discarding buffered audio data),
+    /// same return value as `seek()`.
+    fn reset(&mut self) -> bool {
+        self.scene.reset()
+    }
+
+    /// Returns the next block of audio data (one row per file source),
+    /// like the C function `asdf_get_audio_data()`.
+    ///
+    /// This doesn't wait for the reader thread(s),
+    /// if no data is available yet, `RuntimeError` is raised
+    /// (like for any other streaming error).
+    #[pyo3(signature = (rolling = true))]
+    fn get_audio_data<'py>(
+        &mut self,
+        py: Python<'py>,
+        rolling: bool,
+    ) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let blocksize = self.scene.streamer.blocksize() as usize;
+        let channels = self.scene.file_sources() as usize;
+        let mut data = Array2::<f32>::zeros((channels, blocksize));
+        let pointers: Vec<_> = data
+            .as_slice_mut()
+            .unwrap()
+            .chunks_exact_mut(blocksize)
+            .map(|row| row.as_mut_ptr())
+            .collect();
+        if !unsafe { self.scene.get_audio_data(&pointers, rolling) } {
+            return Err(runtime_error(self.scene.streaming_error().unwrap()));
+        }
+        Ok(data.into_pyarray(py))
+    }
+
+    /// Renders the audio data from `begin` to `end` (in frames),
+    /// with one row per file source.
+    ///
+    /// Like `Scene::export_range()`, this waits for the reader thread(s),
+    /// the beginning is faded in.
+    /// Afterwards, playback is stopped and the scene has to be seeked before
+    /// it can be used again.
+    fn render<'py>(
+        &mut self,
+        py: Python<'py>,
+        begin: u64,
+        end: u64,
+    ) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        if end <= begin {
+            return Err(PyValueError::new_err("end must be after begin"));
+        }
+        let scene = &mut self.scene;
+        let blocksize = scene.streamer.blocksize() as usize;
+        let channels = scene.file_sources() as usize;
+        let frames = (end - begin) as usize;
+        let mut buffers = vec![vec![0.0f32; blocksize]; channels];
+        let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let mut data = Array2::<f32>::zeros((channels, frames));
+
+        scene.streamer.stop();
+        let _holds = scene.streamer.suspend_holds();
+        wait_for(|| scene.seek(begin)).map_err(runtime_error)?;
+        let mut offset = 0;
+        while offset < frames {
+            wait_for(|| scene.streamer.frames_available() >= blocksize).map_err(runtime_error)?;
+            if !unsafe { scene.get_source_data_frames(&pointers, blocksize as u32, true) } {
+                return Err(runtime_error(scene.streaming_error().unwrap()));
+            }
+            let n = (frames - offset).min(blocksize);
+            for (mut row, buffer) in data.outer_iter_mut().zip(&buffers) {
+                for (target, &value) in row.iter_mut().skip(offset).zip(&buffer[..n]) {
+                    *target = value;
+                }
+            }
+            offset += n;
+            py.check_signals()?;
+        }
+        scene.streamer.stop();
+        Ok(data.into_pyarray(py))
+    }
+}
+
+#[pymodule]
+fn asdf(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyScene>()?;
+    m.add_class::<PyTransform>()?;
+    Ok(())
+}
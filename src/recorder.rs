@@ -0,0 +1,167 @@
+//! Recording of live source movements (see `Scene::set_source_override()`
+//! and live inputs), which can be exported as ASDF trajectories.
+
+use std::fmt::Write;
+
+use crate::transform::Vec3;
+use crate::Scene;
+
+/// A recorded source position.
+pub struct Breakpoint {
+    pub frame: u64,
+    /// Zero-based source index
+    pub source: usize,
+    pub position: [f32; 3],
+}
+
+pub struct Recorder {
+    samplerate: u32,
+    source_ids: Box<[Option<String>]>,
+    /// Sources which depend on live inputs (overrides are checked separately)
+    live_sources: Box<[bool]>,
+    /// Last recorded position of each source (`None` if inactive)
+    last_positions: Box<[Option<Vec3>]>,
+    /// Frame number of the last call to `capture()`
+    last_frame: Option<u64>,
+    /// NB: The capacity is reserved in advance, it never grows
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Recorder {
+    /// Allocates space for (at most) `capacity` breakpoints.
+    pub fn new(scene: &Scene, capacity: usize) -> Recorder {
+        let source_count = scene.sources.len();
+        Recorder {
+            samplerate: scene.samplerate,
+            source_ids: scene.sources.iter().map(|s| s.id.clone()).collect(),
+            live_sources: (0..source_count)
+                .map(|idx| scene.is_source_live(idx))
+                .collect(),
+            last_positions: vec![None; source_count].into(),
+            last_frame: None,
+            breakpoints: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Records the positions of all live sources at the given frame
+    /// (typically called once per audio block).
+    ///
+    /// Only positions that changed since the last call are stored.
+    /// Calls with a frame number smaller than in the previous call are ignored
+    /// (e.g. after seeking backwards).
+    /// Returns `false` if the capacity is exhausted.
+    pub fn capture(&mut self, scene: &Scene, frame: u64) -> bool {
+        // NB: This function is supposed to be realtime-safe!
+        if self.last_frame.map_or(false, |last| frame < last) {
+            return true;
+        }
+        self.last_frame = Some(frame);
+        for (idx, last_position) in self.last_positions.iter_mut().enumerate() {
+            if !self.live_sources[idx] && scene.position_overrides[idx].is_none() {
+                *last_position = None;
+                continue;
+            }
+            // NB: Transform modifiers must not end up in the recorded scene
+            let position = scene
+                .get_unmodified_source_transform(idx, frame)
+                .and_then(|t| t.translation);
+            if let Some(p) = position.filter(|&p| Some(p) != *last_position) {
+                if self.breakpoints.len() == self.breakpoints.capacity() {
+                    return false;
+                }
+                self.breakpoints.push(Breakpoint {
+                    frame,
+                    source: idx,
+                    position: p.into(),
+                });
+            }
+            *last_position = position;
+        }
+        true
+    }
+
+    /// All recorded breakpoints, sorted by frame number.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Removes all breakpoints (the capacity is kept).
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        for position in self.last_positions.iter_mut() {
+            *position = None;
+        }
+        self.last_frame = None;
+    }
+
+    /// Creates a `<par>` element with one `<transform>` per recorded source,
+    /// which can be pasted into the `<body>` of an ASDF file.
+    ///
+    /// The first recorded position is used from the beginning, the last one
+    /// is kept until the end of the recording, positions are linearly
+    /// interpolated in between.
+    /// Sources without ID are skipped.
+    pub fn to_asdf(&self) -> String {
+        let mut result = String::new();
+        let end = match self.last_frame {
+            Some(frame) if frame > 0 => frame,
+            _ => return result,
+        };
+        let seconds = |frame: u64| frame as f64 / f64::from(self.samplerate);
+        for (idx, id) in self.source_ids.iter().enumerate() {
+            let id = match id {
+                Some(id) => id,
+                None => continue,
+            };
+            let mut breakpoints = self
+                .breakpoints
+                .iter()
+                .filter(|b| b.source == idx)
+                .peekable();
+            let first = match breakpoints.peek() {
+                Some(&first) => first,
+                None => continue,
+            };
+            writeln!(
+                result,
+                "  <transform apply-to=\"{}\" interpolation=\"linear\">",
+                escape(id)
+            )
+            .unwrap();
+            let mut write_node = |frame: u64, position: &[f32; 3]| {
+                writeln!(
+                    result,
+                    "    <o time=\"{}\" pos=\"{} {} {}\"/>",
+                    seconds(frame),
+                    position[0],
+                    position[1],
+                    position[2]
+                )
+                .unwrap();
+            };
+            if first.frame > 0 {
+                write_node(0, &first.position);
+            }
+            let mut last = first;
+            for breakpoint in breakpoints {
+                write_node(breakpoint.frame, &breakpoint.position);
+                last = breakpoint;
+            }
+            if last.frame < end {
+                write_node(end, &last.position);
+            }
+            result.push_str("  </transform>\n");
+        }
+        if result.is_empty() {
+            result
+        } else {
+            format!("<par>\n{}</par>\n", result)
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
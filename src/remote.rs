@@ -0,0 +1,343 @@
+//! TCP server for remote control of a headless player, e.g. from a browser UI
+//! (requires the `remote` feature).
+//!
+//! Clients send newline-delimited JSON objects:
+//!
+//! * `{"command": "seek", "time": <seconds>}`
+//! * `{"command": "cue", "marker": "<marker name>"}`
+//! * `{"command": "rolling", "value": <bool>}`
+//! * `{"command": "mute", "source": "<source ID>", "value": <bool>}`
+//! * `{"command": "solo", "source": "<source ID>", "value": <bool>}`
+//! * `{"command": "subscribe"}`
+//!
+//! Invalid commands are answered with `{"error": "<message>"}`.
+//! Subscribed clients regularly receive the current state:
+//!
+//! ```text
+//! {"frame": <u64>, "time": <seconds>, "rolling": <bool>,
+//!  "muted": [<source IDs>], "soloed": [<source IDs>]}
+//! ```
+//!
+//! Mute/solo commands are applied to the scene by
+//! [`RemoteServer::process()`](struct.RemoteServer.html#method.process),
+//! transport commands are handed to the host.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::queue;
+use serde_json::{json, Value};
+
+use crate::{Scene, TransportRequest};
+
+/// Maximum number of commands between two calls to `process()`
+const QUEUE_CAPACITY: usize = 64;
+
+/// Time between state updates sent to subscribed clients
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time between checks whether the server is still running
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Clients which don't accept data within this time are disconnected
+/// (e.g. subscribers which stopped reading state updates)
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+enum Command {
+    Seek(u64),
+    Rolling(bool),
+    Mute(usize, bool),
+    Solo(usize, bool),
+}
+
+pub struct RemoteServer {
+    shared: Arc<Shared>,
+    commands: queue::spsc::Consumer<Command>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+/// State shared between the audio thread and the network threads
+struct Shared {
+    keep_running: AtomicBool,
+    frame: AtomicU64,
+    rolling: AtomicBool,
+    /// Copy of the scene's mute state (indexed by source number)
+    muted: Box<[AtomicBool]>,
+    /// Copy of the scene's solo state (indexed by source number)
+    soloed: Box<[AtomicBool]>,
+    /// NB: The producer is only used from network threads, never in the audio thread
+    producer: Mutex<queue::spsc::Producer<Command>>,
+    subscribers: Mutex<Vec<TcpStream>>,
+    samplerate: u32,
+    /// Map from source ID to source number
+    sources: HashMap<String, usize>,
+    source_ids: Box<[Option<String>]>,
+    markers: Box<[(String, u64)]>,
+}
+
+impl RemoteServer {
+    /// Starts listening for TCP connections on `address` (e.g. `"0.0.0.0:9001"`).
+    ///
+    /// Source IDs and markers are taken from `scene`.
+    pub fn new<A: ToSocketAddrs>(
+        scene: &Scene,
+        address: A,
+    ) -> Result<RemoteServer, Box<dyn Error + Send + Sync>> {
+        let listener = TcpListener::bind(address)?;
+        // NB: Non-blocking mode is needed to regularly check `keep_running`
+        listener.set_nonblocking(true)?;
+        let source_ids: Box<[_]> = scene.sources.iter().map(|s| s.id.clone()).collect();
+        let (producer, consumer) = queue::spsc::new(QUEUE_CAPACITY);
+        let shared = Arc::new(Shared {
+            keep_running: AtomicBool::new(true),
+            frame: AtomicU64::new(0),
+            rolling: AtomicBool::new(false),
+            muted: source_ids.iter().map(|_| AtomicBool::new(false)).collect(),
+            soloed: source_ids.iter().map(|_| AtomicBool::new(false)).collect(),
+            producer: Mutex::new(producer),
+            subscribers: Mutex::new(Vec::new()),
+            samplerate: scene.samplerate,
+            sources: source_ids
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, id)| id.clone().map(|id| (id, idx)))
+                .collect(),
+            source_ids,
+            markers: scene
+                .markers
+                .iter()
+                .map(|m| (m.name.clone(), m.frame))
+                .collect(),
+        });
+        let listener_shared = Arc::clone(&shared);
+        let listener_thread = thread::spawn(move || {
+            while listener_shared.keep_running.load(Ordering::Acquire) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let client_shared = Arc::clone(&listener_shared);
+                        // NB: Client threads are detached, they stop with the server
+                        thread::spawn(move || client_shared.handle_client(stream));
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(POLL_INTERVAL);
+                    }
+                    // TODO: log errors?
+                    Err(_) => thread::sleep(POLL_INTERVAL),
+                }
+            }
+        });
+        let publisher_shared = Arc::clone(&shared);
+        let publisher_thread = thread::spawn(move || {
+            while publisher_shared.keep_running.load(Ordering::Acquire) {
+                publisher_shared.publish();
+                thread::sleep(PUBLISH_INTERVAL);
+            }
+        });
+        Ok(RemoteServer {
+            shared,
+            commands: consumer,
+            threads: vec![listener_thread, publisher_thread],
+        })
+    }
+
+    /// Applies all mute/solo commands received since the last call
+    /// and stores the scene's current state (to be sent to subscribers).
+    ///
+    /// Transport commands are not applied, they are returned to the caller.
+    pub fn process(&self, scene: &mut Scene) -> TransportRequest {
+        // NB: This function is supposed to be realtime-safe!
+        let mut request = TransportRequest::default();
+        while let Ok(command) = self.commands.pop() {
+            match command {
+                Command::Seek(frame) => request.seek = Some(frame),
+                Command::Rolling(rolling) => request.rolling = Some(rolling),
+                Command::Mute(idx, mute) => scene.set_source_mute(idx, mute),
+                Command::Solo(idx, solo) => scene.set_source_solo(idx, solo),
+            }
+        }
+        let shared = &self.shared;
+        let position = scene.position();
+        shared.frame.store(position.frame, Ordering::Relaxed);
+        shared.rolling.store(position.rolling, Ordering::Relaxed);
+        for idx in 0..shared.muted.len() {
+            shared.muted[idx].store(scene.is_source_muted(idx), Ordering::Relaxed);
+            shared.soloed[idx].store(scene.is_source_soloed(idx), Ordering::Relaxed);
+        }
+        request
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.shared.keep_running.store(false, Ordering::Release);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    fn handle_client(&self, stream: TcpStream) {
+        // NB: On some platforms, the stream inherits non-blocking mode from the listener.
+        // NB: The timeout is needed to regularly check `keep_running`
+        // NB: The write timeout also applies to clones of the stream (see `publish()`)
+        if stream.set_nonblocking(false).is_err()
+            || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err()
+            || stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err()
+        {
+            return;
+        }
+        let mut writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => return,
+        };
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while self.keep_running.load(Ordering::Acquire) {
+            match reader.read_line(&mut line) {
+                // Connection was closed
+                Ok(0) => return,
+                Ok(_) => {}
+                Err(ref e)
+                    if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+                {
+                    // NB: Incomplete lines are kept in `line`
+                    continue;
+                }
+                Err(_) => return,
+            }
+            if !line.trim().is_empty() {
+                let result = serde_json::from_str::<Value>(&line)
+                    .map_err(|e| format!("Invalid JSON: {}", e))
+                    .and_then(|message| self.handle_message(&message, &writer));
+                if let Err(msg) = result {
+                    let response = json!({ "error": msg });
+                    if writeln!(writer, "{}", response).is_err() {
+                        return;
+                    }
+                }
+            }
+            line.clear();
+        }
+    }
+
+    fn handle_message(&self, message: &Value, stream: &TcpStream) -> Result<(), String> {
+        let command = message
+            .get("command")
+            .and_then(Value::as_str)
+            .ok_or("\"command\" (string) is required")?;
+        match command {
+            "seek" => {
+                let seconds = get_f64(message, "time")?;
+                if seconds < 0.0 {
+                    return Err("\"time\" must not be negative".into());
+                }
+                let frame = (seconds * f64::from(self.samplerate)).round() as u64;
+                self.push(Command::Seek(frame))
+            }
+            "cue" => {
+                let name = get_str(message, "marker")?;
+                let &(_, frame) = self
+                    .markers
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .ok_or_else(|| format!("Unknown marker: {:?}", name))?;
+                self.push(Command::Seek(frame))
+            }
+            "rolling" => self.push(Command::Rolling(get_bool(message, "value")?)),
+            "mute" | "solo" => {
+                let id = get_str(message, "source")?;
+                let &idx = self
+                    .sources
+                    .get(id)
+                    .ok_or_else(|| format!("Unknown source: {:?}", id))?;
+                let value = get_bool(message, "value")?;
+                if command == "mute" {
+                    self.push(Command::Mute(idx, value))
+                } else {
+                    self.push(Command::Solo(idx, value))
+                }
+            }
+            "subscribe" => {
+                let stream = stream
+                    .try_clone()
+                    .map_err(|e| format!("Unable to subscribe: {}", e))?;
+                self.subscribers.lock().unwrap().push(stream);
+                Ok(())
+            }
+            _ => Err(format!("Unknown command: {:?}", command)),
+        }
+    }
+
+    fn push(&self, command: Command) -> Result<(), String> {
+        self.producer
+            .lock()
+            .unwrap()
+            .push(command)
+            .map_err(|_| "Too many pending commands".into())
+    }
+
+    /// Sends the current state to all subscribers and disconnects those
+    /// which are closed or which don't read their updates (see `WRITE_TIMEOUT`).
+    fn publish(&self) {
+        // NB: The lock is not held while writing, new subscribers are not blocked
+        let mut subscribers = std::mem::take(&mut *self.subscribers.lock().unwrap());
+        if subscribers.is_empty() {
+            return;
+        }
+        let ids = |flags: &[AtomicBool]| -> Vec<&str> {
+            flags
+                .iter()
+                .zip(self.source_ids.iter())
+                .filter(|(flag, _)| flag.load(Ordering::Relaxed))
+                .filter_map(|(_, id)| id.as_ref().map(String::as_str))
+                .collect()
+        };
+        let frame = self.frame.load(Ordering::Relaxed);
+        let state = json!({
+            "frame": frame,
+            "time": frame as f64 / f64::from(self.samplerate),
+            "rolling": self.rolling.load(Ordering::Relaxed),
+            "muted": ids(&self.muted),
+            "soloed": ids(&self.soloed),
+        });
+        subscribers.retain(|mut stream| {
+            let ok = writeln!(stream, "{}", state).is_ok();
+            if !ok {
+                // NB: After a timeout, a partial line may have been sent.
+                //     This also ends the client thread.
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+            ok
+        });
+        // NB: Clients may have subscribed in the meantime
+        self.subscribers.lock().unwrap().append(&mut subscribers);
+    }
+}
+
+fn get_f64(message: &Value, key: &str) -> Result<f64, String> {
+    message
+        .get(key)
+        .and_then(Value::as_f64)
+        .ok_or_else(|| format!("{:?} (number) is required", key))
+}
+
+fn get_bool(message: &Value, key: &str) -> Result<bool, String> {
+    message
+        .get(key)
+        .and_then(Value::as_bool)
+        .ok_or_else(|| format!("{:?} (boolean) is required", key))
+}
+
+fn get_str<'a>(message: &'a Value, key: &str) -> Result<&'a str, String> {
+    message
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("{:?} (string) is required", key))
+}
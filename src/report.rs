@@ -0,0 +1,244 @@
+//! Summary of a loaded scene, e.g. for a "pre-flight check" before a show.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::audiofile::{media, signal};
+use crate::Scene;
+
+/// Bytes per sample of decoded audio data (`f32`)
+const BYTES_PER_SAMPLE: usize = 4;
+
+pub struct Report {
+    pub samplerate: u32,
+    pub duration: Duration,
+    pub sources: Vec<SourceReport>,
+    /// All audio files used in the scene (without duplicates)
+    pub files: Vec<PathBuf>,
+    /// Files that don't exist (anymore) at the time the report is created
+    pub missing_files: Vec<PathBuf>,
+    pub markers: usize,
+    pub events: usize,
+    /// Maximum number of simultaneously active clip channels
+    pub peak_active_channels: usize,
+    /// Peak data rate of decoded audio (in bytes per second)
+    pub peak_bandwidth: u64,
+    pub warnings: Vec<String>,
+    // TODO: loudness (requires analyzing the audio files)
+}
+
+pub struct SourceReport {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub clips: usize,
+    /// Total time during which the source is active
+    pub active_duration: Duration,
+}
+
+impl Report {
+    pub(crate) fn new(scene: &Scene) -> Report {
+        let to_duration =
+            |frames: u64| Duration::from_secs_f64(frames as f64 / f64::from(scene.samplerate));
+        let mut warnings = Vec::new();
+        let mut sources = Vec::new();
+        let mut changes = Vec::new();
+        for (idx, source) in scene.sources.iter().enumerate() {
+            let active_frames = source
+                .active_ranges
+                .iter()
+                .map(|&(begin, end)| end - begin)
+                .sum();
+            if source.clips.is_empty() {
+                warnings.push(format!(
+                    "Source {} ({}) has no clips",
+                    idx + 1,
+                    source.id.as_ref().map_or("no ID", String::as_str)
+                ));
+            }
+            // NB: Clips without frames (e.g. missing files in a dry run) are never active
+            for clip in source.clips.iter().filter(|c| c.duration > 0) {
+                changes.push((clip.begin, 1));
+                changes.push((clip.begin + clip.duration, -1));
+            }
+            sources.push(SourceReport {
+                id: source.id.clone(),
+                name: source.name.clone(),
+                model: source.model.clone(),
+                clips: source.clips.len(),
+                active_duration: to_duration(active_frames),
+            });
+        }
+        // NB: Ends are sorted before begins at the same frame
+        changes.sort();
+        let mut active = 0isize;
+        let mut peak_active_channels = 0;
+        for (_, change) in changes {
+            active += change;
+            peak_active_channels = peak_active_channels.max(active as usize);
+        }
+
+        let mut files = scene.clip_files.to_vec();
+        files.sort();
+        files.dedup();
+        // NB: Without a file system, all files have been opened successfully
+        let missing_files: Vec<_> = if scene.open.is_some() {
+            Vec::new()
+        } else {
+            files
+                .iter()
+                .filter(|f| {
+                    !f.exists()
+                        && f.to_str()
+                            .is_none_or(|f| media::find(f).is_none() && !signal::is_location(f))
+                })
+                .cloned()
+                .collect()
+        };
+        for file in &missing_files {
+            warnings.push(format!("File not found: {}", file.display()));
+        }
+        if scene.frames == 0 {
+            warnings.push("The scene has a duration of zero".into());
+        }
+        for marker in scene.markers.iter().filter(|m| m.frame > scene.frames) {
+            warnings.push(format!(
+                "Marker {:?} is after the end of the scene",
+                marker.name
+            ));
+        }
+        let later_events = scene
+            .events
+            .iter()
+            .filter(|e| e.frame > scene.frames)
+            .count();
+        if later_events > 0 {
+            warnings.push(format!(
+                "{} event(s) after the end of the scene",
+                later_events
+            ));
+        }
+
+        Report {
+            samplerate: scene.samplerate,
+            duration: scene.duration(),
+            sources,
+            files,
+            missing_files,
+            markers: scene.markers.len(),
+            events: scene.events.len(),
+            peak_active_channels,
+            peak_bandwidth: (peak_active_channels * BYTES_PER_SAMPLE) as u64
+                * u64::from(scene.samplerate),
+            warnings,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut result = String::new();
+        let w = &mut result;
+        writeln!(w, "# Scene Report\n").unwrap();
+        writeln!(w, "* Duration: {:.3} s", self.duration.as_secs_f64()).unwrap();
+        writeln!(w, "* Sample rate: {} Hz", self.samplerate).unwrap();
+        writeln!(w, "* Markers: {}", self.markers).unwrap();
+        writeln!(w, "* Events: {}", self.events).unwrap();
+        writeln!(
+            w,
+            "* Peak active channels: {} ({:.1} MB/s decoded audio)",
+            self.peak_active_channels,
+            self.peak_bandwidth as f64 / 1e6
+        )
+        .unwrap();
+        writeln!(w, "\n## Sources\n").unwrap();
+        writeln!(w, "| # | ID | Name | Model | Clips | Active time |").unwrap();
+        writeln!(w, "|---|----|------|-------|-------|-------------|").unwrap();
+        for (idx, source) in self.sources.iter().enumerate() {
+            let text = |s: &Option<String>| s.clone().unwrap_or_default().replace('|', "\\|");
+            writeln!(
+                w,
+                "| {} | {} | {} | {} | {} | {:.3} s |",
+                idx + 1,
+                text(&source.id),
+                text(&source.name),
+                text(&source.model),
+                source.clips,
+                source.active_duration.as_secs_f64()
+            )
+            .unwrap();
+        }
+        writeln!(w, "\n## Files\n").unwrap();
+        for file in &self.files {
+            let missing = if self.missing_files.contains(file) {
+                " (missing!)"
+            } else {
+                ""
+            };
+            writeln!(w, "* `{}`{}", file.display(), missing).unwrap();
+        }
+        writeln!(w, "\n## Warnings\n").unwrap();
+        if self.warnings.is_empty() {
+            writeln!(w, "None.").unwrap();
+        }
+        for warning in &self.warnings {
+            writeln!(w, "* {}", warning).unwrap();
+        }
+        result
+    }
+
+    pub fn to_json(&self) -> String {
+        let strings = |items: &mut dyn Iterator<Item = String>| {
+            let items: Vec<_> = items.map(|s| json_string(&s)).collect();
+            format!("[{}]", items.join(", "))
+        };
+        let optional = |s: &Option<String>| s.as_ref().map_or("null".into(), |s| json_string(s));
+        let sources: Vec<_> = self
+            .sources
+            .iter()
+            .map(|source| {
+                format!(
+                    "{{\"id\": {}, \"name\": {}, \"model\": {}, \"clips\": {}, \"active_duration\": {}}}",
+                    optional(&source.id),
+                    optional(&source.name),
+                    optional(&source.model),
+                    source.clips,
+                    source.active_duration.as_secs_f64()
+                )
+            })
+            .collect();
+        let files = |files: &[PathBuf]| strings(&mut files.iter().map(|f| f.display().to_string()));
+        format!(
+            "{{\"samplerate\": {}, \"duration\": {}, \"sources\": [{}], \"files\": {}, \
+             \"missing_files\": {}, \"markers\": {}, \"events\": {}, \
+             \"peak_active_channels\": {}, \"peak_bandwidth\": {}, \"warnings\": {}}}",
+            self.samplerate,
+            self.duration.as_secs_f64(),
+            sources.join(", "),
+            files(&self.files),
+            files(&self.missing_files),
+            self.markers,
+            self.events,
+            self.peak_active_channels,
+            self.peak_bandwidth,
+            strings(&mut self.warnings.iter().cloned())
+        )
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
@@ -0,0 +1,239 @@
+//! Logging of transport actions, seeks, underruns and errors during playback,
+//! see `Scene::enable_session_log()`.
+//!
+//! Each entry has a timestamp (relative to the creation of the log) and the
+//! playback position at that time, which helps to find out afterwards what
+//! happened e.g. "at minute 34" of a performance.
+//! The log can be exported as [JSON Lines](https://jsonlines.org/).
+
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::report::json_string;
+use crate::StreamingError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionEvent {
+    /// Playback was started (`rolling` changed to `true`)
+    Start,
+    /// Playback was stopped (`rolling` changed to `false`)
+    Stop,
+    /// Seeking to the given frame was requested
+    Seek(u64),
+    /// Seeking has finished, playback can continue from the given frame
+    SeekFinished(u64),
+    /// Audio data was not available in time
+    Underrun,
+    /// The source with the given (zero-based) index has an active clip
+    /// but has been silent for a while (see `watchdog` module)
+    Silence(usize),
+    Error(Cow<'static, str>),
+}
+
+impl SessionEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            SessionEvent::Start => "start",
+            SessionEvent::Stop => "stop",
+            SessionEvent::Seek(_) => "seek",
+            SessionEvent::SeekFinished(_) => "seek-finished",
+            SessionEvent::Underrun => "underrun",
+            SessionEvent::Silence(_) => "silence",
+            SessionEvent::Error(_) => "error",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionEntry {
+    /// Time since the log was created
+    /// (always zero on `wasm32-unknown-unknown`, where no clock is available)
+    pub time: Duration,
+    /// Playback position (in frames) when the event happened
+    pub frame: u64,
+    pub event: SessionEvent,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+/// NB: `Instant::now()` panics on `wasm32-unknown-unknown`
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now() -> Option<Instant> {
+    None
+}
+
+pub struct SessionLog {
+    /// `None` if no clock is available, see `now()`
+    start: Option<Instant>,
+    samplerate: u32,
+    /// NB: The capacity is reserved in advance, it never grows
+    entries: Vec<SessionEntry>,
+    /// Number of entries which didn't fit into the log
+    dropped: u64,
+    /// Current playback position (as far as known from seeking and playback)
+    frame: u64,
+    rolling: bool,
+    /// Target frame of a seek operation which hasn't finished yet
+    pending_seek: Option<u64>,
+    /// Total number of underruns reported by the streamer
+    underruns: u64,
+    /// Whether `StreamingError::ReaderDied` has already been logged
+    reader_died: bool,
+}
+
+impl SessionLog {
+    /// Allocates space for (at most) `capacity` entries.
+    pub fn new(samplerate: u32, capacity: usize) -> SessionLog {
+        SessionLog {
+            start: now(),
+            samplerate,
+            entries: Vec::with_capacity(capacity),
+            dropped: 0,
+            frame: 0,
+            rolling: false,
+            pending_seek: None,
+            underruns: 0,
+            reader_died: false,
+        }
+    }
+
+    pub fn entries(&self) -> &[SessionEntry] {
+        &self.entries
+    }
+
+    /// Number of entries which were discarded because the capacity was exhausted.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Removes all entries (but keeps the capacity).
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    /// Adds an entry at the current playback position.
+    ///
+    /// This is realtime-safe (if `event` doesn't contain an allocated string).
+    /// If the capacity is exhausted, the entry is discarded.
+    pub fn log(&mut self, event: SessionEvent) {
+        if self.entries.len() == self.entries.capacity() {
+            self.dropped += 1;
+            return;
+        }
+        self.entries.push(SessionEntry {
+            time: self
+                .start
+                .map_or(Duration::default(), |start| start.elapsed()),
+            frame: self.frame,
+            event,
+        });
+    }
+
+    /// Called by `Scene::reset()`, which clears the underrun counter of the streamer.
+    pub(crate) fn reset(&mut self) {
+        self.underruns = 0;
+    }
+
+    /// Called by `Scene::seek()`.
+    pub(crate) fn seek(&mut self, frame: u64, finished: bool) {
+        // NB: seek() is typically called repeatedly until it succeeds
+        if self.pending_seek != Some(frame) {
+            self.log(SessionEvent::Seek(frame));
+            self.pending_seek = Some(frame);
+        }
+        if finished {
+            self.pending_seek = None;
+            self.frame = frame;
+            self.log(SessionEvent::SeekFinished(frame));
+        }
+    }
+
+    /// Called by `Scene::get_audio_data()` (and friends).
+    pub(crate) fn audio_data(
+        &mut self,
+        frames: u32,
+        rolling: bool,
+        underruns: u64,
+        error: Option<&StreamingError>,
+    ) {
+        if rolling != self.rolling {
+            self.rolling = rolling;
+            self.log(if rolling {
+                SessionEvent::Start
+            } else {
+                SessionEvent::Stop
+            });
+        }
+        if underruns > self.underruns {
+            self.underruns = underruns;
+            self.log(SessionEvent::Underrun);
+        }
+        // NB: Underruns are logged above, the un-recoverable error is
+        //     reported in each call but only logged (and allocated) once
+        match error {
+            Some(error @ StreamingError::ReaderDied(_)) if !self.reader_died => {
+                self.reader_died = true;
+                self.log(SessionEvent::Error(error.to_string().into()));
+            }
+            _ => {}
+        }
+        if rolling {
+            self.frame += u64::from(frames);
+        }
+    }
+
+    /// One JSON object per line, e.g.
+    ///
+    /// ```text
+    /// {"time": 2054.107, "frame": 97920000, "position": 2040, "event": "underrun"}
+    /// ```
+    ///
+    /// `time` and `position` are given in seconds.
+    /// Seek events have an additional `target` (in frames),
+    /// silence events have a (zero-based) `source` index,
+    /// errors have a `message`.
+    pub fn to_json_lines(&self) -> String {
+        let mut result = String::new();
+        for entry in &self.entries {
+            result.push_str(&format!(
+                "{{\"time\": {:.3}, \"frame\": {}, \"position\": {}, \"event\": \"{}\"",
+                entry.time.as_secs_f64(),
+                entry.frame,
+                entry.frame as f64 / f64::from(self.samplerate),
+                entry.event.name()
+            ));
+            match &entry.event {
+                SessionEvent::Seek(target) | SessionEvent::SeekFinished(target) => {
+                    result.push_str(&format!(", \"target\": {}", target));
+                }
+                SessionEvent::Silence(source) => {
+                    result.push_str(&format!(", \"source\": {}", source));
+                }
+                SessionEvent::Error(message) => {
+                    result.push_str(&format!(", \"message\": {}", json_string(message)));
+                }
+                _ => {}
+            }
+            result.push_str("}\n");
+        }
+        if self.dropped > 0 {
+            result.push_str(&format!(
+                "{{\"event\": \"dropped\", \"count\": {}}}\n",
+                self.dropped
+            ));
+        }
+        result
+    }
+
+    /// Writes the log as JSON Lines (see `to_json_lines()`).
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json_lines())
+    }
+}
@@ -0,0 +1,631 @@
+//! Separate handles for the audio thread and for control threads,
+//! see [`Scene::split()`](../struct.Scene.html#method.split).
+//!
+//! A [`SceneAudio`](struct.SceneAudio.html) provides the audio data,
+//! it is meant to be used in the audio thread (and only there).
+//!
+//! A [`SceneControl`](struct.SceneControl.html) provides transform queries,
+//! live inputs and transport control (e.g. for a GUI thread).
+//! It can be shared between any number of threads.
+//! Transport commands are queued and applied at the beginning of the next
+//! call to `SceneAudio::get_audio_data()` (or one of its variants),
+//! the audio thread never waits for a lock.
+//! Changes of the transform settings (e.g. `SceneControl::set_area()`)
+//! wait until no other thread is using the previous settings,
+//! transform queries never wait.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::queue;
+
+use crate::expression::LiveInputs;
+use crate::streamer::{HoldState, StreamingStats};
+use crate::trace::{Tracer, TransformTrace};
+use crate::transform::{Transform, Vec3};
+use crate::{
+    Area, AreaPolicy, Event, FadeShape, Group, LatencyViolations, Position, Scene, Source,
+    StreamingError, TransformModifier, TransformView, TransformerStorage, TransportState,
+};
+
+/// Maximum number of commands between two calls to `get_audio_data()`
+const QUEUE_CAPACITY: usize = 64;
+
+/// Indexed by `TransportState as u8`
+const TRANSPORT_STATES: [TransportState; 5] = [
+    TransportState::Playing,
+    TransportState::FadingOutForSeek,
+    TransportState::Seeking,
+    TransportState::Primed,
+    TransportState::Finished,
+];
+
+enum Command {
+    Seek(u64),
+    Reset,
+    /// Index into `Shared::trigger_ids`
+    Trigger(usize),
+    Mute(usize, bool),
+    Solo(usize, bool),
+    Fade(Duration, FadeShape),
+}
+
+/// State shared between the audio thread and the control threads
+struct Shared {
+    /// NB: The producer is only used from control threads, never in the audio thread
+    producer: Mutex<queue::spsc::Producer<Command>>,
+    frame: AtomicU64,
+    rolling: AtomicBool,
+    /// See `TRANSPORT_STATES`
+    transport_state: AtomicU8,
+    /// Number of seek (and reset) commands sent by control threads
+    seeks_requested: AtomicU64,
+    /// Number of seek (and reset) commands completed by the audio thread
+    seeks_completed: AtomicU64,
+    /// Indexed by source number
+    muted: Box<[AtomicBool]>,
+    /// Indexed by source number
+    soloed: Box<[AtomicBool]>,
+    /// Distinct IDs of `<wait-for-trigger>` elements
+    trigger_ids: Box<[String]>,
+}
+
+impl Shared {
+    /// Returns `false` if the queue is full.
+    fn send(&self, command: Command) -> bool {
+        self.producer.lock().unwrap().push(command).is_ok()
+    }
+}
+
+/// Realtime-safe part of a split scene, see `Scene::split()`.
+pub struct SceneAudio {
+    /// NB: The transformers have been moved to `SceneControl`
+    scene: Scene,
+    shared: Arc<Shared>,
+    commands: queue::spsc::Consumer<Command>,
+    /// Seek which hasn't been completed yet (see `Scene::seek()`)
+    pending_seek: Option<u64>,
+    /// Number of seek (and reset) commands received so far
+    seeks_received: u64,
+}
+
+/// Thread-safe control part of a split scene, see `Scene::split()`.
+pub struct SceneControl {
+    shared: Arc<Shared>,
+    samplerate: u32,
+    frames: u64,
+    sources: Box<[Source]>,
+    groups: Box<[Group]>,
+    transformers: TransformerStorage,
+    transformer_map: HashMap<String, Box<[usize]>>,
+    reference_transform: Transform,
+    settings: SwapCell<Settings>,
+    inputs: LiveInputs,
+    input_map: HashMap<String, usize>,
+    markers: Box<[(String, u64)]>,
+    /// See `TransformView::holds`
+    holds: Arc<HoldState>,
+}
+
+/// Transform settings which can be changed through a shared `SceneControl`
+#[derive(Clone)]
+struct Settings {
+    transform_modifiers: Vec<Arc<dyn TransformModifier>>,
+    /// Indexed like `SceneControl::sources`
+    position_overrides: Box<[Option<Vec3>]>,
+    transform_tracer: Option<Arc<Tracer>>,
+    area: Option<(Area, AreaPolicy)>,
+}
+
+/// Value which can be replaced while other threads are reading it.
+///
+/// Readers never wait, a writer waits until the previous value
+/// isn't used anymore (and drops it).
+/// Readers are counted separately for each "generation" of values,
+/// therefore new readers don't delay a writer.
+struct SwapCell<T> {
+    value: AtomicPtr<T>,
+    /// Least significant bit selects the reader counter for new readers
+    generation: AtomicUsize,
+    readers: [AtomicUsize; 2],
+    /// Only one writer at a time
+    writer: Mutex<()>,
+}
+
+/// Decrements the reader counter (even if the reader panics).
+struct ReadGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ReadGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<T> SwapCell<T> {
+    fn new(value: T) -> SwapCell<T> {
+        SwapCell {
+            value: AtomicPtr::new(Box::into_raw(Box::new(value))),
+            generation: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// This is realtime-safe and lock-free (as long as `f` is).
+    fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let _guard = loop {
+            let generation = self.generation.load(Ordering::SeqCst);
+            let counter = &self.readers[generation & 1];
+            counter.fetch_add(1, Ordering::SeqCst);
+            let guard = ReadGuard(counter);
+            // NB: If a writer has started a new generation in the meantime,
+            //     it might not have seen the increment
+            if self.generation.load(Ordering::SeqCst) == generation {
+                break guard;
+            }
+        };
+        // NB: The value is loaded after the counter has been incremented,
+        //     a writer which replaces it waits for this counter (see `update()`)
+        f(unsafe { &*self.value.load(Ordering::SeqCst) })
+    }
+
+    /// Replaces the value with the result of `f` (which gets the current value).
+    fn update(&self, f: impl FnOnce(&T) -> T) {
+        let _lock = self.writer.lock().unwrap();
+        // NB: Only writers change the value, and they are serialized
+        let new = f(unsafe { &*self.value.load(Ordering::SeqCst) });
+        let old = self
+            .value
+            .swap(Box::into_raw(Box::new(new)), Ordering::SeqCst);
+        // NB: Readers which still got the old value
+        //     have loaded the previous generation
+        let previous = self.generation.fetch_add(1, Ordering::SeqCst) & 1;
+        while self.readers[previous].load(Ordering::SeqCst) != 0 {
+            thread::yield_now();
+        }
+        drop(unsafe { Box::from_raw(old) });
+    }
+}
+
+impl<T> Drop for SwapCell<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(*self.value.get_mut()) });
+    }
+}
+
+// NB: Values are shared between threads and dropped by any writer
+unsafe impl<T: Send + Sync> Send for SwapCell<T> {}
+unsafe impl<T: Send + Sync> Sync for SwapCell<T> {}
+
+impl Scene {
+    /// Splits the scene into a part for the audio thread and a part for
+    /// control threads (e.g. GUI, OSC or network threads), see `split` module.
+    ///
+    /// The output map, transform modifiers, tracing and other settings
+    /// of the scene are kept.
+    /// The current mute and solo states are taken over by the control part.
+    pub fn split(mut self) -> (SceneAudio, SceneControl) {
+        let (producer, consumer) = queue::spsc::new(QUEUE_CAPACITY);
+        let sources = self.sources.len();
+        let mut trigger_ids: Vec<String> = Vec::new();
+        for trigger in self.triggers.iter() {
+            if !trigger_ids.contains(&trigger.id) {
+                trigger_ids.push(trigger.id.clone());
+            }
+        }
+        let shared = Arc::new(Shared {
+            producer: Mutex::new(producer),
+            frame: AtomicU64::new(self.position().frame),
+            rolling: AtomicBool::new(self.position().rolling),
+            transport_state: AtomicU8::new(self.transport_state() as u8),
+            seeks_requested: AtomicU64::new(0),
+            seeks_completed: AtomicU64::new(0),
+            muted: (0..sources)
+                .map(|idx| AtomicBool::new(self.is_source_muted(idx)))
+                .collect(),
+            soloed: (0..sources)
+                .map(|idx| AtomicBool::new(self.is_source_soloed(idx)))
+                .collect(),
+            trigger_ids: trigger_ids.into(),
+        });
+        let control = SceneControl {
+            shared: Arc::clone(&shared),
+            samplerate: self.samplerate,
+            frames: self.frames,
+            holds: Arc::clone(self.streamer.hold_state()),
+            sources: self.sources.clone().into(),
+            groups: self.groups.clone(),
+            transformers: std::mem::take(&mut self.transformers),
+            transformer_map: std::mem::take(&mut self.transformer_map),
+            reference_transform: self.reference_transform.clone(),
+            settings: SwapCell::new(Settings {
+                transform_modifiers: std::mem::take(&mut self.transform_modifiers),
+                position_overrides: self.position_overrides.clone(),
+                transform_tracer: self.transform_tracer.take().map(Arc::new),
+                area: self.area.take(),
+            }),
+            inputs: std::mem::replace(&mut self.inputs, LiveInputs::new(0)),
+            input_map: std::mem::take(&mut self.input_map),
+            markers: self
+                .markers
+                .iter()
+                .map(|m| (m.name.clone(), m.frame))
+                .collect(),
+        };
+        let audio = SceneAudio {
+            scene: self,
+            shared,
+            commands: consumer,
+            pending_seek: None,
+            seeks_received: 0,
+        };
+        (audio, control)
+    }
+}
+
+impl SceneAudio {
+    pub fn samplerate(&self) -> u32 {
+        self.scene.samplerate
+    }
+
+    pub fn blocksize(&self) -> u32 {
+        self.scene.streamer.blocksize()
+    }
+
+    /// Number of target channels expected by `get_audio_data()`,
+    /// see `Scene::output_channels()`.
+    pub fn channels(&self) -> u32 {
+        self.scene.output_channels()
+    }
+
+    /// See `Scene::get_audio_data()`.
+    ///
+    /// Return value of `false` means un-recoverable error
+    #[must_use]
+    pub unsafe fn get_audio_data(&mut self, target: &[*mut f32], rolling: bool) -> bool {
+        self.process_commands();
+        let result = self.scene.get_audio_data(target, rolling);
+        self.publish();
+        result
+    }
+
+    /// See `Scene::get_audio_data_frames()`.
+    ///
+    /// Return value of `false` means un-recoverable error
+    #[must_use]
+    pub unsafe fn get_audio_data_frames(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+    ) -> bool {
+        self.process_commands();
+        let result = self.scene.get_audio_data_frames(target, frames, rolling);
+        self.publish();
+        result
+    }
+
+    /// See `Scene::get_audio_data_with_ramp()`.
+    ///
+    /// Return value of `false` means un-recoverable error
+    #[must_use]
+    pub unsafe fn get_audio_data_with_ramp(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+        offset: u32,
+    ) -> bool {
+        self.process_commands();
+        let result = self
+            .scene
+            .get_audio_data_with_ramp(target, frames, rolling, offset);
+        self.publish();
+        result
+    }
+
+    /// See `Scene::streaming_error()`.
+    pub fn streaming_error(&self) -> Option<&StreamingError> {
+        self.scene.streaming_error()
+    }
+
+    pub fn streaming_stats(&self) -> StreamingStats {
+        self.scene.streaming_stats()
+    }
+
+    /// See `Scene::latency_violations()`.
+    pub fn latency_violations(&self) -> LatencyViolations {
+        self.scene.latency_violations()
+    }
+
+    pub fn position(&self) -> Position {
+        self.scene.position()
+    }
+
+    pub fn transport_state(&self) -> TransportState {
+        self.scene.transport_state()
+    }
+
+    /// See `Scene::get_events()`.
+    pub fn get_events(&self, frame: u64, frames: u32) -> impl Iterator<Item = (u64, &Event)> {
+        self.scene.get_events(frame, frames)
+    }
+
+    fn process_commands(&mut self) {
+        // NB: This function is supposed to be realtime-safe!
+        while let Ok(command) = self.commands.pop() {
+            match command {
+                Command::Seek(frame) => {
+                    self.seeks_received += 1;
+                    self.pending_seek = Some(frame);
+                }
+                Command::Reset => {
+                    self.seeks_received += 1;
+                    self.pending_seek = if self.scene.reset() { None } else { Some(0) };
+                }
+                Command::Trigger(idx) => {
+                    self.scene.trigger(&self.shared.trigger_ids[idx]);
+                }
+                Command::Mute(idx, mute) => self.scene.set_source_mute(idx, mute),
+                Command::Solo(idx, solo) => self.scene.set_source_solo(idx, solo),
+                Command::Fade(fade, shape) => self.scene.set_fade(fade, shape),
+            }
+        }
+        if let Some(frame) = self.pending_seek {
+            if self.scene.seek(frame) {
+                self.pending_seek = None;
+            }
+        }
+    }
+
+    fn publish(&self) {
+        let shared = &self.shared;
+        let position = self.scene.position();
+        shared.frame.store(position.frame, Ordering::Relaxed);
+        shared.rolling.store(position.rolling, Ordering::Relaxed);
+        shared
+            .transport_state
+            .store(self.scene.transport_state() as u8, Ordering::Relaxed);
+        if self.pending_seek.is_none() {
+            shared
+                .seeks_completed
+                .store(self.seeks_received, Ordering::Release);
+        }
+    }
+}
+
+impl SceneControl {
+    pub fn samplerate(&self) -> u32 {
+        self.samplerate
+    }
+
+    /// See `Scene::frames()`.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn file_sources(&self) -> u32 {
+        self.sources.len() as u32
+    }
+
+    pub fn get_source_id(&self, index: usize) -> Option<&String> {
+        self.sources[index].id.as_ref()
+    }
+
+    /// See `Scene::get_source_transform()`.
+    ///
+    /// This is realtime-safe and lock-free.
+    pub fn get_source_transform(&self, source_idx: usize, frame: u64) -> Option<Transform> {
+        self.with_transform_view(|view| view.get_source_transform(source_idx, frame))
+    }
+
+    /// Writes the transforms of all sources at `frame` into `result`
+    /// (which must have one element per source).
+    ///
+    /// This is realtime-safe and lock-free.
+    pub fn get_source_transforms(&self, result: &mut [Option<Transform>], frame: u64) {
+        assert_eq!(result.len(), self.sources.len());
+        self.with_transform_view(|view| view.get_source_transforms(result, frame));
+    }
+
+    /// See `Scene::get_reference_transform()`.
+    ///
+    /// This is realtime-safe and lock-free.
+    pub fn get_reference_transform(&self, frame: u64) -> Option<Transform> {
+        self.with_transform_view(|view| view.get_reference_transform(frame))
+    }
+
+    /// See `Scene::set_source_override()`.
+    ///
+    /// This and the following settings may be changed from any thread,
+    /// but they are not realtime-safe.
+    pub fn set_source_override(&self, source_idx: usize, position: Option<[f32; 3]>) {
+        assert!(source_idx < self.sources.len());
+        self.settings.update(|settings| {
+            let mut settings = settings.clone();
+            settings.position_overrides[source_idx] = position.map(Vec3::from);
+            settings
+        });
+    }
+
+    /// See `Scene::set_area()`.
+    pub fn set_area(&self, area: Option<Area>, policy: AreaPolicy) {
+        self.settings.update(|settings| Settings {
+            area: area.map(|area| (area, policy)),
+            ..settings.clone()
+        });
+    }
+
+    /// See `Scene::add_transform_modifier()`.
+    pub fn add_transform_modifier(&self, modifier: Box<dyn TransformModifier>) {
+        self.settings.update(|settings| {
+            let mut settings = settings.clone();
+            settings.transform_modifiers.push(modifier.into());
+            settings
+        });
+    }
+
+    pub fn clear_transform_modifiers(&self) {
+        self.settings.update(|settings| Settings {
+            transform_modifiers: Vec::new(),
+            ..settings.clone()
+        });
+    }
+
+    /// See `Scene::enable_transform_trace()`.
+    pub fn enable_transform_trace(&self, capacity: usize) {
+        self.settings.update(|settings| Settings {
+            transform_tracer: Some(Arc::new(Tracer::new(capacity))),
+            ..settings.clone()
+        });
+    }
+
+    pub fn transform_trace(&self) -> Option<TransformTrace> {
+        self.settings
+            .read(|settings| settings.transform_tracer.as_deref().map(Tracer::snapshot))
+    }
+
+    /// Names of all live inputs used in the scene.
+    pub fn input_names(&self) -> impl Iterator<Item = &String> {
+        self.input_map.keys()
+    }
+
+    /// See `Scene::set_input()`.
+    pub fn set_input(&self, name: &str, value: f32) -> bool {
+        if let Some(&idx) = self.input_map.get(name) {
+            self.inputs.set(idx, value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Requests a seek, which is completed by the audio thread
+    /// (see `Scene::seek()`), `is_seeking()` can be used to check for that.
+    ///
+    /// Like with `Scene::seek()`, playback has to be stopped
+    /// (i.e. `rolling` has to be `false`) for the seek to complete.
+    ///
+    /// Returns `false` if too many commands are pending.
+    pub fn seek(&self, frame: u64) -> bool {
+        self.send_seek(Command::Seek(frame))
+    }
+
+    /// Seeks to the first marker with the given name (see `seek()`).
+    /// Returns `None` if there is no such marker.
+    pub fn seek_to_marker(&self, name: &str) -> Option<bool> {
+        let &(_, frame) = self.markers.iter().find(|(n, _)| n == name)?;
+        Some(self.seek(frame))
+    }
+
+    /// Requests `Scene::reset()`, see `seek()`.
+    pub fn reset(&self) -> bool {
+        self.send_seek(Command::Reset)
+    }
+
+    /// Whether a seek (or reset) has been requested but not yet completed.
+    pub fn is_seeking(&self) -> bool {
+        let shared = &self.shared;
+        // NB: The number of completed seeks is loaded first
+        let completed = shared.seeks_completed.load(Ordering::Acquire);
+        completed < shared.seeks_requested.load(Ordering::Acquire)
+    }
+
+    fn send_seek(&self, command: Command) -> bool {
+        let producer = self.shared.producer.lock().unwrap();
+        if producer.push(command).is_err() {
+            return false;
+        }
+        // NB: The counter is only incremented while the producer is locked
+        self.shared.seeks_requested.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+
+    /// Requests `Scene::trigger()`.
+    ///
+    /// Returns `false` if there is no `<wait-for-trigger>` element
+    /// with the given `id` or if too many commands are pending.
+    pub fn trigger(&self, id: &str) -> bool {
+        match self.shared.trigger_ids.iter().position(|i| i == id) {
+            Some(idx) => self.shared.send(Command::Trigger(idx)),
+            None => false,
+        }
+    }
+
+    /// Requests `Scene::set_source_mute()`.
+    ///
+    /// Returns `false` if too many commands are pending.
+    /// Panics if `source_idx` is out of range.
+    pub fn set_source_mute(&self, source_idx: usize, mute: bool) -> bool {
+        assert!(source_idx < self.sources.len());
+        let sent = self.shared.send(Command::Mute(source_idx, mute));
+        if sent {
+            self.shared.muted[source_idx].store(mute, Ordering::Relaxed);
+        }
+        sent
+    }
+
+    /// Requested mute state (which might not yet have reached the audio thread).
+    /// Panics if `source_idx` is out of range.
+    pub fn is_source_muted(&self, source_idx: usize) -> bool {
+        self.shared.muted[source_idx].load(Ordering::Relaxed)
+    }
+
+    /// Requests `Scene::set_source_solo()`, see `set_source_mute()`.
+    pub fn set_source_solo(&self, source_idx: usize, solo: bool) -> bool {
+        assert!(source_idx < self.sources.len());
+        let sent = self.shared.send(Command::Solo(source_idx, solo));
+        if sent {
+            self.shared.soloed[source_idx].store(solo, Ordering::Relaxed);
+        }
+        sent
+    }
+
+    /// Requested solo state (which might not yet have reached the audio thread).
+    /// Panics if `source_idx` is out of range.
+    pub fn is_source_soloed(&self, source_idx: usize) -> bool {
+        self.shared.soloed[source_idx].load(Ordering::Relaxed)
+    }
+
+    /// Requests `Scene::set_fade()`.
+    ///
+    /// Returns `false` if too many commands are pending.
+    pub fn set_fade(&self, fade: Duration, shape: FadeShape) -> bool {
+        self.shared.send(Command::Fade(fade, shape))
+    }
+
+    /// Playback position after the most recent call to `SceneAudio::get_audio_data()`.
+    pub fn position(&self) -> Position {
+        Position {
+            frame: self.shared.frame.load(Ordering::Relaxed),
+            rolling: self.shared.rolling.load(Ordering::Relaxed),
+        }
+    }
+
+    /// State of playback after the most recent call to
+    /// `SceneAudio::get_audio_data()`.
+    pub fn transport_state(&self) -> TransportState {
+        TRANSPORT_STATES[self.shared.transport_state.load(Ordering::Relaxed) as usize]
+    }
+
+    fn with_transform_view<R>(&self, f: impl FnOnce(&TransformView) -> R) -> R {
+        self.settings.read(|settings| {
+            f(&TransformView {
+                sources: &self.sources,
+                groups: &self.groups,
+                transformers: &self.transformers,
+                transformer_map: &self.transformer_map,
+                reference_transform: &self.reference_transform,
+                transform_modifiers: &settings.transform_modifiers,
+                position_overrides: &settings.position_overrides,
+                inputs: &self.inputs,
+                transform_tracer: settings.transform_tracer.as_deref(),
+                area: settings.area.as_ref(),
+                holds: &self.holds,
+            })
+        })
+    }
+}
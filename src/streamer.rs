@@ -1,35 +1,75 @@
 use std::error::Error;
+use std::fmt;
+#[cfg(feature = "threads")]
+use std::sync::mpsc;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
     Arc,
 };
 use std::thread;
 use std::time::Duration;
+#[cfg(feature = "threads")]
+use std::time::Instant;
 
 use crossbeam::queue;
 
 use crate::parser::{FileStorage, PlaylistEntry};
 
-enum Fade {
-    In,
-    Out,
-    None,
+/// Ratio between suggested buffer size and measured maximum buffer depletion
+const BUFFER_SAFETY_FACTOR: u32 = 2;
+
+/// Length (in frames) of the gain ramp when muting/unmuting a channel
+const MUTE_RAMP_LENGTH: u32 = 256;
+
+/// Maximum number of scheduled seeks not yet seen by the reader thread
+const CUE_QUEUE_CAPACITY: usize = 4;
+
+/// Shape of the fade-in and fade-out when starting and stopping playback.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FadeShape {
+    Linear,
+    RaisedCosine,
+}
+
+impl Default for FadeShape {
+    fn default() -> FadeShape {
+        FadeShape::Linear
+    }
+}
+
+impl FadeShape {
+    /// `position` goes from 0 (silent) to `length` (full volume)
+    pub(crate) fn gain(self, position: u32, length: u32) -> f32 {
+        let x = position as f32 / length as f32;
+        match self {
+            FadeShape::Linear => x,
+            FadeShape::RaisedCosine => 0.5 - 0.5 * (std::f32::consts::PI * x).cos(),
+        }
+    }
 }
 
 struct Block {
     channels: Box<[Box<[f32]>]>,
+    /// Delay of each hold (see `HoldTimeline`) at the time the block was read
+    delays: Box<[u64]>,
+    /// Whether each hold was waiting at the time the block was read
+    waiting: Box<[bool]>,
 }
 
 impl Block {
-    fn new(frames: u32, channels: u32) -> Block {
+    fn new(frames: u32, channels: u32, holds: usize) -> Block {
         Block {
             channels: (0..channels)
                 .map(|_| (0..frames).map(|_| 0.0f32).collect())
                 .collect(),
+            delays: vec![0; holds].into(),
+            waiting: vec![false; holds].into(),
         }
     }
 }
 
+#[cfg(feature = "threads")]
 struct DataProducer {
     data_producer: queue::spsc::Producer<Block>,
     recycling_consumer: queue::spsc::Consumer<Block>,
@@ -37,16 +77,28 @@ struct DataProducer {
 
 struct DataConsumer {
     blocksize: u32,
+    /// The hold state of each block is published when it is consumed
+    holds: Arc<HoldState>,
     data_consumer: queue::spsc::Consumer<Block>,
     recycling_producer: queue::spsc::Producer<Block>,
+    /// Partially consumed block (if the host requests fewer frames than `blocksize`)
+    current_block: Option<Block>,
+    /// Number of frames of `current_block` that have already been consumed
+    current_offset: u32,
 }
 
-fn make_data_queue(capacity: usize, blocksize: u32, channels: u32) -> (DataProducer, DataConsumer) {
+#[cfg(feature = "threads")]
+fn make_data_queue(
+    capacity: usize,
+    blocksize: u32,
+    channels: u32,
+    holds: &Arc<HoldState>,
+) -> (DataProducer, DataConsumer) {
     let (data_producer, data_consumer) = queue::spsc::new(capacity);
     let (recycling_producer, recycling_consumer) = queue::spsc::new(capacity);
     for _ in 0..capacity {
         recycling_producer
-            .push(Block::new(blocksize, channels))
+            .push(Block::new(blocksize, channels, holds.len()))
             .unwrap();
     }
     (
@@ -56,18 +108,23 @@ fn make_data_queue(capacity: usize, blocksize: u32, channels: u32) -> (DataProdu
         },
         DataConsumer {
             blocksize,
+            holds: Arc::clone(holds),
             data_consumer,
             recycling_producer,
+            current_block: None,
+            current_offset: 0,
         },
     )
 }
 
+#[cfg(feature = "threads")]
 struct WriteBlock<'b> {
     // NB: Option in order to be able to move Block in drop()
     block: Option<Block>,
     queue: &'b mut queue::spsc::Producer<Block>,
 }
 
+#[cfg(feature = "threads")]
 impl<'b> Drop for WriteBlock<'b> {
     fn drop(&mut self) {
         if let Some(block) = self.block.take() {
@@ -76,12 +133,20 @@ impl<'b> Drop for WriteBlock<'b> {
     }
 }
 
+#[cfg(feature = "threads")]
 impl<'b> WriteBlock<'b> {
     fn channels(&mut self) -> &mut [Box<[f32]>] {
         &mut self.block.as_mut().unwrap().channels
     }
+
+    fn set_holds(&mut self, timeline: &HoldTimeline) {
+        let block = self.block.as_mut().unwrap();
+        block.delays.copy_from_slice(&timeline.delays);
+        block.waiting.copy_from_slice(&timeline.waiting);
+    }
 }
 
+#[cfg(feature = "threads")]
 impl DataProducer {
     fn write_block(&mut self) -> Option<WriteBlock> {
         let mut block = match self.recycling_consumer.pop() {
@@ -103,155 +168,1140 @@ impl DataProducer {
 }
 
 impl DataConsumer {
+    #[cfg(feature = "threads")]
     fn clear(&mut self) {
+        if let Some(block) = self.current_block.take() {
+            self.recycling_producer.push(block).unwrap()
+        }
+        self.current_offset = 0;
         while let Ok(data) = self.data_consumer.pop() {
             self.recycling_producer.push(data).unwrap()
         }
     }
 
-    /// Return value of `false` means un-recoverable error (but output buffer is still filled)
+    /// Number of frames that can be obtained without underrun.
+    fn frames_available(&self) -> usize {
+        let partial = match self.current_block {
+            Some(_) => (self.blocksize - self.current_offset) as usize,
+            None => 0,
+        };
+        partial + self.data_consumer.len() * self.blocksize as usize
+    }
+
+    /// Writes `frames` frames (which don't have to be a multiple of `blocksize`),
+    /// starting at frame `start` of `target`.
+    ///
+    /// Returns the number of written frames, less than `frames` means
+    /// un-recoverable error (but output buffer is still filled)
     #[must_use]
-    unsafe fn write_channel_ptrs(&mut self, target: &[*mut f32], fade: Fade) -> bool {
-        if let Ok(block) = self.data_consumer.pop() {
-            for (source, &target) in block.channels.iter().zip(target) {
-                match fade {
-                    Fade::In => {
-                        for i in 0..self.blocksize {
-                            *target.add(i as usize) =
-                                source[i as usize] * (i + 1) as f32 / self.blocksize as f32;
-                        }
+    unsafe fn write_channel_ptrs(&mut self, target: &[*mut f32], start: u32, frames: u32) -> u32 {
+        let mut written = 0;
+        while written < frames {
+            let block = match self.current_block {
+                Some(ref block) => block,
+                None => match self.data_consumer.pop() {
+                    Ok(block) => {
+                        self.current_offset = 0;
+                        self.holds.publish(&block.delays, &block.waiting);
+                        self.current_block.get_or_insert(block)
                     }
-                    Fade::Out => {
-                        for i in 0..self.blocksize {
-                            *target.add(i as usize) = source[i as usize]
-                                * (self.blocksize - i) as f32
-                                / self.blocksize as f32;
+                    Err(_) => {
+                        for ptr in target {
+                            for f in written..frames {
+                                *ptr.add((start + f) as usize) = 0.0f32;
+                            }
                         }
+                        return written;
                     }
-                    Fade::None => {
-                        let target =
-                            std::slice::from_raw_parts_mut(target, self.blocksize as usize);
-                        target.copy_from_slice(source);
-                    }
-                }
+                },
+            };
+            let chunk = (self.blocksize - self.current_offset).min(frames - written);
+            for (source, &target) in block.channels.iter().zip(target) {
+                let source = &source[self.current_offset as usize..][..chunk as usize];
+                let target = std::slice::from_raw_parts_mut(
+                    target.add((start + written) as usize),
+                    chunk as usize,
+                );
+                target.copy_from_slice(source);
+            }
+            written += chunk;
+            self.current_offset += chunk;
+            if self.current_offset == self.blocksize {
+                let block = self.current_block.take().unwrap();
+                self.recycling_producer.push(block).unwrap();
             }
-            self.recycling_producer.push(block).unwrap();
-            true
-        } else {
-            fill_with_zeros(target, self.blocksize);
-            false
         }
+        written
     }
 }
 
+/// Statistics about streaming audio data from disk,
+/// see [`Scene::streaming_stats()`](struct.Scene.html#method.streaming_stats).
+#[derive(Clone, Debug, Default)]
+pub struct StreamingStats {
+    /// Current size of the buffer (in blocks)
+    pub buffer_blocks: u32,
+    /// Buffer size (in blocks) based on the measured refill performance of the
+    /// reader thread, `None` if nothing has been measured yet (i.e. before playback)
+    pub suggested_buffer_blocks: Option<u32>,
+    /// Number of frames currently available in the buffer
+    pub buffered_frames: u64,
+    /// Number of buffer underruns since the scene was loaded (or reset)
+    pub underruns: u64,
+    /// Worst-case time the reader thread needed for reading one block
+    pub max_block_read_time: Duration,
+}
+
+/// Measurements and settings shared between the reader thread and `FileStreamer`
+#[derive(Default)]
+struct SharedStats {
+    /// Current capacity of the data queue (in blocks)
+    buffer_blocks: AtomicU32,
+    /// Maximum number of blocks consumed between two refills of the data queue
+    max_depletion: AtomicU32,
+    /// Upper limit for automatically growing the buffer (0 means no growth)
+    max_buffer_blocks: AtomicU32,
+    /// Worst-case time needed for reading one block (in microseconds)
+    max_block_read_time: AtomicU64,
+}
+
+impl SharedStats {
+    fn suggested_buffer_blocks(&self) -> Option<u32> {
+        match self.max_depletion.load(Ordering::Relaxed) {
+            0 => None,
+            depletion => Some(depletion * BUFFER_SAFETY_FACTOR),
+        }
+    }
+
+    /// New buffer size, if the buffer should be grown
+    #[cfg(feature = "threads")]
+    fn grown_buffer_blocks(&self) -> Option<u32> {
+        let max = self.max_buffer_blocks.load(Ordering::Relaxed);
+        let current = self.buffer_blocks.load(Ordering::Relaxed);
+        self.suggested_buffer_blocks()
+            .map(|suggested| suggested.min(max))
+            .filter(|&new| new > current)
+    }
+}
+
+/// Streams the audio files of a playlist, see the `streaming` module.
+///
+/// Created with `StreamerBuilder::build()`.
 pub struct FileStreamer {
     ready_consumer: queue::spsc::Consumer<(u64, DataConsumer)>,
     seek_producer: queue::spsc::Producer<(u64, DataConsumer)>,
     data_consumer: Option<DataConsumer>,
     reader_thread: Option<thread::JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>>,
     reader_thread_keep_reading: Arc<AtomicBool>,
+    /// Set once the reader thread has stopped unexpectedly
+    /// (NB: cloning doesn't allocate, see `get_data()`)
+    reader_error: Option<Arc<str>>,
+    /// Only used in single-threaded mode (i.e. without `reader_thread`)
+    pull_reader: Option<PullReader>,
+    /// Without audio files, nothing is read and `get_data()` provides silence
+    has_files: bool,
+    stats: Arc<SharedStats>,
+    /// Number of buffer underruns in `get_data()`
+    underruns: u64,
     channels: u32,
     blocksize: u32,
-    previously_rolling: bool,
+    /// Length of fade-in and fade-out (in frames, at least 1)
+    fade_length: u32,
+    fade_shape: FadeShape,
+    /// Current position within the fade, from 0 (stopped) to `fade_length` (rolling)
+    fade_position: u32,
     seek_frame: Option<u64>,
+    /// Frame number of the data which is delivered next by `get_data()`
+    next_frame: u64,
+    /// Value of `rolling` in the previous call to `get_data()`
+    rolling: bool,
+    /// See `position()`
+    position: Position,
+    /// See `set_mute()`
+    muted: Box<[bool]>,
+    /// See `set_solo()`
+    soloed: Box<[bool]>,
+    /// See `set_gain()`
+    levels: Box<[f32]>,
+    /// See `set_gain_at()`: new level and its offset (in frames) counted from
+    /// the beginning of the next call to `get_data()`
+    pending_levels: Box<[Option<(u32, f32)>]>,
+    /// Current gain of each channel, ramped between 0 (muted) and its level
+    channel_gains: Box<[f32]>,
+    /// See `StreamerBuilder::holds()`
+    holds: Arc<HoldState>,
+    /// Only used without audio files (otherwise the reader keeps track of the holds)
+    hold_timeline: HoldTimeline,
+    /// Scheduled seeks (with seek generation, `at` and `target`)
+    cue_producer: queue::spsc::Producer<(u64, u64, u64)>,
+    /// Answers of the reader thread (with seek generation, `at` and whether it was accepted)
+    cue_result_consumer: queue::spsc::Consumer<(u64, u64, bool)>,
+    /// Number of seeks sent to the reader thread
+    seek_generation: u64,
+    /// See `seek_at()`
+    scheduled_seek: Option<ScheduledSeek>,
+    /// Target frame of a scheduled seek which has taken place in the most
+    /// recent call to `get_data()`
+    executed_seek: Option<u64>,
+}
+
+/// See [`Scene::transport_state()`](struct.Scene.html#method.transport_state).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportState {
+    /// Audio data is delivered
+    /// (this includes the fade-out after `rolling` was set to `false`)
+    Playing,
+    /// A seek was requested before the fade-out was finished,
+    /// `seek()` has to be called again after the fade-out
+    FadingOutForSeek,
+    /// Waiting for audio data at the new position,
+    /// `seek()` has to be called again until it returns `true`
+    Seeking,
+    /// Stopped and ready to play from `Scene::position()`
+    Primed,
+    /// Stopped at (or after) the end of the scene
+    Finished,
+}
+
+/// Playback position, see [`Scene::position()`](struct.Scene.html#method.position).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    /// While rolling: frame number of the first frame delivered in the most
+    /// recent call to `get_audio_data()`.
+    /// While stopped: frame number where playback will continue.
+    pub frame: u64,
+    /// Whether audio data was delivered in the most recent call
+    /// (this includes the fade-out after stopping)
+    pub rolling: bool,
+}
+
+/// State of a seek scheduled with
+/// [`Scene::seek_at()`](struct.Scene.html#method.seek_at).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduledSeekState {
+    /// Waiting for the reader thread to confirm that it can prepare the seek
+    Pending,
+    /// The seek will take place exactly at the scheduled frame
+    Armed,
+    /// The seek has taken place
+    Done,
+    /// The reader thread had already read past the scheduled frame,
+    /// playback continued without seeking
+    Missed,
+}
+
+/// See [`Scene::scheduled_seek()`](struct.Scene.html#method.scheduled_seek).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduledSeek {
+    /// Frame at which the seek takes place
+    pub at: u64,
+    /// Frame which is played instead of `at`
+    pub target: u64,
+    pub state: ScheduledSeekState,
+}
+
+impl ScheduledSeek {
+    fn is_active(&self) -> bool {
+        matches!(
+            self.state,
+            ScheduledSeekState::Pending | ScheduledSeekState::Armed
+        )
+    }
+}
+
+/// Reason for `get_data()` not delivering (all) the requested audio data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StreamingError {
+    /// Not enough data was available (the missing frames are filled with zeros)
+    Underrun,
+    /// The reader thread has stopped because of an error (given as message),
+    /// this is un-recoverable
+    ReaderDied(Arc<str>),
+}
+
+impl fmt::Display for StreamingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamingError::Underrun => write!(f, "Buffer underrun"),
+            StreamingError::ReaderDied(msg) => write!(f, "Reader thread died: {}", msg),
+        }
+    }
+}
+
+impl Error for StreamingError {}
+
+/// A position in the playlist where (some of) the following entries wait
+/// until the hold is released, see `StreamerBuilder::holds()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hold {
+    /// In frames (not including the delay of `parent`)
+    pub frame: u64,
+    /// Index of the hold which delays this hold (if any),
+    /// it has to come before this hold
+    pub parent: Option<usize>,
+}
+
+/// State of all holds, shared between the reader, the audio thread
+/// and transform queries (which may happen in other threads).
+pub(crate) struct HoldState {
+    holds: Box<[Hold]>,
+    /// See `FileStreamer::release_hold()`, cleared when seeking
+    released: Box<[AtomicBool]>,
+    /// Delay of each hold (including its parents) in the most recently
+    /// delivered block (in frames)
+    delays: Box<[AtomicU64]>,
+    /// Whether each hold was waiting in the most recently delivered block
+    waiting: Box<[AtomicBool]>,
+    /// Holds are ignored (i.e. treated as released) while this is `false`
+    enabled: AtomicBool,
+}
+
+impl HoldState {
+    fn new(holds: Vec<Hold>) -> HoldState {
+        HoldState {
+            released: holds.iter().map(|_| AtomicBool::new(false)).collect(),
+            delays: holds.iter().map(|_| AtomicU64::new(0)).collect(),
+            waiting: holds.iter().map(|_| AtomicBool::new(false)).collect(),
+            holds: holds.into(),
+            enabled: AtomicBool::new(true),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.holds.len()
+    }
+
+    /// Delay (in frames) of the playlist entries (and transforms and events)
+    /// which belong to the given hold, `None` is never delayed.
+    pub(crate) fn delay(&self, hold: Option<usize>) -> u64 {
+        hold.map_or(0, |hold| self.delays[hold].load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn is_waiting(&self, hold: usize) -> bool {
+        self.waiting[hold].load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn is_released(&self, hold: usize) -> bool {
+        self.released[hold].load(Ordering::Acquire)
+    }
+
+    fn publish(&self, delays: &[u64], waiting: &[bool]) {
+        for (target, &delay) in self.delays.iter().zip(delays) {
+            target.store(delay, Ordering::Relaxed);
+        }
+        for (target, &waiting) in self.waiting.iter().zip(waiting) {
+            target.store(waiting, Ordering::Relaxed);
+        }
+    }
+
+    /// After seeking, nothing is delayed (until the next block is delivered).
+    fn clear_delays(&self) {
+        for delay in self.delays.iter() {
+            delay.store(0, Ordering::Relaxed);
+        }
+        for waiting in self.waiting.iter() {
+            waiting.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// After seeking, all holds have to be released again.
+    fn clear_releases(&self) {
+        for released in self.released.iter() {
+            released.store(false, Ordering::Release);
+        }
+    }
 }
 
-struct ActiveIter<'a> {
-    block_start: u64,
-    block_end: u64,
-    inner: std::slice::IterMut<'a, PlaylistEntry>,
+/// Ignores all holds as long as it is alive, see `FileStreamer::suspend_holds()`.
+pub(crate) struct SuspendedHolds(Arc<HoldState>);
+
+impl Drop for SuspendedHolds {
+    fn drop(&mut self) {
+        self.0.enabled.store(true, Ordering::Relaxed);
+    }
 }
 
-impl<'a> Iterator for ActiveIter<'a> {
-    type Item = &'a mut PlaylistEntry;
+/// Delays of all holds as seen by the reader, updated once per block.
+///
+/// While a hold is waiting, its delay grows with each block,
+/// the delay of its children includes its own delay.
+struct HoldTimeline {
+    state: Arc<HoldState>,
+    /// Time spent waiting at each hold (in frames)
+    own: Box<[u64]>,
+    /// Sum of `own` of each hold and all its parents
+    delays: Box<[u64]>,
+    waiting: Box<[bool]>,
+    /// Holds before the most recent seek (or jump) target are not waited for
+    passed: Box<[bool]>,
+}
 
-    fn next(&mut self) -> Option<&'a mut PlaylistEntry> {
-        while let Some(entry) = self.inner.next() {
-            if entry.begin < self.block_end && self.block_start < (entry.begin + entry.duration) {
-                return Some(entry);
+impl HoldTimeline {
+    fn new(state: Arc<HoldState>) -> HoldTimeline {
+        let holds = state.len();
+        HoldTimeline {
+            state,
+            own: vec![0; holds].into(),
+            delays: vec![0; holds].into(),
+            waiting: vec![false; holds].into(),
+            passed: vec![false; holds].into(),
+        }
+    }
+
+    /// Starts from scratch at `frame` (after seeking or jumping).
+    fn reset(&mut self, frame: u64) {
+        for (idx, hold) in self.state.holds.iter().enumerate() {
+            self.own[idx] = 0;
+            self.delays[idx] = 0;
+            self.waiting[idx] = false;
+            self.passed[idx] = hold.frame < frame;
+        }
+    }
+
+    /// Updates the delays for the block from `frame` to `frame + frames`.
+    ///
+    /// A hold which is reached within the block (and not released) waits
+    /// exactly at its frame, its delay grows until it is released.
+    fn advance(&mut self, frame: u64, frames: u32) {
+        let end = frame + u64::from(frames);
+        let enabled = self.state.enabled.load(Ordering::Relaxed);
+        for (idx, hold) in self.state.holds.iter().enumerate() {
+            let parent_delay = hold.parent.map_or(0, |parent| self.delays[parent]);
+            let released = !enabled || self.passed[idx] || self.state.is_released(idx);
+            // NB: End of the block in the (delayed) time of the parent
+            let position = end.saturating_sub(parent_delay);
+            self.waiting[idx] = !released && position > hold.frame + self.own[idx];
+            if self.waiting[idx] {
+                self.own[idx] = position - hold.frame;
             }
+            self.delays[idx] = parent_delay + self.own[idx];
         }
-        None
+    }
+
+    fn delay(&self, hold: Option<usize>) -> u64 {
+        hold.map_or(0, |hold| self.delays[hold])
+    }
+
+    fn publish(&self) {
+        self.state.publish(&self.delays, &self.waiting);
     }
 }
 
-impl FileStreamer {
-    pub fn new(
-        mut playlist: Vec<PlaylistEntry>,
-        mut file_storage: FileStorage,
-        blocksize: u32,
-        channels: u32,
-        buffer_blocks: u32,
-        sleeptime: Duration,
-    ) -> FileStreamer {
-        let (ready_producer, ready_consumer) = queue::spsc::new(1);
-        let (seek_producer, seek_consumer) = queue::spsc::new::<(u64, DataConsumer)>(1);
-        let (mut data_producer, data_consumer) =
-            make_data_queue(buffer_blocks as usize, blocksize, channels);
-        let reader_thread_keep_reading = Arc::new(AtomicBool::new(true));
-        let keep_reading = Arc::clone(&reader_thread_keep_reading);
-        let reader_thread = thread::spawn(move || -> Result<(), Box<dyn Error + Send + Sync>> {
-            let mut data_consumer = Some(data_consumer);
-            let mut current_frame = 0;
-            let mut seek_frame = 0;
+/// Implicit augmented interval tree for finding active playlist entries,
+/// see https://github.com/lh3/cgranges.
+///
+/// The entries are sorted by begin time and stored in an array,
+/// which is interpreted as an (in-order) binary tree.
+struct PlaylistIndex {
+    entries: Box<[PlaylistEntry]>,
+    /// Maximum end time of all entries in the subtree rooted at each entry
+    max_end: Box<[u64]>,
+    /// Level of the root node
+    max_level: u32,
+    /// Re-used in `find_active()` to avoid re-allocations
+    stack: Vec<(u32, usize, bool)>,
+}
 
-            while keep_reading.load(Ordering::Acquire) {
-                if let Ok((frame, mut queue)) = seek_consumer.pop() {
-                    queue.clear();
-                    data_consumer = Some(queue);
-                    current_frame = frame;
-                    seek_frame = frame;
+impl PlaylistIndex {
+    fn new(mut entries: Vec<PlaylistEntry>) -> PlaylistIndex {
+        entries.sort_by_key(|entry| entry.begin);
+        let n = entries.len();
+        let mut max_end: Vec<u64> = entries
+            .iter()
+            .map(|entry| entry.begin + entry.duration)
+            .collect();
+        let mut max_level = 0;
+        if n > 0 {
+            // Leaf nodes (even indices) don't need to be changed
+            let mut last_idx = (n - 1) & !1;
+            let mut last = max_end[last_idx];
+            let mut level = 1;
+            while 1 << level <= n {
+                let x = 1 << (level - 1);
+                let mut idx = (x << 1) - 1;
+                while idx < n {
+                    let left = max_end[idx - x];
+                    // NB: The right child might not exist
+                    let right = if idx + x < n { max_end[idx + x] } else { last };
+                    max_end[idx] = max_end[idx].max(left).max(right);
+                    idx += x << 2;
                 }
-                let mut block = match data_producer.write_block() {
-                    Some(block) => block,
-                    None => {
-                        thread::sleep(sleeptime);
-                        continue;
-                    }
+                last_idx = if (last_idx >> level) & 1 == 1 {
+                    last_idx - x
+                } else {
+                    last_idx + x
                 };
-                let mut active_files = ActiveIter {
-                    block_start: current_frame,
-                    block_end: current_frame + u64::from(blocksize),
-                    inner: playlist.iter_mut(),
-                };
-                // TODO: Is linear search too slow? How long can playlists be?
-                for entry in &mut active_files {
-                    let (file, channel_map) = &mut file_storage[entry.idx];
-                    let offset = if entry.begin < current_frame {
-                        if current_frame == seek_frame {
-                            file.seek(current_frame - entry.begin)?;
-                        }
-                        0
-                    } else {
-                        file.seek(0)?;
-                        (entry.begin - current_frame) as u32
-                    };
-                    file.fill_channels(&channel_map, blocksize, offset, block.channels())?;
+                if last_idx < n && max_end[last_idx] > last {
+                    last = max_end[last_idx];
+                }
+                level += 1;
+            }
+            max_level = level - 1;
+        }
+        PlaylistIndex {
+            entries: entries.into(),
+            max_end: max_end.into(),
+            max_level,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Appends indices of all entries which overlap the range
+    /// from `start` to `end` (exclusive) to `result` (in no particular order).
+    fn find_active(&mut self, start: u64, end: u64, result: &mut Vec<usize>) {
+        let n = self.entries.len();
+        if n == 0 {
+            return;
+        }
+        let entries = &self.entries;
+        let entry_end = |idx: usize| entries[idx].begin + entries[idx].duration;
+        let stack = &mut self.stack;
+        stack.clear();
+        // level, index, whether the left subtree has already been visited
+        stack.push((self.max_level, (1 << self.max_level) - 1, false));
+        while let Some((level, x, visited)) = stack.pop() {
+            if level <= 3 {
+                // Small subtrees are searched linearly
+                let first = x >> level << level;
+                let last = (first + (1 << (level + 1)) - 1).min(n);
+                for idx in first..last {
+                    if entries[idx].begin >= end {
+                        break;
+                    }
+                    if start < entry_end(idx) {
+                        result.push(idx);
+                    }
                 }
-                current_frame += u64::from(blocksize);
+            } else if !visited {
+                let left = x - (1 << (level - 1));
+                stack.push((level, x, true));
+                // NB: If `left` is out of range, the left subtree is only partially filled
+                if left >= n || self.max_end[left] > start {
+                    stack.push((level - 1, left, false));
+                }
+            } else if x < n && entries[x].begin < end {
+                if start < entry_end(x) {
+                    result.push(x);
+                }
+                stack.push((level - 1, x + (1 << (level - 1)), false));
+            }
+        }
+    }
+}
+
+/// Playlist entries grouped by their hold (the first group is not held).
+struct HeldPlaylist {
+    groups: Box<[PlaylistIndex]>,
+    /// Re-used in `find_active()` to avoid re-allocations
+    indices: Vec<usize>,
+}
 
-                // Make sure the block is queued before data_consumer is sent
-                drop(block);
+impl HeldPlaylist {
+    fn new(playlist: Vec<PlaylistEntry>, holds: usize) -> HeldPlaylist {
+        let mut groups = vec![Vec::new(); holds + 1];
+        for entry in playlist {
+            groups[entry.hold.map_or(0, |hold| hold + 1)].push(entry);
+        }
+        HeldPlaylist {
+            groups: groups.into_iter().map(PlaylistIndex::new).collect(),
+            indices: Vec::new(),
+        }
+    }
 
-                if current_frame - seek_frame >= u64::from(buffer_blocks) * u64::from(blocksize) {
-                    if let Some(data_consumer) = data_consumer.take() {
-                        // There is only one data queue, push() will always succeed
-                        ready_producer.push((seek_frame, data_consumer)).unwrap();
+    /// Replaces the contents of `result` with all entries which are active
+    /// in the block from `frame` to `frame + frames`.
+    ///
+    /// The entries are delayed according to `timeline`.
+    fn find_active(
+        &mut self,
+        frame: u64,
+        frames: u32,
+        timeline: &HoldTimeline,
+        result: &mut Vec<PlaylistEntry>,
+    ) {
+        result.clear();
+        for (group, playlist) in self.groups.iter_mut().enumerate() {
+            let delay = timeline.delay(group.checked_sub(1));
+            // NB: While waiting, the block may start before the (delayed) beginning
+            let end = (frame + u64::from(frames)).saturating_sub(delay);
+            if end == 0 {
+                continue;
+            }
+            self.indices.clear();
+            playlist.find_active(frame.saturating_sub(delay), end, &mut self.indices);
+            result.extend(self.indices.iter().map(|&idx| {
+                let entry = &playlist.entries[idx];
+                PlaylistEntry {
+                    begin: entry.begin + delay,
+                    ..entry.clone()
+                }
+            }));
+        }
+    }
+}
+
+/// A block to be read by a worker thread (only used with multiple reader threads)
+#[cfg(feature = "threads")]
+struct ReadJob {
+    current_frame: u64,
+    seek_frame: u64,
+    entries: Vec<PlaylistEntry>,
+    block: Block,
+}
+
+#[cfg(feature = "threads")]
+type ReadResult = Result<ReadJob, Box<dyn Error + Send + Sync>>;
+
+#[cfg(feature = "threads")]
+struct ReaderWorker {
+    jobs: mpsc::Sender<ReadJob>,
+    results: mpsc::Receiver<ReadResult>,
+    /// `None` while the job is being processed by the worker thread
+    job: Option<ReadJob>,
+    thread: thread::JoinHandle<()>,
+}
+
+#[cfg(feature = "threads")]
+impl ReaderWorker {
+    /// `files` contains every `shards`-th file of the `FileStorage`
+    fn new(
+        mut files: FileStorage,
+        shards: usize,
+        blocksize: u32,
+        channels: u32,
+        name: Option<String>,
+    ) -> ReaderWorker {
+        let (jobs, job_receiver) = mpsc::channel::<ReadJob>();
+        let (result_sender, results) = mpsc::channel();
+        let mut builder = thread::Builder::new();
+        if let Some(name) = name {
+            builder = builder.name(name);
+        }
+        let body = move || {
+            for mut job in job_receiver {
+                for channel in job.block.channels.iter_mut() {
+                    for value in channel.iter_mut() {
+                        *value = 0.0f32;
                     }
                 }
+                let result = read_entries(
+                    &mut files,
+                    shards,
+                    &job.entries,
+                    job.current_frame,
+                    job.seek_frame,
+                    blocksize,
+                    &mut job.block.channels,
+                );
+                let failed = result.is_err();
+                if result_sender.send(result.map(|()| job)).is_err() || failed {
+                    break;
+                }
             }
-            Ok(())
-        });
+        };
+        let thread = builder.spawn(body).expect("failed to spawn worker thread");
+        ReaderWorker {
+            jobs,
+            results,
+            job: Some(ReadJob {
+                current_frame: 0,
+                seek_frame: 0,
+                entries: Vec::new(),
+                block: Block::new(blocksize, channels, 0),
+            }),
+            thread,
+        }
+    }
+}
+
+/// Polarity and delay of one channel, applied while reading the audio data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChannelTrim {
+    pub inverted: bool,
+    /// In frames
+    pub delay: u32,
+}
+
+/// Delay lines (and polarity) of all channels.
+struct Trims {
+    trims: Box<[ChannelTrim]>,
+    /// Ring buffers with `delay` frames (empty for channels without delay)
+    delay_lines: Box<[Box<[f32]>]>,
+    positions: Box<[usize]>,
+}
+
+impl Trims {
+    fn new(trims: Box<[ChannelTrim]>) -> Trims {
+        Trims {
+            delay_lines: trims
+                .iter()
+                .map(|trim| vec![0.0; trim.delay as usize].into())
+                .collect(),
+            positions: vec![0; trims.len()].into(),
+            trims,
+        }
+    }
+
+    /// Applies the trims to one block, the data is moved
+    /// through the delay lines.
+    fn apply(&mut self, channels: &mut [Box<[f32]>]) {
+        for (((trim, line), position), channel) in self
+            .trims
+            .iter()
+            .zip(self.delay_lines.iter_mut())
+            .zip(self.positions.iter_mut())
+            .zip(channels.iter_mut())
+        {
+            if trim.inverted {
+                channel.iter_mut().for_each(|x| *x = -*x);
+            }
+            if line.is_empty() {
+                continue;
+            }
+            for value in channel.iter_mut() {
+                std::mem::swap(value, &mut line[*position]);
+                *position = (*position + 1) % line.len();
+            }
+        }
+    }
+
+    /// Clears the delay lines (e.g. after seeking),
+    /// the first `delay` frames of each channel are silent afterwards.
+    fn reset(&mut self) {
+        for line in self.delay_lines.iter_mut() {
+            line.fill(0.0);
+        }
+    }
+}
+
+/// Reads the given playlist `entries` into `channels`.
+///
+/// `files` contains every `shards`-th file of the `FileStorage`,
+/// all `entries` must refer to one of those.
+fn read_entries<'e>(
+    files: &mut FileStorage,
+    shards: usize,
+    entries: impl IntoIterator<Item = &'e PlaylistEntry>,
+    current_frame: u64,
+    seek_frame: u64,
+    blocksize: u32,
+    channels: &mut [Box<[f32]>],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for entry in entries {
+        let (file, channel_map) = &mut files[entry.idx / shards];
+        let offset = if entry.begin < current_frame {
+            if current_frame == seek_frame {
+                file.seek(current_frame - entry.begin)?;
+            }
+            0
+        } else {
+            file.seek(0)?;
+            (entry.begin - current_frame) as u32
+        };
+        file.fill_channels(channel_map, blocksize, offset, channels)?;
+    }
+    Ok(())
+}
+
+/// Reads audio data on demand, without a separate thread.
+///
+/// This is meant for environments without threads (e.g. WebAssembly in
+/// web browsers) and for offline rendering, where the reading time
+/// doesn't matter.
+/// In real-time applications, this would most likely lead to dropouts.
+struct PullReader {
+    files: FileStorage,
+    playlist: HeldPlaylist,
+    active_entries: Vec<PlaylistEntry>,
+    holds: HoldTimeline,
+    blocksize: u32,
+    block: Block,
+    /// Number of frames of `block` that have already been consumed
+    offset: u32,
+    /// Begin of the next block to be read
+    current_frame: u64,
+    seek_frame: u64,
+    trims: Trims,
+}
+
+impl PullReader {
+    fn new(
+        playlist: Vec<PlaylistEntry>,
+        files: FileStorage,
+        blocksize: u32,
+        channels: u32,
+        trims: Box<[ChannelTrim]>,
+        holds: Arc<HoldState>,
+    ) -> PullReader {
+        PullReader {
+            files,
+            playlist: HeldPlaylist::new(playlist, holds.len()),
+            active_entries: Vec::new(),
+            holds: HoldTimeline::new(holds),
+            blocksize,
+            block: Block::new(blocksize, channels, 0),
+            offset: blocksize,
+            current_frame: 0,
+            seek_frame: 0,
+            trims: Trims::new(trims),
+        }
+    }
+
+    fn seek(&mut self, frame: u64) {
+        self.jump(frame);
+        self.trims.reset();
+    }
+
+    /// Like `seek()`, but the delay lines are kept
+    /// (the remaining data of the current block is discarded).
+    fn jump(&mut self, frame: u64) {
+        self.current_frame = frame;
+        self.seek_frame = frame;
+        self.offset = self.blocksize;
+        self.holds.reset(frame);
+    }
+
+    fn read_block(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for channel in self.block.channels.iter_mut() {
+            for value in channel.iter_mut() {
+                *value = 0.0f32;
+            }
+        }
+        self.holds.advance(self.current_frame, self.blocksize);
+        // NB: The reader runs in the audio thread, there is no need to wait
+        self.holds.publish();
+        self.playlist.find_active(
+            self.current_frame,
+            self.blocksize,
+            &self.holds,
+            &mut self.active_entries,
+        );
+        read_entries(
+            &mut self.files,
+            1,
+            &self.active_entries,
+            self.current_frame,
+            self.seek_frame,
+            self.blocksize,
+            &mut self.block.channels,
+        )?;
+        self.trims.apply(&mut self.block.channels);
+        self.current_frame += u64::from(self.blocksize);
+        self.offset = 0;
+        Ok(())
+    }
+
+    /// Writes `frames` frames, starting at frame `start` of `target`.
+    ///
+    /// Returns the number of written frames, less than `frames` means
+    /// un-recoverable error (the rest of the output buffer is filled with zeros)
+    #[must_use]
+    unsafe fn write_channel_ptrs(&mut self, target: &[*mut f32], start: u32, frames: u32) -> u32 {
+        let mut written = 0;
+        while written < frames {
+            if self.offset == self.blocksize && self.read_block().is_err() {
+                for ptr in target {
+                    for f in written..frames {
+                        *ptr.add((start + f) as usize) = 0.0f32;
+                    }
+                }
+                return written;
+            }
+            let chunk = (self.blocksize - self.offset).min(frames - written);
+            for (source, &target) in self.block.channels.iter().zip(target) {
+                let source = &source[self.offset as usize..][..chunk as usize];
+                let target = std::slice::from_raw_parts_mut(
+                    target.add((start + written) as usize),
+                    chunk as usize,
+                );
+                target.copy_from_slice(source);
+            }
+            written += chunk;
+            self.offset += chunk;
+        }
+        written
+    }
+}
+
+/// How long the reader thread sleeps while the buffer is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    Fixed(Duration),
+    /// Starts with `min`, doubled each time the buffer is still full
+    /// (up to `max`), reset to `min` once a block has been read
+    Exponential {
+        min: Duration,
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    #[cfg(feature = "threads")]
+    fn initial(self) -> Duration {
+        match self {
+            Backoff::Fixed(duration) => duration,
+            Backoff::Exponential { min, .. } => min,
+        }
+    }
+
+    #[cfg(feature = "threads")]
+    fn next(self, current: Duration) -> Duration {
+        match self {
+            Backoff::Fixed(duration) => duration,
+            Backoff::Exponential { max, .. } => (current * 2).min(max),
+        }
+    }
+}
+
+/// Configuration of a `FileStreamer`.
+///
+/// ```text
+/// let streamer = StreamerBuilder::new(blocksize, channels)
+///     .buffer_blocks(16)
+///     .backoff(Backoff::Exponential { min, max })
+///     .thread_name("my-reader")
+///     .build(playlist, file_storage);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamerBuilder {
+    blocksize: u32,
+    channels: u32,
+    trims: Vec<ChannelTrim>,
+    buffer_blocks: u32,
+    backoff: Backoff,
+    reader_threads: u32,
+    thread_name: Option<String>,
+    priming_blocks: Option<u32>,
+    fade_length: Option<u32>,
+    fade_shape: FadeShape,
+    holds: Vec<Hold>,
+}
+
+impl StreamerBuilder {
+    /// The defaults are 16 buffer blocks, a fixed sleep time of 10 ms,
+    /// one reader thread (without name), priming the whole buffer and
+    /// a linear fade over one block.
+    pub fn new(blocksize: u32, channels: u32) -> StreamerBuilder {
+        StreamerBuilder {
+            blocksize,
+            channels,
+            trims: Vec::new(),
+            buffer_blocks: 16,
+            backoff: Backoff::Fixed(Duration::from_millis(10)),
+            reader_threads: 1,
+            thread_name: None,
+            priming_blocks: None,
+            fade_length: None,
+            fade_shape: FadeShape::default(),
+            holds: Vec::new(),
+        }
+    }
+
+    /// Polarity and delay, one entry per channel (or none).
+    pub fn trims(mut self, trims: Vec<ChannelTrim>) -> StreamerBuilder {
+        self.trims = trims;
+        self
+    }
+
+    /// Size of the buffer between the reader thread and `get_data()`.
+    pub fn buffer_blocks(mut self, blocks: u32) -> StreamerBuilder {
+        self.buffer_blocks = blocks;
+        self
+    }
+
+    /// Same as `backoff(Backoff::Fixed(sleeptime))`.
+    pub fn sleeptime(self, sleeptime: Duration) -> StreamerBuilder {
+        self.backoff(Backoff::Fixed(sleeptime))
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> StreamerBuilder {
+        self.backoff = backoff;
+        self
+    }
+
+    /// With `threads > 1`, files are distributed among multiple
+    /// threads, which is useful for scenes with many simultaneously playing
+    /// files (especially on slow disks).
+    /// The worker threads read in lock-step, one block at a time:
+    /// they don't read ahead independently, reading a block takes as long
+    /// as the slowest worker needs for its files.
+    /// This doesn't help if a single file is slow to read.
+    ///
+    /// With `threads == 0` (or without the `threads` feature),
+    /// no reader thread is started, audio data is read in `get_data()`
+    /// instead (see `PullReader`).
+    pub fn reader_threads(mut self, threads: u32) -> StreamerBuilder {
+        self.reader_threads = threads;
+        self
+    }
+
+    /// Name of the reader thread (e.g. for debuggers and profilers),
+    /// worker threads get the suffix `-1`, `-2` etc.
+    pub fn thread_name<S: Into<String>>(mut self, name: S) -> StreamerBuilder {
+        self.thread_name = Some(name.into());
+        self
+    }
+
+    /// Number of blocks which have to be read after seeking before playback
+    /// can start (limited to the buffer size, which is the default).
+    ///
+    /// Fewer blocks allow starting earlier, at the risk of underruns.
+    pub fn priming_blocks(mut self, blocks: u32) -> StreamerBuilder {
+        self.priming_blocks = Some(blocks.max(1));
+        self
+    }
+
+    /// Length (in frames) and shape of fade-in and fade-out,
+    /// see `FileStreamer::set_fade()`.
+    pub fn fade(mut self, length: u32, shape: FadeShape) -> StreamerBuilder {
+        self.fade_length = Some(length);
+        self.fade_shape = shape;
+        self
+    }
+
+    /// Positions where (parts of) the playlist wait until they are
+    /// released with `FileStreamer::release_hold()`, e.g. for interactive
+    /// content.
+    ///
+    /// Each `PlaylistEntry` with a `hold` is delayed by the time its hold
+    /// has been waiting (plus the delays of all the hold's parents),
+    /// entries without `hold` are never delayed.
+    /// A hold waits once the (delayed) playback reaches its `frame`.
+    /// The delays are updated once per block, a release therefore takes
+    /// effect when the reader reads the next block, i.e. only after
+    /// the already buffered data has been played.
+    ///
+    /// Entries of the same file in different holds must not overlap
+    /// (after applying the delays).
+    pub fn holds(mut self, holds: Vec<Hold>) -> StreamerBuilder {
+        self.holds = holds;
+        self
+    }
+
+    /// If `playlist` is empty (e.g. in scenes with only live sources),
+    /// no reader thread is started and `get_data()` always provides silence.
+    ///
+    /// Panics if the number of trims doesn't match the number of channels.
+    pub fn build(self, playlist: Vec<PlaylistEntry>, file_storage: FileStorage) -> FileStreamer {
+        FileStreamer::new(self, playlist, file_storage)
+    }
+}
+
+impl FileStreamer {
+    #[cfg_attr(not(feature = "threads"), allow(unused_variables))]
+    fn new(
+        config: StreamerBuilder,
+        playlist: Vec<PlaylistEntry>,
+        file_storage: FileStorage,
+    ) -> FileStreamer {
+        let StreamerBuilder {
+            blocksize,
+            channels,
+            trims,
+            buffer_blocks,
+            backoff,
+            reader_threads,
+            thread_name,
+            priming_blocks,
+            fade_length,
+            fade_shape,
+            holds,
+        } = config;
+        assert!(
+            trims.is_empty() || trims.len() == channels as usize,
+            "One trim per channel is required"
+        );
+        assert!(
+            holds
+                .iter()
+                .enumerate()
+                .all(|(idx, hold)| hold.parent.map_or(true, |parent| parent < idx)),
+            "The parent of a hold has to come before it"
+        );
+        let holds = Arc::new(HoldState::new(holds));
+        let (ready_producer, ready_consumer) = queue::spsc::new(1);
+        let (seek_producer, seek_consumer) = queue::spsc::new::<(u64, DataConsumer)>(1);
+        let (cue_producer, cue_consumer) = queue::spsc::new(CUE_QUEUE_CAPACITY);
+        let (cue_result_producer, cue_result_consumer) = queue::spsc::new(2 * CUE_QUEUE_CAPACITY);
+        let reader_thread_keep_reading = Arc::new(AtomicBool::new(true));
+        let keep_reading = Arc::clone(&reader_thread_keep_reading);
+        let stats = Arc::new(SharedStats::default());
+        stats.buffer_blocks.store(buffer_blocks, Ordering::Relaxed);
+        let mut pull_reader = None;
+        let has_files = !playlist.is_empty();
+        let reader_thread = if !has_files {
+            // NB: Without audio files, there is nothing to read (and no buffer is allocated)
+            None
+        } else if reader_threads == 0 || cfg!(not(feature = "threads")) {
+            pull_reader = Some(PullReader::new(
+                playlist,
+                file_storage,
+                blocksize,
+                channels,
+                trims.into(),
+                Arc::clone(&holds),
+            ));
+            None
+        } else {
+            #[cfg(feature = "threads")]
+            let thread = Some(spawn_reader_thread(
+                playlist,
+                file_storage,
+                blocksize,
+                channels,
+                trims.into(),
+                buffer_blocks,
+                priming_blocks,
+                backoff,
+                reader_threads,
+                thread_name,
+                keep_reading,
+                Arc::clone(&stats),
+                Arc::clone(&holds),
+                seek_consumer,
+                ready_producer,
+                cue_consumer,
+                cue_result_producer,
+            ));
+            // NB: Without threads, the `PullReader` is always used (see above)
+            #[cfg(not(feature = "threads"))]
+            let thread = None;
+            thread
+        };
         FileStreamer {
             ready_consumer,
             seek_producer,
             data_consumer: None,
-            reader_thread: Some(reader_thread),
+            reader_thread,
             reader_thread_keep_reading,
+            reader_error: None,
+            pull_reader,
+            has_files,
+            stats,
+            underruns: 0,
             channels,
             blocksize,
-            previously_rolling: false,
+            fade_length: fade_length.unwrap_or(blocksize).max(1),
+            fade_shape,
+            fade_position: 0,
             seek_frame: None,
+            next_frame: 0,
+            rolling: false,
+            position: Position::default(),
+            muted: vec![false; channels as usize].into(),
+            soloed: vec![false; channels as usize].into(),
+            levels: vec![1.0; channels as usize].into(),
+            pending_levels: vec![None; channels as usize].into(),
+            channel_gains: vec![1.0; channels as usize].into(),
+            cue_producer,
+            cue_result_consumer,
+            seek_generation: 0,
+            scheduled_seek: None,
+            executed_seek: None,
+            hold_timeline: HoldTimeline::new(Arc::clone(&holds)),
+            holds,
         }
     }
 
@@ -259,50 +1309,631 @@ impl FileStreamer {
         self.channels
     }
 
-    // TODO: more information on error, use Result?
-    /// Return value of `false` means un-recoverable error
-    #[must_use]
-    pub unsafe fn get_data(&mut self, target: &[*mut f32], rolling: bool) -> bool {
-        // TODO: Check if disk thread is still running? return false if not?
+    pub fn blocksize(&self) -> u32 {
+        self.blocksize
+    }
+
+    pub fn stats(&self) -> StreamingStats {
+        StreamingStats {
+            buffer_blocks: self.stats.buffer_blocks.load(Ordering::Relaxed),
+            suggested_buffer_blocks: self.stats.suggested_buffer_blocks(),
+            buffered_frames: self.frames_available() as u64,
+            underruns: self.underruns,
+            max_block_read_time: Duration::from_micros(
+                self.stats.max_block_read_time.load(Ordering::Relaxed),
+            ),
+        }
+    }
+
+    /// Enables automatic growing of the buffer (up to `max_blocks`) to the
+    /// suggested size, `None` disables it.
+    ///
+    /// The buffer is only re-allocated when seeking.
+    pub fn set_buffer_growth_limit(&mut self, max_blocks: Option<u32>) {
+        self.stats
+            .max_buffer_blocks
+            .store(max_blocks.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Length of the fade-in (and fade-out) in frames.
+    pub fn fade_length(&self) -> u32 {
+        self.fade_length
+    }
+
+    /// Sets length (in frames) and shape of fade-in and fade-out.
+    ///
+    /// The default is a linear fade over one block.
+    /// If a fade is currently in progress, its relative position is kept.
+    pub fn set_fade(&mut self, length: u32, shape: FadeShape) {
+        let length = length.max(1);
+        self.fade_position = (u64::from(self.fade_position) * u64::from(length)
+            / u64::from(self.fade_length)) as u32;
+        self.fade_length = length;
+        self.fade_shape = shape;
+    }
+
+    /// Stops playback immediately, without fade-out.
+    ///
+    /// The output is not faded, this should only be used if the output is discarded.
+    pub fn stop(&mut self) {
+        self.fade_position = 0;
+        self.seek_frame = None;
+    }
+
+    /// Like `stop()`, but also finishes mute/solo/gain ramps
+    /// and clears the underrun counter.
+    ///
+    /// Buffered data is discarded with the next call to `seek()`.
+    pub fn reset(&mut self) {
+        self.stop();
+        self.rolling = false;
+        self.underruns = 0;
+        for (level, pending) in self.levels.iter_mut().zip(self.pending_levels.iter_mut()) {
+            if let Some((_, new_level)) = pending.take() {
+                *level = new_level;
+            }
+        }
+        let any_solo = self.soloed.iter().any(|&solo| solo);
+        for channel in 0..self.channel_gains.len() {
+            self.channel_gains[channel] = self.gain_goal(channel, any_solo);
+        }
+    }
+
+    /// Gain which the given channel is ramped to.
+    fn gain_goal(&self, channel: usize, any_solo: bool) -> f32 {
+        let audible = !self.muted[channel] && (!any_solo || self.soloed[channel]);
+        if audible {
+            self.levels[channel]
+        } else {
+            0.0
+        }
+    }
+
+    /// Mutes (or unmutes) the given channel, with a short gain ramp.
+    /// Panics if `channel` is out of range.
+    pub fn set_mute(&mut self, channel: usize, mute: bool) {
+        self.muted[channel] = mute;
+    }
+
+    pub fn is_muted(&self, channel: usize) -> bool {
+        self.muted[channel]
+    }
+
+    /// As long as any channel is soloed, all non-soloed channels are muted.
+    /// Panics if `channel` is out of range.
+    pub fn set_solo(&mut self, channel: usize, solo: bool) {
+        self.soloed[channel] = solo;
+    }
+
+    pub fn is_soloed(&self, channel: usize) -> bool {
+        self.soloed[channel]
+    }
+
+    /// Sets the (linear) gain of the given channel, which is ramped
+    /// like with `set_mute()`.
+    ///
+    /// Returns `false` (and leaves the gain unchanged) if `gain` is negative
+    /// or not finite, see `is_valid_gain()`.
+    /// Panics if `channel` is out of range.
+    pub fn set_gain(&mut self, channel: usize, gain: f32) -> bool {
+        if !is_valid_gain(gain) {
+            return false;
+        }
+        self.levels[channel] = gain;
+        self.pending_levels[channel] = None;
+        true
+    }
+
+    /// Like `set_gain()`, but the gain ramp starts exactly at frame `offset`
+    /// of the next call to `get_data()` (offsets beyond that call
+    /// refer to the following calls).
+    ///
+    /// A further call to `set_gain()` or `set_gain_at()` (for the same channel)
+    /// replaces the pending change.
+    ///
+    /// This is realtime-safe.
+    pub fn set_gain_at(&mut self, channel: usize, gain: f32, offset: u32) -> bool {
+        if !is_valid_gain(gain) {
+            return false;
+        }
+        self.pending_levels[channel] = Some((offset, gain));
+        true
+    }
+
+    /// The most recently set gain (see `set_gain()` and `set_gain_at()`),
+    /// even if it has not yet taken effect.
+    pub fn gain(&self, channel: usize) -> f32 {
+        match self.pending_levels[channel] {
+            Some((_, level)) => level,
+            None => self.levels[channel],
+        }
+    }
+
+    /// Whether the given channel is not silenced by mute, solo or gain settings
+    /// (it might still be ramping towards its target gain).
+    /// Panics if `channel` is out of range.
+    pub fn is_audible(&self, channel: usize) -> bool {
+        let any_solo = self.soloed.iter().any(|&solo| solo);
+        self.gain_goal(channel, any_solo) != 0.0
+    }
+
+    /// This is realtime-safe.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Value of `rolling` in the most recent call to `get_data()`.
+    pub fn previous_rolling(&self) -> bool {
+        self.rolling
+    }
+
+    /// Frame number of the data which is delivered next by `get_data()`.
+    pub fn next_frame(&self) -> u64 {
+        self.next_frame
+    }
+
+    /// This never returns `TransportState::Finished`.
+    pub fn transport_state(&self) -> TransportState {
+        if self.seek_frame.is_some() {
+            TransportState::FadingOutForSeek
+        } else if self.fade_position > 0 || self.position.rolling {
+            TransportState::Playing
+        } else if self.has_files && self.pull_reader.is_none() && self.data_consumer.is_none() {
+            TransportState::Seeking
+        } else {
+            TransportState::Primed
+        }
+    }
+
+    /// Number of frames that can be obtained with `get_data()` without underrun.
+    pub fn frames_available(&self) -> usize {
+        if !self.has_files || self.pull_reader.is_some() {
+            return usize::MAX;
+        }
+        self.data_consumer
+            .as_ref()
+            .map_or(0, |queue| queue.frames_available())
+    }
 
-        let previously = self.previously_rolling;
-        let result = if !rolling && !previously {
-            fill_with_zeros(target, self.blocksize);
+    /// Writes `frames` frames into each of the `target` channels.
+    ///
+    /// `frames` doesn't have to be equal to the `blocksize` given in
+    /// `StreamerBuilder::new()`,
+    /// partially used blocks are kept for the next call.
+    /// When starting or stopping, the fade (see `set_fade()`) may span
+    /// multiple calls; after `rolling` is set to `false`,
+    /// data is still consumed until the fade-out is finished.
+    ///
+    /// If the reader thread has died, silence is delivered and
+    /// `StreamingError::ReaderDied` is returned (in this and all further calls).
+    pub unsafe fn get_data(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+    ) -> Result<(), StreamingError> {
+        self.executed_seek = None;
+        let result = self.get_data_range(target, 0, frames, rolling);
+        self.advance_pending_levels(frames);
+        result
+    }
+
+    /// Like `get_data()`, but a change of `rolling` only takes effect at frame
+    /// `offset` (which must not be larger than `frames`),
+    /// the frames before that are processed with the previous value.
+    ///
+    /// This way, fades start exactly at the host's transport change
+    /// (instead of at the beginning of the block).
+    pub unsafe fn get_data_with_ramp(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        rolling: bool,
+        offset: u32,
+    ) -> Result<(), StreamingError> {
+        assert!(offset <= frames);
+        let previous = self.rolling;
+        if offset == 0 || previous == rolling {
+            return self.get_data(target, frames, rolling);
+        }
+        self.executed_seek = None;
+        let first = self.get_data_range(target, 0, offset, previous);
+        let position = self.position;
+        let second = self.get_data_range(target, offset, frames - offset, rolling);
+        self.advance_pending_levels(frames);
+        if position.rolling {
+            // NB: The position refers to the first delivered frame
+            self.position = position;
+        }
+        first.and(second)
+    }
+
+    unsafe fn get_data_range(
+        &mut self,
+        target: &[*mut f32],
+        start: u32,
+        frames: u32,
+        rolling: bool,
+    ) -> Result<(), StreamingError> {
+        self.rolling = rolling;
+        self.check_reader_thread();
+        self.poll_cue_results();
+        if let Some(msg) = self.reader_error.clone() {
+            fill_with_zeros(target, start, frames);
+            self.advance(0, false);
+            return Err(StreamingError::ReaderDied(msg));
+        }
+        if !self.has_files {
+            // NB: There are no audio files (and maybe no channels at all)
+            fill_with_zeros(target, start, frames);
+            if rolling {
+                self.hold_timeline.advance(self.next_frame, frames);
+                self.hold_timeline.publish();
+            }
+            self.advance(if rolling { frames } else { 0 }, rolling);
+            return Ok(());
+        }
+
+        let complete = if !rolling && self.fade_position == 0 {
+            fill_with_zeros(target, start, frames);
+            self.advance(0, false);
             true
-        } else if let Some(ref mut queue) = self.data_consumer {
-            let fade = if rolling && !previously {
-                Fade::In
-            } else if !rolling && previously {
-                Fade::Out
-            } else {
-                Fade::None
+        } else if self.pull_reader.is_some() {
+            let jump = self.scheduled_jump(frames);
+            let reader = self.pull_reader.as_mut().unwrap();
+            let written = match jump {
+                Some((offset, frame)) => {
+                    let written = reader.write_channel_ptrs(target, start, offset);
+                    if written == offset {
+                        reader.jump(frame);
+                        written + reader.write_channel_ptrs(target, start + offset, frames - offset)
+                    } else {
+                        written
+                    }
+                }
+                None => reader.write_channel_ptrs(target, start, frames),
             };
-            queue.write_channel_ptrs(target, fade)
+            self.advance(written, true);
+            self.apply_fade(target, start, frames, rolling);
+            self.apply_mute(target, start, frames);
+            written == frames
+        } else if let Some(ref mut queue) = self.data_consumer {
+            let written = queue.write_channel_ptrs(target, start, frames);
+            if written < frames {
+                self.underruns += 1;
+            }
+            self.advance(written, true);
+            self.apply_fade(target, start, frames, rolling);
+            self.apply_mute(target, start, frames);
+            written == frames
         } else {
-            fill_with_zeros(target, self.blocksize);
+            fill_with_zeros(target, start, frames);
+            self.advance(0, false);
             false
         };
-        // NB: This has to be updated before seeking:
-        self.previously_rolling = rolling;
-        if let Some(frame) = self.seek_frame.take() {
+        // NB: fade_position has to be updated before seeking
+        if let Some(frame) = self.seek_frame {
             if rolling {
-                // NB: Seeking while rolling is not supported
-                return false;
+                // NB: Playback was resumed before the fade-out was finished
+                self.seek_frame = None;
+            } else if self.fade_position == 0 {
+                self.seek_frame = None;
+                let _ = self.seek(frame);
             }
-            let _ = self.seek(frame);
         }
-        result
+        if complete {
+            Ok(())
+        } else {
+            Err(StreamingError::Underrun)
+        }
+    }
+
+    /// Checks whether the reader thread has stopped on its own (i.e. because
+    /// of an error or a panic), in which case the error is logged and stored.
+    ///
+    /// This is only realtime-unsafe once, when the thread has died.
+    fn check_reader_thread(&mut self) {
+        match &self.reader_thread {
+            Some(thread) if thread.is_finished() => {}
+            _ => return,
+        }
+        let msg = match self.reader_thread.take().unwrap().join() {
+            Ok(Ok(())) => "Reader thread stopped unexpectedly".to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => panic_message(&*e),
+        };
+        log::error!("Reader thread died: {}", msg);
+        self.reader_error = Some(msg.into());
+    }
+
+    /// Updates `position` after `frames` frames of audio data have been delivered
+    /// (which can be less than requested in case of underruns).
+    ///
+    /// If the frame of a scheduled seek was delivered,
+    /// the frame numbers continue at its target.
+    fn advance(&mut self, frames: u32, rolling: bool) {
+        let mut frame = self.next_frame;
+        let mut next_frame = frame + u64::from(frames);
+        if let Some(seek) = self.scheduled_seek.filter(|s| s.is_active()) {
+            if frame <= seek.at && seek.at < next_frame {
+                // NB: The reader thread answers before queueing the modified block
+                self.poll_cue_results();
+                let seek = self.scheduled_seek.as_mut().unwrap();
+                if seek.state == ScheduledSeekState::Armed {
+                    seek.state = ScheduledSeekState::Done;
+                    if frame == seek.at {
+                        frame = seek.target;
+                    }
+                    next_frame = seek.target + (next_frame - seek.at);
+                    self.executed_seek = Some(seek.target);
+                } else {
+                    seek.state = ScheduledSeekState::Missed;
+                }
+            }
+        }
+        self.position = Position { frame, rolling };
+        self.next_frame = next_frame;
+    }
+
+    /// Offset (within the next `frames` frames) and target frame
+    /// of an armed scheduled seek.
+    fn scheduled_jump(&self, frames: u32) -> Option<(u32, u64)> {
+        self.scheduled_seek
+            .filter(|seek| seek.state == ScheduledSeekState::Armed)
+            .filter(|seek| seek.at < self.next_frame + u64::from(frames))
+            .map(|seek| ((seek.at - self.next_frame) as u32, seek.target))
+    }
+
+    fn poll_cue_results(&mut self) {
+        while let Ok((generation, at, accepted)) = self.cue_result_consumer.pop() {
+            match &mut self.scheduled_seek {
+                Some(seek)
+                    if generation == self.seek_generation
+                        && seek.at == at
+                        && seek.state == ScheduledSeekState::Pending =>
+                {
+                    seek.state = if accepted {
+                        ScheduledSeekState::Armed
+                    } else {
+                        ScheduledSeekState::Missed
+                    };
+                }
+                // NB: Answers to outdated requests are ignored
+                _ => {}
+            }
+        }
+    }
+
+    /// Schedules a seek to `target` which takes place exactly when frame `at`
+    /// would be delivered by `get_data()` (even in the middle of a block).
+    ///
+    /// Returns `false` if `at` has already been delivered,
+    /// if another scheduled seek is still active or while seeking.
+    /// Otherwise, `scheduled_seek()` can be used to check whether
+    /// the reader thread is able to prepare the seek in time.
+    /// A regular `seek()` cancels the scheduled seek.
+    ///
+    /// This is realtime-safe.
+    pub fn seek_at(&mut self, at: u64, target: u64) -> bool {
+        self.check_reader_thread();
+        self.poll_cue_results();
+        if self.reader_error.is_some()
+            || at < self.next_frame
+            || self.scheduled_seek.is_some_and(|s| s.is_active())
+        {
+            return false;
+        }
+        let state = match self.transport_state() {
+            TransportState::Playing | TransportState::Primed => {
+                if self.reader_thread.is_some() {
+                    if self
+                        .cue_producer
+                        .push((self.seek_generation, at, target))
+                        .is_err()
+                    {
+                        return false;
+                    }
+                    ScheduledSeekState::Pending
+                } else {
+                    ScheduledSeekState::Armed
+                }
+            }
+            _ => return false,
+        };
+        self.scheduled_seek = Some(ScheduledSeek { at, target, state });
+        true
+    }
+
+    /// The most recent seek scheduled with `seek_at()`
+    /// (`None` if it was cancelled by a regular seek).
+    ///
+    /// The answer of the reader thread is checked in `get_data()`.
+    pub fn scheduled_seek(&self) -> Option<ScheduledSeek> {
+        self.scheduled_seek
+    }
+
+    /// Target frame of a scheduled seek, if it has taken place
+    /// in the most recent call to `get_data()`.
+    pub fn executed_seek(&self) -> Option<u64> {
+        self.executed_seek
+    }
+
+    /// Cancels the scheduled seek (if any) when the reader thread is
+    /// (or will be) re-positioned.
+    fn cancel_scheduled_seek(&mut self) {
+        self.scheduled_seek = None;
+        self.executed_seek = None;
+    }
+
+    /// Moves `fade_position` towards the target (given by `rolling`),
+    /// one step per frame, and applies the corresponding gain to `target`
+    /// (starting at frame `start`).
+    unsafe fn apply_fade(&mut self, target: &[*mut f32], start: u32, frames: u32, rolling: bool) {
+        let length = self.fade_length;
+        if rolling && self.fade_position == length {
+            return;
+        }
+        for i in 0..frames as usize {
+            let gain = if rolling && self.fade_position < length {
+                self.fade_position += 1;
+                self.fade_shape.gain(self.fade_position, length)
+            } else if !rolling && self.fade_position > 0 {
+                let gain = self.fade_shape.gain(self.fade_position, length);
+                self.fade_position -= 1;
+                gain
+            } else {
+                self.fade_shape.gain(self.fade_position, length)
+            };
+            for ptr in target {
+                *ptr.add(start as usize + i) *= gain;
+            }
+        }
+    }
+
+    /// Moves the gain of each channel towards its target (given by mute,
+    /// solo and gain settings) and applies it to `target`
+    /// (starting at frame `start`).
+    ///
+    /// Pending gain changes (see `set_gain_at()`) take effect at their offset.
+    unsafe fn apply_mute(&mut self, target: &[*mut f32], start: u32, frames: u32) {
+        let any_solo = self.soloed.iter().any(|&solo| solo);
+        let end = start + frames;
+        for (channel, &ptr) in target.iter().enumerate() {
+            let mut begin = start;
+            if let Some((offset, level)) = self.pending_levels[channel] {
+                if offset < end {
+                    let split = offset.max(start);
+                    self.ramp_channel(channel, ptr, begin, split - begin, any_solo);
+                    self.levels[channel] = level;
+                    self.pending_levels[channel] = None;
+                    begin = split;
+                }
+            }
+            self.ramp_channel(channel, ptr, begin, end - begin, any_solo);
+        }
+    }
+
+    unsafe fn ramp_channel(
+        &mut self,
+        channel: usize,
+        ptr: *mut f32,
+        start: u32,
+        frames: u32,
+        any_solo: bool,
+    ) {
+        let goal = self.gain_goal(channel, any_solo);
+        let gain = &mut self.channel_gains[channel];
+        let data = std::slice::from_raw_parts_mut(ptr.add(start as usize), frames as usize);
+        if *gain == goal {
+            if goal == 0.0 {
+                data.iter_mut().for_each(|x| *x = 0.0);
+            } else if goal != 1.0 {
+                data.iter_mut().for_each(|x| *x *= goal);
+            }
+            return;
+        }
+        let step = 1.0 / MUTE_RAMP_LENGTH as f32;
+        for value in data {
+            *gain = if *gain < goal {
+                (*gain + step).min(goal)
+            } else {
+                (*gain - step).max(goal)
+            };
+            *value *= *gain;
+        }
+    }
+
+    /// Called after `frames` frames have been requested with `get_data()`,
+    /// pending gain changes which have not been applied in `apply_mute()`
+    /// (e.g. because nothing was played) take effect immediately.
+    fn advance_pending_levels(&mut self, frames: u32) {
+        for (level, pending) in self.levels.iter_mut().zip(self.pending_levels.iter_mut()) {
+            match pending {
+                Some((offset, new_level)) if *offset < frames => {
+                    *level = *new_level;
+                    *pending = None;
+                }
+                Some((offset, _)) => *offset -= frames,
+                None => {}
+            }
+        }
+    }
+
+    /// Releases the given hold (see `StreamerBuilder::holds()`).
+    ///
+    /// If it is currently waiting, its entries continue with the next block
+    /// which is read, otherwise they will not wait when reaching the hold.
+    /// Seeking cancels all releases.
+    ///
+    /// This is realtime-safe.
+    /// Panics if `hold` is out of range.
+    pub fn release_hold(&self, hold: usize) {
+        self.holds.released[hold].store(true, Ordering::Release);
+    }
+
+    /// Whether the given hold was waiting in the most recently delivered block.
+    /// Panics if `hold` is out of range.
+    pub fn is_hold_waiting(&self, hold: usize) -> bool {
+        self.holds.is_waiting(hold)
+    }
+
+    /// Time (in frames) by which the entries of the given hold are delayed
+    /// in the most recently delivered block.
+    /// Panics if `hold` is out of range.
+    pub fn hold_delay(&self, hold: usize) -> u64 {
+        self.holds.delay(Some(hold))
+    }
+
+    pub(crate) fn hold_state(&self) -> &Arc<HoldState> {
+        &self.holds
+    }
+
+    /// Ignores all holds until the returned value is dropped
+    /// (e.g. for offline rendering).
+    ///
+    /// This has to be called before seeking.
+    pub(crate) fn suspend_holds(&self) -> SuspendedHolds {
+        self.holds.enabled.store(false, Ordering::Relaxed);
+        SuspendedHolds(Arc::clone(&self.holds))
     }
 
     #[must_use]
     pub fn seek(&mut self, frame: u64) -> bool {
-        // TODO: Check if disk thread is still running? What if not?
+        // NB: Releases made before the seek is finished are discarded as well
+        self.holds.clear_releases();
+        let result = self.try_seek(frame);
+        if result {
+            self.holds.clear_delays();
+            self.next_frame = frame;
+            self.position = Position {
+                frame,
+                rolling: false,
+            };
+        }
+        result
+    }
 
-        if self.previously_rolling {
+    fn try_seek(&mut self, frame: u64) -> bool {
+        self.check_reader_thread();
+        if self.reader_error.is_some() {
+            return false;
+        }
+        if !self.has_files {
+            self.cancel_scheduled_seek();
+            self.hold_timeline.reset(frame);
+            return true;
+        }
+
+        if self.fade_position > 0 {
             self.seek_frame = Some(frame);
             // Don't seek yet; get_data() fades out and calls seek afterwards
             return false;
         }
+        if let Some(reader) = &mut self.pull_reader {
+            reader.seek(frame);
+            self.cancel_scheduled_seek();
+            return true;
+        }
         if self.data_consumer.is_none() {
             // NB: There can never be more than one message
             if let Ok((ready_frame, queue)) = self.ready_consumer.pop() {
@@ -314,6 +1945,8 @@ impl FileStreamer {
         }
         if let Some(queue) = self.data_consumer.take() {
             self.seek_producer.push((frame, queue)).unwrap();
+            self.seek_generation += 1;
+            self.cancel_scheduled_seek();
         }
         false
     }
@@ -323,15 +1956,472 @@ impl Drop for FileStreamer {
     fn drop(&mut self) {
         self.reader_thread_keep_reading
             .store(false, Ordering::Release);
-        // TODO: handle error from closure? log errors?
-        self.reader_thread.take().unwrap().join().unwrap().unwrap();
+        // NB: Errors are only logged, dropping never panics
+        if let Some(thread) = self.reader_thread.take() {
+            match thread.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => log::error!("Reader thread failed: {}", e),
+                Err(e) => log::error!("Reader thread panicked: {}", panic_message(&*e)),
+            }
+        }
+    }
+}
+
+/// Gains have to be finite and non-negative (see `FileStreamer::set_gain()`),
+/// otherwise the gain ramps would never reach their target.
+pub(crate) fn is_valid_gain(gain: f32) -> bool {
+    gain.is_finite() && gain >= 0.0
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Reader thread panicked".to_string()
+    }
+}
+
+/// Reads the block starting at `current_frame` into `channels`
+/// (which have to be filled with zeros),
+/// using the worker threads (if there are any).
+///
+/// NB: This waits for all workers which got a job, i.e. the workers
+/// only run in parallel within one block, none of them reads ahead.
+#[cfg(feature = "threads")]
+#[allow(clippy::too_many_arguments)]
+fn read_block(
+    workers: &mut [ReaderWorker],
+    file_shards: &mut [FileStorage],
+    playlist: &mut HeldPlaylist,
+    holds: &HoldTimeline,
+    active_entries: &mut Vec<PlaylistEntry>,
+    current_frame: u64,
+    seek_frame: u64,
+    blocksize: u32,
+    channels: &mut [Box<[f32]>],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let shards = workers.len().max(1);
+    playlist.find_active(current_frame, blocksize, holds, active_entries);
+    if workers.is_empty() {
+        read_entries(
+            &mut file_shards[0],
+            1,
+            active_entries.iter(),
+            current_frame,
+            seek_frame,
+            blocksize,
+            channels,
+        )?;
+    } else {
+        for (shard, worker) in workers.iter_mut().enumerate() {
+            let mut job = worker.job.take().unwrap();
+            job.entries.clear();
+            job.entries.extend(
+                active_entries
+                    .iter()
+                    .filter(|entry| entry.idx % shards == shard)
+                    .cloned(),
+            );
+            if job.entries.is_empty() {
+                worker.job = Some(job);
+                continue;
+            }
+            job.current_frame = current_frame;
+            job.seek_frame = seek_frame;
+            worker
+                .jobs
+                .send(job)
+                .map_err(|_| "Reader worker thread has terminated")?;
+        }
+        for worker in workers.iter_mut().filter(|w| w.job.is_none()) {
+            let job = worker
+                .results
+                .recv()
+                .map_err(|_| "Reader worker thread has terminated")??;
+            // NB: Different files never write to the same frames of a channel
+            for (target, source) in channels.iter_mut().zip(job.block.channels.iter()) {
+                for (t, s) in target.iter_mut().zip(source.iter()) {
+                    *t += s;
+                }
+            }
+            worker.job = Some(job);
+        }
+    }
+    Ok(())
+}
+
+/// Starts a thread which reads audio data ahead of time
+/// (and distributes files among worker threads, if `reader_threads > 1`).
+#[cfg(feature = "threads")]
+#[allow(clippy::too_many_arguments)]
+fn spawn_reader_thread(
+    playlist: Vec<PlaylistEntry>,
+    file_storage: FileStorage,
+    blocksize: u32,
+    channels: u32,
+    trims: Box<[ChannelTrim]>,
+    buffer_blocks: u32,
+    priming_blocks: Option<u32>,
+    backoff: Backoff,
+    reader_threads: u32,
+    thread_name: Option<String>,
+    keep_reading: Arc<AtomicBool>,
+    stats: Arc<SharedStats>,
+    holds: Arc<HoldState>,
+    seek_consumer: queue::spsc::Consumer<(u64, DataConsumer)>,
+    ready_producer: queue::spsc::Producer<(u64, DataConsumer)>,
+    cue_consumer: queue::spsc::Consumer<(u64, u64, u64)>,
+    cue_result_producer: queue::spsc::Producer<(u64, u64, bool)>,
+) -> thread::JoinHandle<Result<(), Box<dyn Error + Send + Sync>>> {
+    let shards = reader_threads as usize;
+    let mut file_shards: Vec<FileStorage> = (0..shards).map(|_| Vec::new()).collect();
+    for (idx, file) in file_storage.into_iter().enumerate() {
+        file_shards[idx % shards].push(file);
     }
+    let mut builder = thread::Builder::new();
+    if let Some(name) = &thread_name {
+        builder = builder.name(name.clone());
+    }
+    let body = move || -> Result<(), Box<dyn Error + Send + Sync>> {
+        let (mut data_producer, data_consumer) =
+            make_data_queue(buffer_blocks as usize, blocksize, channels, &holds);
+        // NB: With a single reader thread, no worker threads are used
+        let mut workers = Vec::new();
+        if shards > 1 {
+            for (idx, files) in file_shards.drain(..).enumerate() {
+                let name = thread_name
+                    .as_ref()
+                    .map(|name| format!("{}-{}", name, idx + 1));
+                workers.push(ReaderWorker::new(files, shards, blocksize, channels, name));
+            }
+        }
+        let mut sleeptime = backoff.initial();
+        let mut buffer_blocks = buffer_blocks;
+        let mut data_consumer = Some(data_consumer);
+        let mut playlist = HeldPlaylist::new(playlist, holds.len());
+        let mut active_entries = Vec::new();
+        let mut timeline = HoldTimeline::new(Arc::clone(&holds));
+        let mut current_frame = 0;
+        let mut seek_frame = 0;
+        // NB: Files are re-positioned when reading this frame (after seeks and jumps)
+        let mut reposition_frame = 0;
+        let mut frames_since_seek = 0;
+        let mut seek_generation = 0;
+        // Scheduled seek (`at` and `target`) which has been accepted
+        let mut jump: Option<(u64, u64)> = None;
+        // NB: Only used for jumps within a block
+        let mut scratch = Block::new(blocksize, channels, 0);
+        let mut trims = Trims::new(trims);
+
+        while keep_reading.load(Ordering::Acquire) {
+            if let Ok((frame, mut queue)) = seek_consumer.pop() {
+                if let Some(new_size) = stats.grown_buffer_blocks() {
+                    // NB: While seeking, the data queue is not used in the audio thread,
+                    //     it can safely be replaced by a larger one.
+                    let (producer, consumer) =
+                        make_data_queue(new_size as usize, blocksize, channels, &holds);
+                    data_producer = producer;
+                    queue = consumer;
+                    buffer_blocks = new_size;
+                    stats.buffer_blocks.store(new_size, Ordering::Relaxed);
+                } else {
+                    queue.clear();
+                }
+                data_consumer = Some(queue);
+                current_frame = frame;
+                seek_frame = frame;
+                reposition_frame = frame;
+                frames_since_seek = 0;
+                seek_generation += 1;
+                jump = None;
+                trims.reset();
+                timeline.reset(frame);
+            }
+            while let Ok((generation, at, target)) = cue_consumer.pop() {
+                // NB: Requests made before the most recent seek are outdated
+                let accepted =
+                    generation == seek_generation && jump.is_none() && at >= current_frame;
+                if accepted {
+                    jump = Some((at, target));
+                }
+                // NB: This can only fail if the audio thread doesn't ask for answers,
+                //     which means it isn't interested in them.
+                let _ = cue_result_producer.push((generation, at, accepted));
+            }
+            if data_consumer.is_none() {
+                // NB: Recycled blocks have been consumed since the last refill
+                let depletion = data_producer.recycling_consumer.len() as u32;
+                stats.max_depletion.fetch_max(depletion, Ordering::Relaxed);
+            }
+            let mut block = match data_producer.write_block() {
+                Some(block) => block,
+                None => {
+                    thread::sleep(sleeptime);
+                    sleeptime = backoff.next(sleeptime);
+                    continue;
+                }
+            };
+            sleeptime = backoff.initial();
+            let read_start = Instant::now();
+            if let Some((_, target)) = jump.filter(|&(at, _)| at == current_frame) {
+                current_frame = target;
+                reposition_frame = target;
+                jump = None;
+                timeline.reset(target);
+            }
+            timeline.advance(current_frame, blocksize);
+            read_block(
+                &mut workers,
+                &mut file_shards,
+                &mut playlist,
+                &timeline,
+                &mut active_entries,
+                current_frame,
+                reposition_frame,
+                blocksize,
+                block.channels(),
+            )?;
+            let mut next_frame = current_frame + u64::from(blocksize);
+            if let Some((at, target)) = jump.filter(|&(at, _)| at < next_frame) {
+                // NB: The rest of the block is taken from the target position
+                let offset = (at - current_frame) as usize;
+                for channel in scratch.channels.iter_mut() {
+                    channel.fill(0.0);
+                }
+                timeline.reset(target);
+                timeline.advance(target, blocksize);
+                read_block(
+                    &mut workers,
+                    &mut file_shards,
+                    &mut playlist,
+                    &timeline,
+                    &mut active_entries,
+                    target,
+                    target,
+                    blocksize,
+                    &mut scratch.channels,
+                )?;
+                let remaining = blocksize as usize - offset;
+                for (channel, source) in block.channels().iter_mut().zip(scratch.channels.iter()) {
+                    channel[offset..].copy_from_slice(&source[..remaining]);
+                }
+                next_frame = target + remaining as u64;
+                reposition_frame = next_frame;
+                jump = None;
+            }
+            trims.apply(block.channels());
+            block.set_holds(&timeline);
+            current_frame = next_frame;
+            frames_since_seek += u64::from(blocksize);
+            let read_time = read_start.elapsed().as_micros() as u64;
+            stats
+                .max_block_read_time
+                .fetch_max(read_time, Ordering::Relaxed);
+
+            // Make sure the block is queued before data_consumer is sent
+            drop(block);
+
+            let priming_blocks = priming_blocks.map_or(buffer_blocks, |b| b.min(buffer_blocks));
+            if frames_since_seek >= u64::from(priming_blocks) * u64::from(blocksize) {
+                if let Some(data_consumer) = data_consumer.take() {
+                    // There is only one data queue, push() will always succeed
+                    ready_producer.push((seek_frame, data_consumer)).unwrap();
+                }
+            }
+        }
+        for worker in workers {
+            // NB: Dropping the sender terminates the worker thread
+            drop(worker.jobs);
+            let _ = worker.thread.join();
+        }
+        Ok(())
+    };
+    builder.spawn(body).expect("failed to spawn reader thread")
 }
 
-unsafe fn fill_with_zeros(target: &[*mut f32], blocksize: u32) {
+unsafe fn fill_with_zeros(target: &[*mut f32], start: u32, frames: u32) {
     for ptr in target {
-        for f in 0..blocksize {
+        for f in start..start + frames {
             *ptr.add(f as usize) = 0.0f32;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(ranges: &[(u64, u64)]) -> Vec<PlaylistEntry> {
+        ranges
+            .iter()
+            .enumerate()
+            .map(|(idx, &(begin, duration))| PlaylistEntry {
+                begin,
+                duration,
+                idx,
+                hold: None,
+            })
+            .collect()
+    }
+
+    /// Compares `find_active()` with a linear search for (short) ranges
+    /// between (and around) the given boundaries.
+    fn check(ranges: &[(u64, u64)]) {
+        let mut index = PlaylistIndex::new(entries(ranges));
+        let mut times: Vec<u64> = ranges
+            .iter()
+            .flat_map(|&(begin, duration)| vec![begin, begin + duration])
+            .flat_map(|t| vec![t.saturating_sub(1), t, t + 1])
+            .collect();
+        times.sort_unstable();
+        times.dedup();
+        times.push(u64::MAX);
+        let mut result = Vec::new();
+        for (i, &start) in times.iter().enumerate() {
+            for &end in times[i + 1..].iter().take(10).chain(Some(&u64::MAX)) {
+                result.clear();
+                index.find_active(start, end, &mut result);
+                let mut found: Vec<usize> = result.iter().map(|&i| index.entries[i].idx).collect();
+                found.sort_unstable();
+                let expected: Vec<usize> = ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &(begin, duration))| begin < end && start < begin + duration)
+                    .map(|(idx, _)| idx)
+                    .collect();
+                assert_eq!(found, expected, "{:?} from {} to {}", ranges, start, end);
+            }
+        }
+    }
+
+    /// Simple deterministic pseudo-random numbers (xorshift).
+    fn random_ranges(count: usize, mut seed: u64) -> Vec<(u64, u64)> {
+        let mut next = move |max: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % max
+        };
+        (0..count).map(|_| (next(1000), next(100))).collect()
+    }
+
+    #[test]
+    fn empty_playlist() {
+        let mut index = PlaylistIndex::new(Vec::new());
+        let mut result = Vec::new();
+        index.find_active(0, u64::MAX, &mut result);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn various_sizes() {
+        for count in 1..=70 {
+            check(&random_ranges(count, count as u64));
+        }
+        check(&random_ranges(1000, 1));
+        check(&random_ranges(1023, 2));
+        check(&random_ranges(1025, 3));
+    }
+
+    #[test]
+    fn nested_ranges() {
+        let ranges: Vec<_> = (0..40).map(|i| (i * 10, 1000 - i * 20)).collect();
+        check(&ranges);
+    }
+
+    #[test]
+    fn long_ranges() {
+        for count in [5, 16, 33, 100].iter() {
+            let mut ranges: Vec<_> = (0..*count).map(|i| (i * 10, 5)).collect();
+            // The long entry is hidden at different positions in the tree
+            for position in 0..*count as usize {
+                ranges[position] = (position as u64 * 10, 10_000);
+                check(&ranges);
+                ranges[position] = (position as u64 * 10, 5);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_length_ranges() {
+        check(&[(0, 0), (5, 0), (5, 10), (10, 0), (15, 0)]);
+        let ranges: Vec<_> = (0..50).map(|i| (i * 3, i % 3)).collect();
+        check(&ranges);
+    }
+
+    #[test]
+    fn exact_boundaries() {
+        let mut index = PlaylistIndex::new(entries(&[(10, 10), (20, 10)]));
+        let mut find = |start, end| {
+            let mut result = Vec::new();
+            index.find_active(start, end, &mut result);
+            let mut found: Vec<usize> = result.iter().map(|&i| index.entries[i].idx).collect();
+            found.sort_unstable();
+            found
+        };
+        assert_eq!(find(0, 10), []);
+        assert_eq!(find(0, 11), [0]);
+        assert_eq!(find(19, 20), [0]);
+        assert_eq!(find(20, 21), [1]);
+        assert_eq!(find(19, 21), [0, 1]);
+        assert_eq!(find(29, 30), [1]);
+        assert_eq!(find(30, 40), []);
+    }
+
+    #[test]
+    fn trims() {
+        let mut trims = Trims::new(
+            vec![
+                ChannelTrim {
+                    inverted: true,
+                    delay: 0,
+                },
+                // Longer than one block
+                ChannelTrim {
+                    inverted: false,
+                    delay: 5,
+                },
+                ChannelTrim {
+                    inverted: true,
+                    delay: 2,
+                },
+            ]
+            .into(),
+        );
+        // Three channels with four consecutive numbers, starting with `first`
+        fn block(trims: &mut Trims, first: usize) -> Vec<Vec<f32>> {
+            let mut channels: Vec<Box<[f32]>> = (0..3)
+                .map(|_| (first..first + 4).map(|x| x as f32).collect())
+                .collect();
+            trims.apply(&mut channels);
+            channels.into_iter().map(Vec::from).collect()
+        }
+        assert_eq!(
+            block(&mut trims, 1),
+            [
+                [-1.0, -2.0, -3.0, -4.0],
+                [0.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, -1.0, -2.0],
+            ]
+        );
+        assert_eq!(
+            block(&mut trims, 5),
+            [
+                [-5.0, -6.0, -7.0, -8.0],
+                [0.0, 1.0, 2.0, 3.0],
+                [-3.0, -4.0, -5.0, -6.0],
+            ]
+        );
+        trims.reset();
+        assert_eq!(
+            block(&mut trims, 20),
+            [
+                [-20.0, -21.0, -22.0, -23.0],
+                [0.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, -20.0, -21.0],
+            ]
+        );
+    }
+}
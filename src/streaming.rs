@@ -0,0 +1,58 @@
+//! Real-time streaming of audio files without a `Scene`,
+//! e.g. for applications with their own scene description format.
+//!
+//! The streaming engine consists of three layers:
+//!
+//! * The *file storage* ([`FileStorage`](type.FileStorage.html)) contains
+//!   all opened audio files (see [`load_audio_file()`](fn.load_audio_file.html)),
+//!   each with a [`ChannelMap`](type.ChannelMap.html) which specifies
+//!   the output channel(s) of each file channel.
+//! * The *playlist* contains one [`PlaylistEntry`](struct.PlaylistEntry.html)
+//!   per playback of a file (the same file can be played multiple times,
+//!   but the entries of one file must not overlap).
+//! * The [`FileStreamer`](struct.FileStreamer.html), created with a
+//!   [`StreamerBuilder`](struct.StreamerBuilder.html), reads the audio data
+//!   in a separate thread (see `StreamerBuilder::reader_threads()`)
+//!   and provides it in real-time-safe calls of `FileStreamer::get_data()`.
+//!
+//! ```no_run
+//! use asdf::streaming::{load_audio_file, AudioFileBasics, PlaylistEntry, StreamerBuilder};
+//! use std::num::NonZeroU64;
+//!
+//! let file = load_audio_file(
+//!     "my-file.wav",
+//!     48000,
+//!     1.0,
+//!     NonZeroU64::new(1).unwrap(),
+//!     None,
+//!     false,
+//!     None,
+//! )
+//! .unwrap();
+//! let frames = file.frames();
+//! // NB: The first file channel is written to output channel 0
+//! let channel_map = vec![vec![0].into_boxed_slice()].into_boxed_slice();
+//! let playlist = vec![PlaylistEntry {
+//!     begin: 48000,
+//!     duration: frames,
+//!     idx: 0,
+//!     hold: None,
+//! }];
+//! let mut streamer = StreamerBuilder::new(1024, 1)
+//!     .buffer_blocks(8)
+//!     .build(playlist, vec![(file, channel_map)]);
+//! while !streamer.seek(0) {
+//!     std::thread::sleep(std::time::Duration::from_millis(1));
+//! }
+//! let mut buffer = vec![0.0f32; 1024];
+//! // In the audio callback:
+//! unsafe { streamer.get_data(&[buffer.as_mut_ptr()], 1024, true) }.unwrap();
+//! ```
+//!
+//! Sources that are not backed by files (e.g. live inputs),
+//! transforms and all other parts of a scene are not handled here.
+
+pub use crate::audiofile::dynamic::{load_audio_file, AudioFile, LoadError as AudioFileError};
+pub use crate::audiofile::{AudioFileBasics, ChannelMap};
+pub use crate::parser::{FileStorage, PlaylistEntry};
+pub use crate::streamer::{Backoff, ChannelTrim, FileStreamer, Hold, StreamerBuilder};
@@ -0,0 +1,129 @@
+//! Conversion between frames and musical time (bars and beats).
+//!
+//! A tempo map is defined with `<tempo>` elements in the `<head>` of a scene.
+//! The first bar begins after the lead-in.
+
+/// Part of the tempo map with constant tempo and time signature
+#[derive(Clone)]
+struct Segment {
+    /// Number of beats (counted from zero) at the begin of the segment
+    beat: f64,
+    /// Number of bars (counted from zero) at the begin of the segment
+    bar: f64,
+    /// Begin of the segment in frames (relative to `TempoMap::offset`)
+    frame: f64,
+    frames_per_beat: f64,
+    beats_per_bar: f64,
+}
+
+/// Position in musical time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MusicalPosition {
+    /// One-based bar number (zero and below during the lead-in)
+    pub bar: i64,
+    /// One-based (fractional) beat number within the bar
+    pub beat: f64,
+}
+
+#[derive(Clone)]
+pub struct TempoMap {
+    samplerate: u32,
+    /// Begin of the first bar (in frames)
+    offset: u64,
+    /// Sorted by begin time, never empty
+    segments: Vec<Segment>,
+}
+
+impl TempoMap {
+    pub(crate) fn new(samplerate: u32, offset: u64, bpm: f64, beats_per_bar: f64) -> TempoMap {
+        TempoMap {
+            samplerate,
+            offset,
+            segments: vec![Segment {
+                beat: 0.0,
+                bar: 0.0,
+                frame: 0.0,
+                frames_per_beat: 60.0 * f64::from(samplerate) / bpm,
+                beats_per_bar,
+            }],
+        }
+    }
+
+    /// Adds a tempo change at the begin of `bar` (counted from zero).
+    ///
+    /// If `beats_per_bar` is `None`, the previous value is used.
+    pub(crate) fn add_change(
+        &mut self,
+        bar: u64,
+        bpm: f64,
+        beats_per_bar: Option<f64>,
+    ) -> Result<(), &'static str> {
+        let last = self.segments.last().unwrap();
+        let bar = bar as f64;
+        if bar <= last.bar {
+            return Err("Tempo changes must be in increasing bar order");
+        }
+        let beat = last.beat + (bar - last.bar) * last.beats_per_bar;
+        let segment = Segment {
+            beat,
+            bar,
+            frame: last.frame + (beat - last.beat) * last.frames_per_beat,
+            frames_per_beat: 60.0 * f64::from(self.samplerate) / bpm,
+            beats_per_bar: beats_per_bar.unwrap_or(last.beats_per_bar),
+        };
+        self.segments.push(segment);
+        Ok(())
+    }
+
+    /// Returns the last segment for which `key` is not larger than `value`
+    /// (or the first segment).
+    fn segment<F: Fn(&Segment) -> f64>(&self, key: F, value: f64) -> &Segment {
+        let idx = self
+            .segments
+            .iter()
+            .rposition(|s| key(s) <= value)
+            .unwrap_or(0);
+        &self.segments[idx]
+    }
+
+    /// Number of beats (counted from zero at the begin of the first bar).
+    ///
+    /// This is negative during the lead-in.
+    pub fn frames_to_beats(&self, frame: u64) -> f64 {
+        let frame = frame as f64 - self.offset as f64;
+        let s = self.segment(|s| s.frame, frame);
+        s.beat + (frame - s.frame) / s.frames_per_beat
+    }
+
+    /// Inverse of `frames_to_beats()` (rounded to the nearest frame).
+    ///
+    /// Negative results are clamped to zero.
+    pub fn beats_to_frames(&self, beats: f64) -> u64 {
+        self.beats_to_fractional_frames(beats).round().max(0.0) as u64
+    }
+
+    pub(crate) fn beats_to_fractional_frames(&self, beats: f64) -> f64 {
+        let s = self.segment(|s| s.beat, beats);
+        self.offset as f64 + s.frame + (beats - s.beat) * s.frames_per_beat
+    }
+
+    pub(crate) fn beats_to_bars(&self, beats: f64) -> f64 {
+        let s = self.segment(|s| s.beat, beats);
+        s.bar + (beats - s.beat) / s.beats_per_bar
+    }
+
+    pub(crate) fn bars_to_beats(&self, bars: f64) -> f64 {
+        let s = self.segment(|s| s.bar, bars);
+        s.beat + (bars - s.bar) * s.beats_per_bar
+    }
+
+    /// Bar and beat at the given `frame`, e.g. for displaying the playback position.
+    pub fn position(&self, frame: u64) -> MusicalPosition {
+        let beats = self.frames_to_beats(frame);
+        let bar = self.beats_to_bars(beats).floor();
+        MusicalPosition {
+            bar: bar as i64 + 1,
+            beat: beats - self.bars_to_beats(bar) + 1.0,
+        }
+    }
+}
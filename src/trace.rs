@@ -0,0 +1,193 @@
+//! Recording of transform queries, see `Scene::enable_transform_trace()`.
+//!
+//! When a renderer and the scene disagree about where a source was at a
+//! given moment, the trace shows which frames were actually queried
+//! (for which source) and which transforms were returned.
+//!
+//! Only the most recent queries are kept, older ones are discarded.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::transform::Transform;
+
+#[derive(Clone)]
+pub struct TransformQuery {
+    /// Zero-based source index, `None` for the reference
+    pub source: Option<usize>,
+    pub frame: u64,
+    /// `None` if the source was inactive
+    pub transform: Option<Transform>,
+}
+
+#[derive(Clone)]
+pub struct TransformTrace {
+    queries: VecDeque<TransformQuery>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl TransformTrace {
+    fn with_capacity(capacity: usize) -> TransformTrace {
+        TransformTrace {
+            queries: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    /// Recorded queries, oldest first.
+    pub fn queries(&self) -> impl Iterator<Item = &TransformQuery> {
+        self.queries.iter()
+    }
+
+    /// Number of queries which were not recorded or have been discarded
+    /// (because the capacity was exceeded).
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Discards the oldest query if the capacity is exceeded.
+    fn push(&mut self, query: TransformQuery) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.queries.len() == self.capacity {
+            self.queries.pop_front();
+            self.dropped += 1;
+        }
+        self.queries.push_back(query);
+    }
+
+    /// Moves all queries from `other` (which keeps its memory).
+    fn append(&mut self, other: &mut TransformTrace) {
+        self.dropped += std::mem::take(&mut other.dropped);
+        for query in other.queries.drain(..) {
+            self.push(query);
+        }
+    }
+}
+
+/// Records queries from multiple threads without blocking.
+///
+/// Queries are recorded into one of two buffers, `snapshot()` switches
+/// to the other one before collecting the queries of the first one,
+/// therefore reading the trace doesn't disturb the recording.
+pub(crate) struct Tracer {
+    buffers: [Mutex<TransformTrace>; 2],
+    /// Index of the buffer used by `record()`
+    active: AtomicUsize,
+    /// Queries collected from `buffers`
+    history: Mutex<TransformTrace>,
+    /// Queries which were skipped because another thread was recording at the same time
+    skipped: AtomicU64,
+}
+
+impl Tracer {
+    /// All memory is allocated in advance.
+    pub(crate) fn new(capacity: usize) -> Tracer {
+        Tracer {
+            buffers: [
+                Mutex::new(TransformTrace::with_capacity(capacity)),
+                Mutex::new(TransformTrace::with_capacity(capacity)),
+            ],
+            active: AtomicUsize::new(0),
+            history: Mutex::new(TransformTrace::with_capacity(capacity)),
+            skipped: AtomicU64::new(0),
+        }
+    }
+
+    /// This is realtime-safe.
+    pub(crate) fn record(&self, source: Option<usize>, frame: u64, transform: Option<&Transform>) {
+        loop {
+            let active = self.active.load(Ordering::Acquire);
+            match self.buffers[active].try_lock() {
+                Ok(mut trace) => {
+                    trace.push(TransformQuery {
+                        source,
+                        frame,
+                        transform: transform.cloned(),
+                    });
+                    return;
+                }
+                // NB: The buffer is being collected by `snapshot()`
+                Err(_) if self.active.load(Ordering::Acquire) != active => continue,
+                Err(_) => {
+                    self.skipped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> TransformTrace {
+        let mut history = self.history.lock().unwrap();
+        let previous = self.active.fetch_xor(1, Ordering::AcqRel);
+        // NB: This only waits for a thread which is just recording into it
+        history.append(&mut self.buffers[previous].lock().unwrap());
+        let mut trace = history.clone();
+        trace.dropped += self.skipped.load(Ordering::Relaxed);
+        trace
+    }
+
+    pub(crate) fn into_trace(self) -> TransformTrace {
+        let active = self.active.into_inner();
+        let mut buffers = self.buffers;
+        let mut trace = self.history.into_inner().unwrap();
+        for &idx in &[1 - active, active] {
+            trace.append(buffers[idx].get_mut().unwrap());
+        }
+        trace.dropped += self.skipped.into_inner();
+        trace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn snapshot_doesnt_drop_queries() {
+        const QUERIES: u64 = 100_000;
+        let tracer = Arc::new(Tracer::new(QUERIES as usize));
+        let recorder = {
+            let tracer = Arc::clone(&tracer);
+            std::thread::spawn(move || {
+                for frame in 0..QUERIES {
+                    tracer.record(Some(0), frame, None);
+                }
+            })
+        };
+        let mut previous = 0;
+        for _ in 0..100 {
+            let trace = tracer.snapshot();
+            assert_eq!(trace.dropped(), 0);
+            let frames: Vec<_> = trace.queries().map(|q| q.frame).collect();
+            assert!(frames.iter().copied().eq(0..frames.len() as u64));
+            assert!(frames.len() >= previous);
+            previous = frames.len();
+        }
+        recorder.join().unwrap();
+        let trace = Arc::try_unwrap(tracer).ok().unwrap().into_trace();
+        assert_eq!(trace.dropped(), 0);
+        assert!(trace.queries().map(|q| q.frame).eq(0..QUERIES));
+    }
+
+    #[test]
+    fn oldest_queries_are_discarded() {
+        let tracer = Tracer::new(3);
+        for frame in 0..5 {
+            tracer.record(None, frame, None);
+        }
+        let trace = tracer.snapshot();
+        assert!(trace.queries().map(|q| q.frame).eq(2..5));
+        assert_eq!(trace.dropped(), 2);
+        tracer.record(None, 5, None);
+        let trace = tracer.into_trace();
+        assert!(trace.queries().map(|q| q.frame).eq(3..6));
+        assert_eq!(trace.dropped(), 3);
+    }
+}
@@ -3,6 +3,7 @@ use std::str::FromStr;
 use xmlparser as xml;
 
 use crate::error::ResultExt;
+use crate::expression::Expression;
 use crate::parser::error::ParseError;
 use crate::parser::Attributes;
 
@@ -22,6 +23,9 @@ pub struct Transform {
     pub translation: Option<Vec3>,
     // NB: Some operations are order-independent: gain, ...
     // TODO: gain, ...
+    /// Whether the position has been moved into the area given by
+    /// `Scene::set_area()`
+    pub clamped: bool,
 }
 
 impl Transform {
@@ -43,6 +47,7 @@ impl Transform {
                     translation: one
                         .translation
                         .map(|v| v + two.translation.unwrap_or_else(Vec3::zeros)),
+                    clamped: one.clamped || two.clamped,
                 }
             } else {
                 one
@@ -70,11 +75,147 @@ impl Transform {
                 *self.translation.get_or_insert_with(Vec3::zeros) += translation;
             }
 
+            self.clamped |= other.clamped;
+
             // TODO: handle other members
         }
     }
 }
 
+/// Progression of time between two keyframes, like CSS `transition-timing-function`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Control points `x1, y1, x2, y2`, the end points are `(0, 0)` and `(1, 1)`.
+    ///
+    /// `x1` and `x2` must be between `0` and `1`.
+    CubicBezier([f32; 4]),
+}
+
+impl Default for Easing {
+    fn default() -> Easing {
+        Easing::Linear
+    }
+}
+
+impl Easing {
+    pub const EASE: Easing = Easing::CubicBezier([0.25, 0.1, 0.25, 1.0]);
+    pub const EASE_IN: Easing = Easing::CubicBezier([0.42, 0.0, 1.0, 1.0]);
+    pub const EASE_OUT: Easing = Easing::CubicBezier([0.0, 0.0, 0.58, 1.0]);
+    pub const EASE_IN_OUT: Easing = Easing::CubicBezier([0.42, 0.0, 0.58, 1.0]);
+
+    /// Maps the relative time `x` (from `0` to `1`) to the relative progress.
+    pub fn apply(&self, x: f32) -> f32 {
+        let [x1, y1, x2, y2] = match *self {
+            Easing::Linear => return x,
+            Easing::CubicBezier(points) => points,
+        };
+        if x <= 0.0 || x >= 1.0 {
+            return x;
+        }
+        let bezier = |a: f32, b: f32, t: f32| {
+            let s = 1.0 - t;
+            3.0 * s * s * t * a + 3.0 * s * t * t * b + t * t * t
+        };
+        // Newton's method, falling back to bisection
+        let mut t = x;
+        for _ in 0..8 {
+            let error = bezier(x1, x2, t) - x;
+            if error.abs() < 1e-6 {
+                return bezier(y1, y2, t);
+            }
+            let s = 1.0 - t;
+            let slope = 3.0 * s * s * x1 + 6.0 * s * t * (x2 - x1) + 3.0 * t * t * (1.0 - x2);
+            if slope.abs() < 1e-6 {
+                break;
+            }
+            t -= error / slope;
+        }
+        let (mut lower, mut upper) = (0.0, 1.0);
+        t = x;
+        for _ in 0..32 {
+            if bezier(x1, x2, t) < x {
+                lower = t;
+            } else {
+                upper = t;
+            }
+            t = (lower + upper) / 2.0;
+        }
+        bezier(y1, y2, t)
+    }
+}
+
+impl std::fmt::Display for Easing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let names = [
+            (Easing::Linear, "linear"),
+            (Easing::EASE, "ease"),
+            (Easing::EASE_IN, "ease-in"),
+            (Easing::EASE_OUT, "ease-out"),
+            (Easing::EASE_IN_OUT, "ease-in-out"),
+        ];
+        if let Some((_, name)) = names.iter().find(|(easing, _)| easing == self) {
+            return f.write_str(name);
+        }
+        match *self {
+            Easing::Linear => unreachable!(),
+            Easing::CubicBezier([x1, y1, x2, y2]) => {
+                write!(f, "cubic-bezier({}, {}, {}, {})", x1, y1, x2, y2)
+            }
+        }
+    }
+}
+
+/// `linear`, `ease`, `ease-in`, `ease-out`, `ease-in-out`
+/// or `cubic-bezier(x1, y1, x2, y2)`.
+pub fn parse_easing(value: xml::StrSpan) -> Result<Easing, ParseError> {
+    let text = value.as_str().trim();
+    Ok(match text {
+        "linear" => Easing::Linear,
+        "ease" => Easing::EASE,
+        "ease-in" => Easing::EASE_IN,
+        "ease-out" => Easing::EASE_OUT,
+        "ease-in-out" => Easing::EASE_IN_OUT,
+        _ => {
+            let args = text
+                .strip_prefix("cubic-bezier(")
+                .and_then(|s| s.strip_suffix(')'))
+                .ok_or_else(|| {
+                    ParseError::new(
+                        "Invalid easing (expected \"linear\", \"ease\", \"ease-in\", \
+                         \"ease-out\", \"ease-in-out\" or \"cubic-bezier(...)\")",
+                        value,
+                    )
+                })?;
+            let mut points = [0.0; 4];
+            let mut values = args
+                .split(',')
+                .map(|s| f32::from_str(s.trim()).context(value));
+            for point in points.iter_mut() {
+                *point = values.next().unwrap_or_else(|| {
+                    Err(ParseError::new(
+                        "Exactly 4 numbers are needed for \"cubic-bezier()\"",
+                        value,
+                    ))
+                })?;
+            }
+            if values.next().is_some() {
+                return Err(ParseError::new(
+                    "Exactly 4 numbers are needed for \"cubic-bezier()\"",
+                    value,
+                ));
+            }
+            if !(0.0..=1.0).contains(&points[0]) || !(0.0..=1.0).contains(&points[2]) {
+                return Err(ParseError::new(
+                    "x values of \"cubic-bezier()\" must be between 0 and 1",
+                    value,
+                ));
+            }
+            Easing::CubicBezier(points)
+        }
+    })
+}
+
 pub fn parse_transform<'a>(
     attributes: &mut Attributes<'a>,
 ) -> Result<Option<Transform>, ParseError> {
@@ -99,6 +240,12 @@ pub fn parse_transform<'a>(
 }
 
 pub fn parse_pos(value: xml::StrSpan) -> Result<Vec3, ParseError> {
+    if value.as_str().contains(',') {
+        return Err(ParseError::new(
+            "Expressions (separated by commas) are only allowed in <transform> elements",
+            value,
+        ));
+    }
     let mut values = value
         .as_str()
         .split_whitespace()
@@ -125,6 +272,35 @@ pub fn parse_pos(value: xml::StrSpan) -> Result<Vec3, ParseError> {
     Ok(Vec3::new(x, y, z))
 }
 
+/// Comma-separated list of 2 or 3 expressions depending on the time `t`,
+/// e.g. `pos="sin(2*pi*t/10)*2, 0, 0"`.
+///
+/// Names of live inputs are appended to `inputs` (if not already there).
+pub fn parse_pos_expression(
+    value: xml::StrSpan,
+    inputs: &mut Vec<String>,
+) -> Result<[Expression; 3], ParseError> {
+    let mut values = value
+        .as_str()
+        .split(',')
+        .map(|s| Expression::parse(s, inputs).map_err(|msg| ParseError::new(msg, value)));
+    let x = values.next().unwrap()?;
+    let y = values.next().unwrap_or_else(|| {
+        Err(ParseError::new(
+            "At least 2 expressions are needed for \"pos\"",
+            value,
+        ))
+    })?;
+    let z = values.next().unwrap_or(Ok(Expression::Constant(0.0)))?;
+    if values.next().is_some() {
+        return Err(ParseError::new(
+            "No more than 3 expressions are allowed for \"pos\"",
+            value,
+        ));
+    }
+    Ok([x, y, z])
+}
+
 pub fn parse_rot(value: xml::StrSpan) -> Result<Quat, ParseError> {
     let mut values = value
         .as_str()
@@ -0,0 +1,146 @@
+//! Test mode for checking that clips are placed sample-accurately,
+//! e.g. when clips begin in the middle of a block.
+
+use std::error::Error;
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+
+use crate::audiofile::dynamic::load_audio_file;
+use crate::export::wait_for;
+use crate::Scene;
+
+/// Maximum difference between streamed and decoded samples
+const TOLERANCE: f32 = 1e-5;
+
+/// Number of blocks compared after the begin of each clip
+const CHECK_BLOCKS: u32 = 4;
+
+/// Result of comparing the beginning of a clip with the contents of its file.
+pub struct ClipAlignment {
+    /// Zero-based source index
+    pub source: usize,
+    /// Begin time of the clip in frames
+    pub begin: u64,
+    pub file: PathBuf,
+    /// Zero-based channel number within the file
+    pub channel: u32,
+    /// Number of frames the streamed audio data is delayed (negative: too early),
+    /// `None` if no match was found within two blocks in each direction
+    pub offset: Option<i64>,
+}
+
+impl ClipAlignment {
+    pub fn is_aligned(&self) -> bool {
+        self.offset == Some(0)
+    }
+}
+
+impl Scene {
+    /// Compares the streamed audio data of all clips with the contents
+    /// of their files (decoded and resampled separately).
+    ///
+    /// For each clip, playback is started shortly before its begin,
+    /// in a way that the clip begins in the middle of a block.
+    /// Only the first few blocks of each clip are checked.
+    /// Clips overlapping other clips of the same source cannot be checked
+    /// reliably, the same is true for clips starting with silence
+    /// or a constant signal.
+    ///
+    /// Like `export_range()`, this uses the same streaming mechanism
+    /// as playback, it must not be called while the scene is used for playback.
+    /// Afterwards, playback is stopped and the scene has to be seeked before
+    /// it can be used again.
+    pub fn verify_clip_alignment(
+        &mut self,
+    ) -> Result<Vec<ClipAlignment>, Box<dyn Error + Send + Sync>> {
+        let blocksize = self.streamer.blocksize();
+        let max_lag = 2 * i64::from(blocksize);
+        let mut pre_roll =
+            max_lag as u64 + u64::from(self.streamer.fade_length()) + u64::from(blocksize / 2);
+        if pre_roll % u64::from(blocksize) == 0 {
+            // NB: The clip should begin in the middle of a block
+            pre_roll += 1;
+        }
+        let clips: Vec<_> = (0..self.sources.len())
+            .flat_map(|source| {
+                self.clips(source).map(move |clip| {
+                    let alignment = ClipAlignment {
+                        source,
+                        begin: clip.begin,
+                        file: clip.file.into(),
+                        channel: clip.channel,
+                        offset: None,
+                    };
+                    (alignment, clip.duration, clip.speed)
+                })
+            })
+            .collect();
+        let mut buffers = vec![vec![0.0f32; blocksize as usize]; self.file_sources() as usize];
+        let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+        let mut results = Vec::with_capacity(clips.len());
+
+        for (mut clip, duration, speed) in clips {
+            let begin = clip.begin;
+            let start = begin.saturating_sub(pre_roll);
+            // NB: The beginning is faded in
+            let compare_begin = (clip.begin.saturating_sub(max_lag as u64))
+                .max(start + u64::from(self.streamer.fade_length()));
+            let compare_end = clip.begin + u64::from(CHECK_BLOCKS * blocksize);
+
+            let mut file = load_audio_file(
+                &clip.file,
+                self.samplerate,
+                speed,
+                NonZeroU64::new(1).unwrap(),
+                None,
+                false,
+                self.open.as_ref(),
+            )?;
+            let reference_frames = (compare_end - clip.begin + max_lag as u64)
+                .min(duration)
+                .min(file.frames()) as usize;
+            let mut channel_map: Vec<Box<[usize]>> = vec![Box::default(); clip.channel as usize];
+            channel_map.push(vec![0].into());
+            let mut reference = [vec![0.0f32; reference_frames].into_boxed_slice()];
+            file.seek(0)?;
+            file.fill_channels(&channel_map, reference_frames as u32, 0, &mut reference)?;
+            let reference = &reference[0];
+
+            self.streamer.stop();
+            let _holds = self.streamer.suspend_holds();
+            wait_for(|| self.seek(start))?;
+            let mut streamed = Vec::new();
+            while start + (streamed.len() as u64) < compare_end {
+                wait_for(|| self.streamer.frames_available() >= blocksize as usize)?;
+                if !unsafe { self.get_audio_data(&pointers, true) } {
+                    return Err("Unrecoverable error getting audio file data".into());
+                }
+                let ptr = pointers[clip.source];
+                streamed.extend((0..blocksize as usize).map(|i| unsafe { *ptr.add(i) }));
+            }
+
+            let matches = |lag: i64| {
+                let mut compared = 0;
+                for frame in compare_begin..compare_end {
+                    let idx = frame as i64 - begin as i64 - lag;
+                    if idx < 0 || idx >= reference.len() as i64 {
+                        continue;
+                    }
+                    let value = streamed[(frame - start) as usize];
+                    if (value - reference[idx as usize]).abs() > TOLERANCE {
+                        return false;
+                    }
+                    compared += 1;
+                }
+                compared > 0
+            };
+            // NB: Smaller offsets are preferred
+            clip.offset = (0..=max_lag)
+                .flat_map(|lag| vec![lag, -lag])
+                .find(|&lag| matches(lag));
+            results.push(clip);
+        }
+        self.streamer.stop();
+        Ok(results)
+    }
+}
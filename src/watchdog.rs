@@ -0,0 +1,80 @@
+//! Detection of sources which deliver only silence although they should be
+//! audible, see `Scene::enable_silence_watchdog()`.
+//!
+//! This helps to find dead files and mis-mapped channels during rehearsal.
+//! Only blocks during which a source has an active clip (and isn't silenced
+//! by mute, solo or gain settings) are taken into account, the silence counter
+//! of a source is reset whenever its clip ends (or doesn't cover the whole block),
+//! whenever it is muted and after seeking.
+
+/// Samples with an absolute value below this (-100 dB) count as silence
+pub const SILENCE_THRESHOLD: f32 = 1e-5;
+
+pub struct SilenceWatchdog {
+    /// Duration of silence (in frames) after which a source is reported
+    limit: u64,
+    /// Number of consecutive silent frames of each (active) source
+    silent_frames: Box<[u64]>,
+}
+
+impl SilenceWatchdog {
+    /// All memory is allocated in advance.
+    pub fn new(sources: usize, limit: u64) -> SilenceWatchdog {
+        SilenceWatchdog {
+            limit,
+            silent_frames: vec![0; sources].into(),
+        }
+    }
+
+    /// Whether the given source has been silent for (at least) the given duration.
+    /// Panics if `source_idx` is out of range.
+    pub fn is_silent(&self, source_idx: usize) -> bool {
+        self.silent_frames[source_idx] >= self.limit
+    }
+
+    /// Zero-based indices of all sources which are currently silent.
+    pub fn silent_sources(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.silent_frames.len()).filter(move |&idx| self.is_silent(idx))
+    }
+
+    pub fn reset(&mut self) {
+        for frames in self.silent_frames.iter_mut() {
+            *frames = 0;
+        }
+    }
+
+    /// Checks one block of audio data (one channel per source).
+    ///
+    /// `active` tells whether a source should be audible during the whole block,
+    /// `report` is called for each source which has just become silent.
+    ///
+    /// This is realtime-safe.
+    pub(crate) unsafe fn process<A, R>(
+        &mut self,
+        target: &[*mut f32],
+        frames: u32,
+        active: A,
+        mut report: R,
+    ) where
+        A: Fn(usize) -> bool,
+        R: FnMut(usize),
+    {
+        let pairs = target.iter().zip(self.silent_frames.iter_mut());
+        for (idx, (&ptr, counter)) in pairs.enumerate() {
+            if !active(idx) {
+                *counter = 0;
+                continue;
+            }
+            let data = std::slice::from_raw_parts(ptr, frames as usize);
+            if data.iter().all(|x| x.abs() < SILENCE_THRESHOLD) {
+                let before = *counter;
+                *counter += u64::from(frames);
+                if before < self.limit && *counter >= self.limit {
+                    report(idx);
+                }
+            } else {
+                *counter = 0;
+            }
+        }
+    }
+}
@@ -0,0 +1,677 @@
+//! Creating ASDF scenes programmatically and writing them as XML,
+//! e.g. in scene-editing tools.
+//!
+//! The structure of [`SceneBuilder`](struct.SceneBuilder.html) follows the
+//! structure of ASDF files (`<head>`, `<body>`, `<seq>`, `<par>`, ...).
+//! A loaded [`Scene`](../struct.Scene.html) cannot be converted back,
+//! because much of the original structure is lost when loading.
+//!
+//! ```no_run
+//! use asdf::writer::{Channel, Clip, Container, Keyframe, SceneBuilder, SourceSpec, TransformSpec};
+//!
+//! let mut scene = SceneBuilder::new();
+//! scene.sources.push(SourceSpec::new("vocals").name("Vocals"));
+//! let mut par = Container::par();
+//! par.push(Clip::new("vocals.wav").channel(Channel::new().source("vocals")));
+//! par.push(
+//!     TransformSpec::new(&["vocals"])
+//!         .keyframe(Keyframe::new().pos([-2.0, 2.0, 0.0]))
+//!         .keyframe(Keyframe::new().pos([2.0, 2.0, 0.0])),
+//! );
+//! scene.body.push(par);
+//! scene.write("my-scene.asdf").unwrap();
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::signal::TestSignal;
+use crate::{AmbisonicsNormalization, Easing, Metadata};
+
+/// Position and orientation, given as in ASDF files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pose {
+    /// `pos` attribute (x, y, z)
+    pub pos: Option<[f32; 3]>,
+    /// `rot` attribute (azimuth, elevation, roll in degrees)
+    pub rot: Option<[f32; 3]>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceSpec {
+    pub id: String,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub port: Option<String>,
+    /// RGB color
+    pub color: Option<[u8; 3]>,
+    pub icon: Option<String>,
+    pub comment: Option<String>,
+    pub tags: Vec<String>,
+    /// `polarity="inverted"`
+    pub inverted: bool,
+    /// In samples
+    pub delay: Option<u32>,
+    pub pose: Pose,
+}
+
+impl SourceSpec {
+    pub fn new<S: Into<String>>(id: S) -> SourceSpec {
+        SourceSpec {
+            id: id.into(),
+            name: None,
+            model: None,
+            port: None,
+            color: None,
+            icon: None,
+            comment: None,
+            tags: Vec::new(),
+            inverted: false,
+            delay: None,
+            pose: Pose::default(),
+        }
+    }
+
+    pub fn name<S: Into<String>>(mut self, name: S) -> SourceSpec {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn model<S: Into<String>>(mut self, model: S) -> SourceSpec {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn port<S: Into<String>>(mut self, port: S) -> SourceSpec {
+        self.port = Some(port.into());
+        self
+    }
+
+    pub fn color(mut self, rgb: [u8; 3]) -> SourceSpec {
+        self.color = Some(rgb);
+        self
+    }
+
+    pub fn icon<S: Into<String>>(mut self, icon: S) -> SourceSpec {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> SourceSpec {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Tags must not contain whitespace.
+    pub fn tag<S: Into<String>>(mut self, tag: S) -> SourceSpec {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn inverted(mut self) -> SourceSpec {
+        self.inverted = true;
+        self
+    }
+
+    /// Static delay in samples (at most 100 ms).
+    pub fn delay(mut self, samples: u32) -> SourceSpec {
+        self.delay = Some(samples);
+        self
+    }
+
+    pub fn pos(mut self, pos: [f32; 3]) -> SourceSpec {
+        self.pose.pos = Some(pos);
+        self
+    }
+
+    pub fn rot(mut self, rot: [f32; 3]) -> SourceSpec {
+        self.pose.rot = Some(rot);
+        self
+    }
+}
+
+/// A `<channel>` element within a `<clip>`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Channel {
+    pub id: Option<String>,
+    /// ID of the source (which doesn't have to be declared in the `<head>`)
+    pub source: Option<String>,
+    /// Number of file channels to skip (can't be combined with other attributes)
+    pub skip: Option<u32>,
+    pub pose: Pose,
+}
+
+impl Channel {
+    pub fn new() -> Channel {
+        Default::default()
+    }
+
+    pub fn skip(count: u32) -> Channel {
+        Channel {
+            skip: Some(count),
+            ..Default::default()
+        }
+    }
+
+    pub fn id<S: Into<String>>(mut self, id: S) -> Channel {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn source<S: Into<String>>(mut self, source: S) -> Channel {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn pos(mut self, pos: [f32; 3]) -> Channel {
+        self.pose.pos = Some(pos);
+        self
+    }
+
+    pub fn rot(mut self, rot: [f32; 3]) -> Channel {
+        self.pose.rot = Some(rot);
+        self
+    }
+}
+
+/// A `<clip>` element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+    pub id: Option<String>,
+    /// Relative paths are relative to the scene file,
+    /// can also be a test signal (see `signal` module)
+    pub file: PathBuf,
+    pub repeat: Option<u64>,
+    /// Crossfade between iterations (in seconds), requires `repeat`
+    pub loop_crossfade: Option<f32>,
+    pub preload: Option<bool>,
+    /// In seconds
+    pub fade_in: Option<f32>,
+    /// In seconds
+    pub fade_out: Option<f32>,
+    /// Playback speed (changes the pitch as well)
+    pub speed: Option<f64>,
+    /// Ambisonics order, see `AmbisonicsGroup`
+    /// (`channels` must be empty in this case)
+    pub ambisonics_order: Option<u32>,
+    pub ambisonics_normalization: Option<AmbisonicsNormalization>,
+    pub channels: Vec<Channel>,
+}
+
+impl Clip {
+    pub fn new<P: Into<PathBuf>>(file: P) -> Clip {
+        Clip {
+            id: None,
+            file: file.into(),
+            repeat: None,
+            loop_crossfade: None,
+            preload: None,
+            fade_in: None,
+            fade_out: None,
+            speed: None,
+            ambisonics_order: None,
+            ambisonics_normalization: None,
+            channels: Vec::new(),
+        }
+    }
+
+    /// Clip with a generated test signal instead of an audio file.
+    pub fn signal(signal: TestSignal) -> Clip {
+        Clip::new(signal.to_string())
+    }
+
+    pub fn id<S: Into<String>>(mut self, id: S) -> Clip {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn repeat(mut self, iterations: u64) -> Clip {
+        self.repeat = Some(iterations);
+        self
+    }
+
+    pub fn loop_crossfade(mut self, seconds: f32) -> Clip {
+        self.loop_crossfade = Some(seconds);
+        self
+    }
+
+    pub fn preload(mut self, preload: bool) -> Clip {
+        self.preload = Some(preload);
+        self
+    }
+
+    pub fn fade_in(mut self, seconds: f32) -> Clip {
+        self.fade_in = Some(seconds);
+        self
+    }
+
+    pub fn fade_out(mut self, seconds: f32) -> Clip {
+        self.fade_out = Some(seconds);
+        self
+    }
+
+    pub fn speed(mut self, speed: f64) -> Clip {
+        self.speed = Some(speed);
+        self
+    }
+
+    pub fn ambisonics(mut self, order: u32, normalization: AmbisonicsNormalization) -> Clip {
+        self.ambisonics_order = Some(order);
+        self.ambisonics_normalization = Some(normalization);
+        self
+    }
+
+    pub fn channel(mut self, channel: Channel) -> Clip {
+        self.channels.push(channel);
+        self
+    }
+}
+
+/// An `<o>` element within a `<transform>`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keyframe {
+    /// In seconds (relative to the begin of the transform),
+    /// the first keyframe must have no time (or zero)
+    pub time: Option<f32>,
+    pub pose: Pose,
+    /// `pos="closed"` (only for the last keyframe)
+    pub closed: bool,
+    pub speed: Option<f32>,
+    pub tension: Option<f32>,
+    pub continuity: Option<f32>,
+    pub bias: Option<f32>,
+    /// Easing of the segment towards the next keyframe
+    /// (not allowed in the last keyframe)
+    pub easing: Option<Easing>,
+}
+
+impl Keyframe {
+    pub fn new() -> Keyframe {
+        Default::default()
+    }
+
+    /// Returns to the first keyframe (closed trajectory).
+    pub fn closed() -> Keyframe {
+        Keyframe {
+            closed: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn time(mut self, seconds: f32) -> Keyframe {
+        self.time = Some(seconds);
+        self
+    }
+
+    pub fn pos(mut self, pos: [f32; 3]) -> Keyframe {
+        self.pose.pos = Some(pos);
+        self
+    }
+
+    pub fn rot(mut self, rot: [f32; 3]) -> Keyframe {
+        self.pose.rot = Some(rot);
+        self
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Keyframe {
+        self.easing = Some(easing);
+        self
+    }
+}
+
+/// A `<transform>` element, either constant (`pose`) or with keyframes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformSpec {
+    pub id: Option<String>,
+    /// IDs of sources, clips, channels, ...
+    pub apply_to: Vec<String>,
+    pub pose: Pose,
+    /// `false`: spline interpolation (the default)
+    pub linear: bool,
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl TransformSpec {
+    pub fn new<S: AsRef<str>>(apply_to: &[S]) -> TransformSpec {
+        TransformSpec {
+            id: None,
+            apply_to: apply_to.iter().map(|s| s.as_ref().into()).collect(),
+            pose: Pose::default(),
+            linear: false,
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn id<S: Into<String>>(mut self, id: S) -> TransformSpec {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn pos(mut self, pos: [f32; 3]) -> TransformSpec {
+        self.pose.pos = Some(pos);
+        self
+    }
+
+    pub fn rot(mut self, rot: [f32; 3]) -> TransformSpec {
+        self.pose.rot = Some(rot);
+        self
+    }
+
+    pub fn linear(mut self) -> TransformSpec {
+        self.linear = true;
+        self
+    }
+
+    pub fn keyframe(mut self, keyframe: Keyframe) -> TransformSpec {
+        self.keyframes.push(keyframe);
+        self
+    }
+}
+
+/// Contents of `<body>`, `<seq>` and `<par>` elements.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Seq(Container),
+    Par(Container),
+    Clip(Clip),
+    Transform(TransformSpec),
+    /// Sub-scene (`<scene>` element) with file name and optional ID
+    Scene(PathBuf, Option<String>),
+    /// Name and time (in seconds, relative to the parent element)
+    Marker(String, f32),
+    /// `<wait-for-trigger>` element with the given trigger ID
+    WaitForTrigger(String),
+}
+
+impl From<Container> for Node {
+    fn from(container: Container) -> Node {
+        if container.parallel {
+            Node::Par(container)
+        } else {
+            Node::Seq(container)
+        }
+    }
+}
+
+impl From<Clip> for Node {
+    fn from(clip: Clip) -> Node {
+        Node::Clip(clip)
+    }
+}
+
+impl From<TransformSpec> for Node {
+    fn from(transform: TransformSpec) -> Node {
+        Node::Transform(transform)
+    }
+}
+
+/// A `<seq>` or `<par>` element.
+///
+/// In a `<par>`, the first element must be the longest.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Container {
+    pub parallel: bool,
+    pub repeat: Option<u64>,
+    pub children: Vec<Node>,
+}
+
+impl Container {
+    pub fn seq() -> Container {
+        Default::default()
+    }
+
+    pub fn par() -> Container {
+        Container {
+            parallel: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn repeat(mut self, iterations: u64) -> Container {
+        self.repeat = Some(iterations);
+        self
+    }
+
+    pub fn push<N: Into<Node>>(&mut self, node: N) {
+        self.children.push(node.into());
+    }
+}
+
+/// The contents of an ASDF file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SceneBuilder {
+    /// In seconds
+    pub lead_in: Option<f32>,
+    /// In seconds
+    pub lead_out: Option<f32>,
+    pub sources: Vec<SourceSpec>,
+    pub reference: Option<Pose>,
+    /// `<meta>` elements
+    pub metadata: Vec<Metadata>,
+    /// File name of the `<poster>` image
+    pub poster: Option<PathBuf>,
+    /// The `<body>`, which is an implicit `<seq>`
+    pub body: Container,
+}
+
+impl SceneBuilder {
+    pub fn new() -> SceneBuilder {
+        Default::default()
+    }
+
+    /// Returns the scene as ASDF (XML) document.
+    pub fn to_xml(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<asdf version=\"0.4\"");
+        write_optional(&mut out, "lead-in", self.lead_in);
+        write_optional(&mut out, "lead-out", self.lead_out);
+        out.push_str(">\n");
+        if !self.sources.is_empty()
+            || self.reference.is_some()
+            || !self.metadata.is_empty()
+            || self.poster.is_some()
+        {
+            out.push_str("  <head>\n");
+            for meta in &self.metadata {
+                out.push_str("    <meta");
+                write_attribute(&mut out, "name", &meta.name);
+                write_attribute(&mut out, "content", &meta.content);
+                write_optional(&mut out, "xml:lang", meta.lang.as_ref());
+                out.push_str("/>\n");
+            }
+            if let Some(poster) = &self.poster {
+                out.push_str("    <poster");
+                write_attribute(&mut out, "file", poster.display());
+                out.push_str("/>\n");
+            }
+            for source in &self.sources {
+                out.push_str("    <source");
+                write_attribute(&mut out, "id", &source.id);
+                write_optional(&mut out, "name", source.name.as_ref());
+                write_optional(&mut out, "model", source.model.as_ref());
+                write_optional(&mut out, "port", source.port.as_ref());
+                if let Some([r, g, b]) = source.color {
+                    let color = format!("#{:02x}{:02x}{:02x}", r, g, b);
+                    write_attribute(&mut out, "color", color);
+                }
+                write_optional(&mut out, "icon", source.icon.as_ref());
+                write_optional(&mut out, "comment", source.comment.as_ref());
+                if !source.tags.is_empty() {
+                    write_attribute(&mut out, "tags", source.tags.join(" "));
+                }
+                if source.inverted {
+                    write_attribute(&mut out, "polarity", "inverted");
+                }
+                write_optional(&mut out, "delay", source.delay.as_ref());
+                write_pose(&mut out, &source.pose);
+                out.push_str("/>\n");
+            }
+            if let Some(pose) = &self.reference {
+                out.push_str("    <reference");
+                write_pose(&mut out, pose);
+                out.push_str("/>\n");
+            }
+            out.push_str("  </head>\n");
+        }
+        out.push_str("  <body");
+        write_optional(&mut out, "repeat", self.body.repeat);
+        if self.body.children.is_empty() {
+            out.push_str("/>\n");
+        } else {
+            out.push_str(">\n");
+            for child in &self.body.children {
+                write_node(&mut out, child, 2);
+            }
+            out.push_str("  </body>\n");
+        }
+        out.push_str("</asdf>\n");
+        out
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_xml())
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn write_attribute<T: ToString>(out: &mut String, name: &str, value: T) {
+    let _ = write!(out, " {}=\"{}\"", name, escape(&value.to_string()));
+}
+
+fn write_optional<T: ToString>(out: &mut String, name: &str, value: Option<T>) {
+    if let Some(value) = value {
+        write_attribute(out, name, value);
+    }
+}
+
+fn join(values: &[f32; 3]) -> String {
+    format!("{} {} {}", values[0], values[1], values[2])
+}
+
+fn write_pose(out: &mut String, pose: &Pose) {
+    write_optional(out, "pos", pose.pos.as_ref().map(join));
+    write_optional(out, "rot", pose.rot.as_ref().map(join));
+}
+
+fn write_node(out: &mut String, node: &Node, level: usize) {
+    let indent = "  ".repeat(level);
+    match node {
+        Node::Seq(container) | Node::Par(container) => {
+            let name = if let Node::Par(_) = node {
+                "par"
+            } else {
+                "seq"
+            };
+            let _ = write!(out, "{}<{}", indent, name);
+            write_optional(out, "repeat", container.repeat);
+            if container.children.is_empty() {
+                out.push_str("/>\n");
+                return;
+            }
+            out.push_str(">\n");
+            for child in &container.children {
+                write_node(out, child, level + 1);
+            }
+            let _ = writeln!(out, "{}</{}>", indent, name);
+        }
+        Node::Clip(clip) => {
+            let _ = write!(out, "{}<clip", indent);
+            write_optional(out, "id", clip.id.as_ref());
+            write_attribute(out, "file", clip.file.display());
+            write_optional(out, "repeat", clip.repeat);
+            write_optional(out, "loop-crossfade", clip.loop_crossfade);
+            write_optional(out, "preload", clip.preload);
+            write_optional(out, "fade-in", clip.fade_in);
+            write_optional(out, "fade-out", clip.fade_out);
+            write_optional(out, "speed", clip.speed);
+            write_optional(out, "ambisonics-order", clip.ambisonics_order);
+            if let Some(normalization) = clip.ambisonics_normalization {
+                let value = match normalization {
+                    AmbisonicsNormalization::Sn3d => "sn3d",
+                    AmbisonicsNormalization::N3d => "n3d",
+                    AmbisonicsNormalization::Fuma => "fuma",
+                };
+                write_attribute(out, "ambisonics-normalization", value);
+            }
+            if clip.channels.is_empty() {
+                out.push_str("/>\n");
+                return;
+            }
+            out.push_str(">\n");
+            for channel in &clip.channels {
+                let _ = write!(out, "{}  <channel", indent);
+                if let Some(skip) = channel.skip {
+                    write_attribute(out, "skip", skip);
+                } else {
+                    write_optional(out, "id", channel.id.as_ref());
+                    write_optional(out, "source", channel.source.as_ref());
+                    write_pose(out, &channel.pose);
+                }
+                out.push_str("/>\n");
+            }
+            let _ = writeln!(out, "{}</clip>", indent);
+        }
+        Node::Transform(transform) => {
+            let _ = write!(out, "{}<transform", indent);
+            write_optional(out, "id", transform.id.as_ref());
+            write_attribute(out, "apply-to", transform.apply_to.join(" "));
+            if transform.linear {
+                write_attribute(out, "interpolation", "linear");
+            }
+            write_pose(out, &transform.pose);
+            if transform.keyframes.is_empty() {
+                out.push_str("/>\n");
+                return;
+            }
+            out.push_str(">\n");
+            for keyframe in &transform.keyframes {
+                let _ = write!(out, "{}  <o", indent);
+                write_optional(out, "time", keyframe.time);
+                if keyframe.closed {
+                    write_attribute(out, "pos", "closed");
+                } else {
+                    write_pose(out, &keyframe.pose);
+                }
+                write_optional(out, "speed", keyframe.speed);
+                write_optional(out, "tension", keyframe.tension);
+                write_optional(out, "continuity", keyframe.continuity);
+                write_optional(out, "bias", keyframe.bias);
+                write_optional(out, "easing", keyframe.easing);
+                out.push_str("/>\n");
+            }
+            let _ = writeln!(out, "{}</transform>", indent);
+        }
+        Node::Scene(file, id) => {
+            let _ = write!(out, "{}<scene", indent);
+            write_optional(out, "id", id.as_ref());
+            write_attribute(out, "file", file.display());
+            out.push_str("/>\n");
+        }
+        Node::Marker(name, time) => {
+            let _ = write!(out, "{}<marker", indent);
+            write_attribute(out, "name", name);
+            write_attribute(out, "time", time);
+            out.push_str("/>\n");
+        }
+        Node::WaitForTrigger(id) => {
+            let _ = write!(out, "{}<wait-for-trigger", indent);
+            write_attribute(out, "id", id);
+            out.push_str("/>\n");
+        }
+    }
+}
@@ -0,0 +1,194 @@
+#![cfg(feature = "threads")]
+
+mod common;
+
+use std::time::Duration;
+
+use asdf::chain::{SceneChain, SceneLoader};
+use asdf::{FadeShape, LoadOptions};
+use common::{load, load_with_options, scene, wav, BLOCKSIZE, SAMPLERATE};
+
+/// Scene with a clip of 640 frames, each source `n` has the value `n + 1`.
+fn scene_loader(scene: String, channels: u16) -> SceneLoader {
+    Box::new(move || load(&scene, &[("a.wav", wav(channels, 640))]))
+}
+
+fn loader(head: &'static str, channels: &'static str) -> SceneLoader {
+    scene_loader(
+        format!(
+            "<asdf version=\"0.4\"><head>{}</head><body>\
+             <clip file=\"a.wav\">{}</clip></body></asdf>",
+            head, channels
+        ),
+        2,
+    )
+}
+
+/// Two sources without IDs
+fn simple_loader() -> SceneLoader {
+    scene_loader(scene(r#"<clip file="a.wav"/>"#), 2)
+}
+
+/// Creates a chain with two channels and waits until the first scene is playing.
+fn start(loaders: Vec<SceneLoader>, crossfade: u32) -> SceneChain {
+    let mut chain = SceneChain::new(loaders, 2, SAMPLERATE, BLOCKSIZE, Duration::from_millis(1));
+    chain.set_crossfade(crossfade, FadeShape::Linear);
+    chain.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; 2];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    while chain.current_index().is_none() {
+        assert!(unsafe { chain.get_audio_data(&pointers, false) });
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    // NB: The second scene is loaded in the background
+    std::thread::sleep(Duration::from_millis(200));
+    chain
+}
+
+/// Plays `blocks` blocks and returns the audio data of both channels.
+fn play(chain: &mut SceneChain, blocks: usize) -> Vec<Vec<f32>> {
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; 2];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    let mut data = vec![Vec::new(); 2];
+    let mut current = chain.current_index();
+    for _ in 0..blocks {
+        assert!(unsafe { chain.get_audio_data(&pointers, true) });
+        for (data, buffer) in data.iter_mut().zip(&buffers) {
+            data.extend_from_slice(buffer);
+        }
+        if chain.current_index() != current {
+            current = chain.current_index();
+            // NB: The following scene is loaded in the background
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+    data
+}
+
+/// Checks that `data` has the given value, except for the first frame of each scene
+/// (which is faded in).
+fn assert_value(data: &[f32], value: f32, scene_starts: &[usize]) {
+    for (i, &x) in data.iter().enumerate() {
+        if !scene_starts.contains(&i) {
+            assert!((x - value).abs() < 1e-5, "frame {}: {}", i, x);
+        }
+    }
+}
+
+#[test]
+fn back_to_back() {
+    let mut chain = start(vec![simple_loader(), simple_loader()], 0);
+    let data = play(&mut chain, 25);
+    assert_eq!(chain.current_index(), Some(1));
+    assert!(chain.is_finished());
+    assert_value(&data[0][..1280], 1.0, &[0, 640]);
+    assert_value(&data[1][..1280], 2.0, &[0, 640]);
+    assert!(data[0][1280..].iter().all(|&x| x == 0.0));
+    assert!(data[1][1280..].iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn crossfade_matches_source_ids() {
+    let mut chain = start(
+        vec![
+            // x = 1.0, y = 2.0
+            loader(
+                r#"<source id="x"/><source id="y"/>"#,
+                r#"<channel source="x"/><channel source="y"/>"#,
+            ),
+            // y = 2.0 (in the first channel), z = 1.0
+            loader(
+                r#"<source id="y"/><source id="z"/>"#,
+                r#"<channel source="z"/><channel source="y"/>"#,
+            ),
+        ],
+        128,
+    );
+    let data = play(&mut chain, 15);
+    assert_eq!(chain.current_index(), Some(1));
+    // Before the crossfade
+    assert!(data[0][1..512].iter().all(|&x| x == 1.0));
+    assert!(data[1][1..512].iter().all(|&x| x == 2.0));
+    // Source "y" moves from the second to the first channel
+    assert!(data[0][513..].iter().all(|&x| (x - 2.0).abs() < 1e-5));
+    // Source "x" stops, source "z" is faded in
+    assert!(data[1][513..640].windows(2).all(|w| w[0] < w[1]));
+    assert!(data[1][640..].iter().all(|&x| x == 1.0));
+}
+
+#[test]
+fn sources_without_ids_are_not_crossfaded() {
+    let mut chain = start(vec![simple_loader(), simple_loader()], 128);
+    let data = play(&mut chain, 15);
+    assert_eq!(chain.current_index(), Some(1));
+    for (data, &value) in data.iter().zip(&[1.0, 2.0]) {
+        assert_value(&data[..512], value, &[0]);
+        // The outgoing sources stop, the incoming ones are faded in
+        assert!(data[512..640].windows(2).all(|w| w[0] < w[1]));
+        assert_value(&data[640..], value, &[]);
+    }
+}
+
+#[test]
+fn crossfade_not_aligned_to_blocks() {
+    let ids = r#"<source id="x"/><source id="y"/>"#;
+    let channels = r#"<channel source="x"/><channel source="y"/>"#;
+    let mut chain = start(vec![loader(ids, channels), loader(ids, channels)], 100);
+    let data = play(&mut chain, 18);
+    assert_eq!(chain.current_index(), Some(1));
+    // NB: With a linear crossfade, the sum of both scenes is constant
+    assert_value(&data[0], 1.0, &[0, 540]);
+    assert_value(&data[1], 2.0, &[0, 540]);
+}
+
+#[test]
+fn skipped_scenes() {
+    let mut chain = start(
+        vec![
+            simple_loader(),
+            Box::new(|| load(&scene(r#"<clip file="missing.wav"/>"#), &[])) as SceneLoader,
+            // More sources than channels
+            scene_loader(scene(r#"<clip file="a.wav"/>"#), 3),
+            // Different block size
+            Box::new(|| {
+                load_with_options(
+                    &scene(r#"<clip file="a.wav"/>"#),
+                    &[("a.wav", wav(2, 640))],
+                    &LoadOptions::new(SAMPLERATE, 2 * BLOCKSIZE),
+                )
+            }),
+            simple_loader(),
+        ],
+        0,
+    );
+    assert_eq!(chain.scenes(), 5);
+    let data = play(&mut chain, 22);
+    assert_eq!(chain.current_index(), Some(4));
+    assert!(chain.is_finished());
+    assert_value(&data[0][..1280], 1.0, &[0, 640]);
+    assert_value(&data[1][..1280], 2.0, &[0, 640]);
+    let errors: Vec<_> = chain
+        .take_load_errors()
+        .into_iter()
+        .map(|(idx, _)| idx)
+        .collect();
+    assert_eq!(errors, [1, 2, 3]);
+    assert!(chain.take_load_errors().is_empty());
+}
+
+#[test]
+fn crossfade_length_changed_during_crossfade() {
+    let ids = r#"<source id="x"/><source id="y"/>"#;
+    let channels = r#"<channel source="x"/><channel source="y"/>"#;
+    let mut chain = start(vec![loader(ids, channels), loader(ids, channels)], 128);
+    // The crossfade starts at frame 512
+    let mut data = play(&mut chain, 9);
+    chain.set_crossfade(32, FadeShape::Linear);
+    for (data, more) in data.iter_mut().zip(play(&mut chain, 9)) {
+        data.extend(more);
+    }
+    assert_eq!(chain.current_index(), Some(1));
+    // NB: The running crossfade keeps its length
+    assert_value(&data[0], 1.0, &[0, 512]);
+    assert_value(&data[1], 2.0, &[0, 512]);
+}
@@ -0,0 +1,123 @@
+//! Helpers for loading scenes (and their audio files) from memory.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use asdf::{LoadError, LoadOptions, Scene};
+
+pub const SAMPLERATE: u32 = 44100;
+pub const BLOCKSIZE: u32 = 64;
+
+/// WAV file (32-bit float) where each sample of channel `c` has the value `c + 1`.
+pub fn wav(channels: u16, frames: usize) -> Vec<u8> {
+    write_wav(channels, frames, |_, c| f32::from(c + 1))
+}
+
+/// Mono WAV file (32-bit float) where each sample has its frame number as value.
+pub fn ramp_wav(frames: usize) -> Vec<u8> {
+    write_wav(1, frames, |frame, _| frame as f32)
+}
+
+/// WAV file (32-bit float) containing only zeros.
+pub fn silent_wav(channels: u16, frames: usize) -> Vec<u8> {
+    write_wav(channels, frames, |_, _| 0.0)
+}
+
+fn write_wav(channels: u16, frames: usize, value: impl Fn(usize, u16) -> f32) -> Vec<u8> {
+    let mut data = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(
+        &mut data,
+        hound::WavSpec {
+            channels,
+            sample_rate: SAMPLERATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        },
+    )
+    .unwrap();
+    for frame in 0..frames {
+        for c in 0..channels {
+            writer.write_sample(value(frame, c)).unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+    data.into_inner()
+}
+
+/// Loads `scene` (the contents of the scene file), `files` are
+/// `(name, contents)` pairs which can be used as `file` attributes.
+pub fn load(scene: &str, files: &[(&str, Vec<u8>)]) -> Result<Scene, LoadError> {
+    load_with_options(scene, files, &LoadOptions::new(SAMPLERATE, BLOCKSIZE))
+}
+
+/// Like `load()`, but with non-default `options`.
+pub fn load_with_options(
+    scene: &str,
+    files: &[(&str, Vec<u8>)],
+    options: &LoadOptions,
+) -> Result<Scene, LoadError> {
+    let mut storage: HashMap<PathBuf, Arc<[u8]>> = files
+        .iter()
+        .map(|(name, data)| (PathBuf::from(name), Arc::from(data.as_slice())))
+        .collect();
+    storage.insert("scene.asd".into(), Arc::from(scene.as_bytes()));
+    Scene::load_with_reader(
+        "scene.asd",
+        Arc::new(move |path: &Path| match storage.get(path) {
+            Some(data) => Ok(Box::new(Cursor::new(Arc::clone(data))) as _),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }),
+        options,
+    )
+}
+
+/// Wraps `body` in an `<asdf>` element.
+pub fn scene(body: &str) -> String {
+    format!("<asdf version=\"0.4\"><body>{}</body></asdf>", body)
+}
+
+/// Plays `blocks` blocks and returns the audio data of each source.
+pub fn play(scene: &mut Scene, blocks: usize) -> Vec<Vec<f32>> {
+    let channels = scene.file_sources() as usize;
+    let mut result = vec![Vec::new(); channels];
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; channels];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    for _ in 0..blocks {
+        let start = Instant::now();
+        while scene.streaming_stats().buffered_frames < u64::from(BLOCKSIZE) {
+            assert!(start.elapsed() < Duration::from_secs(10), "reader is stuck");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(unsafe { scene.get_audio_data(&pointers, true) });
+        for (data, buffer) in result.iter_mut().zip(&buffers) {
+            data.extend_from_slice(buffer);
+        }
+    }
+    result
+}
+
+pub fn seek(scene: &mut Scene, frame: u64) {
+    let start = Instant::now();
+    while !scene.seek(frame) {
+        assert!(start.elapsed() < Duration::from_secs(10), "seek is stuck");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Like `seek()`, but stops playback first.
+pub fn stop_and_seek(scene: &mut Scene, frame: u64) {
+    let channels = scene.file_sources() as usize;
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; channels];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    let start = Instant::now();
+    while !scene.seek(frame) {
+        assert!(start.elapsed() < Duration::from_secs(10), "seek is stuck");
+        assert!(unsafe { scene.get_audio_data(&pointers, false) });
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::time::Duration;
+
+use asdf::{FadeShape, Scene};
+use common::{load, play, seek, wav, BLOCKSIZE};
+
+/// Source 0 (tagged "ambience") has the value 1.0, source 1 has the value 2.0.
+fn tagged_scene() -> Scene {
+    let mut scene = load(
+        r#"<asdf version="0.4">
+             <head><source id="a" tags="ambience"/><source id="b"/></head>
+             <body>
+               <clip file="a.wav"><channel source="a"/><channel source="b"/></clip>
+             </body>
+           </asdf>"#,
+        &[("a.wav", wav(2, 100 * BLOCKSIZE as usize))],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    seek(&mut scene, 0);
+    scene
+}
+
+#[test]
+fn invalid_gain_is_rejected() {
+    let mut scene = tagged_scene();
+    for &gain in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.5] {
+        assert!(!scene.set_source_gain(0, gain), "{}", gain);
+        assert_eq!(scene.set_group_gain("ambience", gain, 0), 0, "{}", gain);
+    }
+    assert_eq!(scene.source_gain(0), 1.0);
+    let data = play(&mut scene, 10);
+    // NB: The first frame is faded in
+    assert!(data[0][1..].iter().all(|&x| x == 1.0));
+    assert!(data[1][1..].iter().all(|&x| x == 2.0));
+}
+
+#[test]
+fn gain_is_ramped() {
+    let mut scene = tagged_scene();
+    play(&mut scene, 1);
+    assert!(scene.set_source_gain(0, 0.0));
+    assert_eq!(scene.set_group_gain("ambience", 0.5, 0), 1);
+    assert_eq!(scene.source_gain(0), 0.5);
+    let data = play(&mut scene, 10);
+    assert!(data[0].windows(2).all(|w| w[0] >= w[1]));
+    assert!(data[0][..BLOCKSIZE as usize].iter().all(|&x| x > 0.5));
+    assert!(data[0][256..].iter().all(|&x| x == 0.5));
+    assert!(data[1].iter().all(|&x| x == 2.0));
+}
+
+#[test]
+fn group_gain_at_offset() {
+    let mut scene = tagged_scene();
+    play(&mut scene, 1);
+    // In the middle of the second block
+    assert_eq!(scene.set_group_gain("ambience", 0.0, 100), 1);
+    assert_eq!(scene.source_gain(0), 0.0);
+    let data = play(&mut scene, 10);
+    assert!(data[0][..100].iter().all(|&x| x == 1.0));
+    assert_eq!(data[0][100], 1.0 - 1.0 / 256.0);
+    assert!(data[0][100..356].windows(2).all(|w| w[0] > w[1]));
+    assert!(data[0][355..].iter().all(|&x| x == 0.0));
+    assert!(data[1].iter().all(|&x| x == 2.0));
+}
+
+#[test]
+fn pending_gain_takes_effect_while_stopped() {
+    let mut scene = tagged_scene();
+    assert_eq!(scene.set_group_gain("ambience", 0.5, 100), 1);
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; 2];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    for _ in 0..2 {
+        assert!(unsafe { scene.get_audio_data(&pointers, false) });
+    }
+    // NB: The gain ramp starts as soon as playback starts
+    let data = play(&mut scene, 4);
+    assert!(data[0][1..128].windows(2).all(|w| w[0] > w[1]));
+    assert!(data[0][128..].iter().all(|&x| x == 0.5));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::io::{self, Cursor};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use asdf::loader::ByteReader;
+use asdf::media::{register_media_source, MediaSource};
+use asdf::{LoadOptions, Scene};
+use common::{load, play, scene, seek, wav, BLOCKSIZE, SAMPLERATE};
+
+/// Provides `memory://two-channels.wav` and counts how often it was opened.
+struct MemorySource {
+    data: Arc<[u8]>,
+    opened: Arc<AtomicUsize>,
+}
+
+impl MediaSource for MemorySource {
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with("memory://")
+    }
+
+    fn open(&self, location: &str) -> io::Result<Box<dyn ByteReader>> {
+        self.opened.fetch_add(1, Ordering::SeqCst);
+        match location {
+            "memory://two-channels.wav" => Ok(Box::new(Cursor::new(Arc::clone(&self.data)))),
+            _ => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+/// NB: All tests share the same registered source
+fn register() -> Arc<AtomicUsize> {
+    lazy_static::lazy_static! {
+        static ref OPENED: Arc<AtomicUsize> = {
+            let opened = Arc::new(AtomicUsize::new(0));
+            register_media_source(MemorySource {
+                data: Arc::from(wav(2, 100 * BLOCKSIZE as usize)),
+                opened: Arc::clone(&opened),
+            });
+            opened
+        };
+    }
+    Arc::clone(&OPENED)
+}
+
+#[test]
+fn clip_from_media_source() {
+    let opened = register();
+    let mut scene = load(&scene(r#"<clip file="memory://two-channels.wav"/>"#), &[]).unwrap();
+    assert!(opened.load(Ordering::SeqCst) > 0);
+    assert_eq!(scene.file_sources(), 2);
+    assert_eq!(scene.frames(), 100 * u64::from(BLOCKSIZE));
+    seek(&mut scene, 0);
+    let data = play(&mut scene, 10);
+    // NB: The first block is faded in
+    assert!(data[0][BLOCKSIZE as usize..].iter().all(|&x| x == 1.0));
+    assert!(data[1][BLOCKSIZE as usize..].iter().all(|&x| x == 2.0));
+}
+
+#[test]
+fn location_is_not_joined_with_scene_directory() {
+    register();
+    let dir = std::env::temp_dir().join(format!("asdf-media-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.asd");
+    std::fs::write(&path, scene(r#"<clip file="memory://two-channels.wav"/>"#)).unwrap();
+    let result = Scene::with_options(&path, &LoadOptions::new(SAMPLERATE, BLOCKSIZE));
+    std::fs::remove_dir_all(&dir).unwrap();
+    let scene = result.unwrap();
+    let clip = scene.clips(0).next().unwrap();
+    assert_eq!(clip.file, Path::new("memory://two-channels.wav"));
+}
+
+#[test]
+fn missing_file_in_media_source() {
+    register();
+    let result = load(&scene(r#"<clip file="memory://missing.wav"/>"#), &[]);
+    assert!(result.is_err());
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{load, scene, wav, SAMPLERATE};
+
+#[test]
+fn event_before_clip_in_par() {
+    let scene = load(
+        &scene(r#"<par><event data="start"/><clip file="a.wav"/></par><clip file="b.wav"/>"#),
+        &[("a.wav", wav(1, 1000)), ("b.wav", wav(1, 500))],
+    )
+    .unwrap();
+    assert_eq!(scene.frames(), 1500);
+    let events: Vec<_> = scene.get_events(0, 1).collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, 0);
+    assert_eq!(events[0].1.data, "start");
+    assert_eq!(scene.clips(1).next().unwrap().begin, 1000);
+}
+
+#[test]
+fn longer_element_after_first_in_par() {
+    let result = load(
+        &scene(r#"<par><clip file="a.wav"/><clip file="b.wav"/></par>"#),
+        &[
+            ("a.wav", wav(1, 500)),
+            ("b.wav", wav(1, SAMPLERATE as usize)),
+        ],
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn marker_before_clip_in_par() {
+    let scene = load(
+        &scene(r#"<clip file="a.wav"/><par><marker name="m"/><clip file="b.wav"/></par>"#),
+        &[("a.wav", wav(1, 300)), ("b.wav", wav(1, 700))],
+    )
+    .unwrap();
+    assert_eq!(scene.frames(), 1000);
+    let markers = scene.markers();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0].name, "m");
+    assert_eq!(markers[0].frame, 300);
+}
+
+#[cfg(not(feature = "resampling"))]
+#[test]
+fn speed_without_resampling() {
+    let result = load(
+        &scene(r#"<clip file="a.wav" speed="2"/>"#),
+        &[("a.wav", wav(1, 1000))],
+    );
+    let error = result.err().unwrap().to_string();
+    assert!(
+        error.contains("\"resampling\" feature is disabled"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn dry_run_collects_missing_files() {
+    let dir = std::env::temp_dir().join(format!("asdf-dry-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.wav"), wav(1, 1000)).unwrap();
+    std::fs::write(
+        dir.join("scene.asd"),
+        scene(
+            r#"<clip file="a.wav"/>
+               <clip file="missing1.wav" channels="3"/>
+               <clip file="missing2.wav">
+                 <channel/>
+                 <channel skip="2"/>
+                 <channel/>
+               </clip>"#,
+        ),
+    )
+    .unwrap();
+    let result = asdf::Scene::load_dry_run(dir.join("scene.asd"), SAMPLERATE, 64).map(|scene| {
+        (
+            scene.frames(),
+            scene.file_sources(),
+            scene.report().missing_files,
+        )
+    });
+    std::fs::remove_dir_all(&dir).unwrap();
+    let (frames, sources, missing_files) = result.unwrap();
+    assert_eq!(frames, 1000);
+    assert_eq!(sources, 4);
+    assert_eq!(
+        missing_files,
+        [dir.join("missing1.wav"), dir.join("missing2.wav")]
+    );
+}
+
+#[test]
+fn duplicate_channel_sources() {
+    let error = load(
+        r#"<asdf version="0.4"><head><source id="x"/></head><body>
+             <clip file="a.wav" channels="1 1">
+               <channel source="x"/>
+               <channel source="x"/>
+             </clip>
+           </body></asdf>"#,
+        &[("a.wav", wav(1, 100))],
+    )
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains("more than one <channel>"));
+}
+
+#[test]
+fn event_with_unknown_source() {
+    let error = load(
+        &scene(r#"<clip file="a.wav"/><event source="x" data="d"/>"#),
+        &[("a.wav", wav(1, 100))],
+    )
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains("Unknown source ID"), "{}", error);
+}
+
+#[test]
+fn event_with_existing_source() {
+    let scene = load(
+        &scene(
+            r#"<par>
+                 <clip file="a.wav"><channel source="x"/></clip>
+                 <event source="x" data="d"/>
+               </par>"#,
+        ),
+        &[("a.wav", wav(1, 100))],
+    )
+    .unwrap();
+    assert_eq!(scene.file_sources(), 1);
+    let events: Vec<_> = scene.get_events(0, 1).collect();
+    assert_eq!(events[0].1.source, Some(0));
+}
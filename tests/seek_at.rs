@@ -0,0 +1,62 @@
+mod common;
+
+use std::time::Duration;
+
+use asdf::{FadeShape, Scene, ScheduledSeekState};
+use common::{load, play, ramp_wav, scene, seek, stop_and_seek, BLOCKSIZE};
+
+/// Source 0 has the frame number as value.
+fn ramp_scene() -> Scene {
+    let mut scene = load(
+        &scene(r#"<clip file="ramp.wav"/>"#),
+        &[("ramp.wav", ramp_wav(100 * BLOCKSIZE as usize))],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    seek(&mut scene, 0);
+    scene
+}
+
+/// Checks that `data` contains consecutive frame numbers, starting with `first`.
+fn assert_ramp(data: &[f32], first: usize) {
+    for (i, &x) in data.iter().enumerate() {
+        assert_eq!(x, (first + i) as f32, "frame {}", i);
+    }
+}
+
+#[test]
+fn seek_in_the_middle_of_a_block() {
+    let mut scene = ramp_scene();
+    let at = 40 * u64::from(BLOCKSIZE) + 10;
+    assert!(scene.seek_at(at, 5000));
+    // Only one scheduled seek at a time
+    assert!(!scene.seek_at(at + 1, 0));
+    let data = play(&mut scene, 50);
+    let seek = scene.scheduled_seek().unwrap();
+    assert_eq!((seek.at, seek.target), (at, 5000));
+    assert_eq!(seek.state, ScheduledSeekState::Done);
+    let at = at as usize;
+    // NB: The first frame is faded in
+    assert_ramp(&data[0][1..at], 1);
+    assert_ramp(&data[0][at..], 5000);
+    // NB: While rolling, this is the beginning of the most recent block
+    let last_block = 49 * BLOCKSIZE as usize;
+    assert_eq!(scene.position().frame, (5000 + last_block - at) as u64);
+    // Frames which have already been played can't be scheduled
+    assert!(!scene.seek_at(5000, 0));
+}
+
+#[test]
+fn regular_seek_cancels_scheduled_seek() {
+    let mut scene = ramp_scene();
+    let at = 40 * u64::from(BLOCKSIZE) + 10;
+    assert!(scene.seek_at(at, 5000));
+    play(&mut scene, 10);
+    assert!(scene.scheduled_seek().is_some());
+    stop_and_seek(&mut scene, 1000);
+    assert_eq!(scene.scheduled_seek(), None);
+    let data = play(&mut scene, 50);
+    assert_eq!(scene.scheduled_seek(), None);
+    // NB: The first frame is faded in
+    assert_ramp(&data[0][1..], 1001);
+}
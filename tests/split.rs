@@ -0,0 +1,188 @@
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use asdf::split::{SceneAudio, SceneControl};
+use asdf::{Area, AreaPolicy, FadeShape};
+use common::{load, scene, wav, BLOCKSIZE};
+
+/// Source 0 has the value 1.0, source 1 has the value 2.0.
+fn split_scene() -> (SceneAudio, Arc<SceneControl>) {
+    let mut scene = load(
+        &scene(r#"<clip file="a.wav"/>"#),
+        &[("a.wav", wav(2, 100 * BLOCKSIZE as usize))],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    let (audio, control) = scene.split();
+    (audio, Arc::new(control))
+}
+
+/// Calls `get_audio_data()` once (without rolling), which applies all pending commands.
+fn stopped(audio: &mut SceneAudio) {
+    let channels = audio.channels() as usize;
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; channels];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    assert!(unsafe { audio.get_audio_data(&pointers, false) });
+}
+
+/// Calls `get_audio_data()` (without rolling) until the seek is completed.
+fn wait_for_seek(audio: &mut SceneAudio, control: &SceneControl) {
+    let start = Instant::now();
+    while control.is_seeking() {
+        assert!(start.elapsed() < Duration::from_secs(10), "seek is stuck");
+        stopped(audio);
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Like `common::play()`, but for the audio part of a split scene.
+fn play(audio: &mut SceneAudio, blocks: usize) -> Vec<Vec<f32>> {
+    let channels = audio.channels() as usize;
+    let mut result = vec![Vec::new(); channels];
+    let mut buffers = vec![vec![0.0f32; BLOCKSIZE as usize]; channels];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    for _ in 0..blocks {
+        let start = Instant::now();
+        while audio.streaming_stats().buffered_frames < u64::from(BLOCKSIZE) {
+            assert!(start.elapsed() < Duration::from_secs(10), "reader is stuck");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(unsafe { audio.get_audio_data(&pointers, true) });
+        for (data, buffer) in result.iter_mut().zip(&buffers) {
+            data.extend_from_slice(buffer);
+        }
+    }
+    result
+}
+
+/// Runs `f` in another thread and waits for it.
+fn in_thread<T: Send + 'static>(
+    control: &Arc<SceneControl>,
+    f: impl FnOnce(&SceneControl) -> T + Send + 'static,
+) -> T {
+    let control = Arc::clone(control);
+    thread::spawn(move || f(&control)).join().unwrap()
+}
+
+#[test]
+fn seek_and_mute_from_other_thread() {
+    let (mut audio, control) = split_scene();
+    let frame = 10 * u64::from(BLOCKSIZE);
+    assert!(in_thread(&control, move |c| c.seek(frame)));
+    assert!(in_thread(&control, |c| c.set_source_mute(1, true)));
+    assert!(control.is_source_muted(1));
+    wait_for_seek(&mut audio, &control);
+    assert_eq!(audio.position().frame, frame);
+    assert_eq!(control.position().frame, frame);
+    let data = play(&mut audio, 10);
+    // NB: While rolling, this is the beginning of the most recent block
+    assert_eq!(control.position().frame, frame + 9 * u64::from(BLOCKSIZE));
+    // NB: The first frame is faded in
+    assert!(data[0][1..].iter().all(|&x| x == 1.0));
+    // The mute is applied with a gain ramp
+    assert!(data[1][1..256].windows(2).all(|w| w[0] > w[1]));
+    assert!(data[1][256..].iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn seek_is_completed_by_audio_thread() {
+    let (mut audio, control) = split_scene();
+    assert!(!control.is_seeking());
+    assert!(in_thread(&control, |c| c.seek(0)));
+    // NB: Nothing happens until the audio thread gets the command
+    thread::sleep(Duration::from_millis(50));
+    assert!(control.is_seeking());
+    wait_for_seek(&mut audio, &control);
+    assert!(!control.is_seeking());
+    // Further seeks (and resets) are counted separately
+    assert!(control.seek(BLOCKSIZE.into()));
+    assert!(control.reset());
+    assert!(control.is_seeking());
+    wait_for_seek(&mut audio, &control);
+    assert_eq!(control.position().frame, 0);
+}
+
+#[test]
+fn full_queue() {
+    let (mut audio, control) = split_scene();
+    let mut sent = 0;
+    while control.set_source_mute(0, sent % 2 == 0) {
+        sent += 1;
+        assert!(sent <= 1000, "queue is unbounded");
+    }
+    assert!(sent > 0);
+    // The last requested state is the one which was sent successfully
+    assert_eq!(control.is_source_muted(0), sent % 2 == 1);
+    assert!(!control.seek(0));
+    assert!(!control.is_seeking());
+    assert!(!control.set_source_solo(1, true));
+    assert!(!control.is_source_soloed(1));
+    // The queue is emptied by the audio thread
+    stopped(&mut audio);
+    assert!(control.seek(0));
+    wait_for_seek(&mut audio, &control);
+    assert!(control.set_source_solo(1, true));
+}
+
+#[test]
+fn settings_from_other_thread() {
+    let (_audio, control) = split_scene();
+    let writer = {
+        let control = Arc::clone(&control);
+        thread::spawn(move || {
+            for i in 0..100 {
+                control.set_source_override(0, Some([i as f32, 0.0, 0.0]));
+                control.set_area(Some(Area::sphere([0.0; 3], 50.0)), AreaPolicy::Clamp);
+            }
+        })
+    };
+    for _ in 0..1000 {
+        let position = control.get_source_transform(0, 0).unwrap().translation;
+        assert!(position.map_or(true, |p| p[0] <= 50.0));
+    }
+    writer.join().unwrap();
+    in_thread(&control, |c| c.enable_transform_trace(10));
+    let transform = control.get_source_transform(0, 0).unwrap();
+    assert_eq!(transform.translation.unwrap()[0], 50.0);
+    assert!(transform.clamped);
+    assert_eq!(control.transform_trace().unwrap().queries().count(), 1);
+    control.set_source_override(0, None);
+    control.set_area(None, AreaPolicy::Clamp);
+    assert!(!control.get_source_transform(0, 0).unwrap().clamped);
+}
+
+#[test]
+fn trigger_from_other_thread() {
+    let mut scene = load(
+        &scene(
+            r#"<seq>
+                 <clip file="a.wav"/>
+                 <wait-for-trigger id="go"/>
+                 <clip file="b.wav"/>
+               </seq>"#,
+        ),
+        &[("a.wav", wav(1, 640)), ("b.wav", wav(1, 640))],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    let (mut audio, control) = scene.split();
+    let control = Arc::new(control);
+    assert!(control.seek(0));
+    wait_for_seek(&mut audio, &control);
+    let data = play(&mut audio, 20);
+    assert!(data[1].iter().all(|&x| x == 0.0));
+    assert!(!in_thread(&control, |c| c.trigger("unknown")));
+    assert!(in_thread(&control, |c| c.trigger("go")));
+    let data = play(&mut audio, 20);
+    let played: Vec<_> = data[1]
+        .iter()
+        .enumerate()
+        .filter(|(_, &x)| x != 0.0)
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(played.len(), 640);
+    assert_eq!(played[639] - played[0], 639);
+}
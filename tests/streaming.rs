@@ -0,0 +1,75 @@
+mod common;
+
+use std::time::Duration;
+
+use asdf::streaming::StreamerBuilder;
+use asdf::{FadeShape, LoadOptions, TransportState};
+use common::{
+    load, load_with_options, play, ramp_wav, scene, seek, stop_and_seek, wav, BLOCKSIZE, SAMPLERATE,
+};
+
+#[test]
+fn streamer_without_channels() {
+    let mut streamer = StreamerBuilder::new(BLOCKSIZE, 0).build(Vec::new(), Vec::new());
+    assert_eq!(streamer.transport_state(), TransportState::Primed);
+    assert!(streamer.seek(1000));
+    assert_eq!(streamer.frames_available(), usize::MAX);
+    unsafe { streamer.get_data(&[], BLOCKSIZE, true) }.unwrap();
+    assert_eq!(streamer.transport_state(), TransportState::Playing);
+    assert_eq!(streamer.next_frame(), 1000 + u64::from(BLOCKSIZE));
+}
+
+#[test]
+fn streamer_without_files() {
+    let mut streamer = StreamerBuilder::new(BLOCKSIZE, 2).build(Vec::new(), Vec::new());
+    let mut buffers = vec![vec![1.0f32; BLOCKSIZE as usize]; 2];
+    let pointers: Vec<_> = buffers.iter_mut().map(|b| b.as_mut_ptr()).collect();
+    unsafe { streamer.get_data(&pointers, BLOCKSIZE, true) }.unwrap();
+    assert!(buffers.iter().flatten().all(|&x| x == 0.0));
+    assert_eq!(streamer.next_frame(), u64::from(BLOCKSIZE));
+}
+
+#[test]
+fn scene_without_channels() {
+    let mut scene = load(&scene(""), &[]).unwrap();
+    assert_eq!(scene.file_sources(), 0);
+    assert_eq!(scene.frames(), 0);
+    assert!(scene.seek(0));
+    assert!(unsafe { scene.get_audio_data(&[], true) });
+    assert_eq!(scene.streaming_stats().underruns, 0);
+}
+
+#[test]
+fn multiple_reader_threads() {
+    // NB: Clip lengths are not aligned to blocks
+    let body = r#"<par>
+                    <clip file="ramp.wav"/>
+                    <seq><clip file="a.wav"/><clip file="ramp.wav"/><clip file="a.wav"/></seq>
+                    <seq><clip file="b.wav"/><clip file="a.wav"/></seq>
+                  </par>"#;
+    let render = |threads| {
+        let mut scene = load_with_options(
+            &scene(body),
+            &[
+                ("ramp.wav", ramp_wav(2000)),
+                ("a.wav", wav(2, 300)),
+                ("b.wav", ramp_wav(1111)),
+            ],
+            &LoadOptions::new(SAMPLERATE, BLOCKSIZE).reader_threads(threads),
+        )
+        .unwrap();
+        scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+        seek(&mut scene, 0);
+        let mut data = play(&mut scene, 40);
+        stop_and_seek(&mut scene, 500);
+        for (data, more) in data.iter_mut().zip(play(&mut scene, 30)) {
+            data.extend(more);
+        }
+        data
+    };
+    let expected = render(1);
+    assert!(expected.iter().all(|data| data.iter().any(|&x| x != 0.0)));
+    for &threads in &[2, 3, 5] {
+        assert!(render(threads) == expected, "{} threads", threads);
+    }
+}
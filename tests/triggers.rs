@@ -0,0 +1,132 @@
+mod common;
+
+use std::time::Duration;
+
+use asdf::{FadeShape, Scene, TransportState};
+use common::{load, play, scene, seek, wav, BLOCKSIZE};
+
+fn held_scene() -> Scene {
+    let mut scene = load(
+        &scene(
+            r#"<par>
+                 <clip file="c.wav"/>
+                 <seq>
+                   <clip file="a.wav"/>
+                   <wait-for-trigger id="go"/>
+                   <event data="held"/>
+                   <clip file="b.wav"/>
+                 </seq>
+               </par>"#,
+        ),
+        &[
+            ("a.wav", wav(1, 640)),
+            ("b.wav", wav(1, 640)),
+            ("c.wav", wav(1, 2560)),
+        ],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    scene
+}
+
+fn active_frames(data: &[f32]) -> Vec<usize> {
+    data.iter()
+        .enumerate()
+        .filter(|(_, &x)| x != 0.0)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[test]
+fn only_enclosing_seq_waits() {
+    let mut scene = held_scene();
+    assert_eq!(scene.file_sources(), 3);
+    assert_eq!(scene.frames(), 2560);
+    seek(&mut scene, 0);
+    let data = play(&mut scene, 50);
+    assert_eq!(scene.waiting_for_trigger(), Some("go"));
+    assert_eq!(active_frames(&data[1]), (0..640).collect::<Vec<_>>());
+    assert!(active_frames(&data[2]).is_empty());
+    // The sibling of the <seq> is not affected
+    assert_eq!(active_frames(&data[0]), (0..2560).collect::<Vec<_>>());
+    // The scene doesn't end while waiting
+    assert!(!scene.is_finished(50 * u64::from(BLOCKSIZE)));
+    assert_ne!(scene.transport_state(), TransportState::Finished);
+    assert_eq!(scene.get_events(0, 50 * BLOCKSIZE).count(), 0);
+
+    assert!(scene.trigger("go"));
+    let data = play(&mut scene, 50);
+    assert_eq!(scene.waiting_for_trigger(), None);
+    let played = active_frames(&data[2]);
+    assert_eq!(played.len(), 640);
+    let begin = 50 * u64::from(BLOCKSIZE) + played[0] as u64;
+    // The event is delayed in the same way as the clip
+    let events: Vec<_> = scene.get_events(begin, 1).collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, begin);
+    assert_eq!(events[0].1.frame, 640);
+    assert!(scene.is_finished(begin + 640));
+    assert!(!scene.is_finished(begin + 639));
+    // Nothing is left to trigger
+    assert!(!scene.trigger("go"));
+}
+
+#[test]
+fn trigger_before_waiting() {
+    let mut scene = held_scene();
+    seek(&mut scene, 0);
+    assert!(scene.trigger("go"));
+    let data = play(&mut scene, 20);
+    assert_eq!(scene.waiting_for_trigger(), None);
+    assert_eq!(active_frames(&data[2]), (640..1280).collect::<Vec<_>>());
+}
+
+#[test]
+fn seeking_cancels_release() {
+    let mut scene = held_scene();
+    seek(&mut scene, 0);
+    assert!(scene.trigger("go"));
+    seek(&mut scene, 0);
+    let data = play(&mut scene, 20);
+    assert_eq!(scene.waiting_for_trigger(), Some("go"));
+    assert!(active_frames(&data[2]).is_empty());
+}
+
+#[test]
+fn seeking_past_trigger() {
+    let mut scene = held_scene();
+    seek(&mut scene, 704);
+    let data = play(&mut scene, 5);
+    assert_eq!(scene.waiting_for_trigger(), None);
+    assert_eq!(active_frames(&data[2]).len(), 5 * BLOCKSIZE as usize);
+}
+
+#[test]
+fn repeated_triggers() {
+    let scene = load(
+        &scene(
+            r#"<seq repeat="2">
+                 <clip file="a.wav"/>
+                 <wait-for-trigger id="first"/>
+                 <clip file="b.wav"/>
+                 <wait-for-trigger id="second"/>
+               </seq>"#,
+        ),
+        &[("a.wav", wav(1, 100)), ("b.wav", wav(1, 200))],
+    )
+    .unwrap();
+    let triggers: Vec<_> = scene
+        .triggers()
+        .iter()
+        .map(|t| (t.frame, t.id.as_str(), t.parent))
+        .collect();
+    assert_eq!(
+        triggers,
+        [
+            (100, "first", None),
+            (300, "second", Some(0)),
+            (400, "first", Some(1)),
+            (600, "second", Some(2)),
+        ]
+    );
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::time::Duration;
+
+use asdf::{FadeShape, Scene};
+use common::{load, play, ramp_wav, seek, stop_and_seek, BLOCKSIZE};
+
+/// All three sources play the same file (with the frame number as value).
+fn trimmed_scene() -> Scene {
+    let mut scene = load(
+        r#"<asdf version="0.4">
+             <head>
+               <source id="a" polarity="inverted"/>
+               <source id="b" delay="100"/>
+               <source id="c" polarity="inverted" delay="70"/>
+             </head>
+             <body>
+               <par>
+                 <clip file="ramp.wav"><channel source="a"/></clip>
+                 <clip file="ramp.wav"><channel source="b"/></clip>
+                 <clip file="ramp.wav"><channel source="c"/></clip>
+               </par>
+             </body>
+           </asdf>"#,
+        &[("ramp.wav", ramp_wav(100 * BLOCKSIZE as usize))],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    seek(&mut scene, 0);
+    scene
+}
+
+/// Checks that `data` starts with `delay` zeros, followed by
+/// consecutive frame numbers (starting with `first`), multiplied by `sign`.
+fn assert_trimmed(data: &[f32], first: usize, delay: usize, sign: f32) {
+    for (i, &x) in data.iter().enumerate() {
+        let expected = if i < delay {
+            0.0
+        } else {
+            sign * (first + i - delay) as f32
+        };
+        assert_eq!(x, expected, "frame {}", i);
+    }
+}
+
+#[test]
+fn polarity_and_delay() {
+    let mut scene = trimmed_scene();
+    let data = play(&mut scene, 20);
+    // NB: The first frame is faded in, but it is zero anyway
+    assert_trimmed(&data[0], 0, 0, -1.0);
+    assert_trimmed(&data[1], 0, 100, 1.0);
+    assert_trimmed(&data[2], 0, 70, -1.0);
+}
+
+#[test]
+fn delay_lines_are_cleared_after_seek() {
+    let mut scene = trimmed_scene();
+    play(&mut scene, 20);
+    stop_and_seek(&mut scene, 1000);
+    let data = play(&mut scene, 20);
+    // NB: The first frame is faded in
+    assert_trimmed(&data[0][1..], 1001, 0, -1.0);
+    assert_trimmed(&data[1], 1000, 100, 1.0);
+    assert_trimmed(&data[2], 1000, 70, -1.0);
+}
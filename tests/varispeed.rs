@@ -0,0 +1,96 @@
+#![cfg(feature = "resampling")]
+
+mod common;
+
+use common::{load, play, scene, seek, wav, BLOCKSIZE};
+
+fn clip_with_speed(speed: &str, frames: usize) -> asdf::Scene {
+    load(
+        &scene(&format!(r#"<clip file="a.wav" speed="{}"/>"#, speed)),
+        &[("a.wav", wav(1, frames))],
+    )
+    .unwrap()
+}
+
+/// Checks that the constant input signal is (approximately) preserved,
+/// apart from the fade-in and the transients of the resampler
+/// (at the beginning and after seeking).
+fn assert_constant(data: &[f32]) {
+    for (i, &value) in data.iter().enumerate().skip(10 * BLOCKSIZE as usize) {
+        assert!((value - 1.0).abs() < 0.01, "frame {}: {}", i, value);
+    }
+}
+
+#[test]
+fn duration_depends_on_speed() {
+    for &(speed, frames) in &[
+        ("1", 1000),
+        ("2", 500),
+        ("4", 250),
+        ("0.5", 2000),
+        ("0.25", 4000),
+    ] {
+        let scene = clip_with_speed(speed, 1000);
+        assert_eq!(scene.frames(), frames, "speed=\"{}\"", speed);
+        let clip = scene.clips(0).next().unwrap();
+        assert_eq!(clip.duration, frames, "speed=\"{}\"", speed);
+        assert_eq!(clip.speed, speed.parse::<f64>().unwrap());
+    }
+}
+
+#[test]
+fn following_clip_is_shifted() {
+    let scene = load(
+        &scene(r#"<clip file="a.wav" speed="2"/><clip file="b.wav"/>"#),
+        &[("a.wav", wav(1, 1000)), ("b.wav", wav(1, 1000))],
+    )
+    .unwrap();
+    assert_eq!(scene.frames(), 1500);
+    assert_eq!(scene.clips(1).next().unwrap().begin, 500);
+    assert_eq!(scene.clips(1).next().unwrap().speed, 1.0);
+}
+
+#[test]
+fn speed_with_repeat() {
+    let scene = load(
+        &scene(r#"<clip file="a.wav" speed="2" repeat="3"/>"#),
+        &[("a.wav", wav(1, 1000))],
+    )
+    .unwrap();
+    assert_eq!(scene.frames(), 1500);
+}
+
+#[test]
+fn invalid_speed() {
+    for speed in &["0", "-1", "inf", "NaN", "fast", ""] {
+        let result = load(
+            &scene(&format!(r#"<clip file="a.wav" speed="{}"/>"#, speed)),
+            &[("a.wav", wav(1, 1000))],
+        );
+        assert!(result.is_err(), "speed=\"{}\"", speed);
+    }
+}
+
+#[test]
+fn faster_playback() {
+    let mut scene = clip_with_speed("2", 100 * BLOCKSIZE as usize);
+    seek(&mut scene, 0);
+    let data = play(&mut scene, 40);
+    assert_constant(&data[0]);
+}
+
+#[test]
+fn slower_playback() {
+    let mut scene = clip_with_speed("0.5", 100 * BLOCKSIZE as usize);
+    seek(&mut scene, 0);
+    let data = play(&mut scene, 150);
+    assert_constant(&data[0]);
+}
+
+#[test]
+fn seeking_with_speed() {
+    let mut scene = clip_with_speed("1.5", 300 * BLOCKSIZE as usize);
+    seek(&mut scene, 100 * u64::from(BLOCKSIZE));
+    let data = play(&mut scene, 50);
+    assert_constant(&data[0]);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::time::Duration;
+
+use asdf::{FadeShape, Scene};
+use common::{load, play, scene, seek, silent_wav, wav, BLOCKSIZE, SAMPLERATE};
+
+/// Source 0 is audible, source 1 is silent (both for 100 blocks).
+fn watched_scene() -> Scene {
+    let mut scene = load(
+        &scene(r#"<par><clip file="a.wav"/><clip file="b.wav"/></par>"#),
+        &[
+            ("a.wav", wav(1, 100 * BLOCKSIZE as usize)),
+            ("b.wav", silent_wav(1, 100 * BLOCKSIZE as usize)),
+        ],
+    )
+    .unwrap();
+    scene.set_fade(Duration::from_secs(0), FadeShape::Linear);
+    // 10 blocks
+    let limit = f64::from(10 * BLOCKSIZE) / f64::from(SAMPLERATE);
+    scene.enable_silence_watchdog(Duration::from_secs_f64(limit));
+    seek(&mut scene, 0);
+    scene
+}
+
+fn silent_sources(scene: &Scene) -> Vec<usize> {
+    scene.silence_watchdog().unwrap().silent_sources().collect()
+}
+
+#[test]
+fn silent_source_is_reported() {
+    let mut scene = watched_scene();
+    play(&mut scene, 5);
+    assert!(silent_sources(&scene).is_empty());
+    play(&mut scene, 15);
+    assert_eq!(silent_sources(&scene), [1]);
+}
+
+#[test]
+fn muted_source_is_not_reported() {
+    let mut scene = watched_scene();
+    play(&mut scene, 5);
+    scene.set_source_mute(0, true);
+    scene.set_source_mute(1, true);
+    play(&mut scene, 50);
+    assert!(silent_sources(&scene).is_empty());
+}
+
+#[test]
+fn source_silenced_by_solo_is_not_reported() {
+    let mut scene = watched_scene();
+    play(&mut scene, 5);
+    scene.set_source_solo(0, true);
+    play(&mut scene, 50);
+    assert!(silent_sources(&scene).is_empty());
+}
+
+#[test]
+fn source_with_zero_gain_is_not_reported() {
+    let mut scene = watched_scene();
+    play(&mut scene, 5);
+    scene.set_source_gain(0, 0.0);
+    play(&mut scene, 50);
+    assert_eq!(silent_sources(&scene), [1]);
+}
+
+#[test]
+fn unmuted_source_is_reported_again() {
+    let mut scene = watched_scene();
+    scene.set_source_mute(1, true);
+    play(&mut scene, 20);
+    assert!(silent_sources(&scene).is_empty());
+    scene.set_source_mute(1, false);
+    play(&mut scene, 5);
+    assert!(silent_sources(&scene).is_empty());
+    play(&mut scene, 15);
+    assert_eq!(silent_sources(&scene), [1]);
+}